@@ -0,0 +1,301 @@
+//! Moving the AST nodes of one [Component](crate::Component) into another.
+//!
+//! Every arena of the appended component is pushed onto the end of the
+//! matching arena, so each of its IDs is offset by the length that arena
+//! had before and [IdRemap] only needs to remember those lengths.
+
+use cranelift_entity::EntityRef;
+
+use crate::{
+    Expression, ExpressionId, ExternalType, FileId, FnType, FuncType, Function, FunctionId, Global,
+    GlobalId, Import, ImportId, NameId, Pattern, Statement, StatementId, TypeDefId, TypeDefinition,
+    TypeId, ValType,
+};
+
+/// Maps the IDs of an appended component to their IDs after appending
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdRemap {
+    pub(crate) files: u32,
+    pub(crate) imports: usize,
+    pub(crate) type_defs: usize,
+    pub(crate) globals: usize,
+    pub(crate) functions: usize,
+    pub(crate) types: usize,
+    pub(crate) statements: usize,
+    pub(crate) expressions: usize,
+    pub(crate) names: usize,
+}
+
+fn offset<T: EntityRef>(id: T, offset: usize) -> T {
+    T::new(id.index() + offset)
+}
+
+impl IdRemap {
+    pub fn file(&self, file: FileId) -> FileId {
+        FileId(file.0 + self.files)
+    }
+
+    pub fn import(&self, id: ImportId) -> ImportId {
+        offset(id, self.imports)
+    }
+
+    pub fn type_def(&self, id: TypeDefId) -> TypeDefId {
+        offset(id, self.type_defs)
+    }
+
+    pub fn global(&self, id: GlobalId) -> GlobalId {
+        offset(id, self.globals)
+    }
+
+    pub fn function(&self, id: FunctionId) -> FunctionId {
+        offset(id, self.functions)
+    }
+
+    pub fn type_id(&self, id: TypeId) -> TypeId {
+        offset(id, self.types)
+    }
+
+    pub fn statement(&self, id: StatementId) -> StatementId {
+        offset(id, self.statements)
+    }
+
+    pub fn expression(&self, id: ExpressionId) -> ExpressionId {
+        offset(id, self.expressions)
+    }
+
+    pub fn name(&self, id: NameId) -> NameId {
+        offset(id, self.names)
+    }
+}
+
+/// AST nodes which refer to other nodes by ID
+pub(crate) trait Remap {
+    fn remap(&mut self, remap: &IdRemap);
+}
+
+impl Remap for Import {
+    fn remap(&mut self, remap: &IdRemap) {
+        match self {
+            Import::Plain(import) => {
+                import.ident = remap.name(import.ident);
+                import.alias = import.alias.map(|alias| remap.name(alias));
+                let ExternalType::Function(fn_type) = &mut import.external_type;
+                fn_type.remap(remap);
+            }
+            Import::ImportFrom(import) => {
+                for (ident, alias) in import.items.iter_mut() {
+                    *ident = remap.name(*ident);
+                    *alias = alias.map(|alias| remap.name(alias));
+                }
+            }
+        }
+    }
+}
+
+impl Remap for FnType {
+    fn remap(&mut self, remap: &IdRemap) {
+        for (ident, type_id) in self.params.iter_mut() {
+            *ident = remap.name(*ident);
+            *type_id = remap.type_id(*type_id);
+        }
+        self.results = self.results.map(|results| remap.type_id(results));
+    }
+}
+
+impl Remap for TypeDefinition {
+    fn remap(&mut self, remap: &IdRemap) {
+        match self {
+            TypeDefinition::Record(record) => {
+                record.ident = remap.name(record.ident);
+                for field in record.fields.iter_mut() {
+                    field.ident = remap.name(field.ident);
+                    field.type_id = remap.type_id(field.type_id);
+                    field.default = field.default.map(|default| remap.expression(default));
+                }
+            }
+            TypeDefinition::Enum(enum_def) => {
+                enum_def.ident = remap.name(enum_def.ident);
+                for case in enum_def.cases.iter_mut() {
+                    case.ident = remap.name(case.ident);
+                    case.discriminant = case.discriminant.map(|value| remap.expression(value));
+                }
+            }
+            TypeDefinition::Newtype(newtype) => {
+                newtype.ident = remap.name(newtype.ident);
+                newtype.inner = remap.type_id(newtype.inner);
+            }
+        }
+    }
+}
+
+impl Remap for Global {
+    fn remap(&mut self, remap: &IdRemap) {
+        self.ident = remap.name(self.ident);
+        self.type_id = remap.type_id(self.type_id);
+        self.init_value = remap.expression(self.init_value);
+    }
+}
+
+impl Remap for Function {
+    fn remap(&mut self, remap: &IdRemap) {
+        self.ident = remap.name(self.ident);
+        for (ident, type_id) in self.params.iter_mut() {
+            *ident = remap.name(*ident);
+            *type_id = remap.type_id(*type_id);
+        }
+        self.results = self.results.map(|results| remap.type_id(results));
+        for statement in self.body.iter_mut() {
+            *statement = remap.statement(*statement);
+        }
+    }
+}
+
+impl Remap for ValType {
+    fn remap(&mut self, remap: &IdRemap) {
+        match self {
+            ValType::Option(inner) | ValType::List(inner) => *inner = remap.type_id(*inner),
+            ValType::Result(result) => {
+                result.ok = remap.type_id(result.ok);
+                result.err = remap.type_id(result.err);
+            }
+            ValType::Primitive(_) => {}
+            ValType::Named(ident) => *ident = remap.name(*ident),
+            ValType::Func(func_type) => func_type.remap(remap),
+        }
+    }
+}
+
+impl Remap for FuncType {
+    fn remap(&mut self, remap: &IdRemap) {
+        for param in self.params.iter_mut() {
+            *param = remap.type_id(*param);
+        }
+        self.results = self.results.map(|results| remap.type_id(results));
+    }
+}
+
+impl Remap for Statement {
+    fn remap(&mut self, remap: &IdRemap) {
+        match self {
+            Statement::Let(let_) => {
+                let_.ident = remap.name(let_.ident);
+                let_.annotation = let_.annotation.map(|annotation| remap.type_id(annotation));
+                let_.expression = remap.expression(let_.expression);
+            }
+            Statement::Assign(assign) => {
+                assign.ident = remap.name(assign.ident);
+                assign.expression = remap.expression(assign.expression);
+            }
+            Statement::Call(call) => {
+                call.ident = remap.name(call.ident);
+                for arg in call.args.iter_mut() {
+                    *arg = remap.expression(*arg);
+                }
+            }
+            Statement::If(if_) => {
+                if_.condition = remap.expression(if_.condition);
+                for statement in if_.block.iter_mut() {
+                    *statement = remap.statement(*statement);
+                }
+            }
+            Statement::Return(return_) => {
+                return_.expression = return_.expression.map(|expr| remap.expression(expr));
+            }
+            Statement::Func(nested) => nested.function = remap.function(nested.function),
+            Statement::Expression(statement) => {
+                statement.expression = remap.expression(statement.expression)
+            }
+        }
+    }
+}
+
+impl Remap for Expression {
+    fn remap(&mut self, remap: &IdRemap) {
+        match self {
+            Expression::Identifier(identifier) => identifier.ident = remap.name(identifier.ident),
+            Expression::Enum(enum_literal) => {
+                enum_literal.enum_name = remap.name(enum_literal.enum_name);
+                enum_literal.case_name = remap.name(enum_literal.case_name);
+            }
+            Expression::Literal(_) => {}
+            Expression::Call(call) => {
+                call.ident = remap.name(call.ident);
+                for arg in call.args.iter_mut() {
+                    *arg = remap.expression(*arg);
+                }
+            }
+            Expression::MethodCall(call) => {
+                call.receiver = remap.expression(call.receiver);
+                call.ident = remap.name(call.ident);
+                for arg in call.args.iter_mut() {
+                    *arg = remap.expression(*arg);
+                }
+            }
+            Expression::Field(access) => {
+                access.record = remap.expression(access.record);
+                access.field = remap.name(access.field);
+            }
+            Expression::Unary(unary) => unary.inner = remap.expression(unary.inner),
+            Expression::Binary(binary) => {
+                binary.left = remap.expression(binary.left);
+                binary.right = remap.expression(binary.right);
+            }
+            Expression::Index(index) => {
+                index.list = remap.expression(index.list);
+                index.index = remap.expression(index.index);
+            }
+            Expression::Slice(slice) => {
+                slice.list = remap.expression(slice.list);
+                slice.start = slice.start.map(|start| remap.expression(start));
+                slice.end = slice.end.map(|end| remap.expression(end));
+            }
+            Expression::Cast(cast) => {
+                cast.inner = remap.expression(cast.inner);
+                cast.target = remap.type_id(cast.target);
+            }
+            Expression::Record(record) => {
+                record.ident = remap.name(record.ident);
+                for (ident, value) in record.fields.iter_mut() {
+                    *ident = remap.name(*ident);
+                    *value = remap.expression(*value);
+                }
+                record.base = record.base.map(|base| remap.expression(base));
+            }
+            Expression::If(if_expression) => {
+                if_expression.condition = remap.expression(if_expression.condition);
+                if_expression.then_value = remap.expression(if_expression.then_value);
+                if_expression.else_value = remap.expression(if_expression.else_value);
+            }
+            Expression::Match(match_expression) => {
+                match_expression.scrutinee = remap.expression(match_expression.scrutinee);
+                for arm in match_expression.arms.iter_mut() {
+                    match &mut arm.pattern {
+                        Pattern::Case {
+                            enum_name,
+                            case_name,
+                        } => {
+                            *enum_name = remap.name(*enum_name);
+                            *case_name = remap.name(*case_name);
+                        }
+                        Pattern::Wildcard(span) => *span = span.in_file(remap.file(span.file())),
+                        Pattern::Binding(ident) => *ident = remap.name(*ident),
+                    }
+                    arm.guard = arm.guard.map(|guard| remap.expression(guard));
+                    arm.value = remap.expression(arm.value);
+                }
+            }
+            Expression::Asm(asm) => {
+                for input in asm.inputs.iter_mut() {
+                    input.ident = remap.name(input.ident);
+                    input.annotation = input.annotation.map(|annotation| remap.type_id(annotation));
+                    input.expression = remap.expression(input.expression);
+                }
+                asm.result = remap.type_id(asm.result);
+                for (ident, type_id) in asm.clobbers.iter_mut() {
+                    *ident = remap.name(*ident);
+                    *type_id = remap.type_id(*type_id);
+                }
+            }
+        }
+    }
+}
@@ -2,17 +2,17 @@
 //! of the AST and contains root items (e.g. import, function),
 //! inner AST nodes (e.g. expression), and the source code.
 
-use std::collections::HashMap;
+use cranelift_entity::{entity_impl, EntityRef, PrimaryMap};
 
-use cranelift_entity::{entity_impl, PrimaryMap};
-
-use crate::PackageName;
+use crate::append::{IdRemap, Remap};
+use crate::{merge, FileId, PackageName};
 use claw_common::Source;
 
 use super::{
-    expressions::{Expression, ExpressionId},
+    expressions::{BinaryExpression, ContextEq, EqContext, Expression, ExpressionId},
     statements::{Statement, StatementId},
-    types::{FnType, TypeDefId, TypeDefinition},
+    symbols::{Interner, Symbol},
+    types::{FnType, FuncType, TypeDefId, TypeDefinition},
     NameId, Span, TypeId, ValType,
 };
 
@@ -40,6 +40,30 @@ entity_impl!(GlobalId, "global");
 pub struct FunctionId(u32);
 entity_impl!(FunctionId, "func");
 
+impl<C: EqContext> ContextEq<C> for ImportId {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        comp.get_import(*self)
+            .context_eq(comp.get_import(*other), context)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for GlobalId {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        comp.get_global(*self)
+            .context_eq(comp.get_global(*other), context)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for FunctionId {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        comp.get_function(*self)
+            .context_eq(comp.get_function(*other), context)
+    }
+}
+
 /// Each Claw source file represents a Component
 /// and this struct represents the root of the AST.
 ///
@@ -51,8 +75,9 @@ entity_impl!(FunctionId, "func");
 /// bad things will happen!
 #[derive(Debug)]
 pub struct Component {
-    /// The source text that the component was created from.
-    src: Source,
+    /// The source text that the component was created from,
+    /// followed by that of any components appended to it.
+    sources: Vec<Source>,
 
     // Top level items
     imports: PrimaryMap<ImportId, Import>,
@@ -60,18 +85,19 @@ pub struct Component {
     globals: PrimaryMap<GlobalId, Global>,
     functions: PrimaryMap<FunctionId, Function>,
 
-    // Inner items
+    // Inner items, each with its span at the same index
     types: PrimaryMap<TypeId, ValType>,
-    type_spans: HashMap<TypeId, Span>,
+    type_spans: Vec<Span>,
 
     statements: PrimaryMap<StatementId, Statement>,
-    statement_spans: HashMap<StatementId, Span>,
+    statement_spans: Vec<Span>,
 
     expressions: PrimaryMap<ExpressionId, Expression>,
-    expression_spans: HashMap<ExpressionId, Span>,
+    expression_spans: Vec<Span>,
 
-    names: PrimaryMap<NameId, String>,
-    name_spans: HashMap<NameId, Span>,
+    names: PrimaryMap<NameId, Symbol>,
+    symbols: Interner,
+    name_spans: Vec<Span>,
 }
 
 impl Component {
@@ -79,8 +105,13 @@ impl Component {
     ///
     /// This does not do any parsing!!!
     pub fn new(src: Source) -> Self {
+        Self::with_symbols(src, Default::default())
+    }
+
+    /// Create a new empty Component AST whose names are interned in `symbols`.
+    pub fn with_symbols(src: Source, symbols: Interner) -> Self {
         Self {
-            src,
+            sources: vec![src],
             imports: Default::default(),
             type_defs: Default::default(),
             globals: Default::default(),
@@ -92,13 +123,93 @@ impl Component {
             expressions: Default::default(),
             expression_spans: Default::default(),
             names: Default::default(),
+            symbols,
             name_spans: Default::default(),
         }
     }
 
     /// The source code that the AST represents.
     pub fn source(&self) -> Source {
-        self.src.clone()
+        self.sources[0].clone()
+    }
+
+    /// The source code that a span covers.
+    pub fn span_text(&self, span: Span) -> &str {
+        let source = &self.sources[span.file().0 as usize];
+        &source.inner()[span.start()..span.end()]
+    }
+
+    /// The number of files the component's nodes are from.
+    pub fn file_count(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// The source code of a file whose nodes were appended to this component.
+    pub fn file_source(&self, file: FileId) -> Source {
+        self.sources[file.0 as usize].clone()
+    }
+
+    /// Move every item and node of `other` into this component,
+    /// giving the IDs they have now in place of their IDs in `other`.
+    ///
+    /// This lets files parsed separately be resolved as one component.
+    /// Names are re-interned and spans point into new files.
+    pub fn append(&mut self, other: Component) -> IdRemap {
+        let remap = IdRemap {
+            files: self.sources.len() as u32,
+            imports: self.imports.len(),
+            type_defs: self.type_defs.len(),
+            globals: self.globals.len(),
+            functions: self.functions.len(),
+            types: self.types.len(),
+            statements: self.statements.len(),
+            expressions: self.expressions.len(),
+            names: self.names.len(),
+        };
+        let span = |span: Span| span.in_file(remap.file(span.file()));
+
+        self.sources.extend(other.sources);
+        for (_, mut import) in other.imports {
+            import.remap(&remap);
+            self.imports.push(import);
+        }
+        for (_, mut type_def) in other.type_defs {
+            type_def.remap(&remap);
+            self.type_defs.push(type_def);
+        }
+        for (_, mut global) in other.globals {
+            global.remap(&remap);
+            self.globals.push(global);
+        }
+        for (_, mut function) in other.functions {
+            function.remap(&remap);
+            self.functions.push(function);
+        }
+        for (_, mut valtype) in other.types {
+            valtype.remap(&remap);
+            self.types.push(valtype);
+        }
+        for (_, mut statement) in other.statements {
+            statement.remap(&remap);
+            self.statements.push(statement);
+        }
+        for (_, mut expression) in other.expressions {
+            expression.remap(&remap);
+            self.expressions.push(expression);
+        }
+        for (_, symbol) in other.names {
+            let symbol = self.symbols.intern(other.symbols.resolve(symbol));
+            self.names.push(symbol);
+        }
+        self.type_spans
+            .extend(other.type_spans.into_iter().map(span));
+        self.statement_spans
+            .extend(other.statement_spans.into_iter().map(span));
+        self.expression_spans
+            .extend(other.expression_spans.into_iter().map(span));
+        self.name_spans
+            .extend(other.name_spans.into_iter().map(span));
+        remap
     }
 
     /// Add a top-level import item to the AST.
@@ -131,6 +242,14 @@ impl Component {
         &self.type_defs[type_def]
     }
 
+    /// Find the type definition item with the given name.
+    pub fn find_type_def(&self, name: &str) -> Option<TypeDefId> {
+        self.type_defs
+            .iter()
+            .find(|(_, type_def)| self.get_name(type_def.ident()) == name)
+            .map(|(id, _)| id)
+    }
+
     /// Add a top-level global item to the AST.
     pub fn push_global(&mut self, global: Global) -> GlobalId {
         self.globals.push(global)
@@ -161,28 +280,41 @@ impl Component {
         &self.functions[function]
     }
 
+    /// Intern an identifier string so that names can refer to it.
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        self.symbols.intern(name)
+    }
+
+    /// Get the string of an interned identifier.
+    pub fn resolve_symbol(&self, symbol: Symbol) -> &str {
+        self.symbols.resolve(symbol)
+    }
+
     /// Create a new name AST node.
-    pub fn new_name(&mut self, name: String, span: Span) -> NameId {
-        let id = self.names.push(name);
-        self.name_spans.insert(id, span);
-        id
+    pub fn new_name(&mut self, symbol: Symbol, span: Span) -> NameId {
+        self.name_spans.push(span);
+        self.names.push(symbol)
+    }
+
+    /// Get the interned identifier of a name.
+    pub fn name_symbol(&self, id: NameId) -> Symbol {
+        self.names[id]
     }
 
     /// Get the value of a name.
     pub fn get_name(&self, id: NameId) -> &str {
-        self.names.get(id).unwrap()
+        self.symbols.resolve(self.names[id])
     }
 
     /// Get the source span for this name.
     pub fn name_span(&self, id: NameId) -> Span {
-        *self.name_spans.get(&id).unwrap()
+        self.name_spans[id.index()]
     }
 
     /// Create a new valtype AST node.
     pub fn new_type(&mut self, valtype: ValType, span: Span) -> TypeId {
-        let id = self.types.push(valtype);
-        self.type_spans.insert(id, span);
-        id
+        self.type_spans.push(span);
+        self.types.push(valtype)
     }
 
     /// Get the value of a valtype AST node.
@@ -190,16 +322,20 @@ impl Component {
         self.types.get(id).unwrap()
     }
 
+    /// Iterate over every valtype AST node.
+    pub fn iter_types(&self) -> impl Iterator<Item = (TypeId, &ValType)> {
+        self.types.iter()
+    }
+
     /// Get the source span for this valtype.
     pub fn type_span(&self, id: TypeId) -> Span {
-        *self.type_spans.get(&id).unwrap()
+        self.type_spans[id.index()]
     }
 
     /// Create a new statement AST node.
     pub fn new_statement(&mut self, statement: Statement, span: Span) -> StatementId {
-        let id = self.statements.push(statement);
-        self.statement_spans.insert(id, span);
-        id
+        self.statement_spans.push(span);
+        self.statements.push(statement)
     }
 
     /// Get the value of a statement AST node.
@@ -209,14 +345,20 @@ impl Component {
 
     /// Get the source span for this statement.
     pub fn statement_span(&self, id: StatementId) -> Span {
-        *self.statement_spans.get(&id).unwrap()
+        self.statement_spans[id.index()]
     }
 
     /// Create a new expression AST node.
     pub fn new_expression(&mut self, expression: Expression, span: Span) -> ExpressionId {
-        let id = self.expressions.push(expression);
-        self.expression_spans.insert(id, span);
-        id
+        self.expression_spans.push(span);
+        self.expressions.push(expression)
+    }
+
+    /// Create a new binary expression AST node.
+    ///
+    /// Its span is worked out from its operands when it is asked for.
+    pub fn new_binary_expression(&mut self, binary: BinaryExpression) -> ExpressionId {
+        self.new_expression(binary.into(), Span::default())
     }
 
     /// Get the value of a expression AST node.
@@ -224,12 +366,83 @@ impl Component {
         self.expressions.get(id).unwrap()
     }
 
+    /// Get the value of a expression AST node to change it.
+    pub fn get_expression_mut(&mut self, id: ExpressionId) -> &mut Expression {
+        self.expressions.get_mut(id).unwrap()
+    }
+
+    /// Get the source span for any kind of node.
+    pub fn node_span(&self, node: Node) -> Span {
+        match node {
+            Node::Name(id) => self.name_span(id),
+            Node::Type(id) => self.type_span(id),
+            Node::Statement(id) => self.statement_span(id),
+            Node::Expression(id) => self.expression_span(id),
+        }
+    }
+
+    /// Find the innermost node whose source covers the byte at `offset`.
+    ///
+    /// When nodes cover the same bytes, like an identifier expression and
+    /// its name, an expression is preferred over a type, name or statement.
+    pub fn node_at(&self, offset: usize) -> Option<Node> {
+        let expressions = self.expressions.keys().map(Node::Expression);
+        let types = self.types.keys().map(Node::Type);
+        let names = self.names.keys().map(Node::Name);
+        let statements = self.statements.keys().map(Node::Statement);
+        expressions
+            .chain(types)
+            .chain(names)
+            .chain(statements)
+            .map(|node| (node, self.node_span(node)))
+            .filter(|(_, span)| span.start() <= offset && offset < span.end())
+            .min_by_key(|(_, span)| span.len())
+            .map(|(node, _)| node)
+    }
+
+    /// Move every span by `delta` bytes and use `src` as the source.
+    ///
+    /// This is for when the source is edited before all of the component's
+    /// code, so its nodes are still correct but have moved.
+    pub fn shift(&mut self, src: Source, delta: isize) {
+        self.sources[0] = src;
+        let spans = self
+            .type_spans
+            .iter_mut()
+            .chain(self.statement_spans.iter_mut())
+            .chain(self.expression_spans.iter_mut())
+            .chain(self.name_spans.iter_mut());
+        for span in spans {
+            *span = span.shifted(delta);
+        }
+    }
+
     /// Get the source span for this expression.
     pub fn expression_span(&self, id: ExpressionId) -> Span {
-        *self.expression_spans.get(&id).unwrap()
+        let edge = |mut id: ExpressionId, left: bool| {
+            while let Expression::Binary(binary) = &self.expressions[id] {
+                id = if left { binary.left } else { binary.right };
+            }
+            self.expression_spans[id.index()]
+        };
+        match &self.expressions[id] {
+            Expression::Binary(binary) => {
+                merge(&edge(binary.left, true), &edge(binary.right, false))
+            }
+            _ => self.expression_spans[id.index()],
+        }
     }
 }
 
+/// Any node inside the items of a [Component]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Node {
+    Name(NameId),
+    Type(TypeId),
+    Statement(StatementId),
+    Expression(ExpressionId),
+}
+
 /// Import AST node (Claw)
 ///
 /// There are two versions: plain and import-from.
@@ -239,6 +452,18 @@ pub enum Import {
     ImportFrom(ImportFrom),
 }
 
+impl<C: EqContext> ContextEq<C> for Import {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        match (self, other) {
+            (Import::Plain(left), Import::Plain(right)) => left.context_eq(right, context),
+            (Import::ImportFrom(left), Import::ImportFrom(right)) => {
+                left.context_eq(right, context)
+            }
+            _ => false,
+        }
+    }
+}
+
 /// Plain Import AST node (Claw)
 ///
 /// ```claw
@@ -255,6 +480,16 @@ pub struct PlainImport {
     pub external_type: ExternalType,
 }
 
+impl<C: EqContext> ContextEq<C> for PlainImport {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let ExternalType::Function(self_type) = &self.external_type;
+        let ExternalType::Function(other_type) = &other.external_type;
+        self.ident.context_eq(&other.ident, context)
+            && self.alias.context_eq(&other.alias, context)
+            && self_type.context_eq(other_type, context)
+    }
+}
+
 /// Import From AST node (Claw)
 ///
 /// ```claw
@@ -271,6 +506,14 @@ pub struct ImportFrom {
     pub interface: String,
 }
 
+impl<C: EqContext> ContextEq<C> for ImportFrom {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.items.context_eq(&other.items, context)
+            && self.package == other.package
+            && self.interface == other.interface
+    }
+}
+
 /// External Type AST node (Claw)
 ///
 /// ```claw
@@ -305,6 +548,16 @@ pub struct Global {
     pub init_value: ExpressionId,
 }
 
+impl<C: EqContext> ContextEq<C> for Global {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.exported == other.exported
+            && self.mutable == other.mutable
+            && self.ident.context_eq(&other.ident, context)
+            && self.type_id.context_eq(&other.type_id, context)
+            && self.init_value.context_eq(&other.init_value, context)
+    }
+}
+
 /// Function Item AST node (Claw)
 ///
 /// ```claw
@@ -319,6 +572,9 @@ pub struct Function {
     /// Indicated by the keyword `export` in front
     /// of the function item.
     pub exported: bool,
+    /// Whether the function is defined inside another function,
+    /// in which case it's only visible in the enclosing block.
+    pub nested: bool,
     /// The name of the function.
     pub ident: NameId,
     /// The function's parameters.
@@ -332,3 +588,24 @@ pub struct Function {
     /// The body of the function.
     pub body: Vec<StatementId>,
 }
+
+impl<C: EqContext> ContextEq<C> for Function {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.exported == other.exported
+            && self.nested == other.nested
+            && self.ident.context_eq(&other.ident, context)
+            && self.params.context_eq(&other.params, context)
+            && self.results.context_eq(&other.results, context)
+            && self.body.context_eq(&other.body, context)
+    }
+}
+
+impl Function {
+    /// The type of the function when it's used as a value
+    pub fn func_type(&self) -> FuncType {
+        FuncType {
+            params: self.params.iter().map(|(_, type_id)| *type_id).collect(),
+            results: self.results,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::NameId;
+use super::{NameId, Span, TypeId};
 use cranelift_entity::entity_impl;
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -9,27 +9,94 @@ pub trait ContextEq<Context> {
     fn context_eq(&self, other: &Self, context: &Context) -> bool;
 }
 
+/// The component that compared nodes are in, and whether
+/// nodes also have to be written in the same place to be equal
+pub trait EqContext {
+    fn component(&self) -> &super::Component;
+    fn compare_spans(&self) -> bool;
+}
+
+impl EqContext for super::Component {
+    fn component(&self) -> &super::Component {
+        self
+    }
+
+    fn compare_spans(&self) -> bool {
+        true
+    }
+}
+
+/// Compares nodes by what they are rather than where they were written,
+/// e.g. the nodes of a component with the ones parsed from printing it
+pub struct IgnoreSpans<'a>(pub &'a super::Component);
+
+impl EqContext for IgnoreSpans<'_> {
+    fn component(&self) -> &super::Component {
+        self.0
+    }
+
+    fn compare_spans(&self) -> bool {
+        false
+    }
+}
+
+impl<C: EqContext, T: ContextEq<C>> ContextEq<C> for Option<T> {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        match (self, other) {
+            (Some(left), Some(right)) => left.context_eq(right, context),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<C: EqContext, T: ContextEq<C>> ContextEq<C> for Vec<T> {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .zip(other.iter())
+                .all(|(left, right)| left.context_eq(right, context))
+    }
+}
+
+impl<C: EqContext, A: ContextEq<C>, B: ContextEq<C>> ContextEq<C> for (A, B) {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.0.context_eq(&other.0, context) && self.1.context_eq(&other.1, context)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
     Identifier(Identifier),
     Enum(EnumLiteral),
     Literal(Literal),
     Call(Call),
+    MethodCall(MethodCall),
+    Field(FieldAccess),
     Unary(UnaryExpression),
     Binary(BinaryExpression),
+    Index(Index),
+    Slice(Slice),
+    Cast(Cast),
+    Record(RecordLiteral),
+    Asm(InlineAsm),
+    If(IfExpression),
+    Match(Match),
 }
 
-impl ContextEq<super::Component> for ExpressionId {
-    fn context_eq(&self, other: &Self, context: &super::Component) -> bool {
-        let self_span = context.expression_span(*self);
-        let other_span = context.expression_span(*other);
-        if self_span != other_span {
+impl<C: EqContext> ContextEq<C> for ExpressionId {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        let self_span = comp.expression_span(*self);
+        let other_span = comp.expression_span(*other);
+        if context.compare_spans() && self_span != other_span {
             dbg!(self_span, other_span);
             return false;
         }
 
-        let self_expr = context.get_expression(*self);
-        let other_expr = context.get_expression(*other);
+        let self_expr = comp.get_expression(*self);
+        let other_expr = comp.get_expression(*other);
         if !self_expr.context_eq(other_expr, context) {
             dbg!(self_expr, other_expr);
             return false;
@@ -38,20 +105,34 @@ impl ContextEq<super::Component> for ExpressionId {
     }
 }
 
-impl ContextEq<super::Component> for Expression {
-    fn context_eq(&self, other: &Self, context: &super::Component) -> bool {
+impl<C: EqContext> ContextEq<C> for Expression {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
         match (self, other) {
             (Expression::Identifier(left), Expression::Identifier(right)) => {
                 left.context_eq(right, context)
             }
+            (Expression::Enum(left), Expression::Enum(right)) => left.context_eq(right, context),
             (Expression::Literal(left), Expression::Literal(right)) => {
                 left.context_eq(right, context)
             }
             (Expression::Call(left), Expression::Call(right)) => left.context_eq(right, context),
+            (Expression::MethodCall(left), Expression::MethodCall(right)) => {
+                left.context_eq(right, context)
+            }
+            (Expression::Field(left), Expression::Field(right)) => left.context_eq(right, context),
             (Expression::Unary(left), Expression::Unary(right)) => left.context_eq(right, context),
             (Expression::Binary(left), Expression::Binary(right)) => {
                 left.context_eq(right, context)
             }
+            (Expression::Index(left), Expression::Index(right)) => left.context_eq(right, context),
+            (Expression::Slice(left), Expression::Slice(right)) => left.context_eq(right, context),
+            (Expression::Cast(left), Expression::Cast(right)) => left.context_eq(right, context),
+            (Expression::Record(left), Expression::Record(right)) => {
+                left.context_eq(right, context)
+            }
+            (Expression::Asm(left), Expression::Asm(right)) => left.context_eq(right, context),
+            (Expression::If(left), Expression::If(right)) => left.context_eq(right, context),
+            (Expression::Match(left), Expression::Match(right)) => left.context_eq(right, context),
             _ => false,
         }
     }
@@ -68,9 +149,10 @@ impl From<Identifier> for Expression {
     }
 }
 
-impl ContextEq<super::Component> for Identifier {
-    fn context_eq(&self, other: &Self, context: &super::Component) -> bool {
-        context.get_name(self.ident) == context.get_name(other.ident)
+impl<C: EqContext> ContextEq<C> for Identifier {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        comp.get_name(self.ident) == comp.get_name(other.ident)
     }
 }
 
@@ -86,10 +168,11 @@ impl From<EnumLiteral> for Expression {
     }
 }
 
-impl ContextEq<super::Component> for EnumLiteral {
-    fn context_eq(&self, other: &Self, context: &super::Component) -> bool {
-        context.get_name(self.enum_name) == context.get_name(other.enum_name)
-            && context.get_name(self.case_name) == context.get_name(other.case_name)
+impl<C: EqContext> ContextEq<C> for EnumLiteral {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        comp.get_name(self.enum_name) == comp.get_name(other.enum_name)
+            && comp.get_name(self.case_name) == comp.get_name(other.case_name)
     }
 }
 
@@ -106,8 +189,8 @@ impl From<Literal> for Expression {
     }
 }
 
-impl ContextEq<super::Component> for Literal {
-    fn context_eq(&self, other: &Self, _context: &super::Component) -> bool {
+impl<C: EqContext> ContextEq<C> for Literal {
+    fn context_eq(&self, other: &Self, _context: &C) -> bool {
         self == other
     }
 }
@@ -124,17 +207,360 @@ impl From<Call> for Expression {
     }
 }
 
-impl ContextEq<super::Component> for Call {
-    fn context_eq(&self, other: &Self, context: &super::Component) -> bool {
+impl<C: EqContext> ContextEq<C> for Call {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.ident.context_eq(&other.ident, context) && self.args.context_eq(&other.args, context)
+    }
+}
+
+/// Calling a function with the value before the `.` as its first argument.
+///
+/// ```claw
+/// values.len()
+/// point.scale(2.0)
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct MethodCall {
+    pub receiver: ExpressionId,
+    pub ident: NameId,
+    /// The arguments after the receiver.
+    pub args: Vec<ExpressionId>,
+}
+
+impl MethodCall {
+    /// The same call written as a plain function call
+    pub fn as_call(&self) -> Call {
+        let args = std::iter::once(self.receiver)
+            .chain(self.args.iter().copied())
+            .collect();
+        Call {
+            ident: self.ident,
+            args,
+        }
+    }
+}
+
+impl From<MethodCall> for Expression {
+    fn from(val: MethodCall) -> Self {
+        Expression::MethodCall(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for MethodCall {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.as_call().context_eq(&other.as_call(), context)
+    }
+}
+
+/// Reading a field of a struct.
+///
+/// ```claw
+/// point.x
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldAccess {
+    pub record: ExpressionId,
+    /// The name of the field.
+    pub field: NameId,
+}
+
+impl From<FieldAccess> for Expression {
+    fn from(val: FieldAccess) -> Self {
+        Expression::Field(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for FieldAccess {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.record.context_eq(&other.record, context)
+            && self.field.context_eq(&other.field, context)
+    }
+}
+
+/// Indexing into a list, which traps when the index is out of bounds.
+///
+/// ```claw
+/// values[i]
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Index {
+    pub list: ExpressionId,
+    pub index: ExpressionId,
+}
+
+impl From<Index> for Expression {
+    fn from(val: Index) -> Self {
+        Expression::Index(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for Index {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.list.context_eq(&other.list, context) && self.index.context_eq(&other.index, context)
+    }
+}
+
+/// A view of part of a list, which shares its elements instead of copying them.
+/// It traps unless `start <= end <= len`.
+///
+/// ```claw
+/// values[1..n]
+/// values[..n]
+/// values[1..]
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Slice {
+    pub list: ExpressionId,
+    /// The index of the first element, the start of the list if left out.
+    pub start: Option<ExpressionId>,
+    /// The index after the last element, the end of the list if left out.
+    pub end: Option<ExpressionId>,
+}
+
+impl From<Slice> for Expression {
+    fn from(val: Slice) -> Self {
+        Expression::Slice(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for Slice {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let bound_eq = |left: Option<ExpressionId>, right: Option<ExpressionId>| match (left, right)
+        {
+            (Some(left), Some(right)) => left.context_eq(&right, context),
+            (None, None) => true,
+            _ => false,
+        };
+        self.list.context_eq(&other.list, context)
+            && bound_eq(self.start, other.start)
+            && bound_eq(self.end, other.end)
+    }
+}
+
+/// Converting between an enum and the integer values of its cases.
+/// Converting an integer to an enum traps unless a case has that value.
+///
+/// ```claw
+/// status::not-found as u32
+/// code as status
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cast {
+    pub inner: ExpressionId,
+    /// The type converted to.
+    pub target: TypeId,
+}
+
+impl From<Cast> for Expression {
+    fn from(val: Cast) -> Self {
+        Expression::Cast(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for Cast {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.inner.context_eq(&other.inner, context)
+            && self.target.context_eq(&other.target, context)
+    }
+}
+
+/// Constructing a struct, where left out fields are filled
+/// in from the base if there is one or their defaults otherwise.
+///
+/// ```claw
+/// point { x: 1, ..origin }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecordLiteral {
+    /// The name of the struct.
+    pub ident: NameId,
+    /// The fields given explicitly, in the order written.
+    pub fields: Vec<(NameId, ExpressionId)>,
+    /// The struct to copy the remaining fields from.
+    pub base: Option<ExpressionId>,
+}
+
+impl From<RecordLiteral> for Expression {
+    fn from(val: RecordLiteral) -> Self {
+        Expression::Record(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for RecordLiteral {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
         let ident_eq = self.ident.context_eq(&other.ident, context);
-        let args_eq = self
-            .args
-            .iter()
-            .zip(other.args.iter())
-            .map(|(l, r)| l.context_eq(r, context))
-            .all(|v| v);
+        let fields_eq = self.fields.len() == other.fields.len()
+            && self.fields.iter().zip(other.fields.iter()).all(
+                |((l_name, l_expr), (r_name, r_expr))| {
+                    l_name.context_eq(r_name, context) && l_expr.context_eq(r_expr, context)
+                },
+            );
+        let base_eq = match (self.base, other.base) {
+            (Some(left), Some(right)) => left.context_eq(&right, context),
+            (None, None) => true,
+            _ => false,
+        };
 
-        ident_eq && args_eq
+        ident_eq && fields_eq && base_eq
+    }
+}
+
+/// Inline WebAssembly text embedded in an expression.
+///
+/// ```claw
+/// asm (a = x, b: u32 = 1) -> u32 clobbers (t: u32) {
+///     "local.get $a local.get $b i32.add"
+/// }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct InlineAsm {
+    /// The named values made available to the body as parameters.
+    pub inputs: Vec<AsmInput>,
+    /// The type of the values left on the stack by the body.
+    pub result: TypeId,
+    /// Scratch locals that the body is allowed to use.
+    pub clobbers: Vec<(NameId, TypeId)>,
+    /// The WebAssembly text instructions.
+    pub body: String,
+}
+
+/// A value passed to inline assembly under the name the body uses for it
+#[derive(Debug, PartialEq, Clone)]
+pub struct AsmInput {
+    pub ident: NameId,
+    /// The type of the value, for values like literals which don't have one of their own.
+    pub annotation: Option<TypeId>,
+    pub expression: ExpressionId,
+}
+
+impl From<InlineAsm> for Expression {
+    fn from(val: InlineAsm) -> Self {
+        Expression::Asm(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for InlineAsm {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.inputs.context_eq(&other.inputs, context)
+            && self.result.context_eq(&other.result, context)
+            && self.clobbers.context_eq(&other.clobbers, context)
+            && self.body == other.body
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for AsmInput {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.ident.context_eq(&other.ident, context)
+            && self.annotation.context_eq(&other.annotation, context)
+            && self.expression.context_eq(&other.expression, context)
+    }
+}
+
+/// Choosing between two values, only evaluating the chosen one.
+///
+/// ```claw
+/// if a < b { a } else { b }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct IfExpression {
+    pub condition: ExpressionId,
+    pub then_value: ExpressionId,
+    /// The value when the condition is false, which is
+    /// another if expression for `else if`.
+    pub else_value: ExpressionId,
+}
+
+impl From<IfExpression> for Expression {
+    fn from(val: IfExpression) -> Self {
+        Expression::If(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for IfExpression {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.condition.context_eq(&other.condition, context)
+            && self.then_value.context_eq(&other.then_value, context)
+            && self.else_value.context_eq(&other.else_value, context)
+    }
+}
+
+/// Choosing a value by the case of an enum, using the first arm that matches.
+///
+/// ```claw
+/// match level {
+///     level::low => 1,
+///     level::high if urgent => 20,
+///     level::high => 10,
+///     _ => 5,
+/// }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Match {
+    /// The value matched against the patterns.
+    pub scrutinee: ExpressionId,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    /// A condition which must also hold to take the arm, e.g. `if n > 10`
+    pub guard: Option<ExpressionId>,
+    pub value: ExpressionId,
+}
+
+/// What a match arm compares the value against
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    /// A case of an enum, e.g. `level::low`
+    Case {
+        enum_name: NameId,
+        case_name: NameId,
+    },
+    /// `_`, which matches anything
+    Wildcard(Span),
+    /// A name, which matches anything and is bound to the value within the arm
+    Binding(NameId),
+}
+
+impl From<Match> for Expression {
+    fn from(val: Match) -> Self {
+        Expression::Match(val)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for Match {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.scrutinee.context_eq(&other.scrutinee, context)
+            && self.arms.len() == other.arms.len()
+            && self
+                .arms
+                .iter()
+                .zip(other.arms.iter())
+                .all(|(left, right)| left.context_eq(right, context))
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for MatchArm {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let pattern_eq = match (&self.pattern, &other.pattern) {
+            (
+                Pattern::Case {
+                    enum_name: l_enum,
+                    case_name: l_case,
+                },
+                Pattern::Case {
+                    enum_name: r_enum,
+                    case_name: r_case,
+                },
+            ) => l_enum.context_eq(r_enum, context) && l_case.context_eq(r_case, context),
+            (Pattern::Wildcard(_), Pattern::Wildcard(_)) => true,
+            (Pattern::Binding(left), Pattern::Binding(right)) => left.context_eq(right, context),
+            _ => false,
+        };
+        pattern_eq
+            && self.guard.context_eq(&other.guard, context)
+            && self.value.context_eq(&other.value, context)
     }
 }
 
@@ -142,7 +568,12 @@ impl ContextEq<super::Component> for Call {
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum UnaryOp {
+    /// `-x`, for numbers
     Negate,
+    /// `!x`, for bools
+    LogicalNot,
+    /// `~x`, flipping every bit of an integer
+    Invert,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -157,11 +588,12 @@ impl From<UnaryExpression> for Expression {
     }
 }
 
-impl ContextEq<super::Component> for UnaryExpression {
-    fn context_eq(&self, other: &Self, context: &super::Component) -> bool {
-        let self_inner = context.get_expression(self.inner);
-        let other_inner = context.get_expression(other.inner);
-        self_inner.context_eq(other_inner, context)
+impl<C: EqContext> ContextEq<C> for UnaryExpression {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        let self_inner = comp.get_expression(self.inner);
+        let other_inner = comp.get_expression(other.inner);
+        self.op == other.op && self_inner.context_eq(other_inner, context)
     }
 }
 
@@ -176,6 +608,13 @@ pub enum BinaryOp {
     Add,
     Subtract,
 
+    // Overflow Controlled Arithmetic
+    WrappingAdd,
+    WrappingSubtract,
+    WrappingMultiply,
+    SaturatingAdd,
+    SaturatingSubtract,
+
     // Shifting Operations
     BitShiftL,
     BitShiftR,
@@ -195,15 +634,51 @@ pub enum BinaryOp {
     BitAnd,
 
     // Logical Operations
+    /// `or`, which only evaluates its right operand when the left is false
     LogicalOr,
+    /// `and`, which only evaluates its right operand when the left is true
     LogicalAnd,
 }
 
+impl BinaryOp {
+    /// How the operator is written in source code
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::WrappingAdd => "+%",
+            BinaryOp::WrappingSubtract => "-%",
+            BinaryOp::WrappingMultiply => "*%",
+            BinaryOp::SaturatingAdd => "+|",
+            BinaryOp::SaturatingSubtract => "-|",
+            BinaryOp::BitShiftL => "<<",
+            BinaryOp::BitShiftR => ">>",
+            BinaryOp::ArithShiftR => ">>>",
+            BinaryOp::LessThan => "<",
+            BinaryOp::LessThanEqual => "<=",
+            BinaryOp::GreaterThan => ">",
+            BinaryOp::GreaterThanEqual => ">=",
+            BinaryOp::Equals => "==",
+            BinaryOp::NotEquals => "!=",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::LogicalOr => "or",
+            BinaryOp::LogicalAnd => "and",
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct BinaryExpression {
     pub op: BinaryOp,
     pub left: ExpressionId,
     pub right: ExpressionId,
+    /// Whether the expression was written inside parentheses.
+    pub parenthesized: bool,
 }
 
 impl From<BinaryExpression> for Expression {
@@ -212,21 +687,46 @@ impl From<BinaryExpression> for Expression {
     }
 }
 
-impl ContextEq<super::Component> for BinaryExpression {
-    fn context_eq(&self, other: &Self, context: &super::Component) -> bool {
-        let self_left = context.get_expression(self.left);
-        let other_left = context.get_expression(other.left);
+impl<C: EqContext> ContextEq<C> for BinaryExpression {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        let self_left = comp.get_expression(self.left);
+        let other_left = comp.get_expression(other.left);
         let left_eq = self_left.context_eq(other_left, context);
 
-        let self_right = context.get_expression(self.right);
-        let other_right = context.get_expression(other.right);
+        let self_right = comp.get_expression(self.right);
+        let other_right = comp.get_expression(other.right);
         let right_eq = self_right.context_eq(other_right, context);
 
-        left_eq && right_eq
+        self.op == other.op && left_eq && right_eq
     }
 }
 
 impl BinaryExpression {
+    /// Whether the operator only applies to integers
+    pub fn is_integer_only(&self) -> bool {
+        use BinaryOp as BE;
+        matches!(
+            self.op,
+            BE::WrappingAdd
+                | BE::WrappingSubtract
+                | BE::WrappingMultiply
+                | BE::SaturatingAdd
+                | BE::SaturatingSubtract
+        )
+    }
+
+    /// Whether the right operand is only evaluated when the left doesn't decide the result
+    pub fn is_logical(&self) -> bool {
+        use BinaryOp as BE;
+        matches!(self.op, BE::LogicalAnd | BE::LogicalOr)
+    }
+
+    pub fn is_bitwise(&self) -> bool {
+        use BinaryOp as BE;
+        matches!(self.op, BE::BitOr | BE::BitXor | BE::BitAnd)
+    }
+
     pub fn is_relation(&self) -> bool {
         use BinaryOp as BE;
         matches!(
@@ -1,40 +1,37 @@
+pub mod append;
 pub mod component;
 pub mod expressions;
+pub mod span;
 pub mod statements;
+pub mod symbols;
 pub mod types;
 
 use cranelift_entity::entity_impl;
-use miette::SourceSpan;
 
 pub use wit_parser::PackageName;
 
-pub type Span = SourceSpan;
-
+pub use append::IdRemap;
 pub use component::*;
 pub use expressions::*;
+pub use span::*;
 pub use statements::*;
+pub use symbols::*;
 pub use types::*;
 
-pub fn merge(left: &Span, right: &Span) -> Span {
-    let left_most = left.offset();
-    let right_most = right.offset() + right.len();
-    let len = right_most - left_most;
-    Span::from((left_most, len))
-}
-
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct NameId(u32);
 entity_impl!(NameId, "name");
 
-impl ContextEq<Component> for NameId {
-    fn context_eq(&self, other: &Self, context: &Component) -> bool {
-        let self_str = context.get_name(*self);
-        let other_str = context.get_name(*other);
+impl<C: EqContext> ContextEq<C> for NameId {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        let self_str = comp.get_name(*self);
+        let other_str = comp.get_name(*other);
         let str_eq = self_str == other_str;
 
-        let self_span = context.name_span(*self);
-        let other_span = context.name_span(*other);
-        let span_eq = self_span == other_span;
+        let self_span = comp.name_span(*self);
+        let other_span = comp.name_span(*other);
+        let span_eq = !context.compare_spans() || self_span == other_span;
 
         str_eq && span_eq
     }
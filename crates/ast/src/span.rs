@@ -0,0 +1,96 @@
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use miette::SourceSpan;
+
+/// Identifies the source file a span points into.
+///
+/// Every span of a single-file component is in [FileId::MAIN].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Default)]
+pub struct FileId(pub u32);
+
+impl FileId {
+    pub const MAIN: FileId = FileId(0);
+}
+
+/// A range of bytes in a source file.
+///
+/// Offsets are stored as `u32`s to keep the many spans in the AST small,
+/// which limits source files to 4GiB.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Span {
+    start: u32,
+    end: u32,
+    file: FileId,
+}
+
+impl Span {
+    /// A span of `len` bytes starting at `start` in the main file.
+    pub fn new(start: usize, len: usize) -> Self {
+        Self::from(start..start + len)
+    }
+
+    /// The same range of bytes in another file.
+    pub fn in_file(self, file: FileId) -> Self {
+        Self { file, ..self }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start as usize
+    }
+
+    pub fn end(&self) -> usize {
+        self.end as usize
+    }
+
+    pub fn len(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn file(&self) -> FileId {
+        self.file
+    }
+
+    /// The span moved by `delta` bytes, stopping at the start of the file.
+    pub fn shifted(self, delta: isize) -> Self {
+        let offset = |offset: u32| (offset as usize).saturating_add_signed(delta);
+        Self {
+            file: self.file,
+            ..Self::from(offset(self.start)..offset(self.end))
+        }
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        let offset = |offset: usize| u32::try_from(offset).expect("Source files are under 4GiB");
+        Self {
+            start: offset(range.start),
+            end: offset(range.end),
+            file: FileId::MAIN,
+        }
+    }
+}
+
+impl From<Span> for SourceSpan {
+    fn from(span: Span) -> Self {
+        SourceSpan::new(span.start().into(), span.len())
+    }
+}
+
+/// The span from the start of `left` to the end of `right`.
+pub fn merge(left: &Span, right: &Span) -> Span {
+    debug_assert_eq!(
+        left.file, right.file,
+        "Can't merge spans of different files"
+    );
+    Span {
+        start: left.start,
+        end: right.end,
+        file: left.file,
+    }
+}
@@ -1,6 +1,8 @@
 use cranelift_entity::entity_impl;
 
-use super::{expressions::ExpressionId, types::TypeId, Call, NameId};
+use super::{
+    expressions::ExpressionId, types::TypeId, Call, ContextEq, EqContext, FunctionId, NameId,
+};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct StatementId(u32);
@@ -13,6 +15,36 @@ pub enum Statement {
     Call(Call),
     If(If),
     Return(Return),
+    Func(NestedFunc),
+    Expression(ExpressionStatement),
+}
+
+impl<C: EqContext> ContextEq<C> for StatementId {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        if context.compare_spans() && comp.statement_span(*self) != comp.statement_span(*other) {
+            return false;
+        }
+        comp.get_statement(*self)
+            .context_eq(comp.get_statement(*other), context)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for Statement {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        match (self, other) {
+            (Statement::Let(left), Statement::Let(right)) => left.context_eq(right, context),
+            (Statement::Assign(left), Statement::Assign(right)) => left.context_eq(right, context),
+            (Statement::Call(left), Statement::Call(right)) => left.context_eq(right, context),
+            (Statement::If(left), Statement::If(right)) => left.context_eq(right, context),
+            (Statement::Return(left), Statement::Return(right)) => left.context_eq(right, context),
+            (Statement::Func(left), Statement::Func(right)) => left.context_eq(right, context),
+            (Statement::Expression(left), Statement::Expression(right)) => {
+                left.context_eq(right, context)
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -23,19 +55,85 @@ pub struct Let {
     pub expression: ExpressionId,
 }
 
+impl<C: EqContext> ContextEq<C> for Let {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.mutable == other.mutable
+            && self.ident.context_eq(&other.ident, context)
+            && self.annotation.context_eq(&other.annotation, context)
+            && self.expression.context_eq(&other.expression, context)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Assign {
     pub ident: NameId,
     pub expression: ExpressionId,
 }
 
+impl<C: EqContext> ContextEq<C> for Assign {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.ident.context_eq(&other.ident, context)
+            && self.expression.context_eq(&other.expression, context)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct If {
     pub condition: ExpressionId,
     pub block: Vec<StatementId>,
 }
 
+impl<C: EqContext> ContextEq<C> for If {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.condition.context_eq(&other.condition, context)
+            && self.block.context_eq(&other.block, context)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Return {
     pub expression: Option<ExpressionId>,
 }
+
+impl<C: EqContext> ContextEq<C> for Return {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.expression.context_eq(&other.expression, context)
+    }
+}
+
+/// An expression evaluated for its side effects, discarding its value.
+///
+/// ```claw
+/// count(items) + 1;
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExpressionStatement {
+    pub expression: ExpressionId,
+}
+
+impl<C: EqContext> ContextEq<C> for ExpressionStatement {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.expression.context_eq(&other.expression, context)
+    }
+}
+
+/// A function defined inside of another function's body.
+///
+/// It is visible throughout the enclosing block, but can only
+/// use items and not the locals of the enclosing function.
+///
+/// ```claw
+/// func double(x: u32) -> u32 {
+///     return x * 2;
+/// }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct NestedFunc {
+    pub function: FunctionId,
+}
+
+impl<C: EqContext> ContextEq<C> for NestedFunc {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.function.context_eq(&other.function, context)
+    }
+}
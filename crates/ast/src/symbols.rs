@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use cranelift_entity::{entity_impl, PrimaryMap};
+
+/// An identifier string which has been interned.
+///
+/// Every occurrence of the same identifier gets the same symbol,
+/// so they can be compared and copied without touching the string.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+entity_impl!(Symbol, "symbol");
+
+/// Stores each distinct identifier string once.
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    strings: PrimaryMap<Symbol, Box<str>>,
+    symbols: HashMap<Box<str>, Symbol>,
+}
+
+impl Interner {
+    /// Get the symbol for a string, adding it if it hasn't been seen before.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return *symbol;
+        }
+        let symbol = self.strings.push(string.into());
+        self.symbols.insert(string.into(), symbol);
+        symbol
+    }
+
+    /// Get the string a symbol was interned from.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol]
+    }
+
+    /// The number of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
@@ -1,6 +1,6 @@
 use cranelift_entity::entity_impl;
 
-use super::{Component, NameId};
+use super::{Component, ContextEq, EqContext, ExpressionId, NameId};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct TypeId(u32);
@@ -10,11 +10,27 @@ entity_impl!(TypeId, "type");
 pub struct TypeDefId(u32);
 entity_impl!(TypeDefId, "typedef");
 
+impl<C: EqContext> ContextEq<C> for TypeId {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        if context.compare_spans() && comp.type_span(*self) != comp.type_span(*other) {
+            return false;
+        }
+        comp.get_type(*self).eq(comp.get_type(*other), comp)
+    }
+}
+
 /// The type for all values
 #[derive(Debug, Hash, Clone)]
 pub enum ValType {
+    Option(TypeId),
+    List(TypeId),
     Result(ResultType),
     Primitive(PrimitiveType),
+    /// A type defined by name elsewhere in the component, e.g. a struct
+    Named(NameId),
+    /// The signature of a function, for passing functions as values
+    Func(FuncType),
 }
 
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
@@ -49,6 +65,16 @@ pub struct ResultType {
 impl ValType {
     pub fn eq(&self, other: &Self, comp: &Component) -> bool {
         match (self, other) {
+            (ValType::Option(left), ValType::Option(right)) => {
+                let l_some = comp.get_type(*left);
+                let r_some = comp.get_type(*right);
+                l_some.eq(r_some, comp)
+            }
+            (ValType::List(left), ValType::List(right)) => {
+                let l_element = comp.get_type(*left);
+                let r_element = comp.get_type(*right);
+                l_element.eq(r_element, comp)
+            }
             (ValType::Result(left), ValType::Result(right)) => {
                 let l_ok = comp.get_type(left.ok);
                 let r_ok = comp.get_type(right.ok);
@@ -61,19 +87,232 @@ impl ValType {
                 ok_eq && err_eq
             }
             (ValType::Primitive(left), ValType::Primitive(right)) => left == right,
+            (ValType::Named(left), ValType::Named(right)) => {
+                comp.get_name(*left) == comp.get_name(*right)
+            }
+            (ValType::Func(left), ValType::Func(right)) => left.eq(right, comp),
             _ => false,
         }
     }
 }
 
+/// Function Type AST node (Claw)
+///
+/// ```claw
+/// func(u32, u32) -> u32
+/// ```
+#[derive(Debug, Hash, Clone)]
+pub struct FuncType {
+    pub params: Vec<TypeId>,
+    pub results: Option<TypeId>,
+}
+
+impl FuncType {
+    /// Signatures match when their parameter and result types do, names aren't part of them
+    pub fn eq(&self, other: &Self, comp: &Component) -> bool {
+        let type_eq =
+            |left: TypeId, right: TypeId| comp.get_type(left).eq(comp.get_type(right), comp);
+        let params_eq = self.params.len() == other.params.len()
+            && self
+                .params
+                .iter()
+                .zip(other.params.iter())
+                .all(|(left, right)| type_eq(*left, *right));
+        let results_eq = match (self.results, other.results) {
+            (Some(left), Some(right)) => type_eq(left, right),
+            (None, None) => true,
+            _ => false,
+        };
+        params_eq && results_eq
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum TypeDefinition {
     Record(RecordTypeDef),
+    Enum(EnumTypeDef),
+    Newtype(NewtypeDef),
+}
+
+impl TypeDefinition {
+    /// The name the type was defined with
+    pub fn ident(&self) -> NameId {
+        match self {
+            TypeDefinition::Record(record) => record.ident,
+            TypeDefinition::Enum(enum_def) => enum_def.ident,
+            TypeDefinition::Newtype(newtype) => newtype.ident,
+        }
+    }
+
+    /// Whether the type is exported
+    pub fn exported(&self) -> bool {
+        match self {
+            TypeDefinition::Record(record) => record.exported,
+            TypeDefinition::Enum(enum_def) => enum_def.exported,
+            TypeDefinition::Newtype(newtype) => newtype.exported,
+        }
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for TypeDefId {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        let comp = context.component();
+        comp.get_type_def(*self)
+            .context_eq(comp.get_type_def(*other), context)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for TypeDefinition {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        match (self, other) {
+            (TypeDefinition::Record(left), TypeDefinition::Record(right)) => {
+                left.context_eq(right, context)
+            }
+            (TypeDefinition::Enum(left), TypeDefinition::Enum(right)) => {
+                left.context_eq(right, context)
+            }
+            (TypeDefinition::Newtype(left), TypeDefinition::Newtype(right)) => {
+                left.context_eq(right, context)
+            }
+            _ => false,
+        }
+    }
 }
 
+/// Struct Item AST node (Claw)
+///
+/// ```claw
+/// struct point {
+///     x: s32 = 0,
+///     y: s32,
+/// }
+/// ```
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct RecordTypeDef {
-    fields: Vec<(NameId, TypeId)>,
+    /// Whether the struct is exported.
+    ///
+    /// Structs used by exported functions are exported regardless.
+    pub exported: bool,
+    /// The name of the struct.
+    pub ident: NameId,
+    /// The fields of the struct in declaration order.
+    pub fields: Vec<RecordField>,
+}
+
+impl RecordTypeDef {
+    /// Find a field and its position by name
+    pub fn field(&self, name: &str, comp: &Component) -> Option<(usize, &RecordField)> {
+        self.fields
+            .iter()
+            .enumerate()
+            .find(|(_, field)| comp.get_name(field.ident) == name)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for RecordTypeDef {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.exported == other.exported
+            && self.ident.context_eq(&other.ident, context)
+            && self.fields.context_eq(&other.fields, context)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct RecordField {
+    /// The name of the field.
+    pub ident: NameId,
+    /// The type of the field.
+    pub type_id: TypeId,
+    /// The value used when a struct literal leaves the field out.
+    pub default: Option<ExpressionId>,
+}
+
+impl<C: EqContext> ContextEq<C> for RecordField {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.ident.context_eq(&other.ident, context)
+            && self.type_id.context_eq(&other.type_id, context)
+            && self.default.context_eq(&other.default, context)
+    }
+}
+
+/// Enum Item AST node (Claw)
+///
+/// ```claw
+/// enum status {
+///     ok = 200,
+///     not-found = 404,
+///     gone,
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct EnumTypeDef {
+    /// Whether the enum is exported.
+    ///
+    /// Enums used by exported functions are exported regardless.
+    pub exported: bool,
+    /// The name of the enum.
+    pub ident: NameId,
+    /// The cases of the enum in declaration order.
+    pub cases: Vec<EnumCase>,
+}
+
+impl EnumTypeDef {
+    /// Find a case and its position by name
+    pub fn case(&self, name: &str, comp: &Component) -> Option<(usize, &EnumCase)> {
+        self.cases
+            .iter()
+            .enumerate()
+            .find(|(_, case)| comp.get_name(case.ident) == name)
+    }
+}
+
+impl<C: EqContext> ContextEq<C> for EnumTypeDef {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.exported == other.exported
+            && self.ident.context_eq(&other.ident, context)
+            && self.cases.context_eq(&other.cases, context)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct EnumCase {
+    /// The name of the case.
+    pub ident: NameId,
+    /// The integer value of the case, one more than the
+    /// previous case's if left out or zero for the first.
+    pub discriminant: Option<ExpressionId>,
+}
+
+impl<C: EqContext> ContextEq<C> for EnumCase {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.ident.context_eq(&other.ident, context)
+            && self.discriminant.context_eq(&other.discriminant, context)
+    }
+}
+
+/// Newtype Item AST node (Claw)
+///
+/// ```claw
+/// newtype meters = f64;
+/// ```
+///
+/// A distinct type with the same representation as its inner type.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct NewtypeDef {
+    /// Whether the newtype is exported.
+    pub exported: bool,
+    /// The name of the newtype.
+    pub ident: NameId,
+    /// The type it wraps.
+    pub inner: TypeId,
+}
+
+impl<C: EqContext> ContextEq<C> for NewtypeDef {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.exported == other.exported
+            && self.ident.context_eq(&other.ident, context)
+            && self.inner.context_eq(&other.inner, context)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -81,3 +320,10 @@ pub struct FnType {
     pub params: Vec<(NameId, TypeId)>,
     pub results: Option<TypeId>,
 }
+
+impl<C: EqContext> ContextEq<C> for FnType {
+    fn context_eq(&self, other: &Self, context: &C) -> bool {
+        self.params.context_eq(&other.params, context)
+            && self.results.context_eq(&other.results, context)
+    }
+}
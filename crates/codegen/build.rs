@@ -2,12 +2,21 @@ use std::{env, fs, path::Path};
 
 fn main() {
     let out_dir = env::var_os("OUT_DIR").unwrap();
-    let dest_path = Path::new(&out_dir).join("allocator.wasm");
 
-    let wat = include_str!("./allocator.wat");
-    let wasm = wat::parse_str(wat).unwrap();
+    // Core modules instantiated by components
+    let modules = [
+        ("allocator", include_str!("./allocator.wat")),
+        ("format", include_str!("./format.wat")),
+        ("stdio", include_str!("./stdio.wat")),
+    ];
+    for (name, wat) in modules.iter() {
+        let wasm = wat::parse_str(wat).unwrap();
+        let dest_path = Path::new(&out_dir).join(format!("{}.wasm", name));
+        fs::write(dest_path, wasm).unwrap();
+    }
 
-    fs::write(dest_path, wasm).unwrap();
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=allocator.wat");
+    println!("cargo:rerun-if-changed=format.wat");
+    println!("cargo:rerun-if-changed=stdio.wat");
 }
@@ -0,0 +1,141 @@
+(module
+    (import "alloc" "memory" (memory 1))
+    ;; Write an integer in the given radix at `$dest`, returning the number
+    ;; of bytes written. At most 20 bytes are written for radix 10.
+    (func $int (export "int")
+        (param $value i64)
+        (param $signed i32)
+        (param $radix i64)
+        (param $dest i32)
+        (result i32)
+        (local $start i32)
+        (local $end i32)
+        (local $rest i64)
+        (local $digit i32)
+        local.get $dest
+        local.set $start
+        ;; Negative numbers are a sign followed by their magnitude,
+        ;; the magnitude of the minimum is correct when read as unsigned
+        (if (i32.and
+                (local.get $signed)
+                (i64.lt_s (local.get $value) (i64.const 0)))
+            (then
+                (i32.store8 (local.get $dest) (i32.const 45)) ;; '-'
+                (local.set $dest (i32.add (local.get $dest) (i32.const 1)))
+                (local.set $value (i64.sub (i64.const 0) (local.get $value)))))
+        ;; Count the digits to find where the last one goes
+        (local.set $end (local.get $dest))
+        (local.set $rest (local.get $value))
+        (loop $count
+            (local.set $end (i32.add (local.get $end) (i32.const 1)))
+            (local.set $rest (i64.div_u (local.get $rest) (local.get $radix)))
+            (br_if $count (i64.ne (local.get $rest) (i64.const 0))))
+        ;; Write the digits from the last to the first
+        (local.set $dest (local.get $end))
+        (loop $write
+            (local.set $dest (i32.sub (local.get $dest) (i32.const 1)))
+            (local.set $digit
+                (i32.wrap_i64 (i64.rem_u (local.get $value) (local.get $radix))))
+            ;; '0' to '9' then 'a' to 'f'
+            (i32.store8 (local.get $dest)
+                (i32.add
+                    (local.get $digit)
+                    (select
+                        (i32.const 48)
+                        (i32.const 87)
+                        (i32.lt_u (local.get $digit) (i32.const 10)))))
+            (local.set $value (i64.div_u (local.get $value) (local.get $radix)))
+            (br_if $write (i64.ne (local.get $value) (i64.const 0))))
+        (i32.sub (local.get $end) (local.get $start))
+    )
+    ;; Write a float with `$precision` decimal places at `$dest`, returning
+    ;; the number of bytes written. When `$trim` is set trailing zeros are
+    ;; dropped, along with the point if they all are.
+    ;;
+    ;; Whole parts beyond the range of a u64 are saturated, so at most
+    ;; 22 + `$precision` bytes are written.
+    (func $float (export "float")
+        (param $value f64)
+        (param $precision i32)
+        (param $trim i32)
+        (param $dest i32)
+        (result i32)
+        (local $start i32)
+        (local $scale i64)
+        (local $whole i64)
+        (local $fraction i64)
+        (local $i i32)
+        local.get $dest
+        local.set $start
+        (if (f64.ne (local.get $value) (local.get $value))
+            (then
+                ;; "NaN"
+                (i32.store8 offset=0 (local.get $dest) (i32.const 78))
+                (i32.store8 offset=1 (local.get $dest) (i32.const 97))
+                (i32.store8 offset=2 (local.get $dest) (i32.const 78))
+                (return (i32.const 3))))
+        (if (f64.lt (f64.copysign (f64.const 1) (local.get $value)) (f64.const 0))
+            (then
+                (i32.store8 (local.get $dest) (i32.const 45)) ;; '-'
+                (local.set $dest (i32.add (local.get $dest) (i32.const 1)))
+                (local.set $value (f64.neg (local.get $value)))))
+        (if (f64.eq (local.get $value) (f64.const inf))
+            (then
+                ;; "inf"
+                (i32.store8 offset=0 (local.get $dest) (i32.const 105))
+                (i32.store8 offset=1 (local.get $dest) (i32.const 110))
+                (i32.store8 offset=2 (local.get $dest) (i32.const 102))
+                (return (i32.sub (i32.add (local.get $dest) (i32.const 3)) (local.get $start)))))
+        ;; 10 to the power of the precision
+        (local.set $scale (i64.const 1))
+        (local.set $i (local.get $precision))
+        (block $done
+            (loop $scale
+                (br_if $done (i32.eqz (local.get $i)))
+                (local.set $scale (i64.mul (local.get $scale) (i64.const 10)))
+                (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+                (br $scale)))
+        (local.set $whole (i64.trunc_sat_f64_u (local.get $value)))
+        (local.set $fraction
+            (i64.trunc_sat_f64_u
+                (f64.nearest
+                    (f64.mul
+                        (f64.sub (local.get $value) (f64.trunc (local.get $value)))
+                        (f64.convert_i64_u (local.get $scale))))))
+        ;; Rounding the fraction up can carry into the whole part
+        (if (i64.ge_u (local.get $fraction) (local.get $scale))
+            (then
+                (local.set $fraction (i64.sub (local.get $fraction) (local.get $scale)))
+                (local.set $whole (i64.add (local.get $whole) (i64.const 1)))))
+        (local.set $dest
+            (i32.add
+                (local.get $dest)
+                (call $int (local.get $whole) (i32.const 0) (i64.const 10) (local.get $dest))))
+        (if (local.get $trim)
+            (then
+                (block $trimmed
+                    (loop $trim
+                        (br_if $trimmed (i32.eqz (local.get $precision)))
+                        (br_if $trimmed
+                            (i64.ne (i64.rem_u (local.get $fraction) (i64.const 10)) (i64.const 0)))
+                        (local.set $fraction (i64.div_u (local.get $fraction) (i64.const 10)))
+                        (local.set $precision (i32.sub (local.get $precision) (i32.const 1)))
+                        (br $trim)))))
+        (if (i32.eqz (local.get $precision))
+            (then (return (i32.sub (local.get $dest) (local.get $start)))))
+        (i32.store8 (local.get $dest) (i32.const 46)) ;; '.'
+        (local.set $dest (i32.add (local.get $dest) (i32.const 1)))
+        ;; The fraction is padded with leading zeros to the precision
+        (local.set $i (local.get $precision))
+        (loop $write
+            (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+            (i32.store8
+                (i32.add (local.get $dest) (local.get $i))
+                (i32.add
+                    (i32.const 48)
+                    (i32.wrap_i64 (i64.rem_u (local.get $fraction) (i64.const 10)))))
+            (local.set $fraction (i64.div_u (local.get $fraction) (i64.const 10)))
+            (br_if $write (local.get $i)))
+        (i32.sub (i32.add (local.get $dest) (local.get $precision)) (local.get $start))
+    )
+)
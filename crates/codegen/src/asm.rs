@@ -0,0 +1,247 @@
+use ast::ExpressionId;
+use claw_ast as ast;
+use wasm_encoder as enc;
+use wasmparser::{Operator, Parser, Payload, Validator};
+
+use crate::code::{CodeGenerator, ExpressionAllocator};
+use crate::expression::EncodeExpression;
+use crate::GenerationError;
+
+/// Inline assembly is lowered by wrapping its body in a standalone module
+///
+/// ```wat
+/// (module
+///     (memory 0)
+///     (func (param $a i32) (result i32) (local $t i32)
+///         BODY))
+/// ```
+///
+/// where each input field is a parameter and each clobber field is a local.
+/// The module is validated and then the body's instructions are copied into
+/// the enclosing function with their local indices remapped.
+impl EncodeExpression for ast::InlineAsm {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc(expression)?;
+        // Clobbers are laid out directly after the result locals
+        for (_name, type_id) in self.clobbers.iter() {
+            allocator.alloc_type(*type_id)?;
+        }
+        for input in self.inputs.iter() {
+            allocator.alloc_child(input.expression)?;
+        }
+        Ok(())
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        for input in self.inputs.iter() {
+            code_gen.encode_child(input.expression)?;
+        }
+
+        // Maps the locals of the wrapper function to locals of the enclosing function
+        let mut local_map = Vec::new();
+
+        let mut params = Vec::new();
+        for input in self.inputs.iter() {
+            let name = code_gen.lookup_name_str(input.ident).to_owned();
+            let fields = code_gen.fields(input.expression)?;
+            let base_index = code_gen.expr_local_index(input.expression);
+            for field in fields.iter() {
+                params.push((
+                    field_name(&name, field.index_offset, fields.len()),
+                    field.stack_type,
+                ));
+                local_map.push(base_index + field.index_offset);
+            }
+        }
+
+        let fields = code_gen.fields(expression)?;
+        let results: Vec<enc::ValType> = fields.iter().map(|f| f.stack_type).collect();
+
+        let mut locals = Vec::new();
+        let mut clobber_index = code_gen.expr_local_index(expression) + results.len() as u32;
+        for (name, type_id) in self.clobbers.iter() {
+            let name = code_gen.lookup_name_str(*name).to_owned();
+            let valtypes = code_gen.flatten_type(*type_id);
+            for (i, valtype) in valtypes.iter().enumerate() {
+                locals.push((field_name(&name, i as u32, valtypes.len()), *valtype));
+                local_map.push(clobber_index);
+                clobber_index += 1;
+            }
+        }
+
+        let wat = wrapper_module(&params, &results, &locals, &self.body)
+            .map_err(|message| code_gen.inline_asm_error(expression, message))?;
+        let wasm = match wat::parse_str(wat) {
+            Ok(wasm) => wasm,
+            Err(err) => {
+                // Only the first line is kept, the rest points into the wrapper module
+                let message = err.to_string();
+                let message = message.lines().next().unwrap_or_default();
+                return Err(code_gen.inline_asm_error(expression, message));
+            }
+        };
+        if let Err(err) = Validator::new().validate_all(&wasm) {
+            return Err(code_gen.inline_asm_error(expression, err.message()));
+        }
+
+        for payload in Parser::new(0).parse_all(&wasm) {
+            let payload =
+                payload.map_err(|err| code_gen.inline_asm_error(expression, err.message()))?;
+            if let Payload::CodeSectionEntry(body) = payload {
+                copy_body(&wasm, body, &local_map, expression, code_gen)?;
+            }
+        }
+
+        // The body leaves the result fields on the stack in order
+        for field in fields.iter().rev() {
+            code_gen.write_expr_field(expression, field);
+        }
+        Ok(())
+    }
+}
+
+fn field_name(name: &str, index: u32, count: usize) -> String {
+    if count == 1 {
+        format!("${}", name)
+    } else {
+        format!("${}.{}", name, index)
+    }
+}
+
+fn wrapper_module(
+    params: &[(String, enc::ValType)],
+    results: &[enc::ValType],
+    locals: &[(String, enc::ValType)],
+    body: &str,
+) -> Result<String, &'static str> {
+    let mut wat = String::from("(module (memory 0) (func");
+    for (name, valtype) in params.iter() {
+        wat.push_str(&format!(" (param {} {})", name, valtype_name(*valtype)?));
+    }
+    for valtype in results.iter() {
+        wat.push_str(&format!(" (result {})", valtype_name(*valtype)?));
+    }
+    for (name, valtype) in locals.iter() {
+        wat.push_str(&format!(" (local {} {})", name, valtype_name(*valtype)?));
+    }
+    wat.push('\n');
+    wat.push_str(body);
+    wat.push_str("\n))");
+    Ok(wat)
+}
+
+fn valtype_name(valtype: enc::ValType) -> Result<&'static str, &'static str> {
+    match valtype {
+        enc::ValType::I32 => Ok("i32"),
+        enc::ValType::I64 => Ok("i64"),
+        enc::ValType::F32 => Ok("f32"),
+        enc::ValType::F64 => Ok("f64"),
+        enc::ValType::V128 => Ok("v128"),
+        enc::ValType::Ref(_) => Err("Reference types are not supported in inline asm"),
+    }
+}
+
+fn copy_body(
+    wasm: &[u8],
+    body: wasmparser::FunctionBody,
+    local_map: &[u32],
+    expression: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let mut reader = body
+        .get_operators_reader()
+        .map_err(|err| code_gen.inline_asm_error(expression, err.message()))?;
+    // Depth of the blocks opened by the body, used to keep branches inside it
+    let mut depth: u32 = 0;
+    while !reader.eof() {
+        let (op, start) = reader
+            .read_with_offset()
+            .map_err(|err| code_gen.inline_asm_error(expression, err.message()))?;
+        let end = reader.original_position();
+
+        let unsupported = |what: &str| format!("{} is not allowed in inline asm", what);
+
+        match op {
+            Operator::LocalGet { local_index } => {
+                code_gen.instruction(&enc::Instruction::LocalGet(local_map[local_index as usize]))
+            }
+            Operator::LocalSet { local_index } => {
+                code_gen.instruction(&enc::Instruction::LocalSet(local_map[local_index as usize]))
+            }
+            Operator::LocalTee { local_index } => {
+                code_gen.instruction(&enc::Instruction::LocalTee(local_map[local_index as usize]))
+            }
+            Operator::Block { blockty } | Operator::Loop { blockty } | Operator::If { blockty } => {
+                if let wasmparser::BlockType::FuncType(_) = blockty {
+                    let message = unsupported("A block with a type index");
+                    return Err(code_gen.inline_asm_error(expression, &message));
+                }
+                depth += 1;
+                code_gen.raw(&wasm[start..end]);
+            }
+            Operator::End => {
+                if depth == 0 {
+                    // The final end belongs to the wrapper function
+                    break;
+                }
+                depth -= 1;
+                code_gen.raw(&wasm[start..end]);
+            }
+            Operator::Br { relative_depth } | Operator::BrIf { relative_depth }
+                if relative_depth >= depth =>
+            {
+                let message = "Branches may not leave the asm block";
+                return Err(code_gen.inline_asm_error(expression, message));
+            }
+            Operator::BrTable { targets } => {
+                let mut escapes = targets.default() >= depth;
+                for target in targets.targets() {
+                    let target = target
+                        .map_err(|err| code_gen.inline_asm_error(expression, err.message()))?;
+                    escapes |= target >= depth;
+                }
+                if escapes {
+                    let message = "Branches may not leave the asm block";
+                    return Err(code_gen.inline_asm_error(expression, message));
+                }
+                code_gen.raw(&wasm[start..end]);
+            }
+            Operator::Return => {
+                return Err(code_gen.inline_asm_error(expression, &unsupported("return")))
+            }
+            Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. } => {
+                return Err(code_gen.inline_asm_error(expression, &unsupported("Calling")))
+            }
+            Operator::GlobalGet { .. } | Operator::GlobalSet { .. } => {
+                return Err(code_gen.inline_asm_error(expression, &unsupported("Using globals")))
+            }
+            Operator::MemoryInit { .. } | Operator::DataDrop { .. } => {
+                let message = unsupported("Using data segments");
+                return Err(code_gen.inline_asm_error(expression, &message));
+            }
+            Operator::TableGet { .. }
+            | Operator::TableSet { .. }
+            | Operator::TableGrow { .. }
+            | Operator::TableSize { .. }
+            | Operator::TableFill { .. }
+            | Operator::TableCopy { .. }
+            | Operator::TableInit { .. }
+            | Operator::ElemDrop { .. } => {
+                return Err(code_gen.inline_asm_error(expression, &unsupported("Using tables")))
+            }
+            _ => code_gen.raw(&wasm[start..end]),
+        }
+    }
+    Ok(())
+}
@@ -25,6 +25,18 @@ pub struct ComponentModuleInstanceIndex(u32);
 #[derive(Clone, Copy, Debug)]
 pub struct ComponentTypeIndex(u32);
 
+impl From<ComponentTypeIndex> for u32 {
+    fn from(value: ComponentTypeIndex) -> Self {
+        value.0
+    }
+}
+
+impl From<ComponentTypeIndex> for enc::ComponentValType {
+    fn from(value: ComponentTypeIndex) -> Self {
+        enc::ComponentValType::Type(value.0)
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct ComponentFunctionIndex(u32);
 
@@ -119,6 +131,42 @@ impl ComponentBuilder {
         self.next_type_idx()
     }
 
+    pub fn option_type(&mut self, some: enc::ComponentValType) -> ComponentTypeIndex {
+        let mut section = enc::ComponentTypeSection::new();
+        section.defined_type().option(some);
+        self.component.section(&section);
+        self.next_type_idx()
+    }
+
+    pub fn list_type(&mut self, element: enc::ComponentValType) -> ComponentTypeIndex {
+        let mut section = enc::ComponentTypeSection::new();
+        section.defined_type().list(element);
+        self.component.section(&section);
+        self.next_type_idx()
+    }
+
+    pub fn record_type<'b, F>(&mut self, fields: F) -> ComponentTypeIndex
+    where
+        F: IntoIterator<Item = (&'b str, enc::ComponentValType)>,
+        F::IntoIter: ExactSizeIterator,
+    {
+        let mut section = enc::ComponentTypeSection::new();
+        section.defined_type().record(fields);
+        self.component.section(&section);
+        self.next_type_idx()
+    }
+
+    pub fn enum_type<'b, C>(&mut self, cases: C) -> ComponentTypeIndex
+    where
+        C: IntoIterator<Item = &'b str>,
+        C::IntoIter: ExactSizeIterator,
+    {
+        let mut section = enc::ComponentTypeSection::new();
+        section.defined_type().enum_type(cases);
+        self.component.section(&section);
+        self.next_type_idx()
+    }
+
     pub fn instance_type(&mut self, instance_type: &enc::InstanceType) -> ComponentTypeIndex {
         let mut section = enc::ComponentTypeSection::new();
         section.instance(instance_type);
@@ -211,6 +259,28 @@ impl ComponentBuilder {
         self.next_func_idx()
     }
 
+    pub fn alias_type(
+        &mut self,
+        instance: ComponentInstanceIndex,
+        name: &str,
+    ) -> ComponentTypeIndex {
+        let mut section = enc::ComponentAliasSection::new();
+        section.alias(enc::Alias::InstanceExport {
+            instance: instance.0,
+            kind: enc::ComponentExportKind::Type,
+            name,
+        });
+        self.component.section(&section);
+        self.next_type_idx()
+    }
+
+    pub fn resource_drop(&mut self, resource: ComponentTypeIndex) -> ComponentCoreFunctionIndex {
+        let mut section = enc::CanonicalFunctionSection::new();
+        section.resource_drop(resource.0);
+        self.component.section(&section);
+        self.next_core_func_idx()
+    }
+
     pub fn lift_func(
         &mut self,
         func: ComponentCoreFunctionIndex,
@@ -247,6 +317,14 @@ impl ComponentBuilder {
         self.next_func_idx()
     }
 
+    /// Exporting a type gives it a new index, which exported functions must use
+    pub fn export_type(&mut self, name: &str, ty: ComponentTypeIndex) -> ComponentTypeIndex {
+        let mut section = enc::ComponentExportSection::new();
+        section.export(name, enc::ComponentExportKind::Type, ty.0, None);
+        self.component.section(&section);
+        self.next_type_idx()
+    }
+
     pub fn finalize(self) -> enc::Component {
         self.component
     }
@@ -1,18 +1,29 @@
+use claw_ast::Span;
 use wasm_encoder as enc;
 
+use crate::source_map;
+
 #[derive(Default)]
 pub struct ModuleBuilder {
     types: enc::TypeSection,
     imports: enc::ImportSection,
     funcs: enc::FunctionSection,
+    tables: enc::TableSection,
     globals: enc::GlobalSection,
     exports: enc::ExportSection,
+    elements: enc::ElementSection,
     data: enc::DataSection,
+    function_names: enc::NameMap,
 
     code: Vec<Option<enc::Function>>,
+    /// Where the code for each piece of source starts in each function body
+    locations: Vec<Vec<(usize, Span)>>,
+    /// The names of the source files, when a source map is wanted
+    source_files: Option<Vec<String>>,
 
     num_types: u32,
     num_funcs: u32,
+    num_tables: u32,
     num_memories: u32,
     num_globals: u32,
     num_data: u32,
@@ -24,6 +35,9 @@ pub struct ModuleTypeIndex(u32);
 #[derive(Clone, Copy, Debug)]
 pub struct ModuleFunctionIndex(u32);
 
+#[derive(Clone, Copy, Debug)]
+pub struct ModuleTableIndex(u32);
+
 #[allow(dead_code)]
 #[derive(Clone, Copy, Debug)]
 pub struct ModuleMemoryIndex(u32);
@@ -35,12 +49,24 @@ pub struct ModuleGlobalIndex(u32);
 #[derive(Clone, Copy, Debug)]
 pub struct ModuleDataIndex(u32);
 
+impl From<ModuleTypeIndex> for u32 {
+    fn from(value: ModuleTypeIndex) -> Self {
+        value.0
+    }
+}
+
 impl From<ModuleFunctionIndex> for u32 {
     fn from(value: ModuleFunctionIndex) -> Self {
         value.0
     }
 }
 
+impl From<ModuleTableIndex> for u32 {
+    fn from(value: ModuleTableIndex) -> Self {
+        value.0
+    }
+}
+
 impl From<ModuleDataIndex> for u32 {
     fn from(value: ModuleDataIndex) -> Self {
         value.0
@@ -82,15 +108,22 @@ impl ModuleBuilder {
         let fn_type = enc::EntityType::Function(fn_type.0);
         self.imports.import(module, field, fn_type);
         self.code.push(None);
+        self.locations.push(Vec::new());
         self.next_func_idx()
     }
 
     pub fn function(&mut self, fn_type: ModuleTypeIndex) -> ModuleFunctionIndex {
         self.funcs.function(fn_type.0);
         self.code.push(None);
+        self.locations.push(Vec::new());
         self.next_func_idx()
     }
 
+    /// Name a function in the name section, in increasing index order
+    pub fn func_name(&mut self, func: ModuleFunctionIndex, name: &str) {
+        self.function_names.append(func.0, name);
+    }
+
     pub fn code(&mut self, func: ModuleFunctionIndex, code: enc::Function) {
         let index = func.0 as usize;
         match self.code[index] {
@@ -101,6 +134,34 @@ impl ModuleBuilder {
         }
     }
 
+    /// Record where the code for each piece of source starts in a function's body
+    pub fn locations(&mut self, func: ModuleFunctionIndex, locations: Vec<(usize, Span)>) {
+        self.locations[func.0 as usize] = locations;
+    }
+
+    /// Add a section mapping the code back to the source, whose files have the given names
+    pub fn source_map(&mut self, files: Vec<String>) {
+        self.source_files = Some(files);
+    }
+
+    /// Define a table holding exactly the given functions, in order
+    pub fn func_table(&mut self, funcs: &[ModuleFunctionIndex]) -> ModuleTableIndex {
+        let table_type = enc::TableType {
+            element_type: enc::RefType::FUNCREF,
+            table64: false,
+            minimum: funcs.len() as u64,
+            maximum: Some(funcs.len() as u64),
+        };
+        self.tables.table(table_type);
+        let index = self.next_table_idx();
+
+        let funcs: Vec<u32> = funcs.iter().map(|func| func.0).collect();
+        let offset = enc::ConstExpr::i32_const(0);
+        self.elements
+            .active(Some(index.0), &offset, enc::Elements::Functions(&funcs));
+        index
+    }
+
     pub fn global(
         &mut self,
         mutable: bool,
@@ -130,8 +191,10 @@ impl ModuleBuilder {
         module.section(&self.types);
         module.section(&self.imports);
         module.section(&self.funcs);
+        module.section(&self.tables);
         module.section(&self.globals);
         module.section(&self.exports);
+        module.section(&self.elements);
 
         if self.num_data > 0 {
             module.section(&enc::DataCountSection {
@@ -139,20 +202,43 @@ impl ModuleBuilder {
             });
         }
 
-        // Encode code sections
+        // Encode code sections, keeping track of where each body starts
+        // relative to the first byte after the section's function count
+        let code_start = module.as_slice().len();
         let mut code = enc::CodeSection::new();
-        for func in self.code.into_iter() {
+        let mut locations = Vec::new();
+        for (func, func_locations) in self.code.into_iter().zip(self.locations) {
             match func {
                 Some(func) => {
+                    let body = code.byte_len() + source_map::leb_len(func.byte_len());
+                    let offsets = func_locations.into_iter();
+                    locations.extend(offsets.map(|(offset, span)| (body + offset, span)));
                     code.function(&func);
                 }
                 None => {}
             }
         }
         module.section(&code);
+        // The section starts with its id, its size and the function count
+        let count_len = source_map::leb_len(code.len() as usize);
+        let header = 1 + source_map::leb_len(count_len + code.byte_len()) + count_len;
+        let locations = locations
+            .into_iter()
+            .map(|(offset, span)| (code_start + header + offset, span));
+        let source_map = self
+            .source_files
+            .map(|files| source_map::encode(&files, locations));
         if self.num_data > 0 {
             module.section(&self.data);
         }
+        if !self.function_names.is_empty() {
+            let mut names = enc::NameSection::new();
+            names.functions(&self.function_names);
+            module.section(&names);
+        }
+        if let Some(source_map) = source_map {
+            module.section(&source_map);
+        }
 
         module
     }
@@ -169,6 +255,12 @@ impl ModuleBuilder {
         index
     }
 
+    fn next_table_idx(&mut self) -> ModuleTableIndex {
+        let index = ModuleTableIndex(self.num_tables);
+        self.num_tables += 1;
+        index
+    }
+
     fn next_memory_idx(&mut self) -> ModuleMemoryIndex {
         let index = ModuleMemoryIndex(self.num_memories);
         self.num_memories += 1;
@@ -0,0 +1,625 @@
+use ast::ExpressionId;
+use claw_ast as ast;
+use claw_resolver::builtins::Builtin;
+
+use crate::code::CodeGenerator;
+use crate::expression::{encode_bounds_check, encode_element_address};
+use crate::format;
+use crate::types::{
+    FieldInfo, Signedness, LIST_LENGTH_FIELD, LIST_POINTER_FIELD, STRING_LENGTH_FIELD,
+    STRING_OFFSET_FIELD, U32_FIELD,
+};
+use crate::GenerationError;
+
+use wasm_encoder as enc;
+use wasm_encoder::Instruction;
+
+/// Lower a call to a builtin into the instructions implementing it.
+///
+/// Apart from printing and storing, builtins have no side effects so when
+/// the result is unused nothing is emitted.
+pub fn encode_builtin_call(
+    builtin: Builtin,
+    args: &[ExpressionId],
+    expression: Option<ExpressionId>,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    if let Builtin::Println | Builtin::Eprintln = builtin {
+        code_gen.read_expr_field(args[0], &STRING_OFFSET_FIELD);
+        code_gen.read_expr_field(args[0], &STRING_LENGTH_FIELD);
+        code_gen.call_println(builtin == Builtin::Eprintln);
+        return Ok(());
+    }
+    if let Some(width) = builtin.access_width() {
+        return encode_access(builtin, width, args, expression, code_gen);
+    }
+    let expression = match expression {
+        Some(expression) => expression,
+        None => return Ok(()),
+    };
+    match builtin {
+        Builtin::CheckedAdd | Builtin::CheckedSub | Builtin::CheckedMul => {
+            return encode_checked_arithmetic(builtin, args, expression, code_gen)
+        }
+        Builtin::IsSome => {
+            let discriminant = code_gen.fields(args[0])?.remove(0);
+            let field = code_gen.one_field(expression)?;
+            code_gen.read_expr_field(args[0], &discriminant);
+            code_gen.write_expr_field(expression, &field);
+            return Ok(());
+        }
+        Builtin::UnwrapOr => return encode_unwrap_or(args, expression, code_gen),
+        Builtin::Len => {
+            let field = code_gen.one_field(expression)?;
+            code_gen.read_expr_field(args[0], &LIST_LENGTH_FIELD);
+            code_gen.write_expr_field(expression, &field);
+            return Ok(());
+        }
+        Builtin::Get => return encode_get(args, expression, code_gen),
+        Builtin::Format => return format::encode_format(args, expression, code_gen),
+        _ => {}
+    }
+
+    let field = code_gen.one_field(expression)?;
+    let is_int = matches!(field.stack_type, enc::ValType::I32 | enc::ValType::I64);
+
+    match builtin {
+        Builtin::Rotl | Builtin::Rotr | Builtin::Clz | Builtin::Ctz | Builtin::Popcount => {
+            match field.arith_mask {
+                Some(mask) => encode_narrow_bit_op(builtin, args, &field, mask, code_gen),
+                None => encode_instruction(builtin, args, &field, code_gen),
+            }
+        }
+        Builtin::Abs | Builtin::Min | Builtin::Max if is_int => {
+            encode_int_math(builtin, args, &field, code_gen)
+        }
+        _ => encode_instruction(builtin, args, &field, code_gen),
+    }
+
+    code_gen.write_expr_field(expression, &field);
+    Ok(())
+}
+
+/// Builtins which map directly to a single instruction
+fn encode_instruction(
+    builtin: Builtin,
+    args: &[ExpressionId],
+    field: &FieldInfo,
+    code_gen: &mut CodeGenerator,
+) {
+    for arg in args.iter() {
+        code_gen.read_expr_field(*arg, field);
+    }
+    let instruction = match (builtin, field.stack_type) {
+        // Bit manipulation
+        (Builtin::Rotl, enc::ValType::I32) => Instruction::I32Rotl,
+        (Builtin::Rotl, enc::ValType::I64) => Instruction::I64Rotl,
+        (Builtin::Rotr, enc::ValType::I32) => Instruction::I32Rotr,
+        (Builtin::Rotr, enc::ValType::I64) => Instruction::I64Rotr,
+        (Builtin::Clz, enc::ValType::I32) => Instruction::I32Clz,
+        (Builtin::Clz, enc::ValType::I64) => Instruction::I64Clz,
+        (Builtin::Ctz, enc::ValType::I32) => Instruction::I32Ctz,
+        (Builtin::Ctz, enc::ValType::I64) => Instruction::I64Ctz,
+        (Builtin::Popcount, enc::ValType::I32) => Instruction::I32Popcnt,
+        (Builtin::Popcount, enc::ValType::I64) => Instruction::I64Popcnt,
+        // Float math
+        (Builtin::Sqrt, enc::ValType::F32) => Instruction::F32Sqrt,
+        (Builtin::Sqrt, enc::ValType::F64) => Instruction::F64Sqrt,
+        (Builtin::Abs, enc::ValType::F32) => Instruction::F32Abs,
+        (Builtin::Abs, enc::ValType::F64) => Instruction::F64Abs,
+        (Builtin::Min, enc::ValType::F32) => Instruction::F32Min,
+        (Builtin::Min, enc::ValType::F64) => Instruction::F64Min,
+        (Builtin::Max, enc::ValType::F32) => Instruction::F32Max,
+        (Builtin::Max, enc::ValType::F64) => Instruction::F64Max,
+        (Builtin::Floor, enc::ValType::F32) => Instruction::F32Floor,
+        (Builtin::Floor, enc::ValType::F64) => Instruction::F64Floor,
+        (Builtin::Ceil, enc::ValType::F32) => Instruction::F32Ceil,
+        (Builtin::Ceil, enc::ValType::F64) => Instruction::F64Ceil,
+        (Builtin::Trunc, enc::ValType::F32) => Instruction::F32Trunc,
+        (Builtin::Trunc, enc::ValType::F64) => Instruction::F64Trunc,
+        (Builtin::Nearest, enc::ValType::F32) => Instruction::F32Nearest,
+        (Builtin::Nearest, enc::ValType::F64) => Instruction::F64Nearest,
+        (Builtin::Copysign, enc::ValType::F32) => Instruction::F32Copysign,
+        (Builtin::Copysign, enc::ValType::F64) => Instruction::F64Copysign,
+        // Fallback
+        (builtin, valtype) => panic!(
+            "Cannot apply builtin {} to type {:?}",
+            builtin.name(),
+            valtype
+        ),
+    };
+    code_gen.instruction(&instruction);
+}
+
+/// 8 and 16-bit integers are stored masked in an i32 so the 32-bit
+/// instructions have to be adjusted to the narrower width.
+fn encode_narrow_bit_op(
+    builtin: Builtin,
+    args: &[ExpressionId],
+    field: &FieldInfo,
+    mask: i32,
+    code_gen: &mut CodeGenerator,
+) {
+    let bits = mask.count_ones() as i32;
+    match builtin {
+        Builtin::Rotl | Builtin::Rotr => {
+            let (value, amount) = (args[0], args[1]);
+            let (towards, away) = match builtin {
+                Builtin::Rotl => (Instruction::I32Shl, Instruction::I32ShrU),
+                _ => (Instruction::I32ShrU, Instruction::I32Shl),
+            };
+            // value shifted by (amount % bits)
+            code_gen.read_expr_field(value, field);
+            code_gen.read_expr_field(amount, field);
+            code_gen.const_i32(bits - 1);
+            code_gen.instruction(&Instruction::I32And);
+            code_gen.instruction(&towards);
+            // value shifted the other way by (bits - amount % bits)
+            code_gen.read_expr_field(value, field);
+            code_gen.const_i32(bits);
+            code_gen.read_expr_field(amount, field);
+            code_gen.const_i32(bits - 1);
+            code_gen.instruction(&Instruction::I32And);
+            code_gen.instruction(&Instruction::I32Sub);
+            code_gen.instruction(&away);
+            // combined and truncated
+            code_gen.instruction(&Instruction::I32Or);
+            code_gen.const_i32(mask);
+            code_gen.instruction(&Instruction::I32And);
+        }
+        Builtin::Clz => {
+            code_gen.read_expr_field(args[0], field);
+            code_gen.instruction(&Instruction::I32Clz);
+            code_gen.const_i32(32 - bits);
+            code_gen.instruction(&Instruction::I32Sub);
+        }
+        Builtin::Ctz => {
+            // Setting the bit just past the width caps the count for zero
+            code_gen.read_expr_field(args[0], field);
+            code_gen.const_i32(1 << bits);
+            code_gen.instruction(&Instruction::I32Or);
+            code_gen.instruction(&Instruction::I32Ctz);
+        }
+        Builtin::Popcount => {
+            code_gen.read_expr_field(args[0], field);
+            code_gen.instruction(&Instruction::I32Popcnt);
+        }
+        _ => unreachable!("{} is not a bit manipulation builtin", builtin.name()),
+    }
+}
+
+/// Integer `abs`, `min`, and `max` have no instructions so they are built from `select`
+fn encode_int_math(
+    builtin: Builtin,
+    args: &[ExpressionId],
+    field: &FieldInfo,
+    code_gen: &mut CodeGenerator,
+) {
+    let is_64 = field.stack_type == enc::ValType::I64;
+    let signed = field.signedness == Signedness::Signed;
+    match builtin {
+        Builtin::Abs => {
+            let value = args[0];
+            let (zero, sub, less_than) = if is_64 {
+                (
+                    Instruction::I64Const(0),
+                    Instruction::I64Sub,
+                    Instruction::I64LtS,
+                )
+            } else {
+                (
+                    Instruction::I32Const(0),
+                    Instruction::I32Sub,
+                    Instruction::I32LtS,
+                )
+            };
+            // negated value
+            code_gen.instruction(&zero);
+            read_extended(value, field, code_gen);
+            code_gen.instruction(&sub);
+            // original value
+            read_extended(value, field, code_gen);
+            // select the negated value when negative
+            read_extended(value, field, code_gen);
+            code_gen.instruction(&zero);
+            code_gen.instruction(&less_than);
+            code_gen.instruction(&Instruction::Select);
+        }
+        Builtin::Min | Builtin::Max => {
+            let (left, right) = (args[0], args[1]);
+            read_extended(left, field, code_gen);
+            read_extended(right, field, code_gen);
+            // select the left value when it is on the correct side of the right
+            read_extended(left, field, code_gen);
+            read_extended(right, field, code_gen);
+            let instruction = match (builtin, is_64, signed) {
+                (Builtin::Min, false, true) => Instruction::I32LtS,
+                (Builtin::Min, false, false) => Instruction::I32LtU,
+                (Builtin::Min, true, true) => Instruction::I64LtS,
+                (Builtin::Min, true, false) => Instruction::I64LtU,
+                (_, false, true) => Instruction::I32GtS,
+                (_, false, false) => Instruction::I32GtU,
+                (_, true, true) => Instruction::I64GtS,
+                (_, true, false) => Instruction::I64GtU,
+            };
+            code_gen.instruction(&instruction);
+            code_gen.instruction(&Instruction::Select);
+        }
+        _ => unreachable!("{} is not an integer math builtin", builtin.name()),
+    }
+    if let Some(mask) = field.arith_mask {
+        code_gen.const_i32(mask);
+        code_gen.instruction(&Instruction::I32And);
+    }
+}
+
+/// Read a field, sign-extending narrow signed integers so they compare correctly
+pub fn read_extended(expression: ExpressionId, field: &FieldInfo, code_gen: &mut CodeGenerator) {
+    code_gen.read_expr_field(expression, field);
+    match (field.signedness, field.arith_mask) {
+        (Signedness::Signed, Some(0xFF)) => code_gen.instruction(&Instruction::I32Extend8S),
+        (Signedness::Signed, Some(0xFFFF)) => code_gen.instruction(&Instruction::I32Extend16S),
+        _ => {}
+    }
+}
+
+/// The operation is performed wrapping into the payload, then an overflow test
+/// branches on whether the result is `some` or `none`
+fn encode_checked_arithmetic(
+    builtin: Builtin,
+    args: &[ExpressionId],
+    expression: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let fields = code_gen.fields(expression)?;
+    let (discriminant, payload) = (&fields[0], &fields[1]);
+    let checked = CheckedOperation {
+        builtin,
+        left: args[0],
+        right: args[1],
+        operand: code_gen.one_field(args[0])?,
+        expression,
+        payload,
+    };
+
+    // Each of these leaves the overflow flag on the stack
+    match payload.arith_mask {
+        Some(mask) => checked.encode_narrow(mask, code_gen),
+        None if builtin == Builtin::CheckedMul => checked.encode_mul(code_gen),
+        None => checked.encode_add_sub(code_gen),
+    }
+
+    code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+    // none
+    code_gen.const_i32(0);
+    code_gen.write_expr_field(expression, discriminant);
+    code_gen.encode_const_int(0, payload);
+    code_gen.write_expr_field(expression, payload);
+    code_gen.instruction(&Instruction::Else);
+    // some
+    code_gen.const_i32(1);
+    code_gen.write_expr_field(expression, discriminant);
+    if let Some(mask) = payload.arith_mask {
+        code_gen.read_expr_field(expression, payload);
+        code_gen.const_i32(mask);
+        code_gen.instruction(&Instruction::I32And);
+        code_gen.write_expr_field(expression, payload);
+    }
+    code_gen.instruction(&Instruction::End);
+    Ok(())
+}
+
+struct CheckedOperation<'a> {
+    builtin: Builtin,
+    left: ExpressionId,
+    right: ExpressionId,
+    operand: FieldInfo,
+    expression: ExpressionId,
+    payload: &'a FieldInfo,
+}
+
+impl CheckedOperation<'_> {
+    fn read_left(&self, code_gen: &mut CodeGenerator) {
+        code_gen.read_expr_field(self.left, &self.operand);
+    }
+
+    fn read_right(&self, code_gen: &mut CodeGenerator) {
+        code_gen.read_expr_field(self.right, &self.operand);
+    }
+
+    fn read_result(&self, code_gen: &mut CodeGenerator) {
+        code_gen.read_expr_field(self.expression, self.payload);
+    }
+
+    fn write_result(&self, code_gen: &mut CodeGenerator) {
+        code_gen.write_expr_field(self.expression, self.payload);
+    }
+
+    fn is_signed(&self) -> bool {
+        self.operand.signedness == Signedness::Signed
+    }
+
+    /// 8 and 16-bit operations can't overflow an i32, so the full result
+    /// is compared against the range of the narrower type
+    fn encode_narrow(&self, mask: i32, code_gen: &mut CodeGenerator) {
+        read_extended(self.left, &self.operand, code_gen);
+        read_extended(self.right, &self.operand, code_gen);
+        code_gen.instruction(&match self.builtin {
+            Builtin::CheckedAdd => Instruction::I32Add,
+            Builtin::CheckedSub => Instruction::I32Sub,
+            _ => Instruction::I32Mul,
+        });
+        self.write_result(code_gen);
+
+        if self.is_signed() {
+            // Overflowed when sign-extending the truncated result changes it
+            self.read_result(code_gen);
+            self.read_result(code_gen);
+            code_gen.instruction(&match mask {
+                0xFF => Instruction::I32Extend8S,
+                _ => Instruction::I32Extend16S,
+            });
+            code_gen.instruction(&Instruction::I32Ne);
+        } else {
+            self.read_result(code_gen);
+            code_gen.const_i32(mask);
+            code_gen.instruction(&Instruction::I32GtU);
+        }
+    }
+
+    fn encode_add_sub(&self, code_gen: &mut CodeGenerator) {
+        let is_64 = self.payload.stack_type == enc::ValType::I64;
+        let is_add = self.builtin == Builtin::CheckedAdd;
+        let (add, sub, xor, and, zero, lt_s, lt_u) = if is_64 {
+            (
+                Instruction::I64Add,
+                Instruction::I64Sub,
+                Instruction::I64Xor,
+                Instruction::I64And,
+                Instruction::I64Const(0),
+                Instruction::I64LtS,
+                Instruction::I64LtU,
+            )
+        } else {
+            (
+                Instruction::I32Add,
+                Instruction::I32Sub,
+                Instruction::I32Xor,
+                Instruction::I32And,
+                Instruction::I32Const(0),
+                Instruction::I32LtS,
+                Instruction::I32LtU,
+            )
+        };
+
+        self.read_left(code_gen);
+        self.read_right(code_gen);
+        code_gen.instruction(if is_add { &add } else { &sub });
+        self.write_result(code_gen);
+
+        match (self.is_signed(), is_add) {
+            // Signed addition overflows when the result's sign differs from both operands
+            (true, true) => {
+                self.read_left(code_gen);
+                self.read_result(code_gen);
+                code_gen.instruction(&xor);
+                self.read_right(code_gen);
+                self.read_result(code_gen);
+                code_gen.instruction(&xor);
+                code_gen.instruction(&and);
+                code_gen.instruction(&zero);
+                code_gen.instruction(&lt_s);
+            }
+            // Signed subtraction overflows when the operands' signs differ
+            // and the result's sign differs from the left operand
+            (true, false) => {
+                self.read_left(code_gen);
+                self.read_right(code_gen);
+                code_gen.instruction(&xor);
+                self.read_left(code_gen);
+                self.read_result(code_gen);
+                code_gen.instruction(&xor);
+                code_gen.instruction(&and);
+                code_gen.instruction(&zero);
+                code_gen.instruction(&lt_s);
+            }
+            // Unsigned addition overflows when the result wraps below the left operand
+            (false, true) => {
+                self.read_result(code_gen);
+                self.read_left(code_gen);
+                code_gen.instruction(&lt_u);
+            }
+            // Unsigned subtraction overflows when the right operand is larger
+            (false, false) => {
+                self.read_left(code_gen);
+                self.read_right(code_gen);
+                code_gen.instruction(&lt_u);
+            }
+        }
+    }
+
+    fn encode_mul(&self, code_gen: &mut CodeGenerator) {
+        self.read_left(code_gen);
+        self.read_right(code_gen);
+        match self.payload.stack_type {
+            enc::ValType::I32 => {
+                code_gen.instruction(&Instruction::I32Mul);
+                self.write_result(code_gen);
+                // Overflowed when the wrapped result differs from the 64-bit product
+                let extend = if self.is_signed() {
+                    Instruction::I64ExtendI32S
+                } else {
+                    Instruction::I64ExtendI32U
+                };
+                self.read_left(code_gen);
+                code_gen.instruction(&extend);
+                self.read_right(code_gen);
+                code_gen.instruction(&extend);
+                code_gen.instruction(&Instruction::I64Mul);
+                self.read_result(code_gen);
+                code_gen.instruction(&extend);
+                code_gen.instruction(&Instruction::I64Ne);
+            }
+            _ => {
+                code_gen.instruction(&Instruction::I64Mul);
+                self.write_result(code_gen);
+                // There is no wider product so instead check that dividing the
+                // result by the left operand gives back the right operand
+                let flag = enc::BlockType::Result(enc::ValType::I32);
+                self.read_left(code_gen);
+                code_gen.instruction(&Instruction::I64Eqz);
+                code_gen.instruction(&Instruction::If(flag));
+                code_gen.const_i32(0);
+                code_gen.instruction(&Instruction::Else);
+                if self.is_signed() {
+                    // MIN / -1 traps so it is tested for directly
+                    self.read_left(code_gen);
+                    code_gen.instruction(&Instruction::I64Const(-1));
+                    code_gen.instruction(&Instruction::I64Eq);
+                    code_gen.instruction(&Instruction::If(flag));
+                    self.read_right(code_gen);
+                    code_gen.instruction(&Instruction::I64Const(i64::MIN));
+                    code_gen.instruction(&Instruction::I64Eq);
+                    code_gen.instruction(&Instruction::Else);
+                    self.encode_div_check(Instruction::I64DivS, code_gen);
+                    code_gen.instruction(&Instruction::End);
+                } else {
+                    self.encode_div_check(Instruction::I64DivU, code_gen);
+                }
+                code_gen.instruction(&Instruction::End);
+            }
+        }
+    }
+
+    fn encode_div_check(&self, div: Instruction<'static>, code_gen: &mut CodeGenerator) {
+        self.read_result(code_gen);
+        self.read_left(code_gen);
+        code_gen.instruction(&div);
+        self.read_right(code_gen);
+        code_gen.instruction(&Instruction::I64Ne);
+    }
+}
+
+/// Each field of the payload is selected against the matching field of the default
+fn encode_unwrap_or(
+    args: &[ExpressionId],
+    expression: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let (option, default) = (args[0], args[1]);
+    let option_fields = code_gen.fields(option)?;
+    let default_fields = code_gen.fields(default)?;
+    let fields = code_gen.fields(expression)?;
+    let discriminant = &option_fields[0];
+    let payload_fields = &option_fields[1..];
+    for ((field, payload), default_field) in fields.iter().zip(payload_fields).zip(&default_fields)
+    {
+        code_gen.read_expr_field(option, payload);
+        code_gen.read_expr_field(default, default_field);
+        code_gen.read_expr_field(option, discriminant);
+        code_gen.instruction(&Instruction::Select);
+        code_gen.write_expr_field(expression, field);
+    }
+    Ok(())
+}
+
+/// The bounds checked version of indexing, giving `none` instead of trapping
+fn encode_get(
+    args: &[ExpressionId],
+    expression: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let (list, index) = (args[0], args[1]);
+    let (element_size, element_fields) = code_gen.list_element(list)?;
+    let fields = code_gen.fields(expression)?;
+    let (discriminant, payload_fields) = (&fields[0], &fields[1..]);
+
+    encode_bounds_check(list, index, code_gen);
+    code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+    // some
+    code_gen.const_i32(1);
+    code_gen.write_expr_field(expression, discriminant);
+    for (payload, element_field) in payload_fields.iter().zip(element_fields.iter()) {
+        encode_element_address(list, index, element_size, code_gen);
+        code_gen.read_mem_field(element_field);
+        code_gen.write_expr_field(expression, payload);
+    }
+    code_gen.instruction(&Instruction::Else);
+    // none
+    code_gen.const_i32(0);
+    code_gen.write_expr_field(expression, discriminant);
+    for payload in payload_fields.iter() {
+        match payload.stack_type {
+            enc::ValType::F32 | enc::ValType::F64 => code_gen.encode_const_float(0.0, payload),
+            _ => code_gen.encode_const_int(0, payload),
+        }
+        code_gen.write_expr_field(expression, payload);
+    }
+    code_gen.instruction(&Instruction::End);
+    Ok(())
+}
+
+/// Lower a load or store of `width` bytes at an offset into a `bytes`,
+/// trapping unless all of them are within it
+fn encode_access(
+    builtin: Builtin,
+    width: u32,
+    args: &[ExpressionId],
+    expression: Option<ExpressionId>,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let (bytes, offset) = (args[0], args[1]);
+    if builtin.has_result() && expression.is_none() {
+        return Ok(());
+    }
+
+    // Loads known to be in bounds skip the check like indexing does
+    if !expression.is_some_and(|expression| code_gen.in_bounds(expression)) {
+        // Trap if offset + width > len, which can't overflow as 64 bits
+        code_gen.read_expr_field(offset, &U32_FIELD);
+        code_gen.instruction(&Instruction::I64ExtendI32U);
+        code_gen.instruction(&Instruction::I64Const(width as i64));
+        code_gen.instruction(&Instruction::I64Add);
+        code_gen.read_expr_field(bytes, &LIST_LENGTH_FIELD);
+        code_gen.instruction(&Instruction::I64ExtendI32U);
+        code_gen.instruction(&Instruction::I64GtU);
+        code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+        code_gen.instruction(&Instruction::Unreachable);
+        code_gen.instruction(&Instruction::End);
+    }
+
+    code_gen.read_expr_field(bytes, &LIST_POINTER_FIELD);
+    code_gen.read_expr_field(offset, &U32_FIELD);
+    code_gen.instruction(&Instruction::I32Add);
+
+    // The offset needn't be aligned
+    let mem_arg = enc::MemArg {
+        offset: 0,
+        align: 0,
+        memory_index: 0,
+    };
+    match expression {
+        Some(expression) => {
+            let field = code_gen.one_field(expression)?;
+            code_gen.instruction(&match width {
+                1 => Instruction::I32Load8U(mem_arg),
+                2 => Instruction::I32Load16U(mem_arg),
+                4 => Instruction::I32Load(mem_arg),
+                _ => Instruction::I64Load(mem_arg),
+            });
+            code_gen.write_expr_field(expression, &field);
+        }
+        None => {
+            let value = args[2];
+            let field = code_gen.one_field(value)?;
+            code_gen.read_expr_field(value, &field);
+            code_gen.instruction(&match width {
+                1 => Instruction::I32Store8(mem_arg),
+                2 => Instruction::I32Store16(mem_arg),
+                4 => Instruction::I32Store(mem_arg),
+                _ => Instruction::I64Store(mem_arg),
+            });
+        }
+    }
+    Ok(())
+}
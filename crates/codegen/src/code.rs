@@ -1,20 +1,27 @@
 use std::collections::HashMap;
 
-use ast::{ExpressionId, FunctionId, NameId, StatementId};
+use ast::{ExpressionId, FunctionId, NameId, Span, StatementId, TypeDefId, TypeId};
 use claw_ast as ast;
 
 use crate::{
     builders::module::{ModuleBuilder, ModuleDataIndex, ModuleFunctionIndex},
+    builtins,
     expression::EncodeExpression,
+    format::FormatFuncs,
     function::{self, EncodedFuncs, EncodedFunction},
     imports::{self, EncodedImports},
+    liveness,
+    peephole::{self, Op},
     statement::EncodeStatement,
-    types::{EncodeType, FieldInfo, Signedness},
-    GenerationError,
+    stdio::StdioFuncs,
+    types::{
+        EncodeType, FieldInfo, Signedness, FUNC_FIELD, STRING_LENGTH_FIELD, STRING_OFFSET_FIELD,
+    },
+    GenerationError, Profile,
 };
 use claw_resolver::{
-    types::ResolvedType, ImportFuncId, ImportType, ImportTypeId, ItemId, LocalId, ParamId,
-    ResolvedComponent, ResolvedFunction,
+    const_eval::ConstValue, enums, records, types::ResolvedType, ImportFuncId, ImportType,
+    ImportTypeId, ItemId, LocalId, ParamId, ResolvedComponent, ResolvedFunction,
 };
 use cranelift_entity::EntityRef;
 use wasm_encoder as enc;
@@ -32,13 +39,20 @@ pub struct CodeGenerator<'gen> {
     func_idx_for_func: &'gen HashMap<FunctionId, ModuleFunctionIndex>,
 
     realloc: ModuleFunctionIndex,
+    format: FormatFuncs,
+    /// Only imported by components which write to stdout or stderr
+    stdio: Option<StdioFuncs>,
     // Function structs
     function: &'gen ast::Function,
     resolved_func: &'gen ResolvedFunction,
     encoded_func: &'gen EncodedFunction,
 
     // Internal output
-    builder: enc::Function,
+    params: u32,
+    locals: Vec<enc::ValType>,
+    ops: Vec<Op>,
+    /// The source of the statement or expression being encoded
+    location: Option<Span>,
 
     // Tracking state
     #[allow(dead_code)]
@@ -70,6 +84,8 @@ impl<'gen> CodeGenerator<'gen> {
         encoded_func: &'gen EncodedFunction,
         id: FunctionId,
         realloc: ModuleFunctionIndex,
+        format: FormatFuncs,
+        stdio: Option<StdioFuncs>,
     ) -> Result<Self, GenerationError> {
         let function = &comp.get_function(id);
         let resolved_func = &rcomp.funcs[&id];
@@ -118,27 +134,28 @@ impl<'gen> CodeGenerator<'gen> {
             statement.alloc_expr_locals(&mut allocator)?;
         }
 
-        let locals = &local_space[locals_start..];
-        let locals = locals.iter().map(|l| (1, *l));
-        let mut builder = enc::Function::new(locals);
+        let params = locals_start as u32;
+        let locals = local_space[locals_start..].to_vec();
+        let mut ops = Vec::new();
 
         if let Some(return_index) = return_index {
+            let mut instruction = |i| ops.push(Op::Instruction(i));
             // old ptr, old size
-            builder.instruction(&enc::Instruction::I32Const(0));
-            builder.instruction(&enc::Instruction::I32Const(0));
+            instruction(enc::Instruction::I32Const(0));
+            instruction(enc::Instruction::I32Const(0));
 
             let result_type = comp.get_function(id).results.unwrap();
             // align
             let align = result_type.align(comp, rcomp);
             let align = 2u32.pow(align);
-            builder.instruction(&enc::Instruction::I32Const(align as i32));
+            instruction(enc::Instruction::I32Const(align as i32));
             // new size
             let size = result_type.mem_size(comp, rcomp);
-            builder.instruction(&enc::Instruction::I32Const(size as i32));
+            instruction(enc::Instruction::I32Const(size as i32));
             // call allocator
-            builder.instruction(&enc::Instruction::Call(realloc.into()));
+            instruction(enc::Instruction::Call(realloc.into()));
             // store address
-            builder.instruction(&enc::Instruction::LocalSet(return_index));
+            instruction(enc::Instruction::LocalSet(return_index));
         }
 
         Ok(Self {
@@ -148,12 +165,17 @@ impl<'gen> CodeGenerator<'gen> {
             imports,
             functions,
             realloc,
+            format,
+            stdio,
             func_idx_for_import,
             func_idx_for_func,
             function,
             resolved_func,
             encoded_func,
-            builder,
+            params,
+            locals,
+            ops,
+            location: None,
             local_space,
             return_index,
             call_params_index,
@@ -165,21 +187,65 @@ impl<'gen> CodeGenerator<'gen> {
 
     pub fn encode_statement(&mut self, statement: StatementId) -> Result<(), GenerationError> {
         let stmt = self.comp.get_statement(statement);
-        stmt.encode(self)
+        let span = self.comp.statement_span(statement);
+        self.located(span, |code_gen| stmt.encode(code_gen))
     }
 
     pub fn encode_child(&mut self, expression: ExpressionId) -> Result<(), GenerationError> {
-        let expr = self.comp.get_expression(expression);
-        expr.encode(expression, self)
+        let span = self.comp.expression_span(expression);
+        self.located(span, |code_gen| {
+            if let Some(value) = code_gen.resolved_func.constants.get(&expression) {
+                return code_gen.encode_constant(expression, *value);
+            }
+            let expr = code_gen.comp.get_expression(expression);
+            expr.encode(expression, code_gen)
+        })
+    }
+
+    /// Encode the code for the source at `span`, marking where it starts
+    /// and where the code for the enclosing source carries on after it
+    fn located<T>(&mut self, span: Span, encode: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = self.location.replace(span);
+        self.ops.push(Op::Location(span));
+        let result = encode(self);
+        self.location = outer;
+        if let Some(outer) = outer {
+            self.ops.push(Op::Location(outer));
+        }
+        result
+    }
+
+    fn encode_constant(
+        &mut self,
+        expression: ExpressionId,
+        value: ConstValue,
+    ) -> Result<(), GenerationError> {
+        let field = self.one_field(expression)?;
+        match value {
+            ConstValue::Int(int) => {
+                let int = match field.arith_mask {
+                    Some(mask) => int & mask as i128,
+                    None => int,
+                };
+                self.encode_const_int(int as u64, &field);
+            }
+            ConstValue::Float(float) => self.encode_const_float(float, &field),
+        }
+        self.write_expr_field(expression, &field);
+        Ok(())
+    }
+
+    pub fn instruction(&mut self, instruction: &enc::Instruction<'static>) {
+        self.ops.push(Op::Instruction(instruction.clone()));
     }
 
-    pub fn instruction(&mut self, instruction: &enc::Instruction) {
-        self.builder.instruction(instruction);
+    /// Copy already encoded instructions into the function body
+    pub fn raw(&mut self, bytes: &[u8]) {
+        self.ops.push(Op::Raw(bytes.to_vec()));
     }
 
     pub fn const_i32(&mut self, constant: i32) {
-        self.builder
-            .instruction(&enc::Instruction::I32Const(constant));
+        self.instruction(&enc::Instruction::I32Const(constant));
     }
 
     pub fn expression_type(
@@ -198,10 +264,19 @@ impl<'gen> CodeGenerator<'gen> {
         let ptype = match rtype {
             ResolvedType::Primitive(ptype) => Some(ptype),
             ResolvedType::Import(_) => todo!(),
+            ResolvedType::Option(_)
+            | ResolvedType::Record(_)
+            | ResolvedType::Enum(_)
+            | ResolvedType::Newtype(_)
+            | ResolvedType::Function(_) => None,
             ResolvedType::Defined(type_id) => {
                 let valtype = self.comp.get_type(type_id);
                 match valtype {
-                    ast::ValType::Result(_) => None,
+                    ast::ValType::Option(_)
+                    | ast::ValType::List(_)
+                    | ast::ValType::Result(_)
+                    | ast::ValType::Named(_)
+                    | ast::ValType::Func(_) => None,
                     ast::ValType::Primitive(ptype) => Some(*ptype),
                 }
             }
@@ -225,6 +300,78 @@ impl<'gen> CodeGenerator<'gen> {
         Ok(rtype.fields(self.comp, self.rcomp))
     }
 
+    /// The size in memory and fields of the elements of a list expression
+    pub fn list_element(
+        &self,
+        list: ExpressionId,
+    ) -> Result<(u32, Vec<FieldInfo>), GenerationError> {
+        let rtype = self.expression_type(list)?;
+        let element = rtype
+            .list_element(self.comp)
+            .expect("Expected expression to be a list");
+        let size = element.mem_size(self.comp, self.rcomp);
+        Ok((size, element.fields(self.comp, self.rcomp)))
+    }
+
+    /// The definition of a struct expression
+    pub fn record(
+        &self,
+        expression: ExpressionId,
+    ) -> Result<&'gen ast::RecordTypeDef, GenerationError> {
+        let rtype = self.expression_type(expression)?;
+        let type_def = rtype
+            .record(self.comp)
+            .expect("Expected expression to be a struct");
+        match self.comp.get_type_def(type_def) {
+            ast::TypeDefinition::Record(record) => Ok(record),
+            _ => unreachable!("Only structs resolve to records"),
+        }
+    }
+
+    /// The values of the cases of an enum expression, if it is one
+    pub fn enum_values(
+        &self,
+        expression: ExpressionId,
+    ) -> Result<Option<Vec<i64>>, GenerationError> {
+        let rtype = self.expression_type(expression)?;
+        let values = rtype
+            .enum_def(self.comp)
+            .map(|enum_id| enums::discriminants(self.comp, enum_id));
+        Ok(values)
+    }
+
+    /// The position of a case of an enum defined in the component
+    pub fn enum_case_index(&self, enum_id: TypeDefId, case: NameId) -> usize {
+        let case_name = self.comp.get_name(case);
+        let (index, _) = enums::enum_def(self.comp, enum_id)
+            .case(case_name, self.comp)
+            .expect("Cases are checked by the resolver");
+        index
+    }
+
+    /// The literal a struct field is given when left out
+    pub fn field_default(&self, field: &ast::RecordField) -> ast::Literal {
+        records::default_value(self.comp, field)
+            .expect("Missing fields and defaults are checked by the resolver")
+    }
+
+    /// The format string literal passed to `format`
+    pub fn format_string(&self, expression: ExpressionId) -> &'gen str {
+        match self.comp.get_expression(expression) {
+            ast::Expression::Literal(ast::Literal::String(string)) => string,
+            _ => unreachable!("Format strings are checked to be literals by the resolver"),
+        }
+    }
+
+    /// Whether an index expression is known to be in bounds
+    pub fn in_bounds(&self, expression: ExpressionId) -> bool {
+        self.resolved_func.in_bounds.contains(&expression)
+    }
+
+    pub fn flatten_type(&self, type_id: TypeId) -> Vec<enc::ValType> {
+        type_id.flatten(self.comp, self.rcomp)
+    }
+
     pub fn lookup_name(&self, ident: NameId) -> ItemId {
         self.resolved_func.bindings[&ident]
     }
@@ -249,6 +396,84 @@ impl<'gen> CodeGenerator<'gen> {
         self.instruction(&enc::Instruction::Call(self.realloc.into()))
     }
 
+    pub fn call_format_int(&mut self) {
+        self.instruction(&enc::Instruction::Call(self.format.int.into()))
+    }
+
+    pub fn call_format_float(&mut self) {
+        self.instruction(&enc::Instruction::Call(self.format.float.into()))
+    }
+
+    /// Write the string, which MUST be on the stack, and a newline
+    /// to stdout or stderr
+    pub fn call_println(&mut self, stderr: bool) {
+        let stdio = self.stdio.expect("Components which print import stdio");
+        let func = if stderr {
+            stdio.eprintln
+        } else {
+            stdio.println
+        };
+        self.instruction(&enc::Instruction::Call(func.into()))
+    }
+
+    /// Report a panic with its message and where `ident` is, then trap
+    pub fn encode_panic(&mut self, ident: NameId, message: ExpressionId) {
+        let prefix = format!("panicked at {}: ", self.location(ident));
+        let prefix_len = prefix.len() as i32;
+        let data = self.encode_const_bytes(prefix.as_bytes());
+
+        // Allocate the whole report
+        self.const_i32(0);
+        self.const_i32(0);
+        self.const_i32(1);
+        self.const_i32(prefix_len);
+        self.read_expr_field(message, &STRING_LENGTH_FIELD);
+        self.instruction(&enc::Instruction::I32Add);
+        self.allocate();
+        self.local_set(self.call_params_index);
+        // The location comes first, then the message
+        self.local_get(self.call_params_index);
+        self.const_i32(0);
+        self.const_i32(prefix_len);
+        self.instruction(&enc::Instruction::MemoryInit {
+            mem: 0,
+            data_index: data.into(),
+        });
+        self.local_get(self.call_params_index);
+        self.const_i32(prefix_len);
+        self.instruction(&enc::Instruction::I32Add);
+        self.read_expr_field(message, &STRING_OFFSET_FIELD);
+        self.read_expr_field(message, &STRING_LENGTH_FIELD);
+        self.instruction(&enc::Instruction::MemoryCopy {
+            src_mem: 0,
+            dst_mem: 0,
+        });
+
+        self.local_get(self.call_params_index);
+        self.const_i32(prefix_len);
+        self.read_expr_field(message, &STRING_LENGTH_FIELD);
+        self.instruction(&enc::Instruction::I32Add);
+        match self.rcomp.imports.panic_handler() {
+            Some(handler) => {
+                let index = self.func_idx_for_import[&handler];
+                self.instruction(&enc::Instruction::Call(index.into()));
+            }
+            None => self.call_println(true),
+        }
+        self.instruction(&enc::Instruction::Unreachable);
+    }
+
+    /// The file, line and column `ident` is at, e.g. `main.claw:3:5`
+    fn location(&self, ident: NameId) -> String {
+        let span = self.comp.name_span(ident);
+        let src = self.comp.file_source(span.file());
+        let before = &src.inner()[..span.start()];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        format!("{}:{}:{}", src.name(), line, column)
+    }
+
     pub fn encode_call(
         &mut self,
         item: ItemId,
@@ -258,6 +483,23 @@ impl<'gen> CodeGenerator<'gen> {
         match item {
             ItemId::ImportFunc(id) => self.encode_import_call(id, args, expression),
             ItemId::Function(id) => self.encode_func_call(id, args, expression),
+            ItemId::Builtin(builtin) => {
+                builtins::encode_builtin_call(builtin, args, expression, self)
+            }
+            ItemId::Param(_) | ItemId::Local(_) | ItemId::Global(_) => {
+                self.encode_indirect_call(item, args, expression)
+            }
+            ItemId::Type(ResolvedType::Newtype(_)) => {
+                // Constructing a newtype copies the value it wraps
+                if let Some(expression) = expression {
+                    let fields = self.fields(expression)?;
+                    for field in fields.iter() {
+                        self.read_expr_field(args[0], field);
+                        self.write_expr_field(expression, field);
+                    }
+                }
+                Ok(())
+            }
             _ => panic!(""),
         }
     }
@@ -361,6 +603,63 @@ impl<'gen> CodeGenerator<'gen> {
         expression: Option<ExpressionId>,
     ) -> Result<(), GenerationError> {
         let encoded_func = self.functions.funcs.get(&id).unwrap();
+        let index = self.func_idx_for_func.get(&id);
+        let index = *index.unwrap();
+        self.encode_call_with(encoded_func, args, expression, |code_gen| {
+            code_gen.instruction(&enc::Instruction::Call(index.into()));
+        })
+    }
+
+    /// Call a function value through the function table
+    fn encode_indirect_call(
+        &mut self,
+        callee: ItemId,
+        args: &[ExpressionId],
+        expression: Option<ExpressionId>,
+    ) -> Result<(), GenerationError> {
+        let type_id = match callee {
+            ItemId::Param(param) => self.resolved_func.params[param],
+            ItemId::Local(local) => self.resolved_func.locals[local].annotation.unwrap(),
+            ItemId::Global(global) => self.comp.get_global(global).type_id,
+            _ => unreachable!(),
+        };
+        let func_type = match self.comp.get_type(type_id) {
+            ast::ValType::Func(func_type) => func_type,
+            _ => panic!("Callee types are checked by the resolver"),
+        };
+        let params = func_type
+            .params
+            .iter()
+            .map(|type_id| (String::new(), ResolvedType::Defined(*type_id)))
+            .collect();
+        let results = func_type.results.map(ResolvedType::Defined);
+        let encoded_func = EncodedFunction::new(params, results, self.comp, self.rcomp);
+        let type_index = encoded_func.encode_mod_type(self.mod_builder);
+
+        self.encode_call_with(&encoded_func, args, expression, |code_gen| {
+            // The table index goes on top of the arguments
+            match callee {
+                ItemId::Param(param) => code_gen.read_param_field(param, &FUNC_FIELD),
+                ItemId::Local(local) => code_gen.read_local_field(local, &FUNC_FIELD),
+                ItemId::Global(global) => {
+                    code_gen.instruction(&enc::Instruction::GlobalGet(global.index() as u32))
+                }
+                _ => unreachable!(),
+            }
+            code_gen.instruction(&enc::Instruction::CallIndirect {
+                ty: type_index.into(),
+                table: 0,
+            });
+        })
+    }
+
+    fn encode_call_with(
+        &mut self,
+        encoded_func: &EncodedFunction,
+        args: &[ExpressionId],
+        expression: Option<ExpressionId>,
+        call: impl FnOnce(&mut Self),
+    ) -> Result<(), GenerationError> {
         // Prepare arguments
         if let Some(spilled_params) = &encoded_func.spill_params {
             self.prepare_function_spilled_args(spilled_params, &encoded_func.params, args)?;
@@ -375,9 +674,7 @@ impl<'gen> CodeGenerator<'gen> {
         }
 
         // Encode call instruction
-        let index = self.func_idx_for_func.get(&id);
-        let index = *index.unwrap();
-        self.instruction(&enc::Instruction::Call(index.into()));
+        call(self);
         // Write expression output if needed
         if let Some(expression) = expression {
             let fields = self.fields(expression)?;
@@ -438,9 +735,9 @@ impl<'gen> CodeGenerator<'gen> {
         let param_info = &self.encoded_func.params[param.index()];
         if self.encoded_func.spill_params.is_some() {
             let mem_index = param_info.mem_offset + field.mem_offset;
-            self.builder.instruction(&enc::Instruction::LocalGet(0));
+            self.instruction(&enc::Instruction::LocalGet(0));
             self.const_i32(mem_index as i32);
-            self.builder.instruction(&enc::Instruction::I32Add);
+            self.instruction(&enc::Instruction::I32Add);
             self.load_field(field);
         } else {
             let local_index = param_info.index_offset + field.index_offset;
@@ -472,6 +769,19 @@ impl<'gen> CodeGenerator<'gen> {
         self.local_set(local_index);
     }
 
+    /// The index of the first local holding the expression's fields
+    pub fn expr_local_index(&self, expression: ExpressionId) -> u32 {
+        self.index_for_expr[&expression].0
+    }
+
+    pub fn inline_asm_error(&self, expression: ExpressionId, message: &str) -> GenerationError {
+        GenerationError::InlineAsm {
+            src: self.comp.source(),
+            span: self.comp.expression_span(expression),
+            message: message.to_owned(),
+        }
+    }
+
     pub fn read_return_ptr(&mut self) -> Result<(), GenerationError> {
         let return_ptr_index = self.return_index.unwrap();
         self.local_get(return_ptr_index);
@@ -518,13 +828,11 @@ impl<'gen> CodeGenerator<'gen> {
     }
 
     fn local_get(&mut self, local_index: u32) {
-        self.builder
-            .instruction(&enc::Instruction::LocalGet(local_index));
+        self.instruction(&enc::Instruction::LocalGet(local_index));
     }
 
     fn local_set(&mut self, local_index: u32) {
-        self.builder
-            .instruction(&enc::Instruction::LocalSet(local_index));
+        self.instruction(&enc::Instruction::LocalSet(local_index));
     }
 
     fn load_field(&mut self, field: &FieldInfo) {
@@ -547,7 +855,7 @@ impl<'gen> CodeGenerator<'gen> {
                 valtype, s, size
             ),
         };
-        self.builder.instruction(&instruction);
+        self.instruction(&instruction);
     }
 
     fn store_field(&mut self, field: &FieldInfo) {
@@ -559,15 +867,54 @@ impl<'gen> CodeGenerator<'gen> {
             enc::ValType::F64 => enc::Instruction::F64Store(mem_arg),
             valtype => panic!("Cannot store value type {:?}", valtype),
         };
-        self.builder.instruction(&instruction);
+        self.instruction(&instruction);
     }
 
-    pub fn finalize(mut self) -> Result<enc::Function, GenerationError> {
+    /// Encode the function body, along with where the code for each
+    /// piece of source starts in it when `debug_info` is set
+    pub fn finalize(
+        mut self,
+        profile: Profile,
+        debug_info: bool,
+    ) -> Result<(enc::Function, Vec<(usize, Span)>), GenerationError> {
         for statement in self.function.body.iter() {
             self.encode_statement(*statement)?;
         }
-        self.builder.instruction(&enc::Instruction::End);
-        Ok(self.builder)
+        self.instruction(&enc::Instruction::End);
+        if !debug_info {
+            self.ops.retain(|op| !matches!(op, Op::Location(_)));
+        }
+
+        let (locals, ops) = match profile {
+            Profile::Debug => (self.locals, self.ops),
+            Profile::Release => {
+                let mut ops = peephole::optimize(self.ops);
+                let locals = liveness::coalesce_locals(self.params, self.locals, &mut ops);
+                // Sharing locals can leave new redundant pairs behind
+                (locals, peephole::optimize(ops))
+            }
+        };
+        let mut builder = enc::Function::new(locals.into_iter().map(|l| (1, l)));
+        let mut locations: Vec<(usize, Span)> = Vec::new();
+        let mut location = None;
+        for op in ops {
+            if let Op::Location(span) = op {
+                location = Some(span);
+                continue;
+            }
+            // Offsets are from the start of the body, after its size
+            if let Some(span) = location.take() {
+                if locations.last().map(|(_, last)| *last) != Some(span) {
+                    locations.push((builder.byte_len(), span));
+                }
+            }
+            match op {
+                Op::Instruction(instruction) => builder.instruction(&instruction),
+                Op::Raw(bytes) => builder.raw(bytes),
+                Op::Location(_) => unreachable!(),
+            };
+        }
+        Ok((builder, locations))
     }
 }
 
@@ -609,12 +956,24 @@ impl<'a> ExpressionAllocator<'a> {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn alloc_extra(&mut self, valtype: enc::ValType) -> Result<(), GenerationError> {
         self.local_space.push(valtype);
         Ok(())
     }
 
+    pub fn get_ptype(
+        &self,
+        expression: ExpressionId,
+    ) -> Result<Option<ast::PrimitiveType>, GenerationError> {
+        let rtype = self.func.expression_type(expression, self.comp)?;
+        Ok(rtype.primitive(self.comp))
+    }
+
+    pub fn alloc_type(&mut self, type_id: TypeId) -> Result<(), GenerationError> {
+        type_id.append_flattened(self.comp, self.rcomp, self.local_space);
+        Ok(())
+    }
+
     pub fn alloc_child(&mut self, expression: ExpressionId) -> Result<(), GenerationError> {
         let expr = self.comp.get_expression(expression);
         expr.alloc_expr_locals(expression, self)
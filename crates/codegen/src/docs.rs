@@ -0,0 +1,310 @@
+//! Markdown documentation for the exports of a component, built from
+//! their signatures and the `///` doc comments written above them.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use ast::{PrimitiveType, Span, ValType};
+use claw_ast as ast;
+
+/// Generate a markdown page with an index of the exported functions,
+/// globals and types of the component, and a section for each.
+///
+/// Types in signatures link to the section of the struct, enum or newtype they name.
+pub fn generate_docs(comp: &ast::Component, title: &str) -> String {
+    let mut gen = DocsGenerator {
+        comp,
+        type_defs: HashSet::new(),
+    };
+
+    let functions: Vec<_> = comp
+        .iter_functions()
+        .map(|(_, function)| function)
+        .filter(|function| function.exported)
+        .collect();
+    let globals: Vec<_> = comp
+        .iter_globals()
+        .map(|(_, global)| global)
+        .filter(|global| global.exported)
+        .collect();
+
+    let mut function_docs = Vec::new();
+    for function in functions.iter() {
+        function_docs.push(gen.function(function));
+    }
+    let mut global_docs = Vec::new();
+    for global in globals.iter() {
+        global_docs.push(gen.global(global));
+    }
+    for (id, type_def) in comp.iter_type_defs() {
+        if type_def.exported() {
+            gen.use_type_def(id);
+        }
+    }
+    // Using a struct or newtype uses the types in it, so they're all found by now
+    let mut records = Vec::new();
+    let mut enums = Vec::new();
+    let mut newtypes = Vec::new();
+    for (id, type_def) in comp.iter_type_defs() {
+        if !gen.type_defs.contains(&id) {
+            continue;
+        }
+        match type_def {
+            ast::TypeDefinition::Record(record) => records.push(record),
+            ast::TypeDefinition::Enum(enum_def) => enums.push((id, enum_def)),
+            ast::TypeDefinition::Newtype(newtype) => newtypes.push(newtype),
+        }
+    }
+    let record_docs: Vec<_> = records.iter().map(|record| gen.record(record)).collect();
+    let enum_docs: Vec<_> = enums
+        .iter()
+        .map(|(id, enum_def)| gen.enum_def(*id, enum_def))
+        .collect();
+    let newtype_docs: Vec<_> = newtypes
+        .iter()
+        .map(|newtype| gen.newtype(newtype))
+        .collect();
+
+    let mut out = String::new();
+    writeln!(out, "# {}", title).unwrap();
+
+    let index = |out: &mut String, kind: &str, name: ast::NameId| {
+        let name = comp.get_name(name);
+        writeln!(out, "- [{} {}](#{}-{})", kind, name, kind, name).unwrap();
+    };
+    writeln!(out).unwrap();
+    writeln!(out, "## Index").unwrap();
+    writeln!(out).unwrap();
+    for record in records.iter() {
+        index(&mut out, "struct", record.ident);
+    }
+    for (_, enum_def) in enums.iter() {
+        index(&mut out, "enum", enum_def.ident);
+    }
+    for newtype in newtypes.iter() {
+        index(&mut out, "newtype", newtype.ident);
+    }
+    for global in globals.iter() {
+        index(&mut out, "global", global.ident);
+    }
+    for function in functions.iter() {
+        index(&mut out, "func", function.ident);
+    }
+
+    let sections = [
+        ("Structs", record_docs),
+        ("Enums", enum_docs),
+        ("Newtypes", newtype_docs),
+        ("Globals", global_docs),
+        ("Functions", function_docs),
+    ];
+    for (heading, docs) in sections.iter() {
+        if docs.is_empty() {
+            continue;
+        }
+        writeln!(out).unwrap();
+        writeln!(out, "## {}", heading).unwrap();
+        for doc in docs.iter() {
+            writeln!(out).unwrap();
+            out.push_str(doc);
+        }
+    }
+    out
+}
+
+struct DocsGenerator<'ctx> {
+    comp: &'ctx ast::Component,
+    /// The types defined in the component used by the exports, which get a section
+    type_defs: HashSet<ast::TypeDefId>,
+}
+
+impl<'ctx> DocsGenerator<'ctx> {
+    fn function(&mut self, function: &ast::Function) -> String {
+        let name = self.comp.get_name(function.ident);
+        let params = function
+            .params
+            .iter()
+            .map(|(name, type_id)| {
+                let name = self.comp.get_name(*name);
+                format!("`{}`: {}", name, self.valtype(*type_id))
+            })
+            .collect::<Vec<_>>();
+
+        let mut out = String::new();
+        writeln!(out, "### func {}", name).unwrap();
+        writeln!(out).unwrap();
+        write!(out, "func `{}`({})", name, params.join(", ")).unwrap();
+        if let Some(results) = function.results {
+            write!(out, " -> {}", self.valtype(results)).unwrap();
+        }
+        writeln!(out).unwrap();
+        self.doc_comment(function.ident, &mut out);
+        out
+    }
+
+    fn global(&mut self, global: &ast::Global) -> String {
+        let name = self.comp.get_name(global.ident);
+        let mut out = String::new();
+        writeln!(out, "### global {}", name).unwrap();
+        writeln!(out).unwrap();
+        let mutable = if global.mutable { "mut " } else { "" };
+        let valtype = self.valtype(global.type_id);
+        writeln!(out, "let {}`{}`: {}", mutable, name, valtype).unwrap();
+        self.doc_comment(global.ident, &mut out);
+        out
+    }
+
+    fn record(&mut self, record: &ast::RecordTypeDef) -> String {
+        let mut out = String::new();
+        writeln!(out, "### struct {}", self.comp.get_name(record.ident)).unwrap();
+        self.doc_comment(record.ident, &mut out);
+        if !record.fields.is_empty() {
+            writeln!(out).unwrap();
+        }
+        for field in record.fields.iter() {
+            let name = self.comp.get_name(field.ident);
+            write!(out, "- `{}`: {}", name, self.valtype(field.type_id)).unwrap();
+            let span = self.comp.name_span(field.ident);
+            if let Some(docs) = doc_comment(self.comp, span) {
+                // Only the first paragraph fits in the list
+                let summary = docs.split("\n\n").next().unwrap().replace('\n', " ");
+                write!(out, " - {}", summary).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        out
+    }
+
+    fn enum_def(&mut self, id: ast::TypeDefId, enum_def: &ast::EnumTypeDef) -> String {
+        let mut out = String::new();
+        writeln!(out, "### enum {}", self.comp.get_name(enum_def.ident)).unwrap();
+        self.doc_comment(enum_def.ident, &mut out);
+        if !enum_def.cases.is_empty() {
+            writeln!(out).unwrap();
+        }
+        let values = claw_resolver::enums::discriminants(self.comp, id);
+        for (case, value) in enum_def.cases.iter().zip(values) {
+            let name = self.comp.get_name(case.ident);
+            write!(out, "- `{}` = {}", name, value).unwrap();
+            let span = self.comp.name_span(case.ident);
+            if let Some(docs) = doc_comment(self.comp, span) {
+                let summary = docs.split("\n\n").next().unwrap().replace('\n', " ");
+                write!(out, " - {}", summary).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        out
+    }
+
+    fn newtype(&mut self, newtype: &ast::NewtypeDef) -> String {
+        let name = self.comp.get_name(newtype.ident);
+        let mut out = String::new();
+        writeln!(out, "### newtype {}", name).unwrap();
+        writeln!(out).unwrap();
+        let inner = self.valtype(newtype.inner);
+        writeln!(out, "newtype `{}` = {}", name, inner).unwrap();
+        self.doc_comment(newtype.ident, &mut out);
+        out
+    }
+
+    fn doc_comment(&self, name: ast::NameId, out: &mut String) {
+        if let Some(docs) = doc_comment(self.comp, self.comp.name_span(name)) {
+            writeln!(out).unwrap();
+            writeln!(out, "{}", docs).unwrap();
+        }
+    }
+
+    fn valtype(&mut self, type_id: ast::TypeId) -> String {
+        match self.comp.get_type(type_id) {
+            ValType::Option(some) => format!("option<{}>", self.valtype(*some)),
+            ValType::List(element) => format!("list<{}>", self.valtype(*element)),
+            ValType::Result(result) => format!(
+                "result<{}, {}>",
+                self.valtype(result.ok),
+                self.valtype(result.err)
+            ),
+            ValType::Primitive(ptype) => format!("`{}`", primitive(*ptype)),
+            ValType::Named(name) => {
+                let name = self.comp.get_name(*name);
+                let type_def = self.comp.find_type_def(name).unwrap();
+                self.use_type_def(type_def);
+                let kind = match self.comp.get_type_def(type_def) {
+                    ast::TypeDefinition::Record(_) => "struct",
+                    ast::TypeDefinition::Enum(_) => "enum",
+                    ast::TypeDefinition::Newtype(_) => "newtype",
+                };
+                format!("[{}](#{}-{})", name, kind, name)
+            }
+            ValType::Func(func_type) => {
+                let params = func_type
+                    .params
+                    .iter()
+                    .map(|param| self.valtype(*param))
+                    .collect::<Vec<_>>();
+                let mut valtype = format!("func({})", params.join(", "));
+                if let Some(results) = func_type.results {
+                    write!(valtype, " -> {}", self.valtype(results)).unwrap();
+                }
+                valtype
+            }
+        }
+    }
+
+    /// Document a type, along with the types used in it
+    fn use_type_def(&mut self, type_def: ast::TypeDefId) {
+        if !self.type_defs.insert(type_def) {
+            return;
+        }
+        match self.comp.get_type_def(type_def) {
+            ast::TypeDefinition::Record(record) => {
+                for field in record.fields.iter() {
+                    self.valtype(field.type_id);
+                }
+            }
+            ast::TypeDefinition::Enum(_) => {}
+            ast::TypeDefinition::Newtype(newtype) => {
+                self.valtype(newtype.inner);
+            }
+        }
+    }
+}
+
+/// The `///` comment lines directly above the line `span` starts on
+fn doc_comment(comp: &ast::Component, span: Span) -> Option<String> {
+    let src = comp.file_source(span.file());
+    let text = src.inner();
+    let line_start = text[..span.start()].rfind('\n').map_or(0, |i| i + 1);
+
+    let mut lines: Vec<&str> = text[..line_start]
+        .lines()
+        .rev()
+        .map(str::trim_start)
+        .take_while(|line| line.starts_with("///"))
+        .map(|line| {
+            let line = line.trim_start_matches("///");
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+fn primitive(ptype: PrimitiveType) -> &'static str {
+    match ptype {
+        PrimitiveType::Bool => "bool",
+        PrimitiveType::U8 => "u8",
+        PrimitiveType::S8 => "s8",
+        PrimitiveType::U16 => "u16",
+        PrimitiveType::S16 => "s16",
+        PrimitiveType::U32 => "u32",
+        PrimitiveType::S32 => "s32",
+        PrimitiveType::U64 => "u64",
+        PrimitiveType::S64 => "s64",
+        PrimitiveType::F32 => "f32",
+        PrimitiveType::F64 => "f64",
+        PrimitiveType::String => "string",
+    }
+}
@@ -1,10 +1,13 @@
 use ast::ExpressionId;
 use claw_ast as ast;
+use claw_resolver::const_eval::int_fits;
 use claw_resolver::{ItemId, ResolvedType};
 
+use crate::builtins::read_extended;
 use crate::code::{CodeGenerator, ExpressionAllocator};
 use crate::types::{
-    Signedness, STRING_CONTENTS_ALIGNMENT, STRING_LENGTH_FIELD, STRING_OFFSET_FIELD,
+    FieldInfo, Signedness, LIST_LENGTH_FIELD, LIST_POINTER_FIELD, STRING_CONTENTS_ALIGNMENT,
+    STRING_LENGTH_FIELD, STRING_OFFSET_FIELD, U32_FIELD,
 };
 use crate::GenerationError;
 
@@ -37,8 +40,17 @@ impl EncodeExpression for ast::Expression {
             ast::Expression::Enum(expr) => expr,
             ast::Expression::Literal(expr) => expr,
             ast::Expression::Call(expr) => expr,
+            ast::Expression::MethodCall(expr) => expr,
+            ast::Expression::Field(expr) => expr,
             ast::Expression::Unary(expr) => expr,
             ast::Expression::Binary(expr) => expr,
+            ast::Expression::Index(expr) => expr,
+            ast::Expression::Slice(expr) => expr,
+            ast::Expression::Record(expr) => expr,
+            ast::Expression::Asm(expr) => expr,
+            ast::Expression::If(expr) => expr,
+            ast::Expression::Match(expr) => expr,
+            ast::Expression::Cast(expr) => expr,
         };
         expr.alloc_expr_locals(expression, allocator)
     }
@@ -53,8 +65,17 @@ impl EncodeExpression for ast::Expression {
             ast::Expression::Enum(expr) => expr,
             ast::Expression::Literal(expr) => expr,
             ast::Expression::Call(expr) => expr,
+            ast::Expression::MethodCall(expr) => expr,
+            ast::Expression::Field(expr) => expr,
             ast::Expression::Unary(expr) => expr,
             ast::Expression::Binary(expr) => expr,
+            ast::Expression::Index(expr) => expr,
+            ast::Expression::Slice(expr) => expr,
+            ast::Expression::Record(expr) => expr,
+            ast::Expression::Asm(expr) => expr,
+            ast::Expression::If(expr) => expr,
+            ast::Expression::Match(expr) => expr,
+            ast::Expression::Cast(expr) => expr,
         };
         expr.encode(expression, code_gen)?;
         Ok(())
@@ -97,7 +118,12 @@ impl EncodeExpression for ast::Identifier {
                     code_gen.write_expr_field(expression, field);
                 }
             }
-            ItemId::Function(_) => panic!("Cannot use function as value!!"),
+            ItemId::Function(function) => {
+                let field = code_gen.one_field(expression)?;
+                code_gen.const_i32(function.index() as i32);
+                code_gen.write_expr_field(expression, &field);
+            }
+            ItemId::Builtin(_) => panic!("Cannot use builtin as value!!"),
         }
         Ok(())
     }
@@ -132,6 +158,12 @@ impl EncodeExpression for ast::EnumLiteral {
                     }
                 }
             }
+            ItemId::Type(ResolvedType::Enum(enum_id)) => {
+                let case_index = code_gen.enum_case_index(enum_id, self.case_name);
+                code_gen.const_i32(case_index as i32);
+                let field = code_gen.one_field(expression)?;
+                code_gen.write_expr_field(expression, &field);
+            }
             _ => unreachable!(),
         }
         Ok(())
@@ -152,43 +184,54 @@ impl EncodeExpression for ast::Literal {
         expression: ExpressionId,
         code_gen: &mut CodeGenerator,
     ) -> Result<(), GenerationError> {
-        match self {
-            ast::Literal::String(string) => {
-                // Allocate string pointer
-                code_gen.const_i32(0);
-                code_gen.const_i32(0);
-                code_gen.const_i32(2i32.pow(STRING_CONTENTS_ALIGNMENT));
-                code_gen.const_i32(string.len() as i32);
-                code_gen.allocate();
-                code_gen.write_expr_field(expression, &STRING_OFFSET_FIELD);
-                // Store the string length
-                code_gen.const_i32(string.len() as i32);
-                code_gen.write_expr_field(expression, &STRING_LENGTH_FIELD);
-                // Copy in the data segment
-                let index = code_gen.encode_const_bytes(string.as_bytes());
-                code_gen.read_expr_field(expression, &STRING_OFFSET_FIELD);
-                code_gen.const_i32(0);
-                code_gen.read_expr_field(expression, &STRING_LENGTH_FIELD);
-                code_gen.instruction(&enc::Instruction::MemoryInit {
-                    mem: 0,
-                    data_index: index.into(),
-                })
-            }
-            ast::Literal::Integer(int) => {
-                let field = code_gen.one_field(expression)?;
-                code_gen.encode_const_int(*int, &field);
-                code_gen.write_expr_field(expression, &field);
-            }
-            ast::Literal::Float(float) => {
-                let field = code_gen.one_field(expression)?;
-                code_gen.encode_const_float(*float, &field);
-                code_gen.write_expr_field(expression, &field);
-            }
-        }
+        let fields = code_gen.fields(expression)?;
+        encode_literal(self, expression, &fields, code_gen);
         Ok(())
     }
 }
 
+/// Write a literal into the given fields of an expression,
+/// which may be a part of a larger value like a struct
+fn encode_literal(
+    literal: &ast::Literal,
+    expression: ExpressionId,
+    fields: &[FieldInfo],
+    code_gen: &mut CodeGenerator,
+) {
+    match literal {
+        ast::Literal::String(string) => {
+            let (offset_field, length_field) = (&fields[0], &fields[1]);
+            // Allocate string pointer
+            code_gen.const_i32(0);
+            code_gen.const_i32(0);
+            code_gen.const_i32(2i32.pow(STRING_CONTENTS_ALIGNMENT));
+            code_gen.const_i32(string.len() as i32);
+            code_gen.allocate();
+            code_gen.write_expr_field(expression, offset_field);
+            // Store the string length
+            code_gen.const_i32(string.len() as i32);
+            code_gen.write_expr_field(expression, length_field);
+            // Copy in the data segment
+            let index = code_gen.encode_const_bytes(string.as_bytes());
+            code_gen.read_expr_field(expression, offset_field);
+            code_gen.const_i32(0);
+            code_gen.read_expr_field(expression, length_field);
+            code_gen.instruction(&enc::Instruction::MemoryInit {
+                mem: 0,
+                data_index: index.into(),
+            })
+        }
+        ast::Literal::Integer(int) => {
+            code_gen.encode_const_int(*int, &fields[0]);
+            code_gen.write_expr_field(expression, &fields[0]);
+        }
+        ast::Literal::Float(float) => {
+            code_gen.encode_const_float(*float, &fields[0]);
+            code_gen.write_expr_field(expression, &fields[0]);
+        }
+    }
+}
+
 impl EncodeExpression for ast::Call {
     fn alloc_expr_locals(
         &self,
@@ -215,6 +258,232 @@ impl EncodeExpression for ast::Call {
     }
 }
 
+impl EncodeExpression for ast::MethodCall {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        self.as_call().alloc_expr_locals(expression, allocator)
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        self.as_call().encode(expression, code_gen)
+    }
+}
+
+impl EncodeExpression for ast::FieldAccess {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc(expression)?;
+        allocator.alloc_child(self.record)
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        code_gen.encode_child(self.record)?;
+
+        // Skip the flattened fields of the members before this one
+        let record = code_gen.record(self.record)?;
+        let name = code_gen.lookup_name_str(self.field);
+        let mut skipped = 0;
+        for member in record.fields.iter() {
+            if code_gen.lookup_name_str(member.ident) == name {
+                break;
+            }
+            skipped += code_gen.flatten_type(member.type_id).len();
+        }
+
+        let record_fields = code_gen.fields(self.record)?;
+        let fields = code_gen.fields(expression)?;
+        for (record_field, field) in record_fields[skipped..].iter().zip(fields.iter()) {
+            code_gen.read_expr_field(self.record, record_field);
+            code_gen.write_expr_field(expression, field);
+        }
+        Ok(())
+    }
+}
+
+impl EncodeExpression for ast::IfExpression {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc(expression)?;
+        allocator.alloc_child(self.condition)?;
+        allocator.alloc_child(self.then_value)?;
+        allocator.alloc_child(self.else_value)
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        code_gen.encode_child(self.condition)?;
+        let field = code_gen.one_field(self.condition)?;
+        code_gen.read_expr_field(self.condition, &field);
+        code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+        encode_branch(self.then_value, expression, code_gen)?;
+        code_gen.instruction(&Instruction::Else);
+        encode_branch(self.else_value, expression, code_gen)?;
+        code_gen.instruction(&Instruction::End);
+        Ok(())
+    }
+}
+
+impl EncodeExpression for ast::Match {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc(expression)?;
+        allocator.alloc_child(self.scrutinee)?;
+        for arm in self.arms.iter() {
+            if let Some(guard) = arm.guard {
+                allocator.alloc_child(guard)?;
+            }
+            allocator.alloc_child(arm.value)?;
+        }
+        Ok(())
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        code_gen.encode_child(self.scrutinee)?;
+
+        // Each arm tests its case and guard in the else branch of the arm before.
+        // Matches are exhaustive, so an unguarded last arm or `_` doesn't need testing.
+        let mut open = 0;
+        let mut exhausted = false;
+        for (index, arm) in self.arms.iter().enumerate() {
+            if let ast::Pattern::Binding(ident) = arm.pattern {
+                bind_scrutinee(ident, self.scrutinee, code_gen)?;
+            }
+            let last = index + 1 == self.arms.len();
+            let case = match arm.pattern {
+                ast::Pattern::Case {
+                    enum_name,
+                    case_name,
+                } if !last || arm.guard.is_some() => Some((enum_name, case_name)),
+                _ => None,
+            };
+            if let Some((enum_name, case_name)) = case {
+                let field = code_gen.one_field(self.scrutinee)?;
+                code_gen.read_expr_field(self.scrutinee, &field);
+                let case_index = pattern_case_index(enum_name, case_name, code_gen);
+                code_gen.const_i32(case_index as i32);
+                code_gen.instruction(&Instruction::I32Eq);
+            }
+            match (case, arm.guard) {
+                (None, None) => {
+                    encode_branch(arm.value, expression, code_gen)?;
+                    exhausted = true;
+                    break;
+                }
+                (Some(_), None) => {}
+                (None, Some(guard)) => encode_guard(guard, code_gen)?,
+                (Some(_), Some(guard)) => {
+                    // Only evaluate the guard once the case is known to match
+                    let block_type = enc::BlockType::Result(enc::ValType::I32);
+                    code_gen.instruction(&Instruction::If(block_type));
+                    encode_guard(guard, code_gen)?;
+                    code_gen.instruction(&Instruction::Else);
+                    code_gen.const_i32(0);
+                    code_gen.instruction(&Instruction::End);
+                }
+            }
+            code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+            encode_branch(arm.value, expression, code_gen)?;
+            code_gen.instruction(&Instruction::Else);
+            open += 1;
+        }
+        // Every arm left is guarded, but an earlier one covers what they don't
+        if !exhausted {
+            code_gen.instruction(&Instruction::Unreachable);
+        }
+        for _ in 0..open {
+            code_gen.instruction(&Instruction::End);
+        }
+        Ok(())
+    }
+}
+
+/// Copy the value being matched to the local a pattern binds it to
+fn bind_scrutinee(
+    ident: ast::NameId,
+    scrutinee: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let ItemId::Local(local) = code_gen.lookup_name(ident) else {
+        panic!("Patterns bind names to locals");
+    };
+    for field in code_gen.fields(scrutinee)?.iter() {
+        code_gen.read_expr_field(scrutinee, field);
+        code_gen.write_local_field(local, field);
+    }
+    Ok(())
+}
+
+/// Evaluate the guard of a match arm, leaving whether it holds on the stack
+fn encode_guard(guard: ExpressionId, code_gen: &mut CodeGenerator) -> Result<(), GenerationError> {
+    code_gen.encode_child(guard)?;
+    let field = code_gen.one_field(guard)?;
+    code_gen.read_expr_field(guard, &field);
+    Ok(())
+}
+
+/// Evaluate one branch of a conditional expression and copy its value to the result
+fn encode_branch(
+    value: ExpressionId,
+    expression: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    code_gen.encode_child(value)?;
+    for field in code_gen.fields(expression)?.iter() {
+        code_gen.read_expr_field(value, field);
+        code_gen.write_expr_field(expression, field);
+    }
+    Ok(())
+}
+
+/// The position of the case named by a pattern, which is the value of the enum
+fn pattern_case_index(
+    enum_name: ast::NameId,
+    case_name: ast::NameId,
+    code_gen: &CodeGenerator,
+) -> usize {
+    match code_gen.lookup_name(enum_name) {
+        ItemId::Type(ResolvedType::Enum(enum_id)) => code_gen.enum_case_index(enum_id, case_name),
+        ItemId::Type(ResolvedType::Import(import_type)) => {
+            let claw_resolver::ImportType::Enum(enum_type) =
+                code_gen.lookup_import_type(import_type);
+            let case_name = code_gen.lookup_name_str(case_name);
+            enum_type
+                .cases
+                .iter()
+                .position(|case| case == case_name)
+                .expect("Cases are checked by the resolver")
+        }
+        _ => unreachable!("Patterns are checked by the resolver"),
+    }
+}
+
 impl EncodeExpression for ast::UnaryExpression {
     fn alloc_expr_locals(
         &self,
@@ -230,19 +499,354 @@ impl EncodeExpression for ast::UnaryExpression {
         expression: ExpressionId,
         code_gen: &mut CodeGenerator,
     ) -> Result<(), GenerationError> {
-        code_gen.const_i32(0); // TODO support 64 bit ints
         code_gen.encode_child(self.inner)?;
-        for field in code_gen.fields(self.inner)?.iter() {
-            code_gen.read_expr_field(self.inner, field);
+        let field = code_gen.one_field(expression)?;
+        let valtype = field.stack_type;
+        match (self.op, valtype) {
+            // Integers are negated by subtracting them from zero
+            (ast::UnaryOp::Negate, enc::ValType::I32) => code_gen.const_i32(0),
+            (ast::UnaryOp::Negate, enc::ValType::I64) => {
+                code_gen.instruction(&enc::Instruction::I64Const(0))
+            }
+            _ => {}
+        }
+        code_gen.read_expr_field(self.inner, &field);
+        let instruction = match (self.op, valtype) {
+            (ast::UnaryOp::Negate, enc::ValType::I32) => enc::Instruction::I32Sub,
+            (ast::UnaryOp::Negate, enc::ValType::I64) => enc::Instruction::I64Sub,
+            (ast::UnaryOp::Negate, enc::ValType::F32) => enc::Instruction::F32Neg,
+            (ast::UnaryOp::Negate, enc::ValType::F64) => enc::Instruction::F64Neg,
+            (ast::UnaryOp::LogicalNot, enc::ValType::I32) => enc::Instruction::I32Eqz,
+            (ast::UnaryOp::Invert, enc::ValType::I32) => {
+                code_gen.const_i32(-1);
+                enc::Instruction::I32Xor
+            }
+            (ast::UnaryOp::Invert, enc::ValType::I64) => {
+                code_gen.instruction(&enc::Instruction::I64Const(-1));
+                enc::Instruction::I64Xor
+            }
+            (op, valtype) => panic!("Cannot apply unary operator {:?} to type {:?}", op, valtype),
+        };
+        code_gen.instruction(&instruction);
+        if let Some(mask) = field.arith_mask {
+            code_gen.const_i32(mask);
+            code_gen.instruction(&enc::Instruction::I32And);
         }
-        code_gen.instruction(&enc::Instruction::I32Sub);
-        for field in code_gen.fields(expression)?.iter() {
+        code_gen.write_expr_field(expression, &field);
+        Ok(())
+    }
+}
+
+impl EncodeExpression for ast::Cast {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc(expression)?;
+        allocator.alloc_child(self.inner)
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        code_gen.encode_child(self.inner)?;
+        match (
+            code_gen.enum_values(self.inner)?,
+            code_gen.enum_values(expression)?,
+        ) {
+            (Some(values), None) => encode_enum_to_int(&values, expression, self.inner, code_gen)?,
+            (None, Some(values)) => encode_int_to_enum(&values, expression, self.inner, code_gen)?,
+            // The same enum, or a newtype being wrapped or unwrapped, which share their layout
+            (Some(_), Some(_)) | (None, None) => {
+                let fields = code_gen.fields(expression)?;
+                for field in fields.iter() {
+                    code_gen.read_expr_field(self.inner, field);
+                    code_gen.write_expr_field(expression, field);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Select the value of the case from the constants of all of them
+fn encode_enum_to_int(
+    values: &[i64],
+    expression: ExpressionId,
+    inner: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let index = code_gen.one_field(inner)?;
+    let field = code_gen.one_field(expression)?;
+    let push_value = |value: i64, code_gen: &mut CodeGenerator| match field.stack_type {
+        enc::ValType::I64 => code_gen.instruction(&Instruction::I64Const(value)),
+        _ => code_gen.const_i32(value as i32),
+    };
+    let (last, rest) = match values.split_last() {
+        Some(split) => split,
+        // Enums without cases have no values to convert
+        None => {
+            code_gen.instruction(&Instruction::Unreachable);
+            return Ok(());
+        }
+    };
+    push_value(*last, code_gen);
+    for (i, value) in rest.iter().enumerate() {
+        push_value(*value, code_gen);
+        code_gen.read_expr_field(inner, &index);
+        code_gen.const_i32(i as i32);
+        code_gen.instruction(&Instruction::I32Ne);
+        code_gen.instruction(&Instruction::Select);
+    }
+    code_gen.write_expr_field(expression, &field);
+    Ok(())
+}
+
+/// Compare the integer to the value of each case, trapping if none match
+fn encode_int_to_enum(
+    values: &[i64],
+    expression: ExpressionId,
+    inner: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let ptype = code_gen.get_ptype(inner)?.unwrap();
+    let from = code_gen.one_field(inner)?;
+    let field = code_gen.one_field(expression)?;
+    code_gen.instruction(&Instruction::Block(enc::BlockType::Empty));
+    for (i, value) in values.iter().enumerate() {
+        // Cases out of the range of the integer type can't match
+        if !int_fits(*value as i128, ptype) {
+            continue;
+        }
+        code_gen.const_i32(i as i32);
+        code_gen.write_expr_field(expression, &field);
+        match (from.stack_type, from.signedness) {
+            (enc::ValType::I64, _) => {
+                code_gen.read_expr_field(inner, &from);
+                code_gen.instruction(&Instruction::I64Const(*value));
+                code_gen.instruction(&Instruction::I64Eq);
+            }
+            (_, Signedness::Signed) => {
+                read_extended(inner, &from, code_gen);
+                code_gen.const_i32(*value as i32);
+                code_gen.instruction(&Instruction::I32Eq);
+            }
+            (_, Signedness::Unsigned) => {
+                code_gen.read_expr_field(inner, &from);
+                if let Some(mask) = from.arith_mask {
+                    code_gen.const_i32(mask);
+                    code_gen.instruction(&Instruction::I32And);
+                }
+                code_gen.const_i32(*value as i32);
+                code_gen.instruction(&Instruction::I32Eq);
+            }
+        }
+        code_gen.instruction(&Instruction::BrIf(0));
+    }
+    code_gen.instruction(&Instruction::Unreachable);
+    code_gen.instruction(&Instruction::End);
+    Ok(())
+}
+
+impl EncodeExpression for ast::RecordLiteral {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc(expression)?;
+        for (_name, value) in self.fields.iter() {
+            allocator.alloc_child(*value)?;
+        }
+        if let Some(base) = self.base {
+            allocator.alloc_child(base)?;
+        }
+        Ok(())
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        // Values are evaluated in the order written, then the base
+        for (_name, value) in self.fields.iter() {
+            code_gen.encode_child(*value)?;
+        }
+        if let Some(base) = self.base {
+            code_gen.encode_child(base)?;
+        }
+
+        let record = code_gen.record(expression)?;
+        let fields = code_gen.fields(expression)?;
+        let mut remaining = fields.as_slice();
+        for member in record.fields.iter() {
+            let count = code_gen.flatten_type(member.type_id).len();
+            let (member_fields, rest) = remaining.split_at(count);
+            remaining = rest;
+
+            let name = code_gen.lookup_name_str(member.ident);
+            let value = self
+                .fields
+                .iter()
+                .find(|(ident, _)| code_gen.lookup_name_str(*ident) == name)
+                .map(|(_, value)| *value);
+            match (value, self.base) {
+                (Some(value), _) => {
+                    let value_fields = code_gen.fields(value)?;
+                    for (value_field, field) in value_fields.iter().zip(member_fields) {
+                        code_gen.read_expr_field(value, value_field);
+                        code_gen.write_expr_field(expression, field);
+                    }
+                }
+                // The base has the same layout so fields are copied as-is
+                (None, Some(base)) => {
+                    for field in member_fields {
+                        code_gen.read_expr_field(base, field);
+                        code_gen.write_expr_field(expression, field);
+                    }
+                }
+                (None, None) => {
+                    let default = code_gen.field_default(member);
+                    encode_literal(&default, expression, member_fields, code_gen);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EncodeExpression for ast::Index {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc(expression)?;
+        allocator.alloc_child(self.list)?;
+        allocator.alloc_child(self.index)?;
+        Ok(())
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        code_gen.encode_child(self.list)?;
+        code_gen.encode_child(self.index)?;
+
+        if !code_gen.in_bounds(expression) {
+            encode_bounds_check(self.list, self.index, code_gen);
+            code_gen.instruction(&Instruction::I32Eqz);
+            code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+            code_gen.instruction(&Instruction::Unreachable);
+            code_gen.instruction(&Instruction::End);
+        }
+
+        let (element_size, element_fields) = code_gen.list_element(self.list)?;
+        let fields = code_gen.fields(expression)?;
+        for (field, element_field) in fields.iter().zip(element_fields.iter()) {
+            encode_element_address(self.list, self.index, element_size, code_gen);
+            code_gen.read_mem_field(element_field);
             code_gen.write_expr_field(expression, field);
         }
         Ok(())
     }
 }
 
+impl EncodeExpression for ast::Slice {
+    fn alloc_expr_locals(
+        &self,
+        expression: ExpressionId,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc(expression)?;
+        allocator.alloc_child(self.list)?;
+        for bound in self.start.iter().chain(self.end.iter()) {
+            allocator.alloc_child(*bound)?;
+        }
+        Ok(())
+    }
+
+    fn encode(
+        &self,
+        expression: ExpressionId,
+        code_gen: &mut CodeGenerator,
+    ) -> Result<(), GenerationError> {
+        code_gen.encode_child(self.list)?;
+        for bound in self.start.iter().chain(self.end.iter()) {
+            code_gen.encode_child(*bound)?;
+        }
+
+        // Trap unless start <= end <= len
+        encode_slice_start(self, code_gen);
+        encode_slice_end(self, code_gen);
+        code_gen.instruction(&Instruction::I32GtU);
+        encode_slice_end(self, code_gen);
+        code_gen.read_expr_field(self.list, &LIST_LENGTH_FIELD);
+        code_gen.instruction(&Instruction::I32GtU);
+        code_gen.instruction(&Instruction::I32Or);
+        code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+        code_gen.instruction(&Instruction::Unreachable);
+        code_gen.instruction(&Instruction::End);
+
+        // The slice points into the list's elements
+        let (element_size, _) = code_gen.list_element(self.list)?;
+        code_gen.read_expr_field(self.list, &LIST_POINTER_FIELD);
+        encode_slice_start(self, code_gen);
+        code_gen.const_i32(element_size as i32);
+        code_gen.instruction(&Instruction::I32Mul);
+        code_gen.instruction(&Instruction::I32Add);
+        code_gen.write_expr_field(expression, &LIST_POINTER_FIELD);
+        encode_slice_end(self, code_gen);
+        encode_slice_start(self, code_gen);
+        code_gen.instruction(&Instruction::I32Sub);
+        code_gen.write_expr_field(expression, &LIST_LENGTH_FIELD);
+        Ok(())
+    }
+}
+
+/// Leaves the index of the first element of the slice on the stack
+fn encode_slice_start(slice: &ast::Slice, code_gen: &mut CodeGenerator) {
+    match slice.start {
+        Some(start) => code_gen.read_expr_field(start, &U32_FIELD),
+        None => code_gen.const_i32(0),
+    }
+}
+
+/// Leaves the index after the last element of the slice on the stack
+fn encode_slice_end(slice: &ast::Slice, code_gen: &mut CodeGenerator) {
+    match slice.end {
+        Some(end) => code_gen.read_expr_field(end, &U32_FIELD),
+        None => code_gen.read_expr_field(slice.list, &LIST_LENGTH_FIELD),
+    }
+}
+
+/// Leaves whether the index is within the bounds of the list on the stack
+pub fn encode_bounds_check(list: ExpressionId, index: ExpressionId, code_gen: &mut CodeGenerator) {
+    code_gen.read_expr_field(index, &U32_FIELD);
+    code_gen.read_expr_field(list, &LIST_LENGTH_FIELD);
+    code_gen.instruction(&Instruction::I32LtU);
+}
+
+/// Leaves the address of the element at the index on the stack
+pub fn encode_element_address(
+    list: ExpressionId,
+    index: ExpressionId,
+    element_size: u32,
+    code_gen: &mut CodeGenerator,
+) {
+    code_gen.read_expr_field(list, &LIST_POINTER_FIELD);
+    code_gen.read_expr_field(index, &U32_FIELD);
+    code_gen.const_i32(element_size as i32);
+    code_gen.instruction(&Instruction::I32Mul);
+    code_gen.instruction(&Instruction::I32Add);
+}
+
 impl EncodeExpression for ast::BinaryExpression {
     fn alloc_expr_locals(
         &self,
@@ -250,6 +854,12 @@ impl EncodeExpression for ast::BinaryExpression {
         allocator: &mut ExpressionAllocator,
     ) -> Result<(), GenerationError> {
         allocator.alloc(expression)?;
+        let ptype = allocator.get_ptype(self.left)?;
+        if self.is_relation() && ptype == Some(ast::PrimitiveType::String) {
+            // Comparing strings needs an index and the difference of the current bytes
+            allocator.alloc_extra(enc::ValType::I32)?;
+            allocator.alloc_extra(enc::ValType::I32)?;
+        }
         allocator.alloc_child(self.left)?;
         allocator.alloc_child(self.right)?;
         Ok(())
@@ -260,22 +870,57 @@ impl EncodeExpression for ast::BinaryExpression {
         expression: ExpressionId,
         code_gen: &mut CodeGenerator,
     ) -> Result<(), GenerationError> {
+        if self.is_logical() {
+            return encode_short_circuit(self.op, expression, self.left, self.right, code_gen);
+        }
         code_gen.encode_child(self.left)?;
         code_gen.encode_child(self.right)?;
 
-        let ptype = code_gen.get_ptype(expression)?;
+        let ptype = code_gen.get_ptype(self.left)?;
         if ptype == Some(ast::PrimitiveType::String) {
             if self.op == ast::BinaryOp::Add {
                 encode_string_concatenation(expression, self.left, self.right, code_gen)
+            } else if self.is_relation() {
+                encode_string_comparison(self.op, expression, self.left, self.right, code_gen)
             } else {
-                panic!("Strings can only be concatenated with '+'");
+                panic!("Strings can only be concatenated with '+' or compared");
             }
+        } else if matches!(
+            self.op,
+            ast::BinaryOp::SaturatingAdd | ast::BinaryOp::SaturatingSubtract
+        ) {
+            encode_saturating_arithmetic(self.op, expression, self.left, self.right, code_gen)
         } else {
             encode_binary_arithmetic(self.op, expression, self.left, self.right, code_gen)
         }
     }
 }
 
+/// The right operand of `and` and `or` is only evaluated in an `if` block
+/// when the left operand doesn't already decide the result
+fn encode_short_circuit(
+    op: ast::BinaryOp,
+    expression: ExpressionId,
+    left: ExpressionId,
+    right: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let field = code_gen.one_field(expression)?;
+    code_gen.encode_child(left)?;
+    code_gen.read_expr_field(left, &field);
+    code_gen.write_expr_field(expression, &field);
+    code_gen.read_expr_field(left, &field);
+    if op == ast::BinaryOp::LogicalOr {
+        code_gen.instruction(&Instruction::I32Eqz);
+    }
+    code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+    code_gen.encode_child(right)?;
+    code_gen.read_expr_field(right, &field);
+    code_gen.write_expr_field(expression, &field);
+    code_gen.instruction(&Instruction::End);
+    Ok(())
+}
+
 fn encode_string_concatenation(
     expression: ExpressionId,
     left: ExpressionId,
@@ -315,6 +960,79 @@ fn encode_string_concatenation(
     Ok(())
 }
 
+/// Strings are compared byte by byte like `memcmp`, with a shorter string
+/// ordered before any longer string it is a prefix of
+fn encode_string_comparison(
+    op: ast::BinaryOp,
+    expression: ExpressionId,
+    left: ExpressionId,
+    right: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    // Scratch locals allocated directly after the result
+    let index = code_gen.expr_local_index(expression) + 1;
+    let difference = index + 1;
+    let byte = enc::MemArg {
+        offset: 0,
+        align: 0,
+        memory_index: 0,
+    };
+
+    code_gen.const_i32(0);
+    code_gen.instruction(&Instruction::LocalSet(index));
+    code_gen.const_i32(0);
+    code_gen.instruction(&Instruction::LocalSet(difference));
+    code_gen.instruction(&Instruction::Block(enc::BlockType::Empty));
+    code_gen.instruction(&Instruction::Loop(enc::BlockType::Empty));
+    // Stop at the end of either string
+    for string in [left, right] {
+        code_gen.instruction(&Instruction::LocalGet(index));
+        code_gen.read_expr_field(string, &STRING_LENGTH_FIELD);
+        code_gen.instruction(&Instruction::I32GeU);
+        code_gen.instruction(&Instruction::BrIf(1));
+    }
+    // Stop at the first differing byte
+    for string in [left, right] {
+        code_gen.read_expr_field(string, &STRING_OFFSET_FIELD);
+        code_gen.instruction(&Instruction::LocalGet(index));
+        code_gen.instruction(&Instruction::I32Add);
+        code_gen.instruction(&Instruction::I32Load8U(byte));
+    }
+    code_gen.instruction(&Instruction::I32Sub);
+    code_gen.instruction(&Instruction::LocalTee(difference));
+    code_gen.instruction(&Instruction::BrIf(1));
+    // Next byte
+    code_gen.instruction(&Instruction::LocalGet(index));
+    code_gen.const_i32(1);
+    code_gen.instruction(&Instruction::I32Add);
+    code_gen.instruction(&Instruction::LocalSet(index));
+    code_gen.instruction(&Instruction::Br(0));
+    code_gen.instruction(&Instruction::End);
+    code_gen.instruction(&Instruction::End);
+
+    // When one string is a prefix of the other the lengths decide
+    code_gen.instruction(&Instruction::LocalGet(difference));
+    code_gen.read_expr_field(left, &STRING_LENGTH_FIELD);
+    code_gen.read_expr_field(right, &STRING_LENGTH_FIELD);
+    code_gen.instruction(&Instruction::I32Sub);
+    code_gen.instruction(&Instruction::LocalGet(difference));
+    code_gen.instruction(&Instruction::Select);
+
+    code_gen.const_i32(0);
+    code_gen.instruction(&match op {
+        ast::BinaryOp::LessThan => Instruction::I32LtS,
+        ast::BinaryOp::LessThanEqual => Instruction::I32LeS,
+        ast::BinaryOp::GreaterThan => Instruction::I32GtS,
+        ast::BinaryOp::GreaterThanEqual => Instruction::I32GeS,
+        ast::BinaryOp::Equals => Instruction::I32Eq,
+        ast::BinaryOp::NotEquals => Instruction::I32Ne,
+        op => panic!("{:?} is not a comparison", op),
+    });
+    let field = code_gen.one_field(expression)?;
+    code_gen.write_expr_field(expression, &field);
+    Ok(())
+}
+
 const S: Signedness = Signedness::Signed;
 const U: Signedness = Signedness::Unsigned;
 
@@ -354,6 +1072,13 @@ fn encode_binary_arithmetic(
         (ast::BinaryOp::Modulo, enc::ValType::I32, U) => enc::Instruction::I32RemU,
         (ast::BinaryOp::Modulo, enc::ValType::I64, S) => enc::Instruction::I64RemS,
         (ast::BinaryOp::Modulo, enc::ValType::I64, U) => enc::Instruction::I64RemU,
+        // Wrapping arithmetic (the result is masked like any other arithmetic)
+        (ast::BinaryOp::WrappingMultiply, enc::ValType::I32, _) => enc::Instruction::I32Mul,
+        (ast::BinaryOp::WrappingMultiply, enc::ValType::I64, _) => enc::Instruction::I64Mul,
+        (ast::BinaryOp::WrappingAdd, enc::ValType::I32, _) => enc::Instruction::I32Add,
+        (ast::BinaryOp::WrappingAdd, enc::ValType::I64, _) => enc::Instruction::I64Add,
+        (ast::BinaryOp::WrappingSubtract, enc::ValType::I32, _) => enc::Instruction::I32Sub,
+        (ast::BinaryOp::WrappingSubtract, enc::ValType::I64, _) => enc::Instruction::I64Sub,
         // Addition
         (ast::BinaryOp::Add, enc::ValType::I32, _) => enc::Instruction::I32Add,
         (ast::BinaryOp::Add, enc::ValType::I64, _) => enc::Instruction::I64Add,
@@ -421,9 +1146,6 @@ fn encode_binary_arithmetic(
         // Bitwise or
         (ast::BinaryOp::BitOr, enc::ValType::I32, _) => enc::Instruction::I32Or,
         (ast::BinaryOp::BitOr, enc::ValType::I64, _) => enc::Instruction::I64Or,
-        // Logical and/or
-        (ast::BinaryOp::LogicalAnd, enc::ValType::I32, _) => enc::Instruction::I32And,
-        (ast::BinaryOp::LogicalOr, enc::ValType::I32, _) => enc::Instruction::I32Or,
         // Fallback
         (operator, valtype, _) => panic!(
             "Cannot apply binary operator {:?} to type {:?}",
@@ -440,3 +1162,147 @@ fn encode_binary_arithmetic(
     code_gen.write_expr_field(expression, &field);
     Ok(())
 }
+
+/// Saturating arithmetic clamps the result to the range of the type instead of wrapping.
+///
+/// The expression's own local holds the unclamped result while the bounds are checked.
+fn encode_saturating_arithmetic(
+    op: ast::BinaryOp,
+    expression: ExpressionId,
+    left: ExpressionId,
+    right: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let field = code_gen.one_field(expression)?;
+    let is_add = op == ast::BinaryOp::SaturatingAdd;
+
+    if let Some(mask) = field.arith_mask {
+        // 8 and 16-bit values can't overflow an i32 so the result is clamped directly
+        read_extended(left, &field, code_gen);
+        read_extended(right, &field, code_gen);
+        code_gen.instruction(if is_add {
+            &Instruction::I32Add
+        } else {
+            &Instruction::I32Sub
+        });
+        code_gen.write_expr_field(expression, &field);
+        let (min, max) = match field.signedness {
+            S => (-(mask + 1) / 2, mask / 2),
+            U => (0, mask),
+        };
+        for (bound, past_bound) in [(max, Instruction::I32GtS), (min, Instruction::I32LtS)] {
+            code_gen.const_i32(bound);
+            code_gen.read_expr_field(expression, &field);
+            code_gen.read_expr_field(expression, &field);
+            code_gen.const_i32(bound);
+            code_gen.instruction(&past_bound);
+            code_gen.instruction(&Instruction::Select);
+            code_gen.write_expr_field(expression, &field);
+        }
+        code_gen.read_expr_field(expression, &field);
+        code_gen.const_i32(mask);
+        code_gen.instruction(&Instruction::I32And);
+        code_gen.write_expr_field(expression, &field);
+        return Ok(());
+    }
+
+    let is_64 = field.stack_type == enc::ValType::I64;
+    let ops = if is_64 { &I64_OPS } else { &I32_OPS };
+
+    code_gen.read_expr_field(left, &field);
+    code_gen.read_expr_field(right, &field);
+    code_gen.instruction(if is_add { &ops.add } else { &ops.sub });
+    code_gen.write_expr_field(expression, &field);
+
+    match field.signedness {
+        U => {
+            // saturated value
+            code_gen.instruction(if is_add { &ops.all_ones } else { &ops.zero });
+            // wrapped value
+            code_gen.read_expr_field(expression, &field);
+            // the sum wrapped if it's less than an operand,
+            // the difference wrapped if the right operand was larger
+            if is_add {
+                code_gen.read_expr_field(expression, &field);
+                code_gen.read_expr_field(left, &field);
+            } else {
+                code_gen.read_expr_field(left, &field);
+                code_gen.read_expr_field(right, &field);
+            }
+            code_gen.instruction(&ops.lt_u);
+        }
+        S => {
+            // saturated value is MIN when the left operand is negative and MAX otherwise
+            code_gen.read_expr_field(left, &field);
+            code_gen.instruction(&ops.sign_shift);
+            code_gen.instruction(&ops.shr_s);
+            code_gen.instruction(&ops.max);
+            code_gen.instruction(&ops.xor);
+            // wrapped value
+            code_gen.read_expr_field(expression, &field);
+            // overflowed if the result's sign differs from what the operands allow
+            code_gen.read_expr_field(left, &field);
+            if is_add {
+                code_gen.read_expr_field(expression, &field);
+                code_gen.instruction(&ops.xor);
+                code_gen.read_expr_field(right, &field);
+                code_gen.read_expr_field(expression, &field);
+            } else {
+                code_gen.read_expr_field(right, &field);
+                code_gen.instruction(&ops.xor);
+                code_gen.read_expr_field(left, &field);
+                code_gen.read_expr_field(expression, &field);
+            }
+            code_gen.instruction(&ops.xor);
+            code_gen.instruction(&ops.and);
+            code_gen.instruction(&ops.zero);
+            code_gen.instruction(&ops.lt_s);
+        }
+    }
+    code_gen.instruction(&Instruction::Select);
+    code_gen.write_expr_field(expression, &field);
+    Ok(())
+}
+
+/// The instructions used for saturating arithmetic of a given width
+struct SaturatingOps {
+    add: Instruction<'static>,
+    sub: Instruction<'static>,
+    xor: Instruction<'static>,
+    and: Instruction<'static>,
+    shr_s: Instruction<'static>,
+    lt_s: Instruction<'static>,
+    lt_u: Instruction<'static>,
+    zero: Instruction<'static>,
+    all_ones: Instruction<'static>,
+    max: Instruction<'static>,
+    sign_shift: Instruction<'static>,
+}
+
+const I32_OPS: SaturatingOps = SaturatingOps {
+    add: Instruction::I32Add,
+    sub: Instruction::I32Sub,
+    xor: Instruction::I32Xor,
+    and: Instruction::I32And,
+    shr_s: Instruction::I32ShrS,
+    lt_s: Instruction::I32LtS,
+    lt_u: Instruction::I32LtU,
+    zero: Instruction::I32Const(0),
+    all_ones: Instruction::I32Const(-1),
+    max: Instruction::I32Const(i32::MAX),
+    sign_shift: Instruction::I32Const(31),
+};
+
+const I64_OPS: SaturatingOps = SaturatingOps {
+    add: Instruction::I64Add,
+    sub: Instruction::I64Sub,
+    xor: Instruction::I64Xor,
+    and: Instruction::I64And,
+    shr_s: Instruction::I64ShrS,
+    lt_s: Instruction::I64LtS,
+    lt_u: Instruction::I64LtU,
+    zero: Instruction::I64Const(0),
+    all_ones: Instruction::I64Const(-1),
+    max: Instruction::I64Const(i64::MAX),
+    sign_shift: Instruction::I64Const(63),
+};
@@ -0,0 +1,199 @@
+use ast::{ExpressionId, PrimitiveType};
+use claw_ast as ast;
+use claw_resolver::format::{parse_format, FormatPiece, FormatSpec};
+
+use crate::builders::module::ModuleFunctionIndex;
+use crate::builtins::read_extended;
+use crate::code::CodeGenerator;
+use crate::types::{Signedness, STRING_LENGTH_FIELD, STRING_OFFSET_FIELD};
+use crate::GenerationError;
+
+use wasm_encoder as enc;
+use wasm_encoder::Instruction;
+
+/// The functions of the format module, which write numbers into memory
+#[derive(Clone, Copy, Debug)]
+pub struct FormatFuncs {
+    /// `(value: i64, signed: i32, radix: i64, dest: i32) -> i32`
+    pub int: ModuleFunctionIndex,
+    /// `(value: f64, precision: i32, trim: i32, dest: i32) -> i32`
+    pub float: ModuleFunctionIndex,
+}
+
+/// Floats without a precision are written with up to this many decimal places
+const DEFAULT_PRECISION: u32 = 6;
+
+/// The most bytes each kind of argument can be written with
+const MAX_INT_LEN: i32 = 20;
+const MAX_HEX_LEN: i32 = 16;
+const MAX_BOOL_LEN: i32 = 5;
+const MAX_FLOAT_LEN: i32 = 22;
+
+/// Lower a call to `format` into building its string.
+///
+/// A string long enough for any values of the arguments is allocated,
+/// then each piece is written after the last with the length of the
+/// result tracking where the next piece goes.
+pub fn encode_format(
+    args: &[ExpressionId],
+    expression: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let format = code_gen.format_string(args[0]);
+    let pieces = parse_format(format).expect("Format strings are checked by the resolver");
+
+    // Allocate the most that could be written
+    let mut max_len = 0;
+    let mut string_args = Vec::new();
+    let mut arg_iter = args[1..].iter();
+    for piece in pieces.iter() {
+        let spec = match piece {
+            FormatPiece::Text(text) => {
+                max_len += text.len() as i32;
+                continue;
+            }
+            FormatPiece::Arg(spec) => spec,
+        };
+        let arg = *arg_iter.next().unwrap();
+        max_len += match (code_gen.get_ptype(arg)?.unwrap(), spec) {
+            (PrimitiveType::String, _) => {
+                string_args.push(arg);
+                0
+            }
+            (PrimitiveType::Bool, _) => MAX_BOOL_LEN,
+            (PrimitiveType::F32 | PrimitiveType::F64, FormatSpec::Precision(precision)) => {
+                MAX_FLOAT_LEN + *precision as i32
+            }
+            (PrimitiveType::F32 | PrimitiveType::F64, _) => {
+                MAX_FLOAT_LEN + DEFAULT_PRECISION as i32
+            }
+            (_, FormatSpec::Hex) => MAX_HEX_LEN,
+            _ => MAX_INT_LEN,
+        };
+    }
+    code_gen.const_i32(0);
+    code_gen.const_i32(0);
+    code_gen.const_i32(1);
+    code_gen.const_i32(max_len);
+    for arg in string_args {
+        code_gen.read_expr_field(arg, &STRING_LENGTH_FIELD);
+        code_gen.instruction(&Instruction::I32Add);
+    }
+    code_gen.allocate();
+    code_gen.write_expr_field(expression, &STRING_OFFSET_FIELD);
+    code_gen.const_i32(0);
+    code_gen.write_expr_field(expression, &STRING_LENGTH_FIELD);
+
+    let mut arg_iter = args[1..].iter();
+    for piece in pieces.iter() {
+        match piece {
+            FormatPiece::Text(text) => encode_text(text, expression, code_gen),
+            FormatPiece::Arg(spec) => {
+                let arg = *arg_iter.next().unwrap();
+                encode_arg(arg, *spec, expression, code_gen)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Push where the next piece of the string is written
+fn read_end(expression: ExpressionId, code_gen: &mut CodeGenerator) {
+    code_gen.read_expr_field(expression, &STRING_OFFSET_FIELD);
+    code_gen.read_expr_field(expression, &STRING_LENGTH_FIELD);
+    code_gen.instruction(&Instruction::I32Add);
+}
+
+/// Add the number of bytes written, which MUST be on the stack, to the length
+fn grow(expression: ExpressionId, code_gen: &mut CodeGenerator) {
+    code_gen.read_expr_field(expression, &STRING_LENGTH_FIELD);
+    code_gen.instruction(&Instruction::I32Add);
+    code_gen.write_expr_field(expression, &STRING_LENGTH_FIELD);
+}
+
+fn encode_text(text: &str, expression: ExpressionId, code_gen: &mut CodeGenerator) {
+    let index = code_gen.encode_const_bytes(text.as_bytes());
+    read_end(expression, code_gen);
+    code_gen.const_i32(0);
+    code_gen.const_i32(text.len() as i32);
+    code_gen.instruction(&Instruction::MemoryInit {
+        mem: 0,
+        data_index: index.into(),
+    });
+    code_gen.const_i32(text.len() as i32);
+    grow(expression, code_gen);
+}
+
+fn encode_arg(
+    arg: ExpressionId,
+    spec: FormatSpec,
+    expression: ExpressionId,
+    code_gen: &mut CodeGenerator,
+) -> Result<(), GenerationError> {
+    let ptype = code_gen.get_ptype(arg)?.unwrap();
+    match ptype {
+        PrimitiveType::String => {
+            read_end(expression, code_gen);
+            code_gen.read_expr_field(arg, &STRING_OFFSET_FIELD);
+            code_gen.read_expr_field(arg, &STRING_LENGTH_FIELD);
+            code_gen.instruction(&Instruction::MemoryCopy {
+                src_mem: 0,
+                dst_mem: 0,
+            });
+            code_gen.read_expr_field(arg, &STRING_LENGTH_FIELD);
+            grow(expression, code_gen);
+        }
+        PrimitiveType::Bool => {
+            let field = code_gen.one_field(arg)?;
+            code_gen.read_expr_field(arg, &field);
+            code_gen.instruction(&Instruction::If(enc::BlockType::Empty));
+            encode_text("true", expression, code_gen);
+            code_gen.instruction(&Instruction::Else);
+            encode_text("false", expression, code_gen);
+            code_gen.instruction(&Instruction::End);
+        }
+        PrimitiveType::F32 | PrimitiveType::F64 => {
+            let field = code_gen.one_field(arg)?;
+            code_gen.read_expr_field(arg, &field);
+            if ptype == PrimitiveType::F32 {
+                code_gen.instruction(&Instruction::F64PromoteF32);
+            }
+            let (precision, trim) = match spec {
+                FormatSpec::Precision(precision) => (precision, false),
+                _ => (DEFAULT_PRECISION, true),
+            };
+            code_gen.const_i32(precision as i32);
+            code_gen.const_i32(trim as i32);
+            read_end(expression, code_gen);
+            code_gen.call_format_float();
+            grow(expression, code_gen);
+        }
+        _ => {
+            let field = code_gen.one_field(arg)?;
+            let hex = spec == FormatSpec::Hex;
+            // Hex is written from the bits of the value, decimal from its value
+            let signed = !hex && field.signedness == Signedness::Signed;
+            match (field.stack_type, signed) {
+                (enc::ValType::I32, true) => {
+                    read_extended(arg, &field, code_gen);
+                    code_gen.instruction(&Instruction::I64ExtendI32S);
+                }
+                (enc::ValType::I32, false) => {
+                    code_gen.read_expr_field(arg, &field);
+                    if let Some(mask) = field.arith_mask {
+                        code_gen.const_i32(mask);
+                        code_gen.instruction(&Instruction::I32And);
+                    }
+                    code_gen.instruction(&Instruction::I64ExtendI32U);
+                }
+                _ => code_gen.read_expr_field(arg, &field),
+            }
+            code_gen.const_i32(signed as i32);
+            code_gen.instruction(&Instruction::I64Const(if hex { 16 } else { 10 }));
+            read_end(expression, code_gen);
+            code_gen.call_format_int();
+            grow(expression, code_gen);
+        }
+    }
+    Ok(())
+}
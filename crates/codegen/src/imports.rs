@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use crate::builders::module::{ModuleBuilder, ModuleTypeIndex};
+use crate::objects::{object_funcs, CoreObject, ObjectFunc, Signature};
 use crate::types::align_to;
 use crate::{builders::component::*, types::EncodeType, GenerationError};
 use crate::{MAX_FLAT_PARAMS, MAX_FLAT_RESULTS};
@@ -22,6 +23,11 @@ pub struct ImportEncoder<'gen> {
     funcs: HashMap<ImportFuncId, EncodedImportFunc>,
 
     inline_export_args: Vec<(String, InlineExportItem)>,
+
+    objects: &'gen [CoreObject],
+    object_funcs: HashMap<String, ObjectFunc>,
+    /// The objects which have been instantiated because an import uses them
+    object_instances: HashMap<usize, ComponentModuleInstanceIndex>,
 }
 
 pub struct EncodedImports {
@@ -127,11 +133,13 @@ impl<'gen> ImportEncoder<'gen> {
         rcomp: &'gen ResolvedComponent,
         memory: ComponentCoreMemoryIndex,
         realloc: ComponentCoreFunctionIndex,
-    ) -> Self {
+        objects: &'gen [CoreObject],
+    ) -> Result<Self, GenerationError> {
         let funcs = HashMap::new();
         let inline_export_args = Vec::new();
+        let object_funcs = object_funcs(objects)?;
 
-        Self {
+        Ok(Self {
             builder,
             comp,
             rcomp,
@@ -139,7 +147,10 @@ impl<'gen> ImportEncoder<'gen> {
             realloc,
             funcs,
             inline_export_args,
-        }
+            objects,
+            object_funcs,
+            object_instances: HashMap::new(),
+        })
     }
 
     pub fn encode(mut self) -> Result<EncodedImports, GenerationError> {
@@ -147,7 +158,7 @@ impl<'gen> ImportEncoder<'gen> {
             self.encode_interface(interface)?;
         }
 
-        self.encode_loose_funcs();
+        self.encode_loose_funcs()?;
 
         let imports_instance = self.builder.inline_export(&self.inline_export_args);
         Ok(EncodedImports {
@@ -160,13 +171,18 @@ impl<'gen> ImportEncoder<'gen> {
         ImportInterfaceEncoder::new(self, interface).encode()
     }
 
-    fn encode_loose_funcs(&mut self) {
+    fn encode_loose_funcs(&mut self) -> Result<(), GenerationError> {
         // Import the loose functions
         for id in self.rcomp.imports.loose_funcs.iter().copied() {
             let import_func = &self.rcomp.imports.funcs[id];
             let import_alias = import_func.alias.as_str();
             let import_name = import_func.name.as_str();
 
+            if self.object_funcs.contains_key(import_name) {
+                self.encode_object_func(id)?;
+                continue;
+            }
+
             let type_idx = self.encode_func_type(import_func);
             let func_idx = self.builder.import_func(import_name, type_idx);
             let core_func_idx = self.builder.lower_func(func_idx, self.memory, self.realloc);
@@ -179,6 +195,62 @@ impl<'gen> ImportEncoder<'gen> {
                 InlineExportItem::Func(core_func_idx),
             ));
         }
+        Ok(())
+    }
+
+    /// Use the function an object exports instead of importing it,
+    /// as long as the object's signature is the one the import lowers to
+    fn encode_object_func(&mut self, id: ImportFuncId) -> Result<(), GenerationError> {
+        let import_func = &self.rcomp.imports.funcs[id];
+        let object_func = &self.object_funcs[&import_func.name];
+        let object = &self.objects[object_func.object];
+
+        let enc_import_func = EncodedImportFunc::new(import_func, self.comp, self.rcomp);
+        // Objects have memories of their own, so only numbers can be passed to them
+        let scalar = |rtype: &ResolvedType| {
+            let ptype = match rtype {
+                ResolvedType::Primitive(ptype) => *ptype,
+                ResolvedType::Defined(type_id) => match self.comp.get_type(*type_id) {
+                    ast::ValType::Primitive(ptype) => *ptype,
+                    _ => return false,
+                },
+                _ => return false,
+            };
+            ptype != ast::PrimitiveType::String
+        };
+        let all_scalar = import_func.params.iter().all(|(_, rtype)| scalar(rtype))
+            && import_func.results.iter().all(scalar);
+        if !all_scalar
+            || enc_import_func.core_params != object_func.params
+            || enc_import_func.core_results != object_func.results
+        {
+            return Err(GenerationError::ObjectSignature {
+                name: import_func.name.clone(),
+                object: object.name.clone(),
+                expected: Signature(&enc_import_func.core_params, &enc_import_func.core_results)
+                    .to_string(),
+                found: Signature(&object_func.params, &object_func.results).to_string(),
+            });
+        }
+
+        let instance = match self.object_instances.get(&object_func.object) {
+            Some(instance) => *instance,
+            None => {
+                let module = self.builder.module_bytes(&object.bytes);
+                let args: Vec<(&str, ModuleInstantiateArgs)> = vec![];
+                let instance = self.builder.instantiate(module, args);
+                self.object_instances.insert(object_func.object, instance);
+                instance
+            }
+        };
+        let core_func_idx = self.builder.alias_core_func(instance, &import_func.name);
+
+        self.funcs.insert(id, enc_import_func);
+        self.inline_export_args.push((
+            import_func.alias.clone(),
+            InlineExportItem::Func(core_func_idx),
+        ));
+        Ok(())
     }
 
     fn encode_func_type(&mut self, import_func: &ImportFunction) -> ComponentTypeIndex {
@@ -203,8 +275,17 @@ impl<'gen> ImportEncoder<'gen> {
     fn rtype_to_comp_valtype(&self, rtype: ResolvedType) -> enc::ComponentValType {
         match rtype {
             ResolvedType::Primitive(ptype) => ptype.to_comp_valtype(self.comp, self.rcomp),
-            ResolvedType::Import(_) => todo!(),
             ResolvedType::Defined(type_id) => type_id.to_comp_valtype(self.comp, self.rcomp),
+            // Loose imports are declared with types written in the source,
+            // which the resolver limits to primitives and newtypes of them
+            ResolvedType::Import(_)
+            | ResolvedType::Option(_)
+            | ResolvedType::Record(_)
+            | ResolvedType::Enum(_)
+            | ResolvedType::Newtype(_)
+            | ResolvedType::Function(_) => {
+                unreachable!("Imported function types are checked by the resolver")
+            }
         }
     }
 }
@@ -368,6 +449,14 @@ impl<'a, 'b, 'c> ImportInterfaceEncoder<'a, 'b, 'c> {
             ResolvedType::Defined(type_id) => {
                 type_id.to_comp_valtype(self.parent.comp, self.parent.rcomp)
             }
+            // Interface imports only refer to primitives and the interface's own types
+            ResolvedType::Option(_)
+            | ResolvedType::Record(_)
+            | ResolvedType::Enum(_)
+            | ResolvedType::Newtype(_)
+            | ResolvedType::Function(_) => {
+                unreachable!("WIT types are resolved to primitives or imported types")
+            }
         }
     }
 }
@@ -0,0 +1,237 @@
+//! TypeScript declarations for the exports of a component, matching
+//! the JavaScript that `jco transpile` generates for it.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use ast::{PrimitiveType, ValType};
+use claw_ast as ast;
+
+use crate::GenerationError;
+
+/// Generate a `.d.ts` file declaring the exported functions of the
+/// component and the types they use.
+pub fn generate_dts(comp: &ast::Component) -> Result<String, GenerationError> {
+    let mut gen = DtsGenerator {
+        comp,
+        type_defs: HashSet::new(),
+    };
+
+    let mut functions = String::new();
+    for (_, function) in comp.iter_functions() {
+        if function.exported {
+            gen.function(function, &mut functions)?;
+        }
+    }
+    for (id, type_def) in comp.iter_type_defs() {
+        if type_def.exported() {
+            gen.use_type_def(id)?;
+        }
+    }
+
+    let mut sections = Vec::new();
+    for (id, type_def) in comp.iter_type_defs() {
+        if !gen.type_defs.contains(&id) {
+            continue;
+        }
+        let mut out = String::new();
+        match type_def {
+            ast::TypeDefinition::Record(record) => gen.record(record, &mut out)?,
+            ast::TypeDefinition::Enum(enum_def) => gen.enum_def(enum_def, &mut out),
+            ast::TypeDefinition::Newtype(newtype) => gen.newtype(newtype, &mut out)?,
+        }
+        sections.push(out);
+    }
+    if !functions.is_empty() {
+        sections.push(functions);
+    }
+    Ok(sections.join("\n"))
+}
+
+struct DtsGenerator<'ctx> {
+    comp: &'ctx ast::Component,
+    /// The types defined in the component used by the exports, which need declaring
+    type_defs: HashSet<ast::TypeDefId>,
+}
+
+impl<'ctx> DtsGenerator<'ctx> {
+    fn function(
+        &mut self,
+        function: &ast::Function,
+        out: &mut String,
+    ) -> Result<(), GenerationError> {
+        let params = function
+            .params
+            .iter()
+            .map(|(name, type_id)| {
+                let name = lower_camel(self.comp.get_name(*name));
+                Ok(format!("{}: {}", name, self.tstype(*type_id)?))
+            })
+            .collect::<Result<Vec<_>, GenerationError>>()?;
+        let results = match function.results {
+            Some(results) => self.tstype(results)?,
+            None => "void".to_owned(),
+        };
+        let name = lower_camel(self.comp.get_name(function.ident));
+        writeln!(
+            out,
+            "export function {}({}): {};",
+            name,
+            params.join(", "),
+            results
+        )
+        .unwrap();
+        Ok(())
+    }
+
+    fn record(
+        &mut self,
+        record: &ast::RecordTypeDef,
+        out: &mut String,
+    ) -> Result<(), GenerationError> {
+        let name = upper_camel(self.comp.get_name(record.ident));
+        writeln!(out, "export interface {} {{", name).unwrap();
+        for field in record.fields.iter() {
+            let name = lower_camel(self.comp.get_name(field.ident));
+            writeln!(out, "  {}: {},", name, self.tstype(field.type_id)?).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+        Ok(())
+    }
+
+    /// Enums are the names of their cases as strings
+    fn enum_def(&mut self, enum_def: &ast::EnumTypeDef, out: &mut String) {
+        let name = upper_camel(self.comp.get_name(enum_def.ident));
+        let cases: Vec<_> = enum_def
+            .cases
+            .iter()
+            .map(|case| format!("'{}'", self.comp.get_name(case.ident)))
+            .collect();
+        writeln!(out, "export type {} = {};", name, cases.join(" | ")).unwrap();
+    }
+
+    /// Newtypes are aliases of the type they wrap
+    fn newtype(
+        &mut self,
+        newtype: &ast::NewtypeDef,
+        out: &mut String,
+    ) -> Result<(), GenerationError> {
+        let name = upper_camel(self.comp.get_name(newtype.ident));
+        writeln!(
+            out,
+            "export type {} = {};",
+            name,
+            self.tstype(newtype.inner)?
+        )
+        .unwrap();
+        Ok(())
+    }
+
+    fn tstype(&mut self, type_id: ast::TypeId) -> Result<String, GenerationError> {
+        let tstype = match self.comp.get_type(type_id) {
+            ValType::Option(some) => format!("{} | undefined", self.tstype(*some)?),
+            ValType::List(element) => match self.comp.get_type(*element) {
+                ValType::Primitive(ptype) if typed_array(*ptype).is_some() => {
+                    typed_array(*ptype).unwrap().to_owned()
+                }
+                _ => format!("Array<{}>", self.tstype(*element)?),
+            },
+            ValType::Result(result) => format!(
+                "{{ tag: 'ok', val: {} }} | {{ tag: 'err', val: {} }}",
+                self.tstype(result.ok)?,
+                self.tstype(result.err)?
+            ),
+            ValType::Primitive(ptype) => primitive(*ptype).to_owned(),
+            ValType::Named(name) => {
+                let name = self.comp.get_name(*name);
+                let type_def = self.comp.find_type_def(name).unwrap();
+                self.use_type_def(type_def)?;
+                upper_camel(name)
+            }
+            ValType::Func(_) => {
+                let span = self.comp.type_span(type_id);
+                return Err(GenerationError::NotInWit {
+                    src: self.comp.file_source(span.file()),
+                    span,
+                });
+            }
+        };
+        Ok(tstype)
+    }
+
+    /// Declare a type, along with the types used in it
+    fn use_type_def(&mut self, type_def: ast::TypeDefId) -> Result<(), GenerationError> {
+        if !self.type_defs.insert(type_def) {
+            return Ok(());
+        }
+        match self.comp.get_type_def(type_def) {
+            ast::TypeDefinition::Record(record) => {
+                for field in record.fields.iter() {
+                    self.tstype(field.type_id)?;
+                }
+            }
+            ast::TypeDefinition::Enum(_) => {}
+            ast::TypeDefinition::Newtype(newtype) => {
+                self.tstype(newtype.inner)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn primitive(ptype: PrimitiveType) -> &'static str {
+    match ptype {
+        PrimitiveType::Bool => "boolean",
+        PrimitiveType::U8
+        | PrimitiveType::S8
+        | PrimitiveType::U16
+        | PrimitiveType::S16
+        | PrimitiveType::U32
+        | PrimitiveType::S32
+        | PrimitiveType::F32
+        | PrimitiveType::F64 => "number",
+        PrimitiveType::U64 | PrimitiveType::S64 => "bigint",
+        PrimitiveType::String => "string",
+    }
+}
+
+/// Lists of numbers are passed as typed arrays
+fn typed_array(ptype: PrimitiveType) -> Option<&'static str> {
+    let array = match ptype {
+        PrimitiveType::U8 => "Uint8Array",
+        PrimitiveType::S8 => "Int8Array",
+        PrimitiveType::U16 => "Uint16Array",
+        PrimitiveType::S16 => "Int16Array",
+        PrimitiveType::U32 => "Uint32Array",
+        PrimitiveType::S32 => "Int32Array",
+        PrimitiveType::U64 => "BigUint64Array",
+        PrimitiveType::S64 => "BigInt64Array",
+        PrimitiveType::F32 => "Float32Array",
+        PrimitiveType::F64 => "Float64Array",
+        PrimitiveType::Bool | PrimitiveType::String => return None,
+    };
+    Some(array)
+}
+
+/// `kebab-case` to `lowerCamelCase`
+fn lower_camel(name: &str) -> String {
+    let upper = upper_camel(name);
+    let mut chars = upper.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => upper,
+    }
+}
+
+/// `kebab-case` to `UpperCamelCase`
+fn upper_camel(name: &str) -> String {
+    name.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
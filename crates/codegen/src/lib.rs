@@ -1,27 +1,124 @@
 #![allow(clippy::single_match)]
 
+mod asm;
 mod builders;
+mod builtins;
 mod code;
+mod docs;
 mod expression;
+mod format;
 mod function;
 mod imports;
+mod js;
+mod link;
+mod liveness;
 mod module;
+mod objects;
+mod peephole;
+mod source_map;
 mod statement;
+mod stdio;
 mod types;
+mod wit;
 
 use builders::component::*;
 
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use ast::Span;
 use claw_ast as ast;
+use claw_common::Source;
 use claw_resolver::{ResolvedComponent, ResolverError};
 use miette::Diagnostic;
 use thiserror::Error;
 use types::EncodeType;
+use wasm_encoder as enc;
+
+pub use docs::generate_docs;
+pub use js::generate_dts;
+pub use link::{link, LinkComponent, LinkError};
+pub use objects::CoreObject;
+pub use source_map::{read_source_map, SourceLocation, SourceMap};
+pub use wit::generate_wit;
 
 #[derive(Error, Debug, Diagnostic)]
 pub enum GenerationError {
     #[error(transparent)]
     #[diagnostic(transparent)]
     Resolver(#[from] ResolverError),
+
+    #[error("Invalid inline asm: {message}")]
+    InlineAsm {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        message: String,
+    },
+
+    #[error("Object \"{object}\" is not valid: {message}")]
+    InvalidObject { object: String, message: String },
+
+    #[error("Object \"{object}\" has imports, which can't be provided to it")]
+    ObjectImports { object: String },
+
+    #[error("\"{name}\" is imported as {expected} but object \"{object}\" exports {found}")]
+    #[diagnostic(help("Only numbers and booleans can be passed to objects"))]
+    ObjectSignature {
+        name: String,
+        object: String,
+        expected: String,
+        found: String,
+    },
+
+    #[error("Function types can't be used by exported functions")]
+    NotInWit {
+        #[source_code]
+        src: Source,
+        #[label("This type has no WIT equivalent")]
+        span: Span,
+    },
+}
+
+/// How much work goes into the generated code
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Profile {
+    /// Unoptimized, with function names for debuggers and stack traces
+    #[default]
+    Debug,
+    /// Optimized, without any debug sections
+    Release,
+}
+
+impl Profile {
+    pub const ALL: &'static [Profile] = &[Profile::Debug, Profile::Release];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Debug => "debug",
+            Profile::Release => "release",
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Profile::ALL
+            .iter()
+            .copied()
+            .find(|profile| profile.name() == s)
+            .ok_or_else(|| format!("Unknown profile \"{}\"", s))
+    }
 }
 
 pub const MAX_FLAT_PARAMS: u8 = 16;
@@ -31,13 +128,31 @@ pub fn generate(
     comp: &ast::Component,
     rcomp: &ResolvedComponent,
 ) -> Result<Vec<u8>, GenerationError> {
-    let builder = generate_component(comp, rcomp)?;
+    generate_with_objects(comp, rcomp, &[], Profile::Debug, false)
+}
+
+/// Generate the component, taking the functions it imports
+/// from the given core wasm objects when they export them.
+///
+/// With `debug_info` the code is mapped back to the source
+/// in a section which [read_source_map] reads.
+pub fn generate_with_objects(
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    objects: &[CoreObject],
+    profile: Profile,
+    debug_info: bool,
+) -> Result<Vec<u8>, GenerationError> {
+    let builder = generate_component(comp, rcomp, objects, profile, debug_info)?;
     Ok(builder.finalize().finish())
 }
 
 fn generate_component(
     comp: &ast::Component,
     rcomp: &ResolvedComponent,
+    objects: &[CoreObject],
+    profile: Profile,
+    debug_info: bool,
 ) -> Result<ComponentBuilder, GenerationError> {
     let mut builder = ComponentBuilder::default();
 
@@ -49,21 +164,36 @@ fn generate_component(
     let memory = builder.alias_memory(alloc_instance, "memory");
     let realloc = builder.alias_core_func(alloc_instance, "realloc");
 
-    let import_encoder = imports::ImportEncoder::new(&mut builder, comp, rcomp, memory, realloc);
+    // Formatting numbers writes into the memory of the allocator
+    let format_module = builder.module_bytes(gen_format());
+    let args = vec![("alloc", ModuleInstantiateArgs::Instance(alloc_instance))];
+    let format_instance = builder.instantiate(format_module, args);
+
+    let import_encoder =
+        imports::ImportEncoder::new(&mut builder, comp, rcomp, memory, realloc, objects)?;
     let imports = import_encoder.encode()?;
 
+    let stdio_instance = stdio::uses_stdio(rcomp)
+        .then(|| stdio::encode_stdio(&mut builder, alloc_instance, memory, realloc));
+
     let function_encoder = function::FunctionEncoder::new(comp, rcomp);
     let functions = function_encoder.encode()?;
 
-    let code_module = builder.module(module::generate(comp, rcomp, &imports, &functions)?);
+    let code_module = builder.module(module::generate(
+        comp, rcomp, &imports, &functions, profile, debug_info,
+    )?);
 
-    let args = vec![
+    let mut args = vec![
         ("alloc", ModuleInstantiateArgs::Instance(alloc_instance)),
+        ("format", ModuleInstantiateArgs::Instance(format_instance)),
         (
             "claw",
             ModuleInstantiateArgs::Instance(imports.imports_instance),
         ),
     ];
+    if let Some(stdio_instance) = stdio_instance {
+        args.push(("stdio", ModuleInstantiateArgs::Instance(stdio_instance)));
+    }
     let code_instance = builder.instantiate(code_module, args);
 
     generate_exports(comp, rcomp, code_instance, memory, realloc, &mut builder)?;
@@ -78,10 +208,21 @@ struct ExportGenerator<'ctx> {
     code_instance: ComponentModuleInstanceIndex,
     memory: ComponentCoreMemoryIndex,
     realloc: ComponentCoreFunctionIndex,
+
+    /// The exported index of each struct or enum type exported so far
+    type_defs: HashMap<ast::TypeDefId, ComponentTypeIndex>,
 }
 
 impl<'ctx> ExportGenerator<'ctx> {
     fn generate(&mut self, builder: &mut ComponentBuilder) -> Result<(), GenerationError> {
+        for (id, type_def) in self.comp.iter_type_defs() {
+            // Newtypes are exported as the type they wrap
+            let newtype = matches!(type_def, ast::TypeDefinition::Newtype(_));
+            if type_def.exported() && !newtype {
+                self.named_type(id, builder);
+            }
+        }
+
         for (_, function) in self.comp.iter_functions() {
             if function.exported {
                 self.generate_function_export(function, builder)?;
@@ -104,22 +245,17 @@ impl<'ctx> ExportGenerator<'ctx> {
             builder.alias_core_func(self.code_instance, format!("{}_post_return", name).as_str());
 
         // Encode component func type
-        let params = function.params.iter().map(|(param_name, param_type)| {
-            let param_name = self.comp.get_name(*param_name);
-            let param_type = self.comp.get_type(*param_type);
-            let param_type = match param_type {
-                ast::ValType::Result(_) => todo!(),
-                ast::ValType::Primitive(ptype) => ptype.to_comp_valtype(self.comp, self.rcomp),
-            };
-            (param_name, param_type)
-        });
-        let results = function.results.map(|result_type| {
-            let result_type = self.comp.get_type(result_type);
-            match result_type {
-                ast::ValType::Result(_) => todo!(),
-                ast::ValType::Primitive(ptype) => ptype.to_comp_valtype(self.comp, self.rcomp),
-            }
-        });
+        let params: Vec<_> = function
+            .params
+            .iter()
+            .map(|(param_name, param_type)| {
+                let param_name = self.comp.get_name(*param_name);
+                (param_name, self.comp_valtype(*param_type, builder))
+            })
+            .collect();
+        let results = function
+            .results
+            .map(|result_type| self.comp_valtype(result_type, builder));
         let type_idx = builder.func_type(params, results);
 
         // Lift aliased function to component function
@@ -135,6 +271,77 @@ impl<'ctx> ExportGenerator<'ctx> {
 
         Ok(())
     }
+
+    /// Options and lists have to be defined in the component before a function type can use them
+    fn comp_valtype(
+        &mut self,
+        type_id: ast::TypeId,
+        builder: &mut ComponentBuilder,
+    ) -> enc::ComponentValType {
+        match self.comp.get_type(type_id) {
+            ast::ValType::Option(some) => {
+                let some = self.comp_valtype(*some, builder);
+                builder.option_type(some).into()
+            }
+            ast::ValType::List(element) => {
+                let element = self.comp_valtype(*element, builder);
+                builder.list_type(element).into()
+            }
+            ast::ValType::Result(_) => todo!(),
+            ast::ValType::Primitive(ptype) => ptype.to_comp_valtype(self.comp, self.rcomp),
+            ast::ValType::Func(_) => type_id.to_comp_valtype(self.comp, self.rcomp),
+            ast::ValType::Named(name) => {
+                let name = self.comp.get_name(*name);
+                let type_def = self.comp.find_type_def(name).unwrap();
+                match self.comp.get_type_def(type_def) {
+                    ast::TypeDefinition::Newtype(newtype) => {
+                        self.comp_valtype(newtype.inner, builder)
+                    }
+                    _ => self.named_type(type_def, builder).into(),
+                }
+            }
+        }
+    }
+
+    /// Structs and enums are exported the first time they're used, as
+    /// exported functions can only refer to exported types
+    fn named_type(
+        &mut self,
+        type_def: ast::TypeDefId,
+        builder: &mut ComponentBuilder,
+    ) -> ComponentTypeIndex {
+        if let Some(index) = self.type_defs.get(&type_def) {
+            return *index;
+        }
+        let index = match self.comp.get_type_def(type_def) {
+            ast::TypeDefinition::Record(record) => {
+                let fields: Vec<_> = record
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let name = self.comp.get_name(field.ident);
+                        (name, self.comp_valtype(field.type_id, builder))
+                    })
+                    .collect();
+                builder.record_type(fields)
+            }
+            ast::TypeDefinition::Enum(enum_def) => {
+                let cases: Vec<_> = enum_def
+                    .cases
+                    .iter()
+                    .map(|case| self.comp.get_name(case.ident))
+                    .collect();
+                builder.enum_type(cases)
+            }
+            ast::TypeDefinition::Newtype(_) => {
+                unreachable!("Newtypes are exported as the type they wrap")
+            }
+        };
+        let name = self.comp.get_name(self.comp.get_type_def(type_def).ident());
+        let index = builder.export_type(name, index);
+        self.type_defs.insert(type_def, index);
+        index
+    }
 }
 
 fn generate_exports(
@@ -151,6 +358,7 @@ fn generate_exports(
         code_instance,
         memory,
         realloc,
+        type_defs: Default::default(),
     };
     gen.generate(builder)
 }
@@ -161,3 +369,7 @@ pub fn gen_allocator() -> &'static [u8] {
     let allocator_wasm = include_bytes!(concat!(env!("OUT_DIR"), "/allocator.wasm"));
     allocator_wasm
 }
+
+pub fn gen_format() -> &'static [u8] {
+    include_bytes!(concat!(env!("OUT_DIR"), "/format.wasm"))
+}
@@ -0,0 +1,536 @@
+//! Composing several components into one.
+//!
+//! Each component is nested inside the linked component and instantiated
+//! with the exports of the components instantiated before it, so its
+//! imports are satisfied without involving the host. The linked component
+//! has the same exports as the root component.
+
+use std::collections::HashMap;
+
+use miette::Diagnostic;
+use thiserror::Error;
+use wasm_encoder as enc;
+use wasmparser::types::{
+    ComponentAnyTypeId, ComponentDefinedType, ComponentDefinedTypeId, ComponentEntityType,
+    ComponentFuncTypeId, ComponentValType, Types,
+};
+use wasmparser::{
+    ComponentExternalKind, ComponentTypeRef, Parser, Payload, PrimitiveValType, Validator,
+    WasmFeatures,
+};
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum LinkError {
+    #[error("Component \"{name}\" is not valid: {message}")]
+    Invalid { name: String, message: String },
+
+    #[error("Import \"{import}\" of \"{component}\" isn't exported by any other component")]
+    #[diagnostic(help("Add a dependency which exports it"))]
+    UnresolvedImport { component: String, import: String },
+
+    #[error("Import \"{import}\" of \"{component}\" is a kind of item that can't be linked")]
+    Unsupported { component: String, import: String },
+
+    #[error("Export \"{export}\" of \"{component}\" uses a type that can't be re-exported")]
+    UnsupportedExport { component: String, export: String },
+
+    #[error("The linked component is not valid: {message}")]
+    InvalidOutput { message: String },
+}
+
+/// A compiled component to link
+pub struct LinkComponent {
+    /// The name to report errors with
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Link the root component against its dependencies.
+///
+/// Dependencies may also import each others' exports,
+/// but no component may import something the host provides.
+pub fn link(root: LinkComponent, dependencies: Vec<LinkComponent>) -> Result<Vec<u8>, LinkError> {
+    let root = Interface::read(root)?;
+    let mut pending = dependencies
+        .into_iter()
+        .map(Interface::read)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut linker = Linker::default();
+    // Instantiate whichever dependency can be next until none are left
+    while !pending.is_empty() {
+        let ready = pending
+            .iter()
+            .position(|dependency| linker.can_instantiate(dependency));
+        match ready {
+            Some(index) => {
+                let dependency = pending.remove(index);
+                linker.instantiate(dependency)?;
+            }
+            None => {
+                let dependency = &pending[0];
+                return Err(linker.unresolved(dependency));
+            }
+        }
+    }
+    if !linker.can_instantiate(&root) {
+        return Err(linker.unresolved(&root));
+    }
+    let root_instance = linker.instantiate_root(&root)?;
+    linker.export_all(&root, root_instance)?;
+
+    let bytes = linker.component.finish();
+    Validator::new_with_features(WasmFeatures::all())
+        .validate_all(&bytes)
+        .map_err(|err| LinkError::InvalidOutput {
+            message: err.message().to_owned(),
+        })?;
+    Ok(bytes)
+}
+
+/// The top-level imports and exports of a component
+struct Interface {
+    name: String,
+    bytes: Vec<u8>,
+    types: Types,
+    imports: Vec<Import>,
+    exports: Vec<(String, ComponentExternalKind)>,
+}
+
+struct Import {
+    name: String,
+    kind: ComponentExternalKind,
+    /// The names of the items of an imported instance
+    instance_items: Vec<String>,
+}
+
+impl Interface {
+    fn read(component: LinkComponent) -> Result<Self, LinkError> {
+        let invalid = |err: wasmparser::BinaryReaderError| LinkError::Invalid {
+            name: component.name.clone(),
+            message: err.message().to_owned(),
+        };
+
+        let types = Validator::new_with_features(WasmFeatures::all())
+            .validate_all(&component.bytes)
+            .map_err(invalid)?;
+
+        let mut imports = Vec::new();
+        let mut exports = Vec::new();
+        // Nested modules and components have sections of their own
+        let mut depth = 0;
+        for payload in Parser::new(0).parse_all(&component.bytes) {
+            match payload.map_err(invalid)? {
+                Payload::Version { .. } => depth += 1,
+                Payload::End(_) => depth -= 1,
+                Payload::ComponentImportSection(reader) if depth == 1 => {
+                    for import in reader {
+                        let import = import.map_err(invalid)?;
+                        let name = import.name.0.to_owned();
+                        let instance_items = match types.component_entity_type_of_import(&name) {
+                            Some(ComponentEntityType::Instance(id)) => {
+                                types[id].exports.keys().cloned().collect()
+                            }
+                            _ => Vec::new(),
+                        };
+                        imports.push(Import {
+                            name,
+                            kind: ref_kind(import.ty),
+                            instance_items,
+                        });
+                    }
+                }
+                Payload::ComponentExportSection(reader) if depth == 1 => {
+                    for export in reader {
+                        let export = export.map_err(invalid)?;
+                        exports.push((export.name.0.to_owned(), export.kind));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Interface {
+            name: component.name,
+            bytes: component.bytes,
+            types,
+            imports,
+            exports,
+        })
+    }
+}
+
+fn ref_kind(ty: ComponentTypeRef) -> ComponentExternalKind {
+    match ty {
+        ComponentTypeRef::Module(_) => ComponentExternalKind::Module,
+        ComponentTypeRef::Func(_) => ComponentExternalKind::Func,
+        ComponentTypeRef::Value(_) => ComponentExternalKind::Value,
+        ComponentTypeRef::Type(_) => ComponentExternalKind::Type,
+        ComponentTypeRef::Instance(_) => ComponentExternalKind::Instance,
+        ComponentTypeRef::Component(_) => ComponentExternalKind::Component,
+    }
+}
+
+fn export_kind(kind: ComponentExternalKind) -> Option<enc::ComponentExportKind> {
+    match kind {
+        ComponentExternalKind::Func => Some(enc::ComponentExportKind::Func),
+        ComponentExternalKind::Type => Some(enc::ComponentExportKind::Type),
+        ComponentExternalKind::Instance => Some(enc::ComponentExportKind::Instance),
+        _ => None,
+    }
+}
+
+/// Where an item can be taken from to satisfy an import
+enum Provider {
+    /// An export of the same name and kind
+    Export(u32),
+    /// The top-level exports of an instance, bundled up into an instance
+    Items(u32),
+}
+
+#[derive(Default)]
+struct Linker {
+    component: enc::Component,
+
+    num_components: u32,
+    num_instances: u32,
+    num_funcs: u32,
+    num_types: u32,
+
+    /// The instance index of each instantiated component and its exports
+    instances: Vec<(u32, HashMap<String, ComponentExternalKind>)>,
+}
+
+impl Linker {
+    fn provider(&self, import: &Import) -> Option<Provider> {
+        for (instance, exports) in self.instances.iter() {
+            if exports.get(&import.name) == Some(&import.kind) {
+                return Some(Provider::Export(*instance));
+            }
+        }
+        if import.kind != ComponentExternalKind::Instance {
+            return None;
+        }
+        // Components which export functions directly can stand in for an interface
+        self.instances
+            .iter()
+            .find(|(_, exports)| {
+                import
+                    .instance_items
+                    .iter()
+                    .all(|item| exports.contains_key(item))
+            })
+            .map(|(instance, _)| Provider::Items(*instance))
+    }
+
+    fn can_instantiate(&self, interface: &Interface) -> bool {
+        interface
+            .imports
+            .iter()
+            .all(|import| self.provider(import).is_some())
+    }
+
+    fn unresolved(&self, interface: &Interface) -> LinkError {
+        let import = interface
+            .imports
+            .iter()
+            .find(|import| self.provider(import).is_none())
+            .unwrap();
+        LinkError::UnresolvedImport {
+            component: interface.name.clone(),
+            import: import.name.clone(),
+        }
+    }
+
+    fn instantiate(&mut self, interface: Interface) -> Result<(), LinkError> {
+        let instance = self.instantiate_root(&interface)?;
+        let exports = interface.exports.into_iter().collect();
+        self.instances.push((instance, exports));
+        Ok(())
+    }
+
+    fn instantiate_root(&mut self, interface: &Interface) -> Result<u32, LinkError> {
+        self.component.section(&enc::RawSection {
+            id: enc::ComponentSectionId::Component.into(),
+            data: &interface.bytes,
+        });
+        let component = self.num_components;
+        self.num_components += 1;
+
+        let mut args = Vec::new();
+        for import in interface.imports.iter() {
+            let unsupported = || LinkError::Unsupported {
+                component: interface.name.clone(),
+                import: import.name.clone(),
+            };
+            let kind = export_kind(import.kind).ok_or_else(unsupported)?;
+            let index = match self.provider(import).unwrap() {
+                Provider::Export(instance) => self.alias(instance, &import.name, kind),
+                Provider::Items(instance) => {
+                    let (_, exports) = self
+                        .instances
+                        .iter()
+                        .find(|(index, _)| *index == instance)
+                        .unwrap();
+                    let exports = exports.clone();
+                    let mut items = Vec::new();
+                    for item in import.instance_items.iter() {
+                        let kind = export_kind(exports[item]).ok_or_else(unsupported)?;
+                        items.push((item.clone(), kind, self.alias(instance, item, kind)));
+                    }
+                    let mut section = enc::ComponentInstanceSection::new();
+                    section.export_items(
+                        items
+                            .iter()
+                            .map(|(name, kind, index)| (name.as_str(), *kind, *index)),
+                    );
+                    self.component.section(&section);
+                    self.next_instance()
+                }
+            };
+            args.push((import.name.clone(), kind, index));
+        }
+
+        let mut section = enc::ComponentInstanceSection::new();
+        section.instantiate(
+            component,
+            args.iter()
+                .map(|(name, kind, index)| (name.as_str(), *kind, *index)),
+        );
+        self.component.section(&section);
+        Ok(self.next_instance())
+    }
+
+    /// Re-export everything the root component exports
+    fn export_all(&mut self, root: &Interface, instance: u32) -> Result<(), LinkError> {
+        // The linked component's index for each type the root exports
+        let mut named = HashMap::new();
+        for (name, kind) in root.exports.iter() {
+            let unsupported = || LinkError::UnsupportedExport {
+                component: root.name.clone(),
+                export: name.clone(),
+            };
+            let kind = match export_kind(*kind) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            // Exports can only refer to types which have been exported, so
+            // functions and types are given copies of their types which do
+            let (index, ty) = match root.types.component_entity_type_of_export(name) {
+                Some(ComponentEntityType::Func(id)) => {
+                    let func_type = self
+                        .func_type(&root.types, id, &named)
+                        .ok_or_else(unsupported)?;
+                    let index = self.alias(instance, name, kind);
+                    (index, Some(enc::ComponentTypeRef::Func(func_type)))
+                }
+                Some(ComponentEntityType::Type {
+                    created: ComponentAnyTypeId::Defined(id),
+                    ..
+                }) => {
+                    let index = self
+                        .defined_type(&root.types, id, &named)
+                        .ok_or_else(unsupported)?;
+                    (index, None)
+                }
+                _ => (self.alias(instance, name, kind), None),
+            };
+            let mut section = enc::ComponentExportSection::new();
+            section.export(name, kind, index, ty);
+            self.component.section(&section);
+            // Exports are given an index of their own
+            let exported = self.next_index(kind);
+
+            if let Some(ComponentEntityType::Type {
+                created: ComponentAnyTypeId::Defined(id),
+                ..
+            }) = root.types.component_entity_type_of_export(name)
+            {
+                named.insert(id, exported);
+            }
+        }
+        Ok(())
+    }
+
+    /// Define a copy of a function type of another component
+    fn func_type(
+        &mut self,
+        types: &Types,
+        id: ComponentFuncTypeId,
+        named: &HashMap<ComponentDefinedTypeId, u32>,
+    ) -> Option<u32> {
+        let func_type = &types[id];
+        let params = func_type
+            .params
+            .iter()
+            .map(|(name, ty)| Some((name.as_str(), self.valtype(types, *ty, named)?)))
+            .collect::<Option<Vec<_>>>()?;
+        let results = func_type
+            .results
+            .iter()
+            .map(|(name, ty)| {
+                let name = name.as_ref().map(|name| name.as_str());
+                Some((name, self.valtype(types, *ty, named)?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut section = enc::ComponentTypeSection::new();
+        let mut encoder = section.function();
+        encoder.params(params);
+        match results.as_slice() {
+            [(None, ty)] => {
+                encoder.result(*ty);
+            }
+            _ => {
+                encoder.results(
+                    results
+                        .iter()
+                        .map(|(name, ty)| (name.unwrap_or_default(), *ty)),
+                );
+            }
+        }
+        self.component.section(&section);
+        Some(self.next_index(enc::ComponentExportKind::Type))
+    }
+
+    /// Refer to a value type of another component, which can
+    /// only name the types of that component which were exported
+    fn valtype(
+        &mut self,
+        types: &Types,
+        ty: ComponentValType,
+        named: &HashMap<ComponentDefinedTypeId, u32>,
+    ) -> Option<enc::ComponentValType> {
+        let id = match ty {
+            ComponentValType::Primitive(ptype) => {
+                return Some(enc::ComponentValType::Primitive(primitive(ptype)))
+            }
+            ComponentValType::Type(id) => id,
+        };
+        if let Some(index) = named.get(&id) {
+            return Some(enc::ComponentValType::Type(*index));
+        }
+        match &types[id] {
+            ComponentDefinedType::Primitive(ptype) => {
+                Some(enc::ComponentValType::Primitive(primitive(*ptype)))
+            }
+            ComponentDefinedType::List(_)
+            | ComponentDefinedType::Option(_)
+            | ComponentDefinedType::Result { .. }
+            | ComponentDefinedType::Tuple(_) => {
+                let index = self.defined_type(types, id, named)?;
+                Some(enc::ComponentValType::Type(index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Define a copy of a type of another component
+    fn defined_type(
+        &mut self,
+        types: &Types,
+        id: ComponentDefinedTypeId,
+        named: &HashMap<ComponentDefinedTypeId, u32>,
+    ) -> Option<u32> {
+        let mut valtype = |ty: &ComponentValType| self.valtype(types, *ty, named);
+
+        let mut section = enc::ComponentTypeSection::new();
+        let encoder = section.defined_type();
+        match &types[id] {
+            ComponentDefinedType::Primitive(ptype) => encoder.primitive(primitive(*ptype)),
+            ComponentDefinedType::Record(record) => {
+                let fields = record
+                    .fields
+                    .iter()
+                    .map(|(name, ty)| Some((name.as_str(), valtype(ty)?)))
+                    .collect::<Option<Vec<_>>>()?;
+                encoder.record(fields);
+            }
+            ComponentDefinedType::Variant(variant) => {
+                let cases = variant
+                    .cases
+                    .iter()
+                    .map(|(name, case)| {
+                        let ty = match &case.ty {
+                            Some(ty) => Some(valtype(ty)?),
+                            None => None,
+                        };
+                        Some((name.as_str(), ty, None))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                encoder.variant(cases);
+            }
+            ComponentDefinedType::List(element) => encoder.list(valtype(element)?),
+            ComponentDefinedType::Tuple(tuple) => {
+                let elements = tuple
+                    .types
+                    .iter()
+                    .map(valtype)
+                    .collect::<Option<Vec<_>>>()?;
+                encoder.tuple(elements);
+            }
+            ComponentDefinedType::Flags(names) => encoder.flags(names.iter().map(|n| n.as_str())),
+            ComponentDefinedType::Enum(tags) => encoder.enum_type(tags.iter().map(|t| t.as_str())),
+            ComponentDefinedType::Option(some) => encoder.option(valtype(some)?),
+            ComponentDefinedType::Result { ok, err } => {
+                let ok = match ok {
+                    Some(ok) => Some(valtype(ok)?),
+                    None => None,
+                };
+                let err = match err {
+                    Some(err) => Some(valtype(err)?),
+                    None => None,
+                };
+                encoder.result(ok, err);
+            }
+            // Resources can't be copied
+            ComponentDefinedType::Own(_) | ComponentDefinedType::Borrow(_) => return None,
+        }
+        self.component.section(&section);
+        Some(self.next_index(enc::ComponentExportKind::Type))
+    }
+
+    fn alias(&mut self, instance: u32, name: &str, kind: enc::ComponentExportKind) -> u32 {
+        let mut section = enc::ComponentAliasSection::new();
+        section.alias(enc::Alias::InstanceExport {
+            instance,
+            kind,
+            name,
+        });
+        self.component.section(&section);
+        self.next_index(kind)
+    }
+
+    fn next_index(&mut self, kind: enc::ComponentExportKind) -> u32 {
+        let count = match kind {
+            enc::ComponentExportKind::Func => &mut self.num_funcs,
+            enc::ComponentExportKind::Type => &mut self.num_types,
+            enc::ComponentExportKind::Instance => &mut self.num_instances,
+            _ => unreachable!("Only functions, types, and instances are linked"),
+        };
+        *count += 1;
+        *count - 1
+    }
+
+    fn next_instance(&mut self) -> u32 {
+        self.next_index(enc::ComponentExportKind::Instance)
+    }
+}
+
+fn primitive(ptype: PrimitiveValType) -> enc::PrimitiveValType {
+    match ptype {
+        PrimitiveValType::Bool => enc::PrimitiveValType::Bool,
+        PrimitiveValType::S8 => enc::PrimitiveValType::S8,
+        PrimitiveValType::U8 => enc::PrimitiveValType::U8,
+        PrimitiveValType::S16 => enc::PrimitiveValType::S16,
+        PrimitiveValType::U16 => enc::PrimitiveValType::U16,
+        PrimitiveValType::S32 => enc::PrimitiveValType::S32,
+        PrimitiveValType::U32 => enc::PrimitiveValType::U32,
+        PrimitiveValType::S64 => enc::PrimitiveValType::S64,
+        PrimitiveValType::U64 => enc::PrimitiveValType::U64,
+        PrimitiveValType::F32 => enc::PrimitiveValType::F32,
+        PrimitiveValType::F64 => enc::PrimitiveValType::F64,
+        PrimitiveValType::Char => enc::PrimitiveValType::Char,
+        PrimitiveValType::String => enc::PrimitiveValType::String,
+    }
+}
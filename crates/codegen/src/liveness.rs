@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+
+use enc::Instruction;
+use wasm_encoder as enc;
+
+use crate::peephole::Op;
+
+/// Share locals between values which are never live at the same time.
+///
+/// Every temporary gets its own local during code generation, so this
+/// computes a live range for each local over the function body and
+/// assigns ranges which don't overlap to the same local with linear scan.
+/// `ops` is rewritten in place and the new (non-parameter) locals are returned.
+///
+/// Ranges are conservative:
+/// * A local used inside a loop is live for the whole loop
+/// * A local which may be read before it is written (relying on
+///   locals starting at zero) is live from the start of the function
+///
+/// Bodies containing raw bytes are left untouched since their control
+/// flow isn't visible.
+pub fn coalesce_locals(
+    params: u32,
+    locals: Vec<enc::ValType>,
+    ops: &mut [Op],
+) -> Vec<enc::ValType> {
+    if ops.iter().any(|op| matches!(op, Op::Raw(_))) {
+        return locals;
+    }
+
+    let ranges = live_ranges(params, ops);
+
+    // Linear scan in order of where each range starts
+    let mut order: Vec<u32> = ranges.keys().copied().collect();
+    order.sort_by_key(|local| (ranges[local].start, *local));
+
+    let mut new_locals = Vec::new();
+    let mut mapping = HashMap::new();
+    let mut active: Vec<(usize, u32)> = Vec::new();
+    let mut free: HashMap<enc::ValType, Vec<u32>> = HashMap::new();
+    for local in order {
+        let range = &ranges[&local];
+        active.retain(|&(end, index)| {
+            if end < range.start {
+                let valtype = new_locals[(index - params) as usize];
+                free.entry(valtype).or_default().push(index);
+                false
+            } else {
+                true
+            }
+        });
+
+        let valtype = locals[(local - params) as usize];
+        let index = match free.get_mut(&valtype).and_then(|free| free.pop()) {
+            Some(index) => index,
+            None => {
+                new_locals.push(valtype);
+                params + new_locals.len() as u32 - 1
+            }
+        };
+        mapping.insert(local, index);
+        active.push((range.end, index));
+    }
+
+    for op in ops.iter_mut() {
+        if let Op::Instruction(
+            Instruction::LocalGet(local)
+            | Instruction::LocalSet(local)
+            | Instruction::LocalTee(local),
+        ) = op
+        {
+            if let Some(index) = mapping.get(local) {
+                *local = *index;
+            }
+        }
+    }
+    new_locals
+}
+
+struct LiveRange {
+    start: usize,
+    end: usize,
+    /// The frame holding the write which comes before every read
+    definition: Option<(usize, usize)>,
+}
+
+/// A block, loop or branch of an if
+struct Frame {
+    id: usize,
+    /// Where a loop starts and the locals used inside it
+    loop_body: Option<(usize, HashSet<u32>)>,
+}
+
+fn live_ranges(params: u32, ops: &[Op]) -> HashMap<u32, LiveRange> {
+    let mut ranges: HashMap<u32, LiveRange> = HashMap::new();
+    let mut frames = vec![Frame {
+        id: 0,
+        loop_body: None,
+    }];
+    let mut next_id = 1;
+    let mut open = |frames: &mut Vec<Frame>, loop_body| {
+        frames.push(Frame {
+            id: next_id,
+            loop_body,
+        });
+        next_id += 1;
+    };
+
+    for (position, op) in ops.iter().enumerate() {
+        let Op::Instruction(instruction) = op else {
+            continue;
+        };
+        let (local, writes) = match instruction {
+            Instruction::Block(_) | Instruction::If(_) => {
+                open(&mut frames, None);
+                continue;
+            }
+            Instruction::Loop(_) => {
+                open(&mut frames, Some((position, HashSet::new())));
+                continue;
+            }
+            Instruction::Else => {
+                // The else branch isn't dominated by writes in the then branch
+                frames.pop();
+                open(&mut frames, None);
+                continue;
+            }
+            Instruction::End => {
+                if frames.len() > 1 {
+                    let frame = frames.pop().unwrap();
+                    if let Some((start, used)) = frame.loop_body {
+                        for local in used {
+                            let range = ranges.get_mut(&local).unwrap();
+                            range.start = range.start.min(start);
+                            range.end = range.end.max(position);
+                        }
+                    }
+                }
+                continue;
+            }
+            Instruction::LocalGet(local) => (*local, false),
+            Instruction::LocalSet(local) | Instruction::LocalTee(local) => (*local, true),
+            _ => continue,
+        };
+        if local < params {
+            continue;
+        }
+
+        for frame in frames.iter_mut() {
+            if let Some((_, used)) = &mut frame.loop_body {
+                used.insert(local);
+            }
+        }
+
+        let depth = frames.len();
+        let frame = frames.last().unwrap().id;
+        let range = ranges.entry(local).or_insert_with(|| LiveRange {
+            start: position,
+            end: position,
+            definition: writes.then_some((depth, frame)),
+        });
+        range.end = position;
+        if let Some((depth, frame)) = range.definition {
+            let dominated = frames.get(depth - 1).is_some_and(|f| f.id == frame);
+            if !dominated {
+                range.definition = None;
+            }
+        }
+    }
+
+    for range in ranges.values_mut() {
+        if range.definition.is_none() {
+            range.start = 0;
+        }
+    }
+    ranges
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use ast::{FunctionId, NameId};
+use ast::{FileId, FunctionId, NameId};
 use claw_ast as ast;
 use claw_resolver::{ImportFuncId, ImportFunction, ResolvedComponent};
 use wasm_encoder as enc;
@@ -8,10 +8,12 @@ use wasm_encoder as enc;
 use crate::{
     builders::module::*,
     code::CodeGenerator,
+    format::FormatFuncs,
     function::{EncodedFuncs, EncodedFunction},
     imports::{EncodedImportFunc, EncodedImports},
+    stdio::{self, StdioFuncs},
     types::EncodeType,
-    GenerationError,
+    GenerationError, Profile,
 };
 
 pub(crate) fn generate(
@@ -19,8 +21,10 @@ pub(crate) fn generate(
     rcomp: &ResolvedComponent,
     imports: &EncodedImports,
     functions: &EncodedFuncs,
+    profile: Profile,
+    debug_info: bool,
 ) -> Result<enc::Module, GenerationError> {
-    ModuleGenerator::new(comp, rcomp, imports, functions).generate()
+    ModuleGenerator::new(comp, rcomp, imports, functions, profile, debug_info).generate()
 }
 
 pub struct ModuleGenerator<'gen> {
@@ -28,6 +32,8 @@ pub struct ModuleGenerator<'gen> {
     pub rcomp: &'gen ResolvedComponent,
     imports: &'gen EncodedImports,
     functions: &'gen EncodedFuncs,
+    profile: Profile,
+    debug_info: bool,
     pub module: ModuleBuilder,
 
     func_idx_for_import: HashMap<ImportFuncId, ModuleFunctionIndex>,
@@ -40,12 +46,16 @@ impl<'gen> ModuleGenerator<'gen> {
         rcomp: &'gen ResolvedComponent,
         imports: &'gen EncodedImports,
         functions: &'gen EncodedFuncs,
+        profile: Profile,
+        debug_info: bool,
     ) -> Self {
         Self {
             comp,
             rcomp,
             imports,
             functions,
+            profile,
+            debug_info,
             module: Default::default(),
             func_idx_for_import: Default::default(),
             func_idx_for_func: Default::default(),
@@ -55,6 +65,8 @@ impl<'gen> ModuleGenerator<'gen> {
     pub fn generate(mut self) -> Result<enc::Module, GenerationError> {
         // There is only ever one memory, memory zero
         let (_memory, realloc, clear) = self.encode_import_allocator();
+        let format = self.encode_import_format();
+        let stdio = stdio::uses_stdio(self.rcomp).then(|| self.encode_import_stdio());
 
         for (id, import_func) in self.rcomp.imports.funcs.iter() {
             let encoded_import_func = self.imports.funcs.get(&id).unwrap();
@@ -70,9 +82,18 @@ impl<'gen> ModuleGenerator<'gen> {
             let func_idx = self.encode_func(function, encoded_func)?;
             self.func_idx_for_func.insert(id, func_idx);
         }
-        // Encode function code
-        for (id, encoded_func) in self.functions.funcs.iter() {
-            let id = *id;
+        // Function values index into table zero, which holds every function in ID order
+        let table: Vec<_> = self
+            .comp
+            .iter_functions()
+            .map(|(id, _)| self.func_idx_for_func[&id])
+            .collect();
+        self.module.func_table(&table);
+
+        // Encode function code in ID order, so that data segments are numbered
+        // the same way every time
+        for (id, _) in self.comp.iter_functions() {
+            let encoded_func = &self.functions.funcs[&id];
             let code_gen = CodeGenerator::new(
                 &mut self.module,
                 self.comp,
@@ -84,14 +105,26 @@ impl<'gen> ModuleGenerator<'gen> {
                 encoded_func,
                 id,
                 realloc,
+                format,
+                stdio,
             )?;
-            let builder = code_gen.finalize()?;
+            let (builder, locations) = code_gen.finalize(self.profile, self.debug_info)?;
             let mod_func_idx = self.func_idx_for_func[&id];
             self.module.code(mod_func_idx, builder);
+            self.module.locations(mod_func_idx, locations);
+        }
+        if self.debug_info {
+            let files = (0..self.comp.file_count())
+                .map(|file| self.comp.file_source(FileId(file as u32)).name().to_owned())
+                .collect();
+            self.module.source_map(files);
         }
 
-        // Encode post returns
+        // Encode post returns, which are only needed by exports
         for (id, function) in self.comp.iter_functions() {
+            if !function.exported {
+                continue;
+            }
             // Encode function
             let ident = function.ident;
             let encoded_func = self.functions.funcs.get(&id).unwrap();
@@ -122,6 +155,22 @@ impl<'gen> ModuleGenerator<'gen> {
         (memory, realloc, clear)
     }
 
+    fn encode_import_format(&mut self) -> FormatFuncs {
+        use enc::ValType::{F64, I32, I64};
+        let int_type = self.module.func_type(vec![I64, I32, I64, I32], vec![I32]);
+        let int = self.module.import_func("format", "int", int_type);
+        let float_type = self.module.func_type(vec![F64, I32, I32, I32], vec![I32]);
+        let float = self.module.import_func("format", "float", float_type);
+        FormatFuncs { int, float }
+    }
+
+    fn encode_import_stdio(&mut self) -> StdioFuncs {
+        let line_type = self.module.func_type(vec![enc::ValType::I32; 2], vec![]);
+        let println = self.module.import_func("stdio", "println", line_type);
+        let eprintln = self.module.import_func("stdio", "eprintln", line_type);
+        StdioFuncs { println, eprintln }
+    }
+
     fn encode_import_func(
         &mut self,
         import_func: &ImportFunction,
@@ -135,15 +184,11 @@ impl<'gen> ModuleGenerator<'gen> {
     fn encode_globals(&mut self) -> Result<(), GenerationError> {
         for (id, global) in self.comp.iter_globals() {
             let valtypes = global.type_id.flatten(self.comp, self.rcomp);
-            assert_eq!(valtypes.len(), 1, "Cannot use non-primitive globals");
+            assert_eq!(valtypes.len(), 1, "Globals are checked by the resolver");
             let valtype = valtypes[0];
 
             let init_expr = if let Some(init_value) = self.rcomp.global_vals.get(&id) {
-                let valtype = self.comp.get_type(global.type_id);
-                match valtype {
-                    ast::ValType::Result(_) => todo!(),
-                    ast::ValType::Primitive(ptype) => literal_to_const_expr(init_value, *ptype),
-                }
+                literal_to_const_expr(init_value, valtype)
             } else {
                 panic!("Cannot generate WASM for unresolved global")
             };
@@ -180,6 +225,11 @@ impl<'gen> ModuleGenerator<'gen> {
         let type_idx = encoded_func.encode_mod_type(&mut self.module);
         let func_idx = self.module.function(type_idx);
 
+        if self.profile == Profile::Debug {
+            let name = self.comp.get_name(function.ident);
+            self.module.func_name(func_idx, name);
+        }
+
         if function.exported {
             let ident = function.ident;
             let name = self.comp.get_name(ident);
@@ -193,17 +243,15 @@ impl<'gen> ModuleGenerator<'gen> {
 
 // Literal
 
-fn literal_to_const_expr(literal: &ast::Literal, ptype: ast::PrimitiveType) -> enc::ConstExpr {
-    use ast::{Literal, PrimitiveType};
-    match (ptype, literal) {
-        (PrimitiveType::S32 | PrimitiveType::U32, Literal::Integer(value)) => {
-            enc::ConstExpr::i32_const(*value as i32)
-        }
-        (PrimitiveType::S64 | PrimitiveType::U64, Literal::Integer(value)) => {
-            enc::ConstExpr::i64_const(*value as i64)
-        }
-        (PrimitiveType::F32, Literal::Float(value)) => enc::ConstExpr::f32_const(*value as f32),
-        (PrimitiveType::F64, Literal::Float(value)) => enc::ConstExpr::f64_const(*value),
-        _ => todo!(),
+/// Global initializers are checked by the resolver to be literals of the global's
+/// type, with enum cases and negative numbers already turned into integers
+fn literal_to_const_expr(literal: &ast::Literal, valtype: enc::ValType) -> enc::ConstExpr {
+    use ast::Literal;
+    match (valtype, literal) {
+        (enc::ValType::I32, Literal::Integer(value)) => enc::ConstExpr::i32_const(*value as i32),
+        (enc::ValType::I64, Literal::Integer(value)) => enc::ConstExpr::i64_const(*value as i64),
+        (enc::ValType::F32, Literal::Float(value)) => enc::ConstExpr::f32_const(*value as f32),
+        (enc::ValType::F64, Literal::Float(value)) => enc::ConstExpr::f64_const(*value),
+        _ => unreachable!("Global initializers are checked by the resolver"),
     }
 }
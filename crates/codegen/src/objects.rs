@@ -0,0 +1,110 @@
+//! Precompiled core wasm objects, e.g. built from C with clang,
+//! whose exported functions can be imported by a component.
+//!
+//! Instead of becoming imports of the component, those imports are
+//! wired straight to the object, which is instantiated inside it.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use wasm_encoder as enc;
+use wasmparser::{ExternalKind, Payload, Validator, WasmFeatures};
+
+use crate::GenerationError;
+
+/// A core wasm module to link into the component
+pub struct CoreObject {
+    /// The name to report errors with
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// A function exported by an object
+pub struct ObjectFunc {
+    /// The index of the object in the list of objects
+    pub object: usize,
+    pub params: Vec<enc::ValType>,
+    pub results: Vec<enc::ValType>,
+}
+
+/// Find the functions exported by the objects, by name.
+///
+/// When several objects export the same name the first is used.
+pub fn object_funcs(
+    objects: &[CoreObject],
+) -> Result<HashMap<String, ObjectFunc>, GenerationError> {
+    let mut funcs = HashMap::new();
+    for (index, object) in objects.iter().enumerate() {
+        let invalid = |err: wasmparser::BinaryReaderError| GenerationError::InvalidObject {
+            object: object.name.clone(),
+            message: err.message().to_owned(),
+        };
+        let types = Validator::new_with_features(WasmFeatures::all())
+            .validate_all(&object.bytes)
+            .map_err(invalid)?;
+
+        for payload in wasmparser::Parser::new(0).parse_all(&object.bytes) {
+            match payload.map_err(invalid)? {
+                // Objects are instantiated on their own, so nothing can be given to them
+                Payload::ImportSection(reader) if reader.count() > 0 => {
+                    return Err(GenerationError::ObjectImports {
+                        object: object.name.clone(),
+                    });
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.map_err(invalid)?;
+                        if export.kind != ExternalKind::Func {
+                            continue;
+                        }
+                        let func_type = types[types.core_function_at(export.index)].unwrap_func();
+                        // Types other than numbers never match a declared import
+                        let params = func_type.params().iter().map(core_valtype).collect();
+                        let results = func_type.results().iter().map(core_valtype).collect();
+                        funcs.entry(export.name.to_owned()).or_insert(ObjectFunc {
+                            object: index,
+                            params,
+                            results,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(funcs)
+}
+
+fn core_valtype(valtype: &wasmparser::ValType) -> enc::ValType {
+    match valtype {
+        wasmparser::ValType::I32 => enc::ValType::I32,
+        wasmparser::ValType::I64 => enc::ValType::I64,
+        wasmparser::ValType::F32 => enc::ValType::F32,
+        wasmparser::ValType::F64 => enc::ValType::F64,
+        wasmparser::ValType::V128 => enc::ValType::V128,
+        wasmparser::ValType::Ref(_) => enc::ValType::Ref(enc::RefType::FUNCREF),
+    }
+}
+
+/// Writes a core function signature like `(i32, i32) -> i32`
+pub struct Signature<'a>(pub &'a [enc::ValType], pub &'a [enc::ValType]);
+
+impl fmt::Display for Signature<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = |types: &[enc::ValType]| {
+            types
+                .iter()
+                .map(|ty| match ty {
+                    enc::ValType::I32 => "i32",
+                    enc::ValType::I64 => "i64",
+                    enc::ValType::F32 => "f32",
+                    enc::ValType::F64 => "f64",
+                    enc::ValType::V128 => "v128",
+                    enc::ValType::Ref(_) => "ref",
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(f, "({}) -> ({})", names(self.0), names(self.1))
+    }
+}
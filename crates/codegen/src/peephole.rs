@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use claw_ast::Span;
+use enc::Instruction;
+use wasm_encoder as enc;
+
+/// A single entry in a function body before it is encoded
+pub enum Op {
+    Instruction(Instruction<'static>),
+    /// Already encoded bytes (e.g. from inline assembly) which
+    /// the optimizer treats as opaque
+    Raw(Vec<u8>),
+    /// Where the code for a piece of source starts, only kept when
+    /// generating debug info. Rewrites don't reach across it.
+    Location(Span),
+}
+
+/// Run the peephole optimizer over a function body.
+///
+/// Each op is pushed onto the output and then the tail of the output
+/// is simplified, so that one rewrite can enable the next:
+/// * `local.set x; local.get x` becomes `local.tee x`
+/// * `local.get x; local.set x` is dropped
+/// * `local.tee x; local.set x` becomes `local.set x`
+/// * `local.tee x; local.tee x` becomes `local.tee x`
+/// * `local.get x` of a local last set to a constant in the same
+///   straight-line run of code becomes that constant
+/// * `const a; const b; add` becomes `const a+b`
+/// * `const a; add; const b; add` becomes `const a+b; add`
+/// * `const 0; add` and `const 1; mul` are dropped
+/// * `const 2^k; mul` becomes `const k; shl`
+pub fn optimize(ops: Vec<Op>) -> Vec<Op> {
+    let mut peephole = Peephole::default();
+    for op in ops {
+        match op {
+            Op::Instruction(instruction) => peephole.push(instruction),
+            Op::Raw(bytes) => {
+                // Raw bytes may set locals or branch anywhere
+                peephole.constants.clear();
+                peephole.output.push(Op::Raw(bytes));
+            }
+            Op::Location(span) => {
+                // Only the last of several locations in a row covers any code
+                if let Some(Op::Location(_)) = peephole.output.last() {
+                    peephole.output.pop();
+                }
+                peephole.output.push(Op::Location(span));
+            }
+        }
+    }
+    peephole.output
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Constant {
+    I32(i32),
+    I64(i64),
+}
+
+impl Constant {
+    fn from_instruction(instruction: &Instruction) -> Option<Self> {
+        match instruction {
+            Instruction::I32Const(value) => Some(Constant::I32(*value)),
+            Instruction::I64Const(value) => Some(Constant::I64(*value)),
+            _ => None,
+        }
+    }
+
+    fn instruction(self) -> Instruction<'static> {
+        match self {
+            Constant::I32(value) => Instruction::I32Const(value),
+            Constant::I64(value) => Instruction::I64Const(value),
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        matches!(self, Constant::I32(0) | Constant::I64(0))
+    }
+
+    fn wrapping_add(self, other: Constant) -> Option<Constant> {
+        match (self, other) {
+            (Constant::I32(a), Constant::I32(b)) => Some(Constant::I32(a.wrapping_add(b))),
+            (Constant::I64(a), Constant::I64(b)) => Some(Constant::I64(a.wrapping_add(b))),
+            _ => None,
+        }
+    }
+
+    /// The base 2 logarithm of the constant when it is a power of two
+    fn log2(self) -> Option<Constant> {
+        match self {
+            Constant::I32(value) if (value as u32).is_power_of_two() => {
+                Some(Constant::I32(value.trailing_zeros() as i32))
+            }
+            Constant::I64(value) if (value as u64).is_power_of_two() => {
+                Some(Constant::I64(value.trailing_zeros() as i64))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Peephole {
+    output: Vec<Op>,
+    /// Locals known to hold a constant at the current point.
+    /// Only tracked within straight-line code.
+    constants: HashMap<u32, Constant>,
+}
+
+impl Peephole {
+    fn push(&mut self, instruction: Instruction<'static>) {
+        match instruction {
+            Instruction::LocalGet(local) => {
+                if let Some(constant) = self.constants.get(&local) {
+                    return self.push(constant.instruction());
+                }
+                if let Some(Instruction::LocalSet(set)) = self.last(0) {
+                    if *set == local {
+                        self.output.pop();
+                        return self.push(Instruction::LocalTee(local));
+                    }
+                }
+            }
+            Instruction::LocalSet(local) => {
+                match self.last(0) {
+                    Some(Instruction::LocalGet(get)) if *get == local => {
+                        self.output.pop();
+                        return;
+                    }
+                    Some(Instruction::LocalTee(tee)) if *tee == local => {
+                        self.output.pop();
+                    }
+                    _ => {}
+                }
+                self.set_local(local);
+            }
+            Instruction::LocalTee(local) => {
+                if let Some(Instruction::LocalTee(tee)) = self.last(0) {
+                    if *tee == local {
+                        return;
+                    }
+                }
+                self.set_local(local);
+            }
+            Instruction::I32Add | Instruction::I64Add => {
+                if let Some(b) = self.last_constant(0) {
+                    if b.is_zero() {
+                        self.output.pop();
+                        return;
+                    }
+                    if let Some(a) = self.last_constant(1) {
+                        if let Some(sum) = a.wrapping_add(b) {
+                            self.output.truncate(self.output.len() - 2);
+                            return self.push(sum.instruction());
+                        }
+                    }
+                    let add_before = matches!(
+                        self.last(1),
+                        Some(Instruction::I32Add | Instruction::I64Add)
+                    );
+                    if add_before {
+                        if let Some(a) = self.last_constant(2) {
+                            if let Some(sum) = a.wrapping_add(b) {
+                                self.output.truncate(self.output.len() - 3);
+                                self.push(sum.instruction());
+                                return self.push(instruction);
+                            }
+                        }
+                    }
+                }
+            }
+            Instruction::I32Mul | Instruction::I64Mul => {
+                if let Some(b) = self.last_constant(0) {
+                    if let Some(shift) = b.log2() {
+                        self.output.pop();
+                        if shift.is_zero() {
+                            return;
+                        }
+                        self.push(shift.instruction());
+                        let shl = match shift {
+                            Constant::I32(_) => Instruction::I32Shl,
+                            Constant::I64(_) => Instruction::I64Shl,
+                        };
+                        return self.push(shl);
+                    }
+                }
+            }
+            // Control flow joins and splits end the straight-line run
+            Instruction::Block(_)
+            | Instruction::Loop(_)
+            | Instruction::If(_)
+            | Instruction::Else
+            | Instruction::End
+            | Instruction::Br(_)
+            | Instruction::BrIf(_)
+            | Instruction::BrTable(_, _)
+            | Instruction::Return
+            | Instruction::Unreachable => self.constants.clear(),
+            _ => {}
+        }
+        self.output.push(Op::Instruction(instruction));
+    }
+
+    /// Record the value written to a local by a set or tee
+    fn set_local(&mut self, local: u32) {
+        match self.last_constant(0) {
+            Some(constant) => self.constants.insert(local, constant),
+            None => self.constants.remove(&local),
+        };
+    }
+
+    /// The instruction `depth` places before the end of the output
+    fn last(&self, depth: usize) -> Option<&Instruction<'static>> {
+        let index = self.output.len().checked_sub(depth + 1)?;
+        match &self.output[index] {
+            Op::Instruction(instruction) => Some(instruction),
+            Op::Raw(_) | Op::Location(_) => None,
+        }
+    }
+
+    fn last_constant(&self, depth: usize) -> Option<Constant> {
+        Constant::from_instruction(self.last(depth)?)
+    }
+}
@@ -0,0 +1,117 @@
+//! A custom section mapping the code of the generated core module back
+//! to the source it came from, so that traps can be traced to a line.
+//!
+//! The section is named `claw-source-map` and holds, with every number
+//! an unsigned LEB128:
+//! * The names of the source files, as a vector of strings
+//! * A vector of locations, each the offset of an instruction from the
+//!   start of the core module followed by the index of the file and the
+//!   start and end of the span of source whose code starts there
+//!
+//! Locations are in increasing order of offset, and the code up to the next
+//! location comes from the same source.
+
+use claw_ast::{FileId, Span};
+use wasm_encoder::{self as enc, Encode};
+use wasmparser::{BinaryReader, BinaryReaderError, Parser, Payload};
+
+const SECTION_NAME: &str = "claw-source-map";
+
+/// The source map read back from a component
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// The names of the source files, indexed by the file of each span
+    pub files: Vec<String>,
+    /// In increasing order of offset
+    pub locations: Vec<SourceLocation>,
+}
+
+/// Where the code starting at an offset in the generated core module came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The offset from the start of the component the map was read from,
+    /// which is how runtimes like wasmtime report where a trap happened
+    pub offset: usize,
+    pub span: Span,
+}
+
+impl SourceMap {
+    /// Find where the code at an offset in the core module came from
+    pub fn find(&self, offset: usize) -> Option<&SourceLocation> {
+        let after = self
+            .locations
+            .partition_point(|location| location.offset <= offset);
+        after.checked_sub(1).map(|index| &self.locations[index])
+    }
+
+    /// The name of the file a span is in
+    pub fn file_name(&self, span: Span) -> Option<&str> {
+        self.files.get(span.file().0 as usize).map(String::as_str)
+    }
+}
+
+/// Read the source map from a component built with debug info,
+/// which is empty when it was built without
+pub fn read_source_map(component: &[u8]) -> Result<SourceMap, BinaryReaderError> {
+    // The offsets in the section are from the start of the core module it's in
+    let mut module_start = 0;
+    for payload in Parser::new(0).parse_all(component) {
+        match payload? {
+            Payload::ModuleSection {
+                unchecked_range, ..
+            } => module_start = unchecked_range.start,
+            Payload::CustomSection(section) if section.name() == SECTION_NAME => {
+                return decode(section.data(), module_start);
+            }
+            _ => {}
+        }
+    }
+    Ok(SourceMap::default())
+}
+
+pub(crate) fn encode(
+    files: &[String],
+    locations: impl Iterator<Item = (usize, Span)>,
+) -> enc::CustomSection<'static> {
+    let mut data = Vec::new();
+    files.len().encode(&mut data);
+    for file in files {
+        file.encode(&mut data);
+    }
+    let locations: Vec<_> = locations.collect();
+    locations.len().encode(&mut data);
+    for (offset, span) in locations {
+        offset.encode(&mut data);
+        span.file().0.encode(&mut data);
+        span.start().encode(&mut data);
+        span.end().encode(&mut data);
+    }
+    enc::CustomSection {
+        name: SECTION_NAME.into(),
+        data: data.into(),
+    }
+}
+
+fn decode(data: &[u8], module_start: usize) -> Result<SourceMap, BinaryReaderError> {
+    let mut reader = BinaryReader::new(data);
+    let mut files = Vec::new();
+    for _ in 0..reader.read_var_u32()? {
+        files.push(reader.read_string()?.to_owned());
+    }
+    let mut locations = Vec::new();
+    for _ in 0..reader.read_var_u32()? {
+        let offset = module_start + reader.read_var_u32()? as usize;
+        let file = FileId(reader.read_var_u32()?);
+        let start = reader.read_var_u32()? as usize;
+        let end = reader.read_var_u32()? as usize;
+        let span = Span::from(start..end).in_file(file);
+        locations.push(SourceLocation { offset, span });
+    }
+    Ok(SourceMap { files, locations })
+}
+
+/// The number of bytes in the LEB128 encoding of a number
+pub(crate) fn leb_len(value: usize) -> usize {
+    let bits = usize::BITS - value.leading_zeros();
+    (bits as usize).div_ceil(7).max(1)
+}
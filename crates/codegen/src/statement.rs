@@ -3,7 +3,7 @@ use crate::code::{CodeGenerator, ExpressionAllocator};
 use super::GenerationError;
 use ast::{ExpressionId, NameId, Statement};
 use claw_ast as ast;
-use claw_resolver::ItemId;
+use claw_resolver::{builtins::Builtin, ItemId};
 
 use cranelift_entity::EntityRef;
 use wasm_encoder as enc;
@@ -27,6 +27,8 @@ impl EncodeStatement for Statement {
             Statement::Call(statement) => statement,
             Statement::If(statement) => statement,
             Statement::Return(statement) => statement,
+            Statement::Func(statement) => statement,
+            Statement::Expression(statement) => statement,
         };
         statement.alloc_expr_locals(allocator)
     }
@@ -38,6 +40,8 @@ impl EncodeStatement for Statement {
             Statement::Call(statement) => statement,
             Statement::If(statement) => statement,
             Statement::Return(statement) => statement,
+            Statement::Func(statement) => statement,
+            Statement::Expression(statement) => statement,
         };
         statement.encode(code_gen)
     }
@@ -85,11 +89,29 @@ impl EncodeStatement for ast::Call {
             code_gen.encode_child(*arg)?;
         }
         let item = code_gen.lookup_name(self.ident);
+        if let ItemId::Builtin(Builtin::Panic) = item {
+            code_gen.encode_panic(self.ident, self.args[0]);
+            return Ok(());
+        }
         code_gen.encode_call(item, &self.args, None)?;
         Ok(())
     }
 }
 
+impl EncodeStatement for ast::ExpressionStatement {
+    fn alloc_expr_locals(
+        &self,
+        allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        allocator.alloc_child(self.expression)
+    }
+
+    fn encode(&self, code_gen: &mut CodeGenerator) -> Result<(), GenerationError> {
+        // The value is left in the expression's locals and never read
+        code_gen.encode_child(self.expression)
+    }
+}
+
 impl EncodeStatement for ast::If {
     fn alloc_expr_locals(
         &self,
@@ -116,6 +138,20 @@ impl EncodeStatement for ast::If {
     }
 }
 
+impl EncodeStatement for ast::NestedFunc {
+    fn alloc_expr_locals(
+        &self,
+        _allocator: &mut ExpressionAllocator,
+    ) -> Result<(), GenerationError> {
+        Ok(())
+    }
+
+    fn encode(&self, _code_gen: &mut CodeGenerator) -> Result<(), GenerationError> {
+        // Nested functions are generated as functions of the module
+        Ok(())
+    }
+}
+
 impl EncodeStatement for ast::Return {
     fn alloc_expr_locals(
         &self,
@@ -176,6 +212,7 @@ fn encode_assignment(
             }
         }
         ItemId::Function(_) => panic!("Assigning to functions isn't allowed!!"),
+        ItemId::Builtin(_) => panic!("Assigning to builtins isn't allowed!!"),
     }
     Ok(())
 }
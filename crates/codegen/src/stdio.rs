@@ -0,0 +1,176 @@
+//! Standard output for the `println` and `eprintln` builtins.
+//!
+//! Components which use them import the WASI streams for stdout and
+//! stderr, and a core module writes lines to them.
+
+use crate::builders::component::*;
+use crate::builders::module::ModuleFunctionIndex;
+
+use claw_resolver::{builtins::Builtin, ResolvedComponent};
+use wasm_encoder as enc;
+
+const WASI_IO_ERROR: &str = "wasi:io/error@0.2.0";
+const WASI_IO_STREAMS: &str = "wasi:io/streams@0.2.0";
+const WASI_CLI_STDOUT: &str = "wasi:cli/stdout@0.2.0";
+const WASI_CLI_STDERR: &str = "wasi:cli/stderr@0.2.0";
+
+const BLOCKING_WRITE_AND_FLUSH: &str = "[method]output-stream.blocking-write-and-flush";
+
+/// The functions of the stdio module, which write a string and a newline
+#[derive(Clone, Copy, Debug)]
+pub struct StdioFuncs {
+    /// `(ptr: i32, len: i32)`
+    pub println: ModuleFunctionIndex,
+    /// `(ptr: i32, len: i32)`
+    pub eprintln: ModuleFunctionIndex,
+}
+
+/// Whether the component writes to stdout or stderr, components which
+/// don't leave out the stdio module so they don't depend on WASI
+pub fn uses_stdio(rcomp: &ResolvedComponent) -> bool {
+    let panics_to_stderr =
+        rcomp.uses_builtin(Builtin::Panic) && rcomp.imports.panic_handler().is_none();
+    rcomp.uses_builtin(Builtin::Println)
+        || rcomp.uses_builtin(Builtin::Eprintln)
+        || panics_to_stderr
+}
+
+/// Import the WASI interfaces used to write to stdout and stderr,
+/// then instantiate the stdio module with them
+pub fn encode_stdio(
+    builder: &mut ComponentBuilder,
+    alloc_instance: ComponentModuleInstanceIndex,
+    memory: ComponentCoreMemoryIndex,
+    realloc: ComponentCoreFunctionIndex,
+) -> ComponentModuleInstanceIndex {
+    // wasi:io/error, the payload of failed writes
+    let mut error_type = enc::InstanceType::new();
+    error_type.export("error", resource());
+    let error_type = builder.instance_type(&error_type);
+    let error_instance = builder.import_instance(WASI_IO_ERROR, error_type);
+    let error = builder.alias_type(error_instance, "error");
+
+    // wasi:io/streams, for the output-stream resource and writing to it
+    let mut streams_type = enc::InstanceType::new();
+    let error = outer_resource(&mut streams_type, "error", error);
+    streams_type.export("output-stream", resource());
+    let output_stream = streams_type.type_count() - 1;
+    streams_type.ty().defined_type().own(error);
+    let own_error = enc::ComponentValType::Type(streams_type.type_count() - 1);
+    streams_type.ty().defined_type().variant([
+        ("last-operation-failed", Some(own_error), None),
+        ("closed", None, None),
+    ]);
+    let stream_error = streams_type.type_count() - 1;
+    streams_type.export("stream-error", eq(stream_error));
+    let stream_error = enc::ComponentValType::Type(streams_type.type_count() - 1);
+    streams_type.ty().defined_type().borrow(output_stream);
+    let borrow_output_stream = enc::ComponentValType::Type(streams_type.type_count() - 1);
+    streams_type
+        .ty()
+        .defined_type()
+        .list(enc::PrimitiveValType::U8);
+    let bytes = enc::ComponentValType::Type(streams_type.type_count() - 1);
+    streams_type
+        .ty()
+        .defined_type()
+        .result(None, Some(stream_error));
+    let result = enc::ComponentValType::Type(streams_type.type_count() - 1);
+    streams_type
+        .ty()
+        .function()
+        .params([("self", borrow_output_stream), ("contents", bytes)])
+        .result(result);
+    let write_type = streams_type.type_count() - 1;
+    streams_type.export(
+        BLOCKING_WRITE_AND_FLUSH,
+        enc::ComponentTypeRef::Func(write_type),
+    );
+    let streams_type = builder.instance_type(&streams_type);
+    let streams_instance = builder.import_instance(WASI_IO_STREAMS, streams_type);
+    let output_stream = builder.alias_type(streams_instance, "output-stream");
+    let write = builder.alias_func(streams_instance, BLOCKING_WRITE_AND_FLUSH);
+
+    let get_stdout = import_get_stream(builder, WASI_CLI_STDOUT, "get-stdout", output_stream);
+    let get_stderr = import_get_stream(builder, WASI_CLI_STDERR, "get-stderr", output_stream);
+
+    let funcs = vec![
+        (
+            "get-stdout",
+            builder.lower_func(get_stdout, memory, realloc),
+        ),
+        (
+            "get-stderr",
+            builder.lower_func(get_stderr, memory, realloc),
+        ),
+        (
+            "blocking-write-and-flush",
+            builder.lower_func(write, memory, realloc),
+        ),
+        ("drop-output-stream", builder.resource_drop(output_stream)),
+    ];
+    let args: Vec<_> = funcs
+        .into_iter()
+        .map(|(name, func)| (name.to_owned(), InlineExportItem::Func(func)))
+        .collect();
+    let wasi_instance = builder.inline_export(&args);
+
+    let stdio_module = builder.module_bytes(gen_stdio());
+    let args = vec![
+        ("alloc", ModuleInstantiateArgs::Instance(alloc_instance)),
+        ("wasi", ModuleInstantiateArgs::Instance(wasi_instance)),
+    ];
+    builder.instantiate(stdio_module, args)
+}
+
+/// Import a `wasi:cli` interface which gives out the stream for one of
+/// the standard outputs, returning its function
+fn import_get_stream(
+    builder: &mut ComponentBuilder,
+    interface: &str,
+    name: &str,
+    output_stream: ComponentTypeIndex,
+) -> ComponentFunctionIndex {
+    let mut instance_type = enc::InstanceType::new();
+    let output_stream = outer_resource(&mut instance_type, "output-stream", output_stream);
+    instance_type.ty().defined_type().own(output_stream);
+    let own_output_stream = enc::ComponentValType::Type(instance_type.type_count() - 1);
+    instance_type
+        .ty()
+        .function()
+        .params([] as [(&str, enc::ComponentValType); 0])
+        .result(own_output_stream);
+    let func_type = instance_type.type_count() - 1;
+    instance_type.export(name, enc::ComponentTypeRef::Func(func_type));
+    let instance_type = builder.instance_type(&instance_type);
+    let instance = builder.import_instance(interface, instance_type);
+    builder.alias_func(instance, name)
+}
+
+/// Use a resource imported by another interface in an instance type,
+/// returning its index within the instance type
+fn outer_resource(
+    instance_type: &mut enc::InstanceType,
+    name: &str,
+    resource: ComponentTypeIndex,
+) -> u32 {
+    instance_type.alias(enc::Alias::Outer {
+        kind: enc::ComponentOuterAliasKind::Type,
+        count: 1,
+        index: resource.into(),
+    });
+    instance_type.export(name, eq(instance_type.type_count() - 1));
+    instance_type.type_count() - 1
+}
+
+fn resource() -> enc::ComponentTypeRef {
+    enc::ComponentTypeRef::Type(enc::TypeBounds::SubResource)
+}
+
+fn eq(index: u32) -> enc::ComponentTypeRef {
+    enc::ComponentTypeRef::Type(enc::TypeBounds::Eq(index))
+}
+
+fn gen_stdio() -> &'static [u8] {
+    include_bytes!(concat!(env!("OUT_DIR"), "/stdio.wasm"))
+}
@@ -19,6 +19,266 @@ fn string_append_fields(out: &mut Vec<FieldInfo>) {
 
 pub const STRING_CONTENTS_ALIGNMENT: u32 = 0;
 
+// Lists are laid out like strings, a pointer to the elements followed by the length
+
+const LIST_COMP_VALTYPE: &str = "List types must be defined in the component type section";
+
+fn list_append_fields(out: &mut Vec<FieldInfo>) {
+    out.push(LIST_POINTER_FIELD);
+    out.push(LIST_LENGTH_FIELD);
+}
+
+// Options are a discriminant followed by the payload, both flat and in memory
+
+const OPTION_COMP_VALTYPE: &str = "Option types must be defined in the component type section";
+
+fn option_flat_size<T: EncodeType>(
+    some: &T,
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+) -> u32 {
+    1 + some.flat_size(comp, rcomp)
+}
+
+fn option_append_flattened<T: EncodeType>(
+    some: &T,
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    out: &mut Vec<enc::ValType>,
+) {
+    out.push(enc::ValType::I32);
+    some.append_flattened(comp, rcomp, out);
+}
+
+fn option_append_fields<T: EncodeType>(
+    some: &T,
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+    out: &mut Vec<FieldInfo>,
+) {
+    out.push(OPTION_DISCRIMINANT_FIELD);
+    let payload_offset = align_to(1, some.align(comp, rcomp));
+    for field in some.fields(comp, rcomp) {
+        out.push(FieldInfo {
+            index_offset: field.index_offset + 1,
+            mem_offset: field.mem_offset + payload_offset,
+            ..field
+        });
+    }
+}
+
+fn option_align<T: EncodeType>(some: &T, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
+    some.align(comp, rcomp)
+}
+
+fn option_mem_size<T: EncodeType>(
+    some: &T,
+    comp: &ast::Component,
+    rcomp: &ResolvedComponent,
+) -> u32 {
+    let alignment = some.align(comp, rcomp);
+    let payload_offset = align_to(1, alignment);
+    align_to(payload_offset + some.mem_size(comp, rcomp), alignment)
+}
+
+// Structs are their fields one after another, each aligned in memory like it would be alone
+
+const RECORD_COMP_VALTYPE: &str = "Struct types must be exported by the component";
+
+/// The definition of the struct or enum that a named type refers to
+pub fn named_type_def(name: ast::NameId, comp: &ast::Component) -> &ast::TypeDefinition {
+    let type_def = comp
+        .find_type_def(comp.get_name(name))
+        .expect("Named types are checked by the resolver");
+    comp.get_type_def(type_def)
+}
+
+impl EncodeType for ast::RecordTypeDef {
+    fn flat_size(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
+        self.fields
+            .iter()
+            .map(|field| field.type_id.flat_size(comp, rcomp))
+            .sum()
+    }
+
+    fn append_flattened(
+        &self,
+        comp: &ast::Component,
+        rcomp: &ResolvedComponent,
+        out: &mut Vec<enc::ValType>,
+    ) {
+        for field in self.fields.iter() {
+            field.type_id.append_flattened(comp, rcomp, out);
+        }
+    }
+
+    fn append_fields(
+        &self,
+        comp: &ast::Component,
+        rcomp: &ResolvedComponent,
+        out: &mut Vec<FieldInfo>,
+    ) {
+        let mut index_offset = 0;
+        let mut mem_offset = 0;
+        for field in self.fields.iter() {
+            mem_offset = align_to(mem_offset, field.type_id.align(comp, rcomp));
+            for inner in field.type_id.fields(comp, rcomp) {
+                out.push(FieldInfo {
+                    index_offset: inner.index_offset + index_offset,
+                    mem_offset: inner.mem_offset + mem_offset,
+                    ..inner
+                });
+            }
+            index_offset += field.type_id.flat_size(comp, rcomp);
+            mem_offset += field.type_id.mem_size(comp, rcomp);
+        }
+    }
+
+    fn to_comp_valtype(&self, _: &ast::Component, _: &ResolvedComponent) -> enc::ComponentValType {
+        panic!("{}", RECORD_COMP_VALTYPE)
+    }
+
+    fn align(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
+        self.fields
+            .iter()
+            .map(|field| field.type_id.align(comp, rcomp))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn mem_size(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
+        let mut size = 0;
+        for field in self.fields.iter() {
+            size = align_to(size, field.type_id.align(comp, rcomp));
+            size += field.type_id.mem_size(comp, rcomp);
+        }
+        align_to(size, self.align(comp, rcomp))
+    }
+}
+
+// Enums are the position of their case, using the smallest integer that fits like the canonical ABI
+
+const ENUM_COMP_VALTYPE: &str = "Enum types must be exported by the component";
+
+impl EncodeType for ast::EnumTypeDef {
+    fn flat_size(&self, _: &ast::Component, _: &ResolvedComponent) -> u32 {
+        1
+    }
+
+    fn append_flattened(
+        &self,
+        _: &ast::Component,
+        _: &ResolvedComponent,
+        out: &mut Vec<enc::ValType>,
+    ) {
+        out.push(enc::ValType::I32);
+    }
+
+    fn append_fields(&self, _: &ast::Component, _: &ResolvedComponent, out: &mut Vec<FieldInfo>) {
+        out.push(enum_field(self));
+    }
+
+    fn to_comp_valtype(&self, _: &ast::Component, _: &ResolvedComponent) -> enc::ComponentValType {
+        panic!("{}", ENUM_COMP_VALTYPE)
+    }
+
+    fn align(&self, _: &ast::Component, _: &ResolvedComponent) -> u32 {
+        enum_field(self).align
+    }
+
+    fn mem_size(&self, _: &ast::Component, _: &ResolvedComponent) -> u32 {
+        enum_field(self).mems_size
+    }
+}
+
+fn enum_field(enum_def: &ast::EnumTypeDef) -> FieldInfo {
+    match enum_def.cases.len() {
+        0..=0x100 => U8_FIELD,
+        0x101..=0x10000 => U16_FIELD,
+        _ => U32_FIELD,
+    }
+}
+
+// Newtypes are laid out like the type they wrap
+
+impl EncodeType for ast::TypeDefinition {
+    fn flat_size(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
+        match self {
+            ast::TypeDefinition::Record(record) => record.flat_size(comp, rcomp),
+            ast::TypeDefinition::Enum(enum_def) => enum_def.flat_size(comp, rcomp),
+            ast::TypeDefinition::Newtype(newtype) => newtype.inner.flat_size(comp, rcomp),
+        }
+    }
+
+    fn append_flattened(
+        &self,
+        comp: &ast::Component,
+        rcomp: &ResolvedComponent,
+        out: &mut Vec<enc::ValType>,
+    ) {
+        match self {
+            ast::TypeDefinition::Record(record) => record.append_flattened(comp, rcomp, out),
+            ast::TypeDefinition::Enum(enum_def) => enum_def.append_flattened(comp, rcomp, out),
+            ast::TypeDefinition::Newtype(newtype) => {
+                newtype.inner.append_flattened(comp, rcomp, out)
+            }
+        }
+    }
+
+    fn append_fields(
+        &self,
+        comp: &ast::Component,
+        rcomp: &ResolvedComponent,
+        out: &mut Vec<FieldInfo>,
+    ) {
+        match self {
+            ast::TypeDefinition::Record(record) => record.append_fields(comp, rcomp, out),
+            ast::TypeDefinition::Enum(enum_def) => enum_def.append_fields(comp, rcomp, out),
+            ast::TypeDefinition::Newtype(newtype) => newtype.inner.append_fields(comp, rcomp, out),
+        }
+    }
+
+    fn to_comp_valtype(
+        &self,
+        comp: &ast::Component,
+        rcomp: &ResolvedComponent,
+    ) -> enc::ComponentValType {
+        match self {
+            ast::TypeDefinition::Record(record) => record.to_comp_valtype(comp, rcomp),
+            ast::TypeDefinition::Enum(enum_def) => enum_def.to_comp_valtype(comp, rcomp),
+            ast::TypeDefinition::Newtype(newtype) => newtype.inner.to_comp_valtype(comp, rcomp),
+        }
+    }
+
+    fn align(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
+        match self {
+            ast::TypeDefinition::Record(record) => record.align(comp, rcomp),
+            ast::TypeDefinition::Enum(enum_def) => enum_def.align(comp, rcomp),
+            ast::TypeDefinition::Newtype(newtype) => newtype.inner.align(comp, rcomp),
+        }
+    }
+
+    fn mem_size(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
+        match self {
+            ast::TypeDefinition::Record(record) => record.mem_size(comp, rcomp),
+            ast::TypeDefinition::Enum(enum_def) => enum_def.mem_size(comp, rcomp),
+            ast::TypeDefinition::Newtype(newtype) => newtype.inner.mem_size(comp, rcomp),
+        }
+    }
+}
+
+// Function values are their index in the function table
+
+const FUNC_COMP_VALTYPE: &str = "Function types can't be used by exported functions";
+
+fn func_append_flattened(out: &mut Vec<enc::ValType>) {
+    out.push(enc::ValType::I32);
+}
+
+fn func_append_fields(out: &mut Vec<FieldInfo>) {
+    out.push(FUNC_FIELD);
+}
+
 pub trait EncodeType {
     fn flat_size(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32;
 
@@ -77,6 +337,11 @@ impl EncodeType for ResolvedType {
                 import_type.flat_size(comp, rcomp)
             }
             ResolvedType::Defined(type_id) => type_id.flat_size(comp, rcomp),
+            ResolvedType::Option(ptype) => option_flat_size(&ptype, comp, rcomp),
+            ResolvedType::Record(id) | ResolvedType::Enum(id) | ResolvedType::Newtype(id) => {
+                comp.get_type_def(id).flat_size(comp, rcomp)
+            }
+            ResolvedType::Function(_) => 1,
         }
     }
 
@@ -93,6 +358,11 @@ impl EncodeType for ResolvedType {
                 import_type.append_flattened(comp, rcomp, out)
             }
             ResolvedType::Defined(type_id) => type_id.append_flattened(comp, rcomp, out),
+            ResolvedType::Option(ptype) => option_append_flattened(&ptype, comp, rcomp, out),
+            ResolvedType::Record(id) | ResolvedType::Enum(id) | ResolvedType::Newtype(id) => {
+                comp.get_type_def(id).append_flattened(comp, rcomp, out)
+            }
+            ResolvedType::Function(_) => func_append_flattened(out),
         }
     }
 
@@ -109,6 +379,11 @@ impl EncodeType for ResolvedType {
                 import_type.append_fields(comp, rcomp, out)
             }
             ResolvedType::Defined(type_id) => type_id.append_fields(comp, rcomp, out),
+            ResolvedType::Option(ptype) => option_append_fields(&ptype, comp, rcomp, out),
+            ResolvedType::Record(id) | ResolvedType::Enum(id) | ResolvedType::Newtype(id) => {
+                comp.get_type_def(id).append_fields(comp, rcomp, out)
+            }
+            ResolvedType::Function(_) => func_append_fields(out),
         }
     }
 
@@ -124,6 +399,11 @@ impl EncodeType for ResolvedType {
                 import_type.to_comp_valtype(comp, rcomp)
             }
             ResolvedType::Defined(type_id) => type_id.to_comp_valtype(comp, rcomp),
+            ResolvedType::Option(_) => panic!("{}", OPTION_COMP_VALTYPE),
+            ResolvedType::Record(_) => panic!("{}", RECORD_COMP_VALTYPE),
+            ResolvedType::Enum(_) => panic!("{}", ENUM_COMP_VALTYPE),
+            ResolvedType::Newtype(id) => comp.get_type_def(id).to_comp_valtype(comp, rcomp),
+            ResolvedType::Function(_) => panic!("{}", FUNC_COMP_VALTYPE),
         }
     }
 
@@ -135,6 +415,11 @@ impl EncodeType for ResolvedType {
                 import_type.align(comp, rcomp)
             }
             ResolvedType::Defined(type_id) => type_id.align(comp, rcomp),
+            ResolvedType::Option(ptype) => option_align(&ptype, comp, rcomp),
+            ResolvedType::Record(id) | ResolvedType::Enum(id) | ResolvedType::Newtype(id) => {
+                comp.get_type_def(id).align(comp, rcomp)
+            }
+            ResolvedType::Function(_) => FUNC_FIELD.align,
         }
     }
 
@@ -146,6 +431,11 @@ impl EncodeType for ResolvedType {
                 import_type.mem_size(comp, rcomp)
             }
             ResolvedType::Defined(type_id) => type_id.mem_size(comp, rcomp),
+            ResolvedType::Option(ptype) => option_mem_size(&ptype, comp, rcomp),
+            ResolvedType::Record(id) | ResolvedType::Enum(id) | ResolvedType::Newtype(id) => {
+                comp.get_type_def(id).mem_size(comp, rcomp)
+            }
+            ResolvedType::Function(_) => FUNC_FIELD.mems_size,
         }
     }
 }
@@ -238,8 +528,12 @@ impl EncodeType for TypeId {
 impl EncodeType for ast::ValType {
     fn flat_size(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
         match *self {
+            ast::ValType::Option(some) => option_flat_size(&some, comp, rcomp),
+            ast::ValType::List(_) => 2,
             ast::ValType::Result(_) => todo!(),
             ast::ValType::Primitive(ptype) => ptype.flat_size(comp, rcomp),
+            ast::ValType::Named(name) => named_type_def(name, comp).flat_size(comp, rcomp),
+            ast::ValType::Func(_) => 1,
         }
     }
 
@@ -250,8 +544,14 @@ impl EncodeType for ast::ValType {
         out: &mut Vec<enc::ValType>,
     ) {
         match *self {
+            ast::ValType::Option(some) => option_append_flattened(&some, comp, rcomp, out),
+            ast::ValType::List(_) => string_append_flatten(out),
             ast::ValType::Result(_) => todo!(),
             ast::ValType::Primitive(ptype) => ptype.append_flattened(comp, rcomp, out),
+            ast::ValType::Named(name) => {
+                named_type_def(name, comp).append_flattened(comp, rcomp, out)
+            }
+            ast::ValType::Func(_) => func_append_flattened(out),
         }
     }
 
@@ -262,8 +562,12 @@ impl EncodeType for ast::ValType {
         out: &mut Vec<FieldInfo>,
     ) {
         match *self {
+            ast::ValType::Option(some) => option_append_fields(&some, comp, rcomp, out),
+            ast::ValType::List(_) => list_append_fields(out),
             ast::ValType::Result(_) => todo!(),
             ast::ValType::Primitive(ptype) => ptype.append_fields(comp, rcomp, out),
+            ast::ValType::Named(name) => named_type_def(name, comp).append_fields(comp, rcomp, out),
+            ast::ValType::Func(_) => func_append_fields(out),
         }
     }
 
@@ -273,22 +577,34 @@ impl EncodeType for ast::ValType {
         rcomp: &ResolvedComponent,
     ) -> enc::ComponentValType {
         match *self {
+            ast::ValType::Option(_) => panic!("{}", OPTION_COMP_VALTYPE),
+            ast::ValType::List(_) => panic!("{}", LIST_COMP_VALTYPE),
             ast::ValType::Result(_) => todo!(),
             ast::ValType::Primitive(ptype) => ptype.to_comp_valtype(comp, rcomp),
+            ast::ValType::Named(name) => named_type_def(name, comp).to_comp_valtype(comp, rcomp),
+            ast::ValType::Func(_) => panic!("{}", FUNC_COMP_VALTYPE),
         }
     }
 
     fn align(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
         match *self {
+            ast::ValType::Option(some) => option_align(&some, comp, rcomp),
+            ast::ValType::List(_) => STRING_ALIGNMENT,
             ast::ValType::Result(_) => todo!(),
             ast::ValType::Primitive(ptype) => ptype.align(comp, rcomp),
+            ast::ValType::Named(name) => named_type_def(name, comp).align(comp, rcomp),
+            ast::ValType::Func(_) => FUNC_FIELD.align,
         }
     }
 
     fn mem_size(&self, comp: &ast::Component, rcomp: &ResolvedComponent) -> u32 {
         match *self {
+            ast::ValType::Option(some) => option_mem_size(&some, comp, rcomp),
+            ast::ValType::List(_) => STRING_MEM_SIZE,
             ast::ValType::Result(_) => todo!(),
             ast::ValType::Primitive(ptype) => ptype.mem_size(comp, rcomp),
+            ast::ValType::Named(name) => named_type_def(name, comp).mem_size(comp, rcomp),
+            ast::ValType::Func(_) => FUNC_FIELD.mems_size,
         }
     }
 }
@@ -398,7 +714,9 @@ pub fn ptype_to_pvaltype(ptype: ast::PrimitiveType) -> enc::PrimitiveValType {
     }
 }
 
+/// Round the offset up to the alignment, which is given as a power of two like in `MemArg`
 pub fn align_to(offset: u32, alignment: u32) -> u32 {
+    let alignment = 1 << alignment;
     offset.div_ceil(alignment) * alignment
 }
 
@@ -446,6 +764,8 @@ pub const BOOL_FIELD: FieldInfo = FieldInfo {
     mems_size: 1,
 };
 
+pub const OPTION_DISCRIMINANT_FIELD: FieldInfo = BOOL_FIELD;
+
 pub const U8_FIELD: FieldInfo = FieldInfo {
     stack_type: enc::ValType::I32,
     signedness: Signedness::Unsigned,
@@ -565,3 +885,9 @@ pub const STRING_LENGTH_FIELD: FieldInfo = FieldInfo {
     align: 2,
     mems_size: 4,
 };
+
+pub const FUNC_FIELD: FieldInfo = U32_FIELD;
+
+pub const LIST_POINTER_FIELD: FieldInfo = STRING_OFFSET_FIELD;
+
+pub const LIST_LENGTH_FIELD: FieldInfo = STRING_LENGTH_FIELD;
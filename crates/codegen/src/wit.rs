@@ -0,0 +1,251 @@
+//! Deriving a WIT world from the exports of a component, so that bindings
+//! for it can be generated without writing the interface by hand.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use ast::{PrimitiveType, ValType};
+use claw_ast as ast;
+
+use crate::GenerationError;
+
+/// Identifiers which are WIT keywords and must be escaped with a `%`
+const WIT_KEYWORDS: &[&str] = &[
+    "as",
+    "bool",
+    "borrow",
+    "char",
+    "constructor",
+    "enum",
+    "export",
+    "f32",
+    "f64",
+    "flags",
+    "float32",
+    "float64",
+    "from",
+    "func",
+    "future",
+    "import",
+    "include",
+    "interface",
+    "list",
+    "option",
+    "own",
+    "package",
+    "record",
+    "resource",
+    "result",
+    "s16",
+    "s32",
+    "s64",
+    "s8",
+    "static",
+    "stream",
+    "string",
+    "tuple",
+    "type",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "use",
+    "variant",
+    "with",
+    "world",
+];
+
+/// Generate a WIT package containing a single world which exports the
+/// same functions and types as the component.
+pub fn generate_wit(
+    comp: &ast::Component,
+    package: &str,
+    world: &str,
+) -> Result<String, GenerationError> {
+    let mut gen = WitGenerator {
+        comp,
+        type_defs: HashSet::new(),
+    };
+
+    let mut exports = String::new();
+    for (_, function) in comp.iter_functions() {
+        if function.exported {
+            gen.function(function, &mut exports)?;
+        }
+    }
+
+    let mut type_defs = Vec::new();
+    for (id, type_def) in comp.iter_type_defs() {
+        if type_def.exported() {
+            gen.use_type_def(id)?;
+        }
+    }
+    for (id, type_def) in comp.iter_type_defs() {
+        if !gen.type_defs.contains(&id) {
+            continue;
+        }
+        let mut out = String::new();
+        match type_def {
+            ast::TypeDefinition::Record(record) => gen.record(record, &mut out)?,
+            ast::TypeDefinition::Enum(enum_def) => gen.enum_def(enum_def, &mut out),
+            ast::TypeDefinition::Newtype(newtype) => gen.newtype(newtype, &mut out)?,
+        }
+        type_defs.push(out);
+    }
+
+    let mut wit = String::new();
+    writeln!(wit, "package {};", package).unwrap();
+    writeln!(wit).unwrap();
+    writeln!(wit, "world {} {{", ident(world)).unwrap();
+    let mut sections = type_defs;
+    if !exports.is_empty() {
+        sections.push(exports);
+    }
+    wit.push_str(&sections.join("\n"));
+    writeln!(wit, "}}").unwrap();
+    Ok(wit)
+}
+
+struct WitGenerator<'ctx> {
+    comp: &'ctx ast::Component,
+    /// The types defined in the component used by the exports, which the world has to define
+    type_defs: HashSet<ast::TypeDefId>,
+}
+
+impl<'ctx> WitGenerator<'ctx> {
+    fn function(
+        &mut self,
+        function: &ast::Function,
+        out: &mut String,
+    ) -> Result<(), GenerationError> {
+        let params = function
+            .params
+            .iter()
+            .map(|(name, type_id)| {
+                let name = ident(self.comp.get_name(*name));
+                Ok(format!("{}: {}", name, self.valtype(*type_id)?))
+            })
+            .collect::<Result<Vec<_>, GenerationError>>()?;
+        let name = ident(self.comp.get_name(function.ident));
+        write!(out, "    export {}: func({})", name, params.join(", ")).unwrap();
+        if let Some(results) = function.results {
+            write!(out, " -> {}", self.valtype(results)?).unwrap();
+        }
+        writeln!(out, ";").unwrap();
+        Ok(())
+    }
+
+    fn record(
+        &mut self,
+        record: &ast::RecordTypeDef,
+        out: &mut String,
+    ) -> Result<(), GenerationError> {
+        writeln!(
+            out,
+            "    record {} {{",
+            ident(self.comp.get_name(record.ident))
+        )
+        .unwrap();
+        for field in record.fields.iter() {
+            let name = ident(self.comp.get_name(field.ident));
+            writeln!(out, "        {}: {},", name, self.valtype(field.type_id)?).unwrap();
+        }
+        writeln!(out, "    }}").unwrap();
+        Ok(())
+    }
+
+    fn enum_def(&mut self, enum_def: &ast::EnumTypeDef, out: &mut String) {
+        writeln!(
+            out,
+            "    enum {} {{",
+            ident(self.comp.get_name(enum_def.ident))
+        )
+        .unwrap();
+        for case in enum_def.cases.iter() {
+            writeln!(out, "        {},", ident(self.comp.get_name(case.ident))).unwrap();
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+
+    /// Newtypes are aliases in WIT, which has no distinct types
+    fn newtype(
+        &mut self,
+        newtype: &ast::NewtypeDef,
+        out: &mut String,
+    ) -> Result<(), GenerationError> {
+        let name = ident(self.comp.get_name(newtype.ident));
+        writeln!(out, "    type {} = {};", name, self.valtype(newtype.inner)?).unwrap();
+        Ok(())
+    }
+
+    fn valtype(&mut self, type_id: ast::TypeId) -> Result<String, GenerationError> {
+        let valtype = match self.comp.get_type(type_id) {
+            ValType::Option(some) => format!("option<{}>", self.valtype(*some)?),
+            ValType::List(element) => format!("list<{}>", self.valtype(*element)?),
+            ValType::Result(result) => format!(
+                "result<{}, {}>",
+                self.valtype(result.ok)?,
+                self.valtype(result.err)?
+            ),
+            ValType::Primitive(ptype) => primitive(*ptype).to_owned(),
+            ValType::Named(name) => {
+                let name = self.comp.get_name(*name);
+                let type_def = self.comp.find_type_def(name).unwrap();
+                self.use_type_def(type_def)?;
+                ident(name)
+            }
+            ValType::Func(_) => {
+                let span = self.comp.type_span(type_id);
+                return Err(GenerationError::NotInWit {
+                    src: self.comp.file_source(span.file()),
+                    span,
+                });
+            }
+        };
+        Ok(valtype)
+    }
+
+    /// Define a type in the world, along with the types used in it
+    fn use_type_def(&mut self, type_def: ast::TypeDefId) -> Result<(), GenerationError> {
+        if !self.type_defs.insert(type_def) {
+            return Ok(());
+        }
+        match self.comp.get_type_def(type_def) {
+            ast::TypeDefinition::Record(record) => {
+                for field in record.fields.iter() {
+                    self.valtype(field.type_id)?;
+                }
+            }
+            ast::TypeDefinition::Enum(_) => {}
+            ast::TypeDefinition::Newtype(newtype) => {
+                self.valtype(newtype.inner)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn primitive(ptype: PrimitiveType) -> &'static str {
+    match ptype {
+        PrimitiveType::Bool => "bool",
+        PrimitiveType::U8 => "u8",
+        PrimitiveType::S8 => "s8",
+        PrimitiveType::U16 => "u16",
+        PrimitiveType::S16 => "s16",
+        PrimitiveType::U32 => "u32",
+        PrimitiveType::S32 => "s32",
+        PrimitiveType::U64 => "u64",
+        PrimitiveType::S64 => "s64",
+        PrimitiveType::F32 => "float32",
+        PrimitiveType::F64 => "float64",
+        PrimitiveType::String => "string",
+    }
+}
+
+fn ident(name: &str) -> String {
+    if WIT_KEYWORDS.contains(&name) {
+        format!("%{}", name)
+    } else {
+        name.to_owned()
+    }
+}
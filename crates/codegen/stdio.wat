@@ -0,0 +1,62 @@
+(module
+    (import "alloc" "memory" (memory 1))
+    (import "alloc" "realloc" (func $realloc (param i32 i32 i32 i32) (result i32)))
+    ;; The WASI functions, lowered by the component
+    (import "wasi" "get-stdout" (func $get-stdout (result i32)))
+    (import "wasi" "get-stderr" (func $get-stderr (result i32)))
+    (import "wasi" "blocking-write-and-flush"
+        (func $blocking-write-and-flush (param i32 i32 i32 i32)))
+    (import "wasi" "drop-output-stream" (func $drop-output-stream (param i32)))
+    ;; Write `$len` bytes at `$ptr` followed by a newline to stdout
+    (func (export "println") (param $ptr i32) (param $len i32)
+        (call $write-line (call $get-stdout) (local.get $ptr) (local.get $len))
+    )
+    ;; Write `$len` bytes at `$ptr` followed by a newline to stderr
+    (func (export "eprintln") (param $ptr i32) (param $len i32)
+        (call $write-line (call $get-stderr) (local.get $ptr) (local.get $len))
+    )
+    ;; Write a line to the stream then drop it
+    (func $write-line (param $stream i32) (param $ptr i32) (param $len i32)
+        (local $scratch i32)
+        (local $chunk i32)
+        ;; The result of each write goes in the first 12 bytes,
+        ;; the newline goes after it
+        (local.set $scratch
+            (call $realloc (i32.const 0) (i32.const 0) (i32.const 4) (i32.const 13)))
+        (i32.store8 offset=12 (local.get $scratch) (i32.const 10)) ;; '\n'
+        ;; Streams only accept 4096 bytes at a time
+        (block $done
+            (loop $chunks
+                (br_if $done (i32.eqz (local.get $len)))
+                (local.set $chunk
+                    (select
+                        (local.get $len)
+                        (i32.const 4096)
+                        (i32.lt_u (local.get $len) (i32.const 4096))))
+                (call $write
+                    (local.get $stream)
+                    (local.get $ptr)
+                    (local.get $chunk)
+                    (local.get $scratch))
+                (local.set $ptr (i32.add (local.get $ptr) (local.get $chunk)))
+                (local.set $len (i32.sub (local.get $len) (local.get $chunk)))
+                (br $chunks)))
+        (call $write
+            (local.get $stream)
+            (i32.add (local.get $scratch) (i32.const 12))
+            (i32.const 1)
+            (local.get $scratch))
+        (call $drop-output-stream (local.get $stream))
+    )
+    ;; Write bytes to the stream, trapping if it fails like a panic would
+    (func $write (param $stream i32) (param $ptr i32) (param $len i32) (param $result i32)
+        (call $blocking-write-and-flush
+            (local.get $stream)
+            (local.get $ptr)
+            (local.get $len)
+            (local.get $result))
+        ;; The result is `result<_, stream-error>`, one is an error
+        (if (i32.load8_u (local.get $result))
+            (then unreachable))
+    )
+)
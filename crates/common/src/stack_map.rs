@@ -1,3 +1,4 @@
+use std::borrow::Borrow;
 use std::cmp;
 use std::collections::HashMap;
 use std::hash::Hash;
@@ -60,9 +61,18 @@ where
         }
     }
 
-    pub fn lookup(&self, key: &K) -> Option<&V> {
+    pub fn lookup<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Hash + cmp::Eq,
+    {
         self.mapping.get(key)
     }
+
+    /// Iterate over the entries that are currently visible
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.mapping.iter()
+    }
 }
 
 #[cfg(test)]
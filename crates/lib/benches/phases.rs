@@ -0,0 +1,110 @@
+//! Benchmarks for each phase of the compiler.
+//!
+//! Run with `cargo bench -p compile-claw`, optionally followed by `-- <filter>`
+//! to only run the benchmarks whose names contain the filter.
+
+use std::time::{Duration, Instant};
+
+use claw_codegen::generate;
+use claw_common::make_source;
+use claw_parser::{parse, tokenize, LangVersion};
+use claw_resolver::{resolve, wit::ResolvedWit};
+use wit_parser::Resolve;
+
+/// How long to spend running each benchmark
+const BUDGET: Duration = Duration::from_millis(500);
+const MIN_ITERATIONS: u32 = 5;
+
+/// A representative chunk of code which is repeated to make larger programs
+const UNIT: &str = "
+let mut calls-nN: u32 = 0;
+
+func square-nN(x: u32) -> u32 {
+    return x * x;
+}
+
+export func sum-of-squares-nN(a: u32, b: u32) -> u32 {
+    calls-nN = calls-nN + 1;
+    let total = square-nN(a) + square-nN(b);
+    if total > 1000 {
+        return 1000;
+    }
+    return total;
+}
+
+export func poly-nN(x: f64) -> f64 {
+    let x2 = x * x;
+    return 3.0 * x2 * x - 2.0 * x2 + 0.5 * x - 7.0;
+}
+
+export func mix-nN(a: s64, b: s64) -> s64 {
+    let mut acc = a *% b;
+    acc = acc +| (a >> 3) ^ (b << 2);
+    return max(acc, min(a, b));
+}
+";
+
+fn program(units: usize) -> String {
+    (0..units)
+        .map(|n| UNIT.replace("nN", &format!("n{}", n)))
+        .collect()
+}
+
+fn main() {
+    let filter = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .unwrap_or_default();
+
+    for (size, units) in [("small", 1), ("medium", 50), ("large", 1000)] {
+        let source_code = program(units);
+        let src = make_source(size, &source_code);
+        let tokens = tokenize(src.clone(), &source_code).unwrap();
+        let (comp, errors) = parse(src.clone(), tokens.clone(), LangVersion::LATEST);
+        assert!(errors.is_empty());
+        let rcomp = resolve(&comp, ResolvedWit::new(Resolve::new())).unwrap();
+
+        let run = |phase: &str, f: &mut dyn FnMut()| {
+            let name = format!("{}/{}", phase, size);
+            if name.contains(&filter) {
+                bench(&name, f);
+            }
+        };
+        run("lex", &mut || {
+            tokenize(src.clone(), &source_code).unwrap();
+        });
+        run("parse", &mut || {
+            parse(src.clone(), tokens.clone(), LangVersion::LATEST);
+        });
+        run("resolve", &mut || {
+            resolve(&comp, ResolvedWit::new(Resolve::new())).unwrap();
+        });
+        run("codegen", &mut || {
+            generate(&comp, &rcomp).unwrap();
+        });
+    }
+}
+
+fn bench(name: &str, f: &mut dyn FnMut()) {
+    // Warm up caches and the allocator
+    f();
+
+    let mut times = Vec::new();
+    let start = Instant::now();
+    while start.elapsed() < BUDGET || (times.len() as u32) < MIN_ITERATIONS {
+        let iteration = Instant::now();
+        f();
+        times.push(iteration.elapsed());
+    }
+
+    times.sort();
+    let mean = times.iter().sum::<Duration>() / times.len() as u32;
+    println!(
+        "{:<20} mean {:>12?}  median {:>12?}  min {:>12?}  ({} runs)",
+        name,
+        mean,
+        times[times.len() / 2],
+        times[0],
+        times.len()
+    );
+}
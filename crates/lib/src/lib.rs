@@ -1,43 +1,214 @@
-use claw_codegen::{generate, GenerationError};
-use claw_common::make_source;
-use claw_parser::{parse, tokenize, LexerError, ParserError};
-use claw_resolver::{resolve, wit::ResolvedWit, ResolverError};
+use claw_ast::Component;
+use claw_codegen::{
+    generate_docs, generate_dts, generate_wit, generate_with_objects, GenerationError,
+};
+pub use claw_codegen::{read_source_map, CoreObject, Profile, SourceLocation, SourceMap};
+use claw_common::{make_source, Source};
+use claw_parser::{parse, print_component, tokenize, LangVersion, LexerError, ParserError, Tokens};
+pub use claw_resolver::lints::{Lint, LintConfig, LintLevel};
+pub use claw_resolver::ResolverWarning;
+use claw_resolver::{resolve_with_lints, wit::ResolvedWit, ResolverError};
 use wit_parser::Resolve;
 
-use miette::Diagnostic;
+use miette::{Diagnostic, LabeledSpan, Severity, SourceCode};
 use thiserror::Error;
 
-#[derive(Error, Debug, Diagnostic)]
+use std::fmt::Display;
+
+/// Why a component couldn't be compiled.
+///
+/// As a diagnostic it is the first problem found, see
+/// [Error::diagnostics] for all of them.
+#[derive(Error, Debug)]
 pub enum Error {
     #[error(transparent)]
-    #[diagnostic(transparent)]
     Lexer(#[from] LexerError),
 
-    #[error(transparent)]
-    #[diagnostic(transparent)]
-    Parser(#[from] ParserError),
+    /// Every error found while parsing, of which there is at least one
+    #[error("{}", .0[0])]
+    Parser(Vec<ParserError>),
 
     #[error(transparent)]
-    #[diagnostic(transparent)]
     Resolver(#[from] ResolverError),
 
     #[error(transparent)]
-    #[diagnostic(transparent)]
     Generator(#[from] GenerationError),
 }
 
+impl Error {
+    /// Each problem found, to be reported separately
+    pub fn diagnostics(&self) -> Vec<&dyn Diagnostic> {
+        match self {
+            Error::Lexer(error) => vec![error],
+            Error::Parser(errors) => errors
+                .iter()
+                .map(|error| error as &dyn Diagnostic)
+                .collect(),
+            Error::Resolver(error) => vec![error],
+            Error::Generator(error) => vec![error],
+        }
+    }
+
+    fn first(&self) -> &dyn Diagnostic {
+        self.diagnostics()[0]
+    }
+}
+
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.first().code()
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        self.first().severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.first().help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.first().url()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.first().source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.first().labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.first().related()
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.first().diagnostic_source()
+    }
+}
+
+/// Parse the tokens as the latest version of the language,
+/// failing with every error found if there are any
+fn parse_all(src: Source, tokens: Tokens) -> Result<Component, Error> {
+    let (comp, errors) = parse(src, tokens, LangVersion::LATEST);
+    match errors.is_empty() {
+        true => Ok(comp),
+        false => Err(Error::Parser(errors)),
+    }
+}
+
 pub fn compile(source_name: String, source_code: &str, wit: Resolve) -> Result<Vec<u8>, Error> {
+    let lints = LintConfig::default();
+    let (output, _warnings) = compile_with_warnings(source_name, source_code, wit, &lints)?;
+    Ok(output)
+}
+
+/// Compile the component, also giving any warnings found along the way
+/// at the given lint levels
+pub fn compile_with_warnings(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    lints: &LintConfig,
+) -> Result<(Vec<u8>, Vec<ResolverWarning>), Error> {
+    compile_with_objects(
+        source_name,
+        source_code,
+        wit,
+        lints,
+        &[],
+        Profile::Debug,
+        false,
+    )
+}
+
+/// Compile the component, taking the functions it imports from
+/// the given core wasm objects when they export them, built with `profile`
+/// and mapped back to the source when `debug_info` is set
+pub fn compile_with_objects(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    lints: &LintConfig,
+    objects: &[CoreObject],
+    profile: Profile,
+    debug_info: bool,
+) -> Result<(Vec<u8>, Vec<ResolverWarning>), Error> {
     let src = make_source(source_name.as_str(), source_code);
 
     let tokens = tokenize(src.clone(), source_code)?;
 
-    let comp = parse(src.clone(), tokens)?;
+    let comp = parse_all(src.clone(), tokens)?;
 
     let wit = ResolvedWit::new(wit);
 
-    let rcomp = resolve(&comp, wit)?;
+    let rcomp = resolve_with_lints(&comp, wit, lints)?;
 
-    let output = generate(&comp, &rcomp)?;
+    let output = generate_with_objects(&comp, &rcomp, objects, profile, debug_info)?;
 
-    Ok(output)
+    Ok((output, rcomp.warnings))
+}
+
+/// Derive a WIT package with one world, named `world`, whose exports
+/// match those of the component
+pub fn derive_wit(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    package: &str,
+    world: &str,
+) -> Result<String, Error> {
+    let src = make_source(source_name.as_str(), source_code);
+
+    let tokens = tokenize(src.clone(), source_code)?;
+
+    let comp = parse_all(src.clone(), tokens)?;
+
+    // The interface is only derived from components which would compile
+    resolve_with_lints(&comp, ResolvedWit::new(wit), &LintConfig::default())?;
+
+    Ok(generate_wit(&comp, package, world)?)
+}
+
+/// Derive TypeScript declarations for the exports of the component
+pub fn derive_dts(source_name: String, source_code: &str, wit: Resolve) -> Result<String, Error> {
+    let src = make_source(source_name.as_str(), source_code);
+
+    let tokens = tokenize(src.clone(), source_code)?;
+
+    let comp = parse_all(src.clone(), tokens)?;
+
+    resolve_with_lints(&comp, ResolvedWit::new(wit), &LintConfig::default())?;
+
+    Ok(generate_dts(&comp)?)
+}
+
+/// Format the source code of a component canonically, keeping its comments
+pub fn format(source_name: String, source_code: &str) -> Result<String, Error> {
+    let src = make_source(source_name.as_str(), source_code);
+
+    let tokens = tokenize(src.clone(), source_code)?;
+
+    let comp = parse_all(src.clone(), tokens)?;
+
+    Ok(print_component(&comp))
+}
+
+/// Generate markdown documentation for the exports of the component
+pub fn derive_docs(
+    source_name: String,
+    source_code: &str,
+    wit: Resolve,
+    title: &str,
+) -> Result<String, Error> {
+    let src = make_source(source_name.as_str(), source_code);
+
+    let tokens = tokenize(src.clone(), source_code)?;
+
+    let comp = parse_all(src.clone(), tokens)?;
+
+    resolve_with_lints(&comp, ResolvedWit::new(wit), &LintConfig::default())?;
+
+    Ok(generate_docs(&comp, title))
 }
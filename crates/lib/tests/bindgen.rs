@@ -0,0 +1,81 @@
+use claw_common::UnwrapPretty;
+use compile_claw::derive_dts;
+
+use std::fs;
+
+use wit_parser::Resolve;
+
+fn dts(name: &str) -> String {
+    let path = format!("./tests/programs/{}.claw", name);
+    let input = fs::read_to_string(path).unwrap();
+    derive_dts(name.to_owned(), &input, Resolve::new()).unwrap_pretty()
+}
+
+#[test]
+fn test_dts_structs() {
+    let expected = "\
+export interface Point {
+  x: number,
+  y: number,
+  label: string,
+}
+
+export interface Segment {
+  start: Point,
+  end: Point,
+  weight: number,
+}
+
+export function atX(x: number): Point;
+export function moved(p: Point, y: number): Point;
+export function renamed(p: Point, label: string): Point;
+export function segmentTo(end: Point): Segment;
+export function reweighted(s: Segment, weight: number): Segment;
+export function endLabel(s: Segment): string;
+export function spanSquared(s: Segment): number;
+export function endLengthSquared(s: Segment): number;
+export function rightmost(s: Segment): number;
+export function defaultStep(): number;
+export function copiedX(p: Point): number;
+export function inferredStep(): number;
+";
+    assert_eq!(dts("structs"), expected);
+}
+
+#[test]
+fn test_dts_newtypes() {
+    let expected = "\
+export type Meters = number;
+
+export type Feet = number;
+
+export type Label = string;
+
+export function addFeet(m: Meters, f: Feet): Meters;
+export function walk(f: Feet): Meters;
+export function distance(m: Meters): number;
+export function makeLabel(name: string): Label;
+export function labelName(l: Label): string;
+";
+    assert_eq!(dts("newtypes"), expected);
+}
+
+#[test]
+fn test_dts_lists() {
+    let expected = "\
+export function count(values: Uint32Array): number;
+export function nth(values: Int16Array, index: number): number;
+export function sum(values: BigUint64Array): bigint;
+export function nthOr(values: Int16Array, index: number, fallback: number): number;
+export function pairSum(values: Uint32Array, a: number, b: number): number;
+export function getOr(values: Uint8Array, index: number, fallback: number): number;
+export function word(words: Array<string>, index: number): string;
+export function identity(values: Float64Array): Float64Array;
+export function middle(values: Uint32Array, start: number, end: number): Uint32Array;
+export function sumTail(values: BigUint64Array): bigint;
+export function firstWords(words: Array<string>, count: number): Array<string>;
+export function copiedFirst(values: Uint32Array): number;
+export function copiedCount(values: Uint32Array): number;
+";
+    assert_eq!(dts("lists"), expected);
+}
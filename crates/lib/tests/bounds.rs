@@ -0,0 +1,52 @@
+use claw_common::UnwrapPretty;
+use compile_claw::compile;
+
+use wit_parser::Resolve;
+
+/// The number of bounds checks left in the function, which each trap with `unreachable`
+fn bounds_checks(body: &str) -> usize {
+    let source =
+        format!("export func access(values: list<u32>, i: u32, j: u32) -> u32 {{\n{body}\n}}");
+    let component = compile("bounds".to_owned(), &source, Resolve::new()).unwrap_pretty();
+    let wat = wasmprinter::print_bytes(component).unwrap();
+    let baseline = "export func access(values: list<u32>, i: u32, j: u32) -> u32 {\nreturn 0;\n}";
+    let baseline = compile("bounds".to_owned(), baseline, Resolve::new()).unwrap_pretty();
+    let baseline = wasmprinter::print_bytes(baseline).unwrap();
+    wat.matches("unreachable").count() - baseline.matches("unreachable").count()
+}
+
+#[test]
+fn test_unchecked_accesses() {
+    assert_eq!(bounds_checks("return values[i];"), 1);
+    assert_eq!(bounds_checks("return values[i] + values[j];"), 2);
+    // Only less than the length is in bounds
+    let body = "if i <= len(values) { return values[i]; }\nreturn 0;";
+    assert_eq!(bounds_checks(body), 1);
+    // The check only applies to the block
+    let body = "if i < len(values) { let x: u32 = 0; }\nreturn values[i];";
+    assert_eq!(bounds_checks(body), 1);
+    // Assigning to the index needs a new check
+    let body = "let mut k = i;\nif k < len(values) { k = j; return values[k]; }\nreturn 0;";
+    assert_eq!(bounds_checks(body), 1);
+    // The right of `or` only runs when the left is false
+    let body = "if i < len(values) or values[i] > 0 { return 1; }\nreturn 0;";
+    assert_eq!(bounds_checks(body), 1);
+}
+
+#[test]
+fn test_checked_accesses() {
+    let body = "if i < len(values) { return values[i]; }\nreturn 0;";
+    assert_eq!(bounds_checks(body), 0);
+    let body =
+        "if len(values) > i and j < len(values) { return values[i] + values[j]; }\nreturn 0;";
+    assert_eq!(bounds_checks(body), 0);
+    let body =
+        "if i >= len(values) or j >= len(values) { return 0; }\nreturn values[i] + values[j];";
+    assert_eq!(bounds_checks(body), 0);
+    let body = "if i < len(values) and values[i] > 0 { return 1; }\nreturn 0;";
+    assert_eq!(bounds_checks(body), 0);
+    // Accessing an element again after the first time didn't trap
+    let body = "let x = values[i];\nreturn x + values[i];";
+    assert_eq!(bounds_checks(body), 1);
+    assert_eq!(bounds_checks("return values[i] + values[i];"), 1);
+}
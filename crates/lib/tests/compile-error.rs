@@ -36,10 +36,13 @@ fn test_bad_programs() {
                 panic!()
             }
             Err(error) => {
+                // Each problem is rendered as a report of its own
                 let mut error_string = String::new();
-                GraphicalReportHandler::new_themed(GraphicalTheme::none())
-                    .render_report(&mut error_string, &error)
-                    .unwrap();
+                for diagnostic in error.diagnostics() {
+                    GraphicalReportHandler::new_themed(GraphicalTheme::none())
+                        .render_report(&mut error_string, diagnostic)
+                        .unwrap();
+                }
                 assert_eq!(error_string, error_file_contents);
             }
         }
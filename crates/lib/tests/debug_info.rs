@@ -0,0 +1,84 @@
+use claw_common::UnwrapPretty;
+use compile_claw::{compile_with_objects, read_source_map, LintConfig, Profile};
+
+use wasmtime::component::{Component, Linker, Val};
+use wasmtime::{Config, Engine, Store, WasmBacktrace};
+use wit_parser::Resolve;
+
+const SOURCE: &str = "\
+func divide(a: u32, b: u32) -> u32 {
+    let quotient = a / b;
+    return quotient;
+}
+
+export func ratio(a: u32, b: u32) -> u32 {
+    return divide(a, b) + 1;
+}
+";
+
+fn compile(profile: Profile, debug_info: bool) -> Vec<u8> {
+    let (component, _) = compile_with_objects(
+        "debug-info.claw".to_owned(),
+        SOURCE,
+        Resolve::new(),
+        &LintConfig::default(),
+        &[],
+        profile,
+        debug_info,
+    )
+    .unwrap_pretty();
+    component
+}
+
+/// Call `ratio` with a divisor of zero, giving the
+/// offsets of the frames of the trap
+fn trap_offsets(component: &[u8]) -> Vec<usize> {
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    let engine = Engine::new(&config).unwrap();
+    let component = Component::new(&engine, component).unwrap();
+    let linker = Linker::new(&engine);
+    let mut store = Store::new(&engine, ());
+    let instance = linker.instantiate(&mut store, &component).unwrap();
+
+    let ratio = instance.get_func(&mut store, "ratio").unwrap();
+    let mut results = [Val::U32(0)];
+    let error = ratio
+        .call(&mut store, &[Val::U32(1), Val::U32(0)], &mut results)
+        .unwrap_err();
+    let backtrace = error.downcast_ref::<WasmBacktrace>().unwrap();
+    backtrace
+        .frames()
+        .iter()
+        .map(|frame| frame.module_offset().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_trap_maps_to_source() {
+    for profile in Profile::ALL {
+        let component = compile(*profile, true);
+        let source_map = read_source_map(&component).unwrap();
+        assert_eq!(source_map.files, vec!["debug-info.claw"]);
+
+        let spans: Vec<&str> = trap_offsets(&component)
+            .into_iter()
+            .map(|offset| {
+                let span = source_map.find(offset).unwrap().span;
+                assert_eq!(source_map.file_name(span), Some("debug-info.claw"));
+                &SOURCE[span.start()..span.end()]
+            })
+            .collect();
+        assert_eq!(spans, vec!["a / b", "divide(a, b)"], "{} profile", profile);
+    }
+}
+
+#[test]
+fn test_debug_info_is_optional() {
+    for profile in Profile::ALL {
+        let component = compile(*profile, false);
+        let source_map = read_source_map(&component).unwrap();
+        assert!(source_map.locations.is_empty());
+        assert!(source_map.files.is_empty());
+    }
+}
@@ -0,0 +1,20 @@
+use claw_common::UnwrapPretty;
+use compile_claw::derive_docs;
+
+use std::fs;
+
+use wit_parser::Resolve;
+
+#[test]
+fn test_docs() {
+    let source_code = fs::read_to_string("./tests/docs/geometry.claw").unwrap();
+    let docs = derive_docs(
+        "geometry.claw".to_owned(),
+        &source_code,
+        Resolve::new(),
+        "geometry",
+    )
+    .unwrap_pretty();
+    let expected = fs::read_to_string("./tests/docs/geometry.md").unwrap();
+    assert_eq!(docs, expected);
+}
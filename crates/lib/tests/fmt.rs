@@ -0,0 +1,60 @@
+use claw_common::UnwrapPretty;
+use compile_claw::{compile_with_objects, format, LintConfig, Profile};
+
+use std::fs;
+
+use wit_parser::Resolve;
+
+#[test]
+fn test_fmt() {
+    let source_code = fs::read_to_string("./tests/fmt/unformatted.claw").unwrap();
+    let formatted = format("unformatted.claw".to_owned(), &source_code).unwrap_pretty();
+    let expected = fs::read_to_string("./tests/fmt/formatted.claw").unwrap();
+    assert_eq!(formatted, expected);
+}
+
+/// Formatting a program keeps its meaning, so it compiles to the
+/// same component, and formatting it again changes nothing
+#[test]
+fn test_fmt_round_trip() {
+    let mut wit = Resolve::new();
+    wit.push_path("./tests/programs/wit").unwrap();
+    let lints = LintConfig::default();
+    let compile = |name: &str, source: &str| {
+        let (bytes, _) = compile_with_objects(
+            name.to_owned(),
+            source,
+            wit.clone(),
+            &lints,
+            &[],
+            Profile::Release,
+            false,
+        )
+        .unwrap_pretty();
+        bytes
+    };
+
+    for entry in fs::read_dir("./tests/programs").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("claw".as_ref()) {
+            continue;
+        }
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let source_code = fs::read_to_string(&path).unwrap();
+
+        let formatted = format(name.clone(), &source_code).unwrap_pretty();
+        let reformatted = format(name.clone(), &formatted).unwrap_pretty();
+        assert_eq!(
+            formatted, reformatted,
+            "Formatting {} again changed it",
+            name
+        );
+
+        let original = compile(&name, &source_code);
+        assert!(
+            original == compile(&name, &formatted),
+            "Formatting {} changed what it compiles to",
+            name
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use claw_common::UnwrapPretty;
+use compile_claw::compile;
+
+use wasmtime::component::{Component, Linker, Val};
+use wasmtime::{Config, Engine, Store};
+use wit_parser::Resolve;
+
+/// More params than fit flat, so they're passed in memory and must be laid
+/// out with the alignment of each type the way the component model does
+const SOURCE: &str = "\
+export func pick-wide(
+    a: u8, b: u64, c: u8, d: u16, e: u32, f: u8, g: u64, h: u16, i: u8,
+    j: u32, k: u8, l: u64, m: u8, n: u16, o: u8, p: u32, q: u8
+) -> u64 {
+    return b + g + l;
+}
+
+export func pick-narrow(
+    a: u8, b: u64, c: u8, d: u16, e: u32, f: u8, g: u64, h: u16, i: u8,
+    j: u32, k: u8, l: u64, m: u8, n: u16, o: u8, p: u32, q: u8
+) -> u16 {
+    return d + h + n;
+}
+
+export func pick-bytes(
+    a: u8, b: u64, c: u8, d: u16, e: u32, f: u8, g: u64, h: u16, i: u8,
+    j: u32, k: u8, l: u64, m: u8, n: u16, o: u8, p: u32, q: u8
+) -> u8 {
+    return a + c + f + i + k + m + o + q;
+}
+
+export func first(a: u8, b: u8) -> u8 {
+    return a;
+}
+";
+
+fn call(name: &str, params: &[Val]) -> Val {
+    let component = compile("layout".to_owned(), SOURCE, Resolve::new()).unwrap_pretty();
+
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    let engine = Engine::new(&config).unwrap();
+    let component = Component::new(&engine, component).unwrap();
+    let linker = Linker::new(&engine);
+    let mut store = Store::new(&engine, ());
+    let instance = linker.instantiate(&mut store, &component).unwrap();
+
+    let func = instance.get_func(&mut store, name).unwrap();
+    let mut results = [Val::Bool(false)];
+    func.call(&mut store, params, &mut results).unwrap();
+    let [result] = results;
+    result
+}
+
+#[test]
+fn test_byte_params() {
+    let result = call("first", &[Val::U8(7), Val::U8(9)]);
+    assert_eq!(result, Val::U8(7));
+}
+
+#[test]
+fn test_spilled_params_are_aligned() {
+    let params = [
+        Val::U8(1),
+        Val::U64(1 << 40),
+        Val::U8(2),
+        Val::U16(3),
+        Val::U32(4),
+        Val::U8(5),
+        Val::U64(2 << 40),
+        Val::U16(30),
+        Val::U8(6),
+        Val::U32(7),
+        Val::U8(8),
+        Val::U64(4 << 40),
+        Val::U8(9),
+        Val::U16(300),
+        Val::U8(10),
+        Val::U32(11),
+        Val::U8(12),
+    ];
+    assert_eq!(call("pick-wide", &params), Val::U64(7 << 40));
+    assert_eq!(call("pick-narrow", &params), Val::U16(333));
+    assert_eq!(call("pick-bytes", &params), Val::U8(53));
+}
@@ -0,0 +1,60 @@
+use claw_codegen::{link, LinkComponent, LinkError};
+use claw_common::UnwrapPretty;
+use compile_claw::compile;
+
+use std::fs;
+
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Config, Engine, Store};
+use wit_parser::Resolve;
+
+fn compile_file(path: &str) -> LinkComponent {
+    let input = fs::read_to_string(path).unwrap();
+    let bytes = compile(path.to_owned(), &input, Resolve::new()).unwrap_pretty();
+    LinkComponent {
+        name: path.to_owned(),
+        bytes,
+    }
+}
+
+fn instantiate(bytes: &[u8]) -> (Store<()>, wasmtime::component::Instance) {
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    let engine = Engine::new(&config).unwrap();
+    let component = Component::new(&engine, bytes).unwrap();
+    let linker = Linker::new(&engine);
+    let mut store = Store::new(&engine, ());
+    let instance = linker.instantiate(&mut store, &component).unwrap();
+    (store, instance)
+}
+
+#[test]
+fn test_link_function_import() {
+    let root = compile_file("./tests/link/fourth-power.claw");
+    let square = compile_file("./tests/link/square.claw");
+    let linked = link(root, vec![square]).unwrap_pretty();
+
+    let (mut store, instance) = instantiate(&linked);
+    let fourth_power = instance
+        .get_typed_func::<(u32,), (u32,)>(&mut store, "fourth-power")
+        .unwrap();
+    let (result,) = fourth_power.call(&mut store, (3,)).unwrap();
+    assert_eq!(result, 81);
+}
+
+#[test]
+fn test_link_keeps_exported_types() {
+    let root = compile_file("./tests/programs/structs.claw");
+    let linked = link(root, vec![]).unwrap_pretty();
+    instantiate(&linked);
+}
+
+#[test]
+fn test_link_unresolved_import() {
+    let root = compile_file("./tests/link/fourth-power.claw");
+    match link(root, vec![]) {
+        Err(LinkError::UnresolvedImport { import, .. }) => assert_eq!(import, "square"),
+        Err(err) => panic!("Unexpected error {:?}", err),
+        Ok(_) => panic!("Linking should have failed"),
+    }
+}
@@ -0,0 +1,35 @@
+use claw_common::UnwrapPretty;
+use compile_claw::{compile_with_objects, LintConfig, Profile};
+
+use wit_parser::Resolve;
+
+/// The number of locals declared across every function in the component
+fn local_count(body: &str) -> usize {
+    let source = format!("export func calc(x: u32, y: u32) -> u32 {{\n{body}\n}}");
+    let component = compile_with_objects(
+        "locals".to_owned(),
+        &source,
+        Resolve::new(),
+        &LintConfig::default(),
+        &[],
+        Profile::Release,
+        false,
+    )
+    .unwrap_pretty()
+    .0;
+    let wat = wasmprinter::print_bytes(component).unwrap();
+    wat.lines()
+        .filter_map(|line| line.trim().strip_prefix("(local "))
+        .map(|locals| locals.split_whitespace().count())
+        .sum()
+}
+
+#[test]
+fn test_sequential_temporaries_share_locals() {
+    let one = local_count("let a = x + y;\nreturn a;");
+    let chain = local_count("let a = x + y;\nlet b = a * y;\nlet c = b - x;\nreturn c;");
+    assert_eq!(chain, one);
+    // `a` is still live while `b` and `c` are computed
+    let body = "let a = x + y;\nlet b = a * y;\nlet c = b - x;\nreturn c + a;";
+    assert_eq!(local_count(body), one + 1);
+}
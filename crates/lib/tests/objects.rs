@@ -0,0 +1,65 @@
+use claw_codegen::GenerationError;
+use claw_common::UnwrapPretty;
+use compile_claw::{compile_with_objects, CoreObject, Error, LintConfig, Profile};
+
+use std::fs;
+
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Config, Engine, Store};
+use wit_parser::Resolve;
+
+fn compile(name: &str) -> Result<Vec<u8>, Box<Error>> {
+    let path = format!("./tests/objects/{}.claw", name);
+    let input = fs::read_to_string(path).unwrap();
+    let object = CoreObject {
+        name: "arith".to_owned(),
+        bytes: wat::parse_file("./tests/objects/arith.wat").unwrap(),
+    };
+    let lints = LintConfig::default();
+    let objects = [object];
+    let (output, _) = compile_with_objects(
+        name.to_owned(),
+        &input,
+        Resolve::new(),
+        &lints,
+        &objects,
+        Profile::Debug,
+        false,
+    )
+    .map_err(Box::new)?;
+    Ok(output)
+}
+
+#[test]
+fn test_object_import() {
+    let component_bytes = compile("add-twice").map_err(|err| *err).unwrap_pretty();
+
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    let engine = Engine::new(&config).unwrap();
+    let component = Component::new(&engine, &component_bytes).unwrap();
+    // The object provides the import, so there's nothing to link
+    let linker = Linker::new(&engine);
+    let mut store = Store::new(&engine, ());
+    let instance = linker.instantiate(&mut store, &component).unwrap();
+
+    let add_twice = instance
+        .get_typed_func::<(i32, i32), (i32,)>(&mut store, "add-twice")
+        .unwrap();
+    let (result,) = add_twice.call(&mut store, (3, 4)).unwrap();
+    assert_eq!(result, 11);
+}
+
+#[test]
+fn test_object_signature_mismatch() {
+    match compile("bad-widen").map_err(|err| *err) {
+        Err(Error::Generator(GenerationError::ObjectSignature {
+            expected, found, ..
+        })) => {
+            assert_eq!(expected, "(i32) -> (i32)");
+            assert_eq!(found, "(i64) -> (i64)");
+        }
+        Err(err) => panic!("Unexpected error {:?}", err),
+        Ok(_) => panic!("Compiling should have failed"),
+    }
+}
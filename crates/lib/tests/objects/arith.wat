@@ -0,0 +1,7 @@
+(module
+  (func (export "add") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add)
+  (func (export "widen") (param i64) (result i64)
+    local.get 0))
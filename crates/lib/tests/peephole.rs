@@ -0,0 +1,47 @@
+use claw_common::UnwrapPretty;
+use compile_claw::{compile_with_objects, LintConfig, Profile};
+
+use wit_parser::Resolve;
+
+/// How many more times each instruction appears in the component
+/// than in one where the function just returns its parameter
+fn instruction_counts<const N: usize>(body: &str, instructions: [&str; N]) -> [usize; N] {
+    let print = |body: &str| {
+        let source = format!("export func calc(x: u32) -> u32 {{\n{body}\n}}");
+        let component = compile_with_objects(
+            "peephole".to_owned(),
+            &source,
+            Resolve::new(),
+            &LintConfig::default(),
+            &[],
+            Profile::Release,
+            false,
+        )
+        .unwrap_pretty()
+        .0;
+        wasmprinter::print_bytes(component).unwrap()
+    };
+    let wat = print(body);
+    let baseline = print("return x;");
+    instructions
+        .map(|instruction| wat.matches(instruction).count() - baseline.matches(instruction).count())
+}
+
+#[test]
+fn test_multiply_by_power_of_two() {
+    let counts = instruction_counts("return x * 8;", ["i32.mul", "i32.shl"]);
+    assert_eq!(counts, [0, 1]);
+    let counts = instruction_counts("return x * 1;", ["i32.mul", "i32.shl"]);
+    assert_eq!(counts, [0, 0]);
+    let counts = instruction_counts("return x * 6;", ["i32.mul", "i32.shl"]);
+    assert_eq!(counts, [1, 0]);
+}
+
+#[test]
+fn test_constant_addition() {
+    let counts = instruction_counts("return x + 0;", ["i32.add"]);
+    assert_eq!(counts, [0]);
+    // Constants stored in locals are forwarded to where they are read
+    let counts = instruction_counts("let y: u32 = 2;\nreturn y + 3;", ["i32.add"]);
+    assert_eq!(counts, [0]);
+}
@@ -0,0 +1,41 @@
+use claw_common::UnwrapPretty;
+use compile_claw::{compile_with_objects, LintConfig, Profile};
+
+use wit_parser::Resolve;
+
+fn print(profile: Profile) -> String {
+    let source = "func double(x: u32) -> u32 {\nreturn x * 2;\n}\n\
+        export func calc(x: u32) -> u32 {\nreturn double(x) + 1;\n}";
+    let lints = LintConfig::default();
+    let (component, _) = compile_with_objects(
+        "profile".to_owned(),
+        source,
+        Resolve::new(),
+        &lints,
+        &[],
+        profile,
+        false,
+    )
+    .unwrap_pretty();
+    wasmprinter::print_bytes(component).unwrap()
+}
+
+#[test]
+fn test_debug_names_functions() {
+    let wat = print(Profile::Debug);
+    assert!(wat.contains("(func $double"));
+    assert!(wat.contains("(func $calc"));
+}
+
+#[test]
+fn test_release_is_optimized_without_names() {
+    let wat = print(Profile::Release);
+    assert!(!wat.contains("$double"));
+    assert!(!wat.contains("$calc"));
+    // The multiply in `double` becomes a shift
+    let debug = print(Profile::Debug);
+    assert_eq!(
+        wat.matches("i32.mul").count() + 1,
+        debug.matches("i32.mul").count()
+    );
+}
@@ -1,9 +1,10 @@
 use claw_common::UnwrapPretty;
-use compile_claw::compile;
+use compile_claw::{compile_with_objects, LintConfig, Profile};
 
 use std::fs;
+use std::sync::{Arc, Mutex};
 
-use wasmtime::component::{bindgen, Component, Linker};
+use wasmtime::component::{bindgen, Component, Linker, Resource, ResourceType, Val};
 use wasmtime::{Config, Engine, Store};
 use wit_parser::Resolve;
 
@@ -16,13 +17,18 @@ struct Runtime {
 }
 
 impl Runtime {
-    pub fn new(name: &str) -> Self {
+    /// Each test runs once for every profile, as both have to behave the same
+    pub fn new(name: &str, profile: Profile) -> Self {
         let path = format!("./tests/programs/{}.claw", name);
         let input = fs::read_to_string(path).unwrap();
         let mut wit = Resolve::new();
         wit.push_path("./tests/programs/wit").unwrap();
-        let component_bytes = compile(name.to_owned(), &input, wit).unwrap_pretty();
+        let lints = LintConfig::default();
+        let (component_bytes, _) =
+            compile_with_objects(name.to_owned(), &input, wit, &lints, &[], profile, false)
+                .unwrap_pretty();
 
+        println!("{} profile", profile);
         println!("{}", wasmprinter::print_bytes(&component_bytes).unwrap());
 
         let mut config = Config::new();
@@ -40,88 +46,605 @@ impl Runtime {
             store,
         }
     }
+
+    /// Stand in for WASI, recording each write to stdout (1) and stderr (2)
+    pub fn stub_stdio(&mut self) -> Arc<Mutex<Writes>> {
+        struct Error;
+        struct OutputStream;
+        let writes: Arc<Mutex<Writes>> = Default::default();
+        let linker = &mut self.linker;
+        linker
+            .instance("wasi:io/error@0.2.0")
+            .unwrap()
+            .resource("error", ResourceType::host::<Error>(), |_, _| Ok(()))
+            .unwrap();
+        let mut streams = linker.instance("wasi:io/streams@0.2.0").unwrap();
+        streams
+            .resource(
+                "output-stream",
+                ResourceType::host::<OutputStream>(),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        let recorded = writes.clone();
+        streams
+            .func_new(
+                "[method]output-stream.blocking-write-and-flush",
+                move |mut store, params, results| {
+                    let stream = match &params[0] {
+                        Val::Resource(stream) => {
+                            Resource::<OutputStream>::try_from_resource_any(*stream, &mut store)?
+                        }
+                        _ => unreachable!(),
+                    };
+                    let contents: Vec<u8> = match &params[1] {
+                        Val::List(bytes) => bytes
+                            .iter()
+                            .map(|byte| match byte {
+                                Val::U8(byte) => *byte,
+                                _ => unreachable!(),
+                            })
+                            .collect(),
+                        _ => unreachable!(),
+                    };
+                    assert!(contents.len() <= 4096, "WASI only allows 4096 byte writes");
+                    recorded.lock().unwrap().push((stream.rep(), contents));
+                    results[0] = Val::Result(Ok(None));
+                    Ok(())
+                },
+            )
+            .unwrap();
+        for (interface, name, rep) in [
+            ("wasi:cli/stdout@0.2.0", "get-stdout", 1),
+            ("wasi:cli/stderr@0.2.0", "get-stderr", 2),
+        ] {
+            linker
+                .instance(interface)
+                .unwrap()
+                .func_wrap(name, move |_, ()| {
+                    Ok((Resource::<OutputStream>::new_own(rep),))
+                })
+                .unwrap();
+        }
+        writes
+    }
+}
+
+type Writes = Vec<(u32, Vec<u8>)>;
+
+/// Everything written to the stream by a stubbed WASI
+fn written(writes: &Mutex<Writes>, stream: u32) -> String {
+    let writes = writes.lock().unwrap();
+    let bytes: Vec<u8> = writes
+        .iter()
+        .filter(|(rep, _)| *rep == stream)
+        .flat_map(|(_, bytes)| bytes.iter().copied())
+        .collect();
+    String::from_utf8(bytes).unwrap()
 }
 
 #[test]
 fn test_arithmetic() {
     bindgen!("arithmetic" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("arithmetic");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("arithmetic", *profile);
 
-    let (arithmetic, _) =
-        Arithmetic::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let (arithmetic, _) =
+            Arithmetic::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
 
-    assert!(arithmetic.call_test_u8_masking(&mut runtime.store).unwrap());
+        assert!(arithmetic.call_test_u8_masking(&mut runtime.store).unwrap());
+    }
 }
 
 #[test]
-fn test_counter() {
-    bindgen!("counter" in "tests/programs/wit");
+fn test_asm() {
+    bindgen!("asm" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("asm", *profile);
+
+        let (asm, _) =
+            Asm::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        assert_eq!(asm.call_add(&mut runtime.store, 2, 3).unwrap(), 5);
+        assert_eq!(asm.call_popcount(&mut runtime.store, 0xFF00FF).unwrap(), 16);
+        assert_eq!(asm.call_sum_to(&mut runtime.store, 10).unwrap(), 55);
+        assert_eq!(asm.call_length(&mut runtime.store, "hello").unwrap(), 5);
+        assert_eq!(asm.call_add_one(&mut runtime.store, 41).unwrap(), 42);
+    }
+}
+
+#[test]
+fn test_bits() {
+    bindgen!("bits" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("bits", *profile);
 
-    let mut runtime = Runtime::new("counter");
+        let (bits, _) =
+            Bits::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
 
-    let (counter_s64, _) =
-        Counter::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        for (x, n) in [(0x1234_5678u32, 0u32), (0x8000_0001, 1), (0xF0, 36)] {
+            let expected = x.rotate_left(n);
+            assert_eq!(
+                bits.call_rotl_u32(&mut runtime.store, x, n).unwrap(),
+                expected
+            );
+            let (x, n) = (x as u64 | 0xAB << 40, n as u64);
+            let expected = x.rotate_right(n as u32);
+            assert_eq!(
+                bits.call_rotr_u64(&mut runtime.store, x, n).unwrap(),
+                expected
+            );
+        }
+        for x in [0u8, 1, 0x81, 0xF0, 0xFF] {
+            for n in [0u8, 1, 7, 8, 13] {
+                let expected = x.rotate_left(n as u32);
+                assert_eq!(
+                    bits.call_rotl_u8(&mut runtime.store, x, n).unwrap(),
+                    expected
+                );
+                let x = x as u16 * 0x101;
+                let expected = x.rotate_right(n as u32);
+                let actual = bits.call_rotr_u16(&mut runtime.store, x, n as u16).unwrap();
+                assert_eq!(actual, expected);
+            }
+            assert_eq!(
+                bits.call_clz_u8(&mut runtime.store, x).unwrap(),
+                x.leading_zeros() as u8
+            );
+            assert_eq!(
+                bits.call_ctz_u16(&mut runtime.store, x as u16).unwrap(),
+                (x as u16).trailing_zeros() as u16
+            );
+        }
+        for x in [0u32, 1, 0x0001_0000, u32::MAX] {
+            assert_eq!(
+                bits.call_clz_u32(&mut runtime.store, x).unwrap(),
+                x.leading_zeros()
+            );
+            assert_eq!(
+                bits.call_ctz_u64(&mut runtime.store, x as u64).unwrap(),
+                (x as u64).trailing_zeros() as u64
+            );
+            assert_eq!(
+                bits.call_popcount_s32(&mut runtime.store, x as i32)
+                    .unwrap(),
+                x.count_ones() as i32
+            );
+        }
+    }
+}
+
+#[test]
+fn test_bytes() {
+    bindgen!("bytes" in "tests/programs/wit");
 
-    for i in 1..200 {
-        // Increase by one
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("bytes", *profile);
+
+        let (bytes, _) =
+            Bytes::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        let data: Vec<u8> = (1..=10).collect();
+        assert_eq!(bytes.call_read_u8(&mut *store, &data, 9).unwrap(), 10);
+        assert_eq!(bytes.call_read_u16(&mut *store, &data, 1).unwrap(), 0x0302);
         assert_eq!(
-            counter_s64.call_increment_s32(&mut runtime.store).unwrap(),
-            i
+            bytes.call_read_u32(&mut *store, &data, 3).unwrap(),
+            0x07060504
         );
         assert_eq!(
-            counter_s64.call_increment_s64(&mut runtime.store).unwrap(),
-            i as i64
+            bytes.call_read_u64(&mut *store, &data, 2).unwrap(),
+            0x0a09080706050403
         );
-        // Increase then decrease by one
+
+        let frame = [2, 0, 0, 0, 0xaa, 0xbb, 7];
+        assert_eq!(bytes.call_frame_tag(&mut *store, &frame).unwrap(), 7);
+
+        let data = [0u8; 10];
+        let mut expected = data;
+        expected[9] = 0xff;
         assert_eq!(
-            counter_s64.call_increment_s32(&mut runtime.store).unwrap(),
-            i + 1
+            bytes.call_write_u8(&mut *store, &data, 9, 0xff).unwrap(),
+            expected
         );
+        let mut expected = data;
+        expected[1..3].copy_from_slice(&0xbeefu16.to_le_bytes());
         assert_eq!(
-            counter_s64.call_increment_s64(&mut runtime.store).unwrap(),
-            i as i64 + 1
+            bytes.call_write_u16(&mut *store, &data, 1, 0xbeef).unwrap(),
+            expected
         );
+        let mut expected = data;
+        expected[5..9].copy_from_slice(&0xdeadbeefu32.to_le_bytes());
         assert_eq!(
-            counter_s64.call_decrement_s32(&mut runtime.store).unwrap(),
-            i
+            bytes
+                .call_write_u32(&mut *store, &data, 5, 0xdeadbeef)
+                .unwrap(),
+            expected
         );
+        let mut expected = data;
+        expected[2..10].copy_from_slice(&u64::MAX.to_le_bytes());
         assert_eq!(
-            counter_s64.call_decrement_s64(&mut runtime.store).unwrap(),
-            i as i64
+            bytes
+                .call_write_u64(&mut *store, &data, 2, u64::MAX)
+                .unwrap(),
+            expected
         );
+
+        // Accesses which don't fit in the bytes trap
+        let data = [0u8; 4];
+        for offset in [1, 4, u32::MAX] {
+            let (bytes, _) =
+                Bytes::instantiate(&mut *store, &runtime.component, &runtime.linker).unwrap();
+            assert!(bytes.call_read_u32(&mut *store, &data, offset).is_err());
+        }
+        for offset in [3, 4] {
+            let (bytes, _) =
+                Bytes::instantiate(&mut *store, &runtime.component, &runtime.linker).unwrap();
+            assert!(bytes.call_write_u16(&mut *store, &data, offset, 1).is_err());
+        }
     }
+}
 
-    for i in (1..200).rev() {
-        assert_eq!(
-            counter_s64.call_decrement_s32(&mut runtime.store).unwrap(),
-            i - 1
-        );
+#[test]
+fn test_checked() {
+    bindgen!("checked" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("checked", *profile);
+
+        let (checked, _) =
+            Checked::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        let u8s = [0u8, 1, 100, 200, 255];
+        for (a, b) in u8s.iter().flat_map(|a| u8s.iter().map(move |b| (*a, *b))) {
+            let added = checked.call_checked_add_u8(&mut *store, a, b).unwrap();
+            assert_eq!(added, a.checked_add(b));
+        }
+        let s8s = [0i8, 1, -1, 100, -100, i8::MAX, i8::MIN];
+        for (a, b) in s8s.iter().flat_map(|a| s8s.iter().map(move |b| (*a, *b))) {
+            let subtracted = checked.call_checked_sub_s8(&mut *store, a, b).unwrap();
+            assert_eq!(subtracted, a.checked_sub(b), "{} - {}", a, b);
+            let (a, b) = (a as i16 * 3, b as i16 * 5);
+            let multiplied = checked.call_checked_mul_s16(&mut *store, a, b).unwrap();
+            assert_eq!(multiplied, a.checked_mul(b), "{} * {}", a, b);
+        }
+        let u32s = [0u32, 1, 3, 1 << 16, 1 << 31, u32::MAX];
+        for (a, b) in u32s.iter().flat_map(|a| u32s.iter().map(move |b| (*a, *b))) {
+            let added = checked.call_checked_add_u32(&mut *store, a, b).unwrap();
+            assert_eq!(added, a.checked_add(b));
+            let subtracted = checked.call_checked_sub_u32(&mut *store, a, b).unwrap();
+            assert_eq!(subtracted, a.checked_sub(b));
+            let multiplied = checked.call_checked_mul_u32(&mut *store, a, b).unwrap();
+            assert_eq!(multiplied, a.checked_mul(b), "{} * {}", a, b);
+            let fallback = checked.call_add_or(&mut *store, a, b, 7).unwrap();
+            assert_eq!(fallback, a.checked_add(b).unwrap_or(7));
+            let (a, b) = (a as u64 * 5, b as u64 * (1 << 30));
+            let multiplied = checked.call_checked_mul_u64(&mut *store, a, b).unwrap();
+            assert_eq!(multiplied, a.checked_mul(b), "{} * {}", a, b);
+        }
+        let s32s = [0i32, 1, -1, 3, 1 << 16, i32::MAX, i32::MIN];
+        for (a, b) in s32s.iter().flat_map(|a| s32s.iter().map(move |b| (*a, *b))) {
+            let added = checked.call_checked_add_s32(&mut *store, a, b).unwrap();
+            assert_eq!(added, a.checked_add(b), "{} + {}", a, b);
+            let subtracted = checked.call_checked_sub_s32(&mut *store, a, b).unwrap();
+            assert_eq!(subtracted, a.checked_sub(b), "{} - {}", a, b);
+            let multiplied = checked.call_checked_mul_s32(&mut *store, a, b).unwrap();
+            assert_eq!(multiplied, a.checked_mul(b), "{} * {}", a, b);
+        }
+        let s64s = [0i64, 1, -1, 1 << 32, i64::MAX, i64::MIN];
+        for (a, b) in s64s.iter().flat_map(|a| s64s.iter().map(move |b| (*a, *b))) {
+            let added = checked.call_checked_add_s64(&mut *store, a, b).unwrap();
+            assert_eq!(added, a.checked_add(b), "{} + {}", a, b);
+            let multiplied = checked.call_checked_mul_s64(&mut *store, a, b).unwrap();
+            assert_eq!(multiplied, a.checked_mul(b), "{} * {}", a, b);
+            let fits = checked.call_mul_fits(&mut *store, a, b).unwrap();
+            assert_eq!(fits, a.checked_mul(b).is_some());
+        }
+    }
+}
+
+#[test]
+fn test_conditionals() {
+    bindgen!("conditionals" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("conditionals", *profile);
+
+        let (conditionals, _) =
+            Conditionals::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
+
+        let store = &mut runtime.store;
+        for (a, b) in [(1, 2), (2, 1), (-5, -5), (i32::MIN, i32::MAX)] {
+            assert_eq!(
+                conditionals.call_larger(&mut *store, a, b).unwrap(),
+                a.max(b)
+            );
+        }
+        for x in [-7, 0, 3, i32::MIN, i32::MAX] {
+            assert_eq!(conditionals.call_sign(&mut *store, x).unwrap(), x.signum());
+        }
+
+        let values = [4, 5, 6];
+        for i in 0..5 {
+            let value = conditionals
+                .call_get_or_zero(&mut *store, &values, i)
+                .unwrap();
+            assert_eq!(value, values.get(i as usize).copied().unwrap_or(0));
+        }
+
+        let levels = [
+            (Level::Low, 1, "not high", Level::Mid),
+            (Level::Mid, 5, "not high", Level::High),
+            (Level::High, 10, "high", Level::High),
+        ];
+        for (level, weight, description, raised) in levels {
+            assert_eq!(
+                conditionals.call_weight(&mut *store, level).unwrap(),
+                weight
+            );
+            assert_eq!(
+                conditionals.call_describe(&mut *store, level).unwrap(),
+                description
+            );
+            assert_eq!(conditionals.call_raise(&mut *store, level).unwrap(), raised);
+        }
+
+        for (level, rank) in [(Level::Low, 1), (Level::Mid, 2), (Level::High, 3)] {
+            assert_eq!(conditionals.call_rank(&mut *store, level).unwrap(), rank);
+            assert_eq!(
+                conditionals.call_low_only(&mut *store, level).unwrap(),
+                (level == Level::Low) as u32
+            );
+        }
+        for (n, bucket) in [(0, 1), (10, 1), (11, 2), (100, 2), (101, 3), (u32::MAX, 3)] {
+            assert_eq!(conditionals.call_bucket(&mut *store, n).unwrap(), bucket);
+        }
+        let urgencies = [
+            (Level::Low, false, 1),
+            (Level::Low, true, 1),
+            (Level::Mid, false, 5),
+            (Level::Mid, true, 7),
+            (Level::High, false, 10),
+            (Level::High, true, 20),
+        ];
+        for (level, late, urgency) in urgencies {
+            assert_eq!(
+                conditionals.call_urgency(&mut *store, level, late).unwrap(),
+                urgency
+            );
+        }
+    }
+}
+
+#[test]
+fn test_counter() {
+    bindgen!("counter" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("counter", *profile);
+
+        let (counter_s64, _) =
+            Counter::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        for i in 1..200 {
+            // Increase by one
+            assert_eq!(
+                counter_s64.call_increment_s32(&mut runtime.store).unwrap(),
+                i
+            );
+            assert_eq!(
+                counter_s64.call_increment_s64(&mut runtime.store).unwrap(),
+                i as i64
+            );
+            // Increase then decrease by one
+            assert_eq!(
+                counter_s64.call_increment_s32(&mut runtime.store).unwrap(),
+                i + 1
+            );
+            assert_eq!(
+                counter_s64.call_increment_s64(&mut runtime.store).unwrap(),
+                i as i64 + 1
+            );
+            assert_eq!(
+                counter_s64.call_decrement_s32(&mut runtime.store).unwrap(),
+                i
+            );
+            assert_eq!(
+                counter_s64.call_decrement_s64(&mut runtime.store).unwrap(),
+                i as i64
+            );
+        }
+
+        for i in (1..200).rev() {
+            assert_eq!(
+                counter_s64.call_decrement_s32(&mut runtime.store).unwrap(),
+                i - 1
+            );
+            assert_eq!(
+                counter_s64.call_decrement_s64(&mut runtime.store).unwrap(),
+                i as i64 - 1
+            );
+        }
+
+        // The discarded value of an expression statement is still computed
         assert_eq!(
-            counter_s64.call_decrement_s64(&mut runtime.store).unwrap(),
-            i as i64 - 1
+            counter_s64
+                .call_increment_twice_s32(&mut runtime.store)
+                .unwrap(),
+            2
         );
     }
 }
 
+#[test]
+fn test_enums() {
+    bindgen!("enums" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("enums", *profile);
+
+        let (enums, _) =
+            Enums::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        assert_eq!(enums.call_not_found(&mut *store).unwrap(), Status::NotFound);
+
+        let codes = [
+            (Status::Ok, 200),
+            (Status::Created, 201),
+            (Status::NotFound, 404),
+            (Status::Teapot, 418),
+            (Status::ServerError, 500),
+        ];
+        for (status, code) in codes {
+            assert_eq!(enums.call_code(&mut *store, status).unwrap(), code);
+            assert_eq!(enums.call_from_code(&mut *store, code).unwrap(), status);
+        }
+
+        let levels = [(Level::Low, -1), (Level::Mid, 0), (Level::High, 1)];
+        for (level, value) in levels {
+            assert_eq!(enums.call_level_value(&mut *store, level).unwrap(), value);
+            assert_eq!(
+                enums.call_from_level_value(&mut *store, value).unwrap(),
+                level
+            );
+        }
+
+        let swapped = enums.call_swap_status(&mut *store, Status::NotFound);
+        assert_eq!(swapped.unwrap(), Status::Created);
+        let swapped = enums.call_swap_status(&mut *store, Status::Ok);
+        assert_eq!(swapped.unwrap(), Status::NotFound);
+
+        // Converting a value no case has traps
+        for code in [0, 202, 403, u32::MAX] {
+            assert!(enums.call_from_code(&mut *store, code).is_err());
+        }
+        assert!(enums.call_from_level_value(&mut *store, 2).is_err());
+        assert!(enums.call_unknown(&mut *store).is_err());
+    }
+}
+
 #[test]
 fn test_factorial() {
     bindgen!("factorial" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("factorial");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("factorial", *profile);
 
-    let (factorial, _) =
-        Factorial::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let (factorial, _) =
+            Factorial::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
 
-    for (i, val) in [1, 1, 2, 6, 24, 120].iter().enumerate() {
-        let fact = factorial
-            .call_factorial(&mut runtime.store, i as u64)
-            .unwrap();
+        for (i, val) in [1, 1, 2, 6, 24, 120].iter().enumerate() {
+            let fact = factorial
+                .call_factorial(&mut runtime.store, i as u64)
+                .unwrap();
+            assert_eq!(
+                fact, *val,
+                "factorial({}) was {} instead of {}",
+                i, fact, *val
+            );
+        }
+    }
+}
+
+#[test]
+fn test_format() {
+    bindgen!("format" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("format", *profile);
+
+        let (format, _) =
+            Format::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let store = &mut runtime.store;
+
+        assert_eq!(
+            format.call_greet(&mut *store, "Ferris", 7).unwrap(),
+            "Ferris is 7 years old"
+        );
+        assert_eq!(format.call_hex(&mut *store, 0).unwrap(), "0x0");
+        assert_eq!(format.call_hex(&mut *store, 0xbeef).unwrap(), "0xbeef");
         assert_eq!(
-            fact, *val,
-            "factorial({}) was {} instead of {}",
-            i, fact, *val
+            format.call_hex(&mut *store, u32::MAX).unwrap(),
+            "0xffffffff"
         );
+        assert_eq!(
+            format.call_signed(&mut *store, -1, i64::MIN).unwrap(),
+            "-1, -9223372036854775808 and ff"
+        );
+        assert_eq!(
+            format.call_signed(&mut *store, 100, 42).unwrap(),
+            "100, 42 and 64"
+        );
+        for (amount, expected) in [
+            (0.0, "$0.00"),
+            (1.005, "$1.00"),
+            (2.5, "$2.50"),
+            (9.999, "$10.00"),
+            (-12.5, "$-12.50"),
+        ] {
+            assert_eq!(format.call_money(&mut *store, amount).unwrap(), expected);
+        }
+        for (value, expected) in [
+            (0.0, "0"),
+            (1.5, "1.5"),
+            (-0.1, "-0.1"),
+            (100.0, "100"),
+            (f32::NAN, "NaN"),
+            (f32::NEG_INFINITY, "-inf"),
+        ] {
+            assert_eq!(format.call_ratio(&mut *store, value).unwrap(), expected);
+        }
+        assert_eq!(format.call_flag(&mut *store, true).unwrap(), "{true}");
+        assert_eq!(format.call_flag(&mut *store, false).unwrap(), "{false}");
+        assert_eq!(format.call_literals(&mut *store).unwrap(), "ff, -3 and 2.5");
+    }
+}
+
+#[test]
+fn test_globals() {
+    bindgen!("globals" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("globals", *profile);
+
+        let (globals, _) =
+            Globals::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        assert_eq!(globals.call_minus_one_s32(&mut *store).unwrap(), -1);
+        assert_eq!(globals.call_min_s64_value(&mut *store).unwrap(), i64::MIN);
+        assert_eq!(globals.call_min_s8_value(&mut *store).unwrap(), i8::MIN);
+        assert_eq!(globals.call_max_u8_value(&mut *store).unwrap(), u8::MAX);
+        assert_eq!(globals.call_shift(&mut *store, 0.5).unwrap(), -1.0);
+        assert_eq!(globals.call_shift(&mut *store, 4.0).unwrap(), 3.0);
+    }
+}
+
+#[test]
+fn test_higher_order() {
+    bindgen!("higher-order" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("higher-order", *profile);
+
+        let (higher_order, _) =
+            HigherOrder::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
+
+        let store = &mut runtime.store;
+        assert_eq!(higher_order.call_twice_doubled(&mut *store, 3).unwrap(), 12);
+        assert_eq!(higher_order.call_twice_squared(&mut *store, 3).unwrap(), 81);
+        assert_eq!(higher_order.call_chosen(&mut *store, 0, 5).unwrap(), 10);
+        assert_eq!(higher_order.call_chosen(&mut *store, 1, 5).unwrap(), 25);
+        assert_eq!(higher_order.call_twice_offset(&mut *store, 1).unwrap(), 21);
     }
 }
 
@@ -129,13 +652,15 @@ fn test_factorial() {
 fn test_identity() {
     bindgen!("identity" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("identity");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("identity", *profile);
 
-    let (identity, _) =
-        Identity::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let (identity, _) =
+            Identity::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
 
-    for i in [0, 1, 2, 12, 5634, 34] {
-        assert_eq!(identity.call_identity(&mut runtime.store, i).unwrap(), i);
+        for i in [0, 1, 2, 12, 5634, 34] {
+            assert_eq!(identity.call_identity(&mut runtime.store, i).unwrap(), i);
+        }
     }
 }
 
@@ -143,45 +668,359 @@ fn test_identity() {
 fn test_compare() {
     bindgen!("compare" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("compare");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("compare", *profile);
 
-    let (compare, _) =
-        Compare::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let (compare, _) =
+            Compare::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
 
-    for i in 1..200 {
-        for j in 1..200 {
-            let expected_min = std::cmp::min(i, j);
-            let expected_max = std::cmp::max(i, j);
-            let actual_min = compare.call_min_u32(&mut runtime.store, i, j).unwrap();
-            let actual_max = compare.call_max_u32(&mut runtime.store, i, j).unwrap();
-            assert_eq!(
-                expected_min, actual_min,
-                "expected min({}, {}) to be {} not {}",
-                i, j, expected_min, actual_min
-            );
-            assert_eq!(
-                expected_max, actual_max,
-                "expected max({}, {}) to be {} not {}",
-                i, j, expected_max, actual_max
-            );
+        for i in 1..200 {
+            for j in 1..200 {
+                let expected_min = std::cmp::min(i, j);
+                let expected_max = std::cmp::max(i, j);
+                let actual_min = compare.call_min_u32(&mut runtime.store, i, j).unwrap();
+                let actual_max = compare.call_max_u32(&mut runtime.store, i, j).unwrap();
+                assert_eq!(
+                    expected_min, actual_min,
+                    "expected min({}, {}) to be {} not {}",
+                    i, j, expected_min, actual_min
+                );
+                assert_eq!(
+                    expected_max, actual_max,
+                    "expected max({}, {}) to be {} not {}",
+                    i, j, expected_max, actual_max
+                );
+
+                let i = i as u64;
+                let j = j as u64;
+                let expected_min = expected_min as u64;
+                let expected_max = expected_max as u64;
+                let actual_min = compare.call_min_u64(&mut runtime.store, i, j).unwrap();
+                let actual_max = compare.call_max_u64(&mut runtime.store, i, j).unwrap();
+                assert_eq!(
+                    expected_min, actual_min,
+                    "expected min({}, {}) to be {} not {}",
+                    i, j, expected_min, actual_min
+                );
+                assert_eq!(
+                    expected_max, actual_max,
+                    "expected max({}, {}) to be {} not {}",
+                    i, j, expected_max, actual_max
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lists() {
+    bindgen!("lists" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("lists", *profile);
+
+        let (lists, _) =
+            Lists::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        let values = [3u32, 1, 4, 1, 5, 9, 2, 6];
+        assert_eq!(lists.call_count(&mut *store, &[]).unwrap(), 0);
+        assert_eq!(lists.call_count(&mut *store, &values).unwrap(), 8);
+
+        let values = [-3i16, 1, i16::MIN, i16::MAX];
+        for (index, value) in values.iter().enumerate() {
+            let actual = lists.call_nth(&mut *store, &values, index as u32).unwrap();
+            assert_eq!(actual, *value);
+        }
+
+        let values: Vec<u64> = (0..100).map(|i| i * i * 1_000_000_007).collect();
+        let expected = values.iter().sum::<u64>();
+        assert_eq!(lists.call_sum(&mut *store, &values).unwrap(), expected);
+
+        // Accesses after checking the length skip the bounds check
+        let values = [-3i16, 1, i16::MIN];
+        assert_eq!(
+            lists.call_nth_or(&mut *store, &values, 2, 7).unwrap(),
+            i16::MIN
+        );
+        assert_eq!(lists.call_nth_or(&mut *store, &values, 3, 7).unwrap(), 7);
+        assert_eq!(lists.call_nth_or(&mut *store, &[], 0, 7).unwrap(), 7);
+        let values = [3u32, 1, 4];
+        assert_eq!(lists.call_pair_sum(&mut *store, &values, 0, 2).unwrap(), 7);
+        assert_eq!(lists.call_pair_sum(&mut *store, &values, 2, 3).unwrap(), 0);
+        assert_eq!(lists.call_pair_sum(&mut *store, &values, 3, 0).unwrap(), 0);
+
+        let values = [7u8, 200];
+        assert_eq!(lists.call_get_or(&mut *store, &values, 1, 0).unwrap(), 200);
+        assert_eq!(lists.call_get_or(&mut *store, &values, 2, 42).unwrap(), 42);
+        assert_eq!(lists.call_get_or(&mut *store, &[], 0, 42).unwrap(), 42);
 
-            let i = i as u64;
-            let j = j as u64;
-            let expected_min = expected_min as u64;
-            let expected_max = expected_max as u64;
-            let actual_min = compare.call_min_u64(&mut runtime.store, i, j).unwrap();
-            let actual_max = compare.call_max_u64(&mut runtime.store, i, j).unwrap();
+        let words = ["lorem".to_owned(), "".to_owned(), "ipsum".to_owned()];
+        for (index, word) in words.iter().enumerate() {
             assert_eq!(
-                expected_min, actual_min,
-                "expected min({}, {}) to be {} not {}",
-                i, j, expected_min, actual_min
+                lists.call_word(&mut *store, &words, index as u32).unwrap(),
+                *word
             );
+        }
+
+        let values = [0.5, -1.0, f64::MAX];
+        assert_eq!(lists.call_identity(&mut *store, &values).unwrap(), values);
+
+        let values = [3u32, 1, 4, 1, 5, 9, 2, 6];
+        for (start, end) in [(0, 8), (2, 5), (3, 3), (8, 8)] {
             assert_eq!(
-                expected_max, actual_max,
-                "expected max({}, {}) to be {} not {}",
-                i, j, expected_max, actual_max
+                lists.call_middle(&mut *store, &values, start, end).unwrap(),
+                &values[start as usize..end as usize]
             );
         }
+        assert_eq!(lists.call_sum_tail(&mut *store, &[1, 2, 3]).unwrap(), 5);
+        assert_eq!(
+            lists.call_first_words(&mut *store, &words, 2).unwrap(),
+            &words[..2]
+        );
+
+        // Locals without an annotation take the type of their initializer
+        let values = [3u32, 1, 4];
+        assert_eq!(lists.call_copied_first(&mut *store, &values).unwrap(), 3);
+        assert_eq!(lists.call_copied_count(&mut *store, &values).unwrap(), 3);
+        assert_eq!(lists.call_copied_count(&mut *store, &[]).unwrap(), 0);
+
+        // Out of bounds indexing traps
+        let values = [-3i16, 1, i16::MIN, i16::MAX];
+        assert!(lists.call_nth(&mut *store, &values, 4).is_err());
+
+        // So does slicing past the end or backwards
+        let values = [3u32, 1, 4];
+        for (start, end) in [(0, 4), (2, 1), (4, 4)] {
+            let (lists, _) =
+                Lists::instantiate(&mut *store, &runtime.component, &runtime.linker).unwrap();
+            assert!(lists.call_middle(&mut *store, &values, start, end).is_err());
+        }
+    }
+}
+
+#[test]
+fn test_math() {
+    bindgen!("math" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("math", *profile);
+
+        let (math, _) =
+            Math::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        assert_eq!(math.call_sqrt_f64(&mut *store, 2.25).unwrap(), 1.5);
+        for x in [0, 5, -5, i32::MAX, i32::MIN] {
+            assert_eq!(math.call_abs_s32(&mut *store, x).unwrap(), x.wrapping_abs());
+        }
+        for x in [0, 5, -5, i8::MAX, i8::MIN] {
+            assert_eq!(math.call_abs_s8(&mut *store, x).unwrap(), x.wrapping_abs());
+        }
+        assert_eq!(math.call_abs_f32(&mut *store, -0.5).unwrap(), 0.5);
+        assert_eq!(math.call_min_u32(&mut *store, 3, u32::MAX).unwrap(), 3);
+        assert_eq!(math.call_max_s64(&mut *store, -3, -30).unwrap(), -3);
+        assert_eq!(math.call_max_s16(&mut *store, -3, 2).unwrap(), 2);
+        assert_eq!(math.call_max_s16(&mut *store, -3, -300).unwrap(), -3);
+        assert_eq!(math.call_min_f64(&mut *store, 1.0, -1.0).unwrap(), -1.0);
+        assert!(math
+            .call_min_f64(&mut *store, 1.0, f64::NAN)
+            .unwrap()
+            .is_nan());
+        assert_eq!(math.call_rounding(&mut *store, 2.5).unwrap(), 2232.0);
+        assert_eq!(math.call_rounding(&mut *store, -1.5).unwrap(), -2112.0);
+        assert_eq!(
+            math.call_copysign_f64(&mut *store, 2.0, -0.0).unwrap(),
+            -2.0
+        );
+        assert_eq!(math.call_folded(&mut *store).unwrap(), 20);
+        assert_eq!(math.call_folded_float(&mut *store).unwrap(), 5.0);
+        assert_eq!(math.call_folded_method(&mut *store).unwrap(), 1);
+    }
+}
+
+#[test]
+fn test_nested() {
+    bindgen!("nested" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("nested", *profile);
+
+        let (nested, _) =
+            Nested::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        assert_eq!(nested.call_scaled_sum(&mut *store, 1, 2).unwrap(), 30);
+        assert_eq!(nested.call_doubled_sum(&mut *store, 1, 2).unwrap(), 6);
+        assert_eq!(nested.call_triangle(&mut *store, 0).unwrap(), 0);
+        assert_eq!(nested.call_triangle(&mut *store, 10).unwrap(), 55);
+        assert_eq!(nested.call_parity(&mut *store, 0).unwrap(), 1);
+        assert_eq!(nested.call_parity(&mut *store, 7).unwrap(), 0);
+        assert_eq!(nested.call_parity(&mut *store, 12).unwrap(), 1);
+    }
+}
+
+#[test]
+fn test_newtypes() {
+    bindgen!("newtypes" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("newtypes", *profile);
+
+        let (newtypes, _) =
+            Newtypes::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        let total = newtypes.call_add_feet(&mut *store, 1.0, 10.0).unwrap();
+        assert!((total - 4.048).abs() < 1e-9);
+        let walked = newtypes.call_walk(&mut *store, 10.0).unwrap();
+        assert!((walked - 3.548).abs() < 1e-9);
+        assert_eq!(newtypes.call_distance(&mut *store, 2.5).unwrap(), 2.5);
+
+        let label = newtypes.call_make_label(&mut *store, "claw").unwrap();
+        assert_eq!(label, "claw");
+        assert_eq!(
+            newtypes.call_label_name(&mut *store, &label).unwrap(),
+            "claw"
+        );
+    }
+}
+
+#[test]
+fn test_overflow() {
+    bindgen!("overflow" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("overflow", *profile);
+
+        let (overflow, _) =
+            Overflow::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let store = &mut runtime.store;
+        let u8s = [0u8, 1, 100, 200, 255];
+        for (a, b) in u8s.iter().flat_map(|a| u8s.iter().map(move |b| (*a, *b))) {
+            let wrapped = overflow.call_wrapping_add_u8(&mut *store, a, b).unwrap();
+            assert_eq!(wrapped, a.wrapping_add(b));
+            let saturated = overflow.call_saturating_add_u8(&mut *store, a, b).unwrap();
+            assert_eq!(saturated, a.saturating_add(b));
+        }
+        let s8s = [0i8, 1, -1, 100, -100, i8::MAX, i8::MIN];
+        for (a, b) in s8s.iter().flat_map(|a| s8s.iter().map(move |b| (*a, *b))) {
+            let saturated = overflow.call_saturating_sub_s8(&mut *store, a, b).unwrap();
+            assert_eq!(saturated, a.saturating_sub(b), "{} -| {}", a, b);
+        }
+        let u32s = [0u32, 1, 1 << 31, u32::MAX];
+        for (a, b) in u32s.iter().flat_map(|a| u32s.iter().map(move |b| (*a, *b))) {
+            let added = overflow.call_saturating_add_u32(&mut *store, a, b).unwrap();
+            assert_eq!(added, a.saturating_add(b));
+            let subtracted = overflow.call_saturating_sub_u32(&mut *store, a, b).unwrap();
+            assert_eq!(subtracted, a.saturating_sub(b));
+        }
+        let s32s = [0i32, 1, -1, i32::MAX, i32::MIN];
+        for (a, b) in s32s.iter().flat_map(|a| s32s.iter().map(move |b| (*a, *b))) {
+            let added = overflow.call_saturating_add_s32(&mut *store, a, b).unwrap();
+            assert_eq!(added, a.saturating_add(b), "{} +| {}", a, b);
+            let multiplied = overflow.call_wrapping_mul_s32(&mut *store, a, b).unwrap();
+            assert_eq!(multiplied, a.wrapping_mul(b));
+            let (a, b) = (a as i64 * 3, b as i64 * 5);
+            let subtracted = overflow.call_saturating_sub_s64(&mut *store, a, b).unwrap();
+            assert_eq!(subtracted, a.saturating_sub(b));
+        }
+        for (a, b) in [(i64::MIN, 1), (i64::MAX, -1), (0, i64::MIN)] {
+            let subtracted = overflow.call_saturating_sub_s64(&mut *store, a, b).unwrap();
+            assert_eq!(subtracted, a.saturating_sub(b), "{} -| {}", a, b);
+        }
+        assert_eq!(
+            overflow.call_wrapping_sub_u64(&mut *store, 0, 1).unwrap(),
+            u64::MAX
+        );
+        assert_eq!(overflow.call_folded(&mut *store).unwrap(), 255);
+        assert_eq!(overflow.call_min_s8(&mut *store).unwrap(), i8::MIN);
+        assert_eq!(overflow.call_min_s64(&mut *store).unwrap(), i64::MIN);
+    }
+}
+
+#[test]
+fn test_panic() {
+    bindgen!("panic" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("panic", *profile);
+        let writes = runtime.stub_stdio();
+
+        let (panic, _) =
+            Panic::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let store = &mut runtime.store;
+
+        assert_eq!(panic.call_checked_div(&mut *store, 7, 2).unwrap(), 3);
+        assert!(written(&writes, 2).is_empty());
+        assert!(panic.call_checked_div(&mut *store, 7, 0).is_err());
+        assert_eq!(
+            written(&writes, 2),
+            "panicked at panic:3:9: division by zero\n"
+        );
+    }
+}
+
+#[test]
+fn test_panic_handler() {
+    bindgen!("panic-handler" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("panic_handler", *profile);
+
+        let messages: Arc<Mutex<Vec<String>>> = Default::default();
+        let handled = messages.clone();
+        runtime
+            .linker
+            .root()
+            .func_wrap("panic-handler", move |_, (message,): (String,)| {
+                handled.lock().unwrap().push(message);
+                Ok(())
+            })
+            .unwrap();
+
+        let (panic_handler, _) =
+            PanicHandler::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
+        let store = &mut runtime.store;
+
+        assert!(panic_handler.call_check(&mut *store, 1).is_ok());
+        assert!(panic_handler.call_check(&mut *store, 0).is_err());
+        assert_eq!(
+            *messages.lock().unwrap(),
+            ["panicked at panic_handler:5:9: expected a positive number"]
+        );
+    }
+}
+
+#[test]
+fn test_print() {
+    bindgen!("print" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("print", *profile);
+
+        let writes = runtime.stub_stdio();
+
+        let (print, _) =
+            Print::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let store = &mut runtime.store;
+
+        let output = |stream| written(&writes, stream);
+
+        print.call_greet(&mut *store, "Ferris").unwrap();
+        print.call_warn(&mut *store, 3).unwrap();
+        print.call_greet(&mut *store, "Corro").unwrap();
+        assert_eq!(output(1), "Hello, Ferris!\nHello, Corro!\n");
+        assert_eq!(output(2), "3 warnings\n");
+
+        // Long lines are written in pieces
+        writes.lock().unwrap().clear();
+        let line = "ab".repeat(5000);
+        print.call_echo(&mut *store, &line).unwrap();
+        assert_eq!(output(1), format!("{}\n", line));
+        assert_eq!(writes.lock().unwrap().len(), 4);
     }
 }
 
@@ -189,22 +1028,25 @@ fn test_compare() {
 fn test_proxy_call() {
     bindgen!("proxy-call" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("proxy_call");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("proxy_call", *profile);
 
-    impl ProxyCallImports for () {
-        fn imported(&mut self, a: u32) -> Result<u32, wasmtime::Error> {
-            Ok(a)
+        impl ProxyCallImports for () {
+            fn imported(&mut self, a: u32) -> Result<u32, wasmtime::Error> {
+                Ok(a)
+            }
         }
-    }
 
-    ProxyCall::add_to_linker(&mut runtime.linker, |s| s).unwrap();
+        ProxyCall::add_to_linker(&mut runtime.linker, |s| s).unwrap();
 
-    let (proxy_call, _) =
-        ProxyCall::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let (proxy_call, _) =
+            ProxyCall::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
 
-    for x in 0..10 {
-        let actual = proxy_call.call_exported(&mut runtime.store, x).unwrap();
-        assert_eq!(x, actual);
+        for x in 0..10 {
+            let actual = proxy_call.call_exported(&mut runtime.store, x).unwrap();
+            assert_eq!(x, actual);
+        }
     }
 }
 
@@ -212,30 +1054,80 @@ fn test_proxy_call() {
 fn test_quadratic() {
     bindgen!("quadratic" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("quadratic");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("quadratic", *profile);
 
-    let (quadratic, _) =
-        Quadratic::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let (quadratic, _) =
+            Quadratic::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
 
-    for x in 0..10 {
-        let expected = 2 * x * x + 3 * x + 4;
-        let actual_f32 = quadratic
-            .call_quad_f32(&mut runtime.store, 2.0, 3.0, 4.0, x as f32)
-            .unwrap();
-        let actual_f32_let = quadratic
-            .call_quad_f32_let(&mut runtime.store, 2.0, 3.0, 4.0, x as f32)
-            .unwrap();
-        let actual_f64 = quadratic
-            .call_quad_f64(&mut runtime.store, 2.0, 3.0, 4.0, x as f64)
-            .unwrap();
-        let actual_f64_let = quadratic
-            .call_quad_f64_let(&mut runtime.store, 2.0, 3.0, 4.0, x as f64)
-            .unwrap();
+        for x in 0..10 {
+            let expected = 2 * x * x + 3 * x + 4;
+            let actual_f32 = quadratic
+                .call_quad_f32(&mut runtime.store, 2.0, 3.0, 4.0, x as f32)
+                .unwrap();
+            let actual_f32_let = quadratic
+                .call_quad_f32_let(&mut runtime.store, 2.0, 3.0, 4.0, x as f32)
+                .unwrap();
+            let actual_f64 = quadratic
+                .call_quad_f64(&mut runtime.store, 2.0, 3.0, 4.0, x as f64)
+                .unwrap();
+            let actual_f64_let = quadratic
+                .call_quad_f64_let(&mut runtime.store, 2.0, 3.0, 4.0, x as f64)
+                .unwrap();
+            let actual_f64_tail = quadratic
+                .call_quad_f64_tail(&mut runtime.store, 2.0, 3.0, 4.0, x as f64)
+                .unwrap();
 
-        assert_eq!(expected as f32, actual_f32);
-        assert_eq!(expected as f32, actual_f32_let);
-        assert_eq!(expected as f64, actual_f64);
-        assert_eq!(expected as f64, actual_f64_let);
+            assert_eq!(expected as f32, actual_f32);
+            assert_eq!(expected as f32, actual_f32_let);
+            assert_eq!(expected as f64, actual_f64);
+            assert_eq!(expected as f64, actual_f64_let);
+            assert_eq!(expected as f64, actual_f64_tail);
+        }
+    }
+}
+
+#[test]
+fn test_short_circuit() {
+    bindgen!("short-circuit" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("short-circuit", *profile);
+
+        let (short_circuit, _) =
+            ShortCircuit::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
+
+        let store = &mut runtime.store;
+        // Each case gives the result and how many operands were evaluated
+        let cases = [
+            (false, false, false, 1),
+            (false, true, false, 1),
+            (true, false, false, 2),
+            (true, true, true, 2),
+        ];
+        for (a, b, result, evaluated) in cases {
+            let before = short_circuit.call_call_count(&mut *store).unwrap();
+            assert_eq!(short_circuit.call_both(&mut *store, a, b).unwrap(), result);
+            let after = short_circuit.call_call_count(&mut *store).unwrap();
+            assert_eq!(after - before, evaluated, "{} and {}", a, b);
+        }
+        let cases = [
+            (false, false, false, 2),
+            (false, true, true, 2),
+            (true, false, true, 1),
+            (true, true, true, 1),
+        ];
+        for (a, b, result, evaluated) in cases {
+            let before = short_circuit.call_call_count(&mut *store).unwrap();
+            assert_eq!(
+                short_circuit.call_either(&mut *store, a, b).unwrap(),
+                result
+            );
+            let after = short_circuit.call_call_count(&mut *store).unwrap();
+            assert_eq!(after - before, evaluated, "{} or {}", a, b);
+        }
     }
 }
 
@@ -243,39 +1135,148 @@ fn test_quadratic() {
 fn test_strings() {
     bindgen!("strings" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("strings");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("strings", *profile);
 
-    let (strings, _) =
-        Strings::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let (strings, _) =
+            Strings::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
 
-    let long_string = "Z".repeat(1000);
-    let cases = [
-        "",
-        "asdf",
-        "673hlksdfkjh5r;4hj6s",
-        "a",
-        long_string.as_str(),
-    ];
+        let long_string = "Z".repeat(1000);
+        let cases = [
+            "",
+            "asdf",
+            "673hlksdfkjh5r;4hj6s",
+            "a",
+            long_string.as_str(),
+        ];
+
+        for case in cases {
+            assert_eq!(
+                case,
+                strings.call_identity(&mut runtime.store, case).unwrap()
+            );
+        }
 
-    for case in cases {
         assert_eq!(
-            case,
-            strings.call_identity(&mut runtime.store, case).unwrap()
+            strings.call_hello_world(&mut runtime.store).unwrap(),
+            "hello, world!"
         );
+        assert_eq!(
+            strings.call_escaped(&mut runtime.store).unwrap(),
+            "line\n\"quoted\" \\"
+        );
+
+        for case in cases {
+            assert_eq!(
+                format!("{}Lorem Ipsum", case).as_str(),
+                strings
+                    .call_concat(&mut runtime.store, case, "Lorem Ipsum")
+                    .unwrap()
+            );
+        }
+
+        let store = &mut runtime.store;
+        let cases = [
+            "",
+            "a",
+            "ab",
+            "b",
+            "asdf",
+            "asdg",
+            "\u{e9}",
+            long_string.as_str(),
+        ];
+        for (left, right) in cases
+            .iter()
+            .flat_map(|l| cases.iter().map(move |r| (*l, *r)))
+        {
+            let equals = strings.call_equals(&mut *store, left, right).unwrap();
+            assert_eq!(equals, left == right, "{:?} == {:?}", left, right);
+            let not_equals = strings.call_not_equals(&mut *store, left, right).unwrap();
+            assert_eq!(not_equals, left != right, "{:?} != {:?}", left, right);
+            let less_than = strings.call_less_than(&mut *store, left, right).unwrap();
+            assert_eq!(less_than, left < right, "{:?} < {:?}", left, right);
+            let greater_than_equal = strings
+                .call_greater_than_equal(&mut *store, left, right)
+                .unwrap();
+            assert_eq!(
+                greater_than_equal,
+                left >= right,
+                "{:?} >= {:?}",
+                left,
+                right
+            );
+        }
     }
+}
 
-    assert_eq!(
-        strings.call_hello_world(&mut runtime.store).unwrap(),
-        "hello, world!"
-    );
+#[test]
+fn test_structs() {
+    bindgen!("structs" in "tests/programs/wit");
+
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("structs", *profile);
+
+        let (structs, _) =
+            Structs::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+
+        let fields = |p: &Point| (p.x, p.y, p.label.clone());
+
+        let store = &mut runtime.store;
+        let point = structs.call_at_x(&mut *store, -7).unwrap();
+        assert_eq!(fields(&point), (-7, -1, "unnamed".to_owned()));
+
+        let moved = structs.call_moved(&mut *store, &point, 12).unwrap();
+        assert_eq!(fields(&moved), (-7, 12, "unnamed".to_owned()));
+
+        let renamed = structs
+            .call_renamed(&mut *store, &moved, "renamed")
+            .unwrap();
+        assert_eq!(fields(&renamed), (-7, 12, "renamed".to_owned()));
+
+        let segment = structs.call_segment_to(&mut *store, &renamed).unwrap();
+        assert_eq!(fields(&segment.start), (0, -1, "origin".to_owned()));
+        assert_eq!(fields(&segment.end), fields(&renamed));
+        assert_eq!(segment.weight, -1.5);
+
+        let reweighted = structs
+            .call_reweighted(&mut *store, &segment, -0.25)
+            .unwrap();
+        assert_eq!(fields(&reweighted.start), fields(&segment.start));
+        assert_eq!(fields(&reweighted.end), fields(&segment.end));
+        assert_eq!(reweighted.weight, -0.25);
 
-    for case in cases {
+        let segment = Segment {
+            start: Point {
+                x: 1,
+                y: -2,
+                label: "a".to_owned(),
+            },
+            end: Point {
+                x: -3,
+                y: 5,
+                label: "b".to_owned(),
+            },
+            weight: 0.5,
+        };
+        assert_eq!(structs.call_end_label(&mut *store, &segment).unwrap(), "b");
         assert_eq!(
-            format!("{}Lorem Ipsum", case).as_str(),
-            strings
-                .call_concat(&mut runtime.store, case, "Lorem Ipsum")
-                .unwrap()
+            structs.call_span_squared(&mut *store, &segment).unwrap(),
+            65
+        );
+        let end_length_squared = structs
+            .call_end_length_squared(&mut *store, &segment)
+            .unwrap();
+        assert_eq!(end_length_squared, 34);
+        assert_eq!(structs.call_rightmost(&mut *store, &segment).unwrap(), 1);
+        assert_eq!(structs.call_default_step(&mut *store).unwrap(), -128);
+
+        // Locals without an annotation take the type of their initializer
+        assert_eq!(
+            structs.call_copied_x(&mut *store, &segment.end).unwrap(),
+            -3
         );
+        assert_eq!(structs.call_inferred_step(&mut *store).unwrap(), -128);
     }
 }
 
@@ -283,55 +1284,88 @@ fn test_strings() {
 fn test_timer_proxy() {
     bindgen!("timer-proxy" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("timer-proxy");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("timer-proxy", *profile);
 
-    impl TimerProxyImports for () {
-        fn foo(&mut self, a: String) -> wasmtime::Result<String> {
-            wasmtime::Result::Ok(a)
+        impl TimerProxyImports for () {
+            fn foo(&mut self, a: String) -> wasmtime::Result<String> {
+                wasmtime::Result::Ok(a)
+            }
         }
-    }
 
-    use wasi::logging::logging;
-    impl logging::Host for () {
-        fn log(
-            &mut self,
-            _level: logging::Level,
-            context: String,
-            message: String,
-        ) -> wasmtime::Result<()> {
-            println!("{}: {}", context, message);
-            wasmtime::Result::Ok(())
+        use wasi::logging::logging;
+        impl logging::Host for () {
+            fn log(
+                &mut self,
+                _level: logging::Level,
+                context: String,
+                message: String,
+            ) -> wasmtime::Result<()> {
+                println!("{}: {}", context, message);
+                wasmtime::Result::Ok(())
+            }
         }
-    }
 
-    use wasi::clocks::monotonic_clock;
-    impl monotonic_clock::Host for () {
-        fn now(&mut self) -> wasmtime::Result<monotonic_clock::Instant> {
-            wasmtime::Result::Ok(monotonic_clock::Instant::from(1u64))
+        use wasi::clocks::monotonic_clock;
+        impl monotonic_clock::Host for () {
+            fn now(&mut self) -> wasmtime::Result<monotonic_clock::Instant> {
+                wasmtime::Result::Ok(monotonic_clock::Instant::from(1u64))
+            }
         }
-    }
 
-    TimerProxy::add_to_linker(&mut runtime.linker, |s| s).unwrap();
+        TimerProxy::add_to_linker(&mut runtime.linker, |s| s).unwrap();
 
-    let (timer, _) =
-        TimerProxy::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        let (timer, _) =
+            TimerProxy::instantiate(&mut runtime.store, &runtime.component, &runtime.linker)
+                .unwrap();
 
-    let found = timer.call_foo(&mut runtime.store, "asdf").unwrap();
-    assert_eq!(found, "asdf");
+        let found = timer.call_foo(&mut runtime.store, "asdf").unwrap();
+        assert_eq!(found, "asdf");
+    }
 }
 
 #[test]
 fn test_unary() {
     bindgen!("unary" in "tests/programs/wit");
 
-    let mut runtime = Runtime::new("unary");
+    for profile in Profile::ALL {
+        let mut runtime = Runtime::new("unary", *profile);
+
+        let (unary, _) =
+            Unary::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
 
-    let (unary, _) =
-        Unary::instantiate(&mut runtime.store, &runtime.component, &runtime.linker).unwrap();
+        for x in 0..10_i32 {
+            unary.call_set(&mut runtime.store, x).unwrap();
+            let inverse = unary.call_get_inverse(&mut runtime.store).unwrap();
+            assert_eq!(-x, inverse);
+        }
 
-    for x in 0..10_i32 {
-        unary.call_set(&mut runtime.store, x).unwrap();
-        let inverse = unary.call_get_inverse(&mut runtime.store).unwrap();
-        assert_eq!(-x, inverse);
+        let store = &mut runtime.store;
+        for x in [0, 1, -7, i64::MAX, i64::MIN] {
+            assert_eq!(
+                unary.call_negate_s64(&mut *store, x).unwrap(),
+                x.wrapping_neg()
+            );
+        }
+        assert_eq!(unary.call_negate_f64(&mut *store, 1.5).unwrap(), -1.5);
+        assert_eq!(unary.call_negate_times(&mut *store, 3, 4).unwrap(), -12);
+        assert!(unary.call_not(&mut *store, false).unwrap());
+        assert!(!unary.call_not(&mut *store, true).unwrap());
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            assert_eq!(unary.call_nand(&mut *store, a, b).unwrap(), !(a && b));
+        }
+        for x in [0, 1, 0x0F, 0xFF] {
+            assert_eq!(unary.call_invert_u8(&mut *store, x).unwrap(), !x);
+        }
+        for x in [0, 1, -1, i16::MAX, i16::MIN] {
+            assert_eq!(unary.call_invert_s16(&mut *store, x).unwrap(), !x);
+        }
+        for x in [0, 1, u64::MAX] {
+            assert_eq!(unary.call_invert_u64(&mut *store, x).unwrap(), !x);
+        }
+        assert_eq!(
+            unary.call_clear_bits(&mut *store, 0b1111, 0b0101).unwrap(),
+            0b1010
+        );
     }
 }
@@ -0,0 +1,86 @@
+//! Compiles each program in `tests/ui` and compares its rendered
+//! diagnostics against the `.stderr` file next to it.
+//!
+//! Run with `BLESS=1` to overwrite the `.stderr` files with the
+//! current output instead.
+
+use compile_claw::{compile_with_warnings, LintConfig};
+use miette::{Diagnostic, GraphicalReportHandler, GraphicalTheme};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use wit_parser::Resolve;
+
+#[test]
+fn test_ui() {
+    let bless = matches!(env::var("BLESS").as_deref(), Ok(value) if value != "0");
+
+    let mut failures = Vec::new();
+    let mut entries: Vec<_> = fs::read_dir("./tests/ui")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some("claw".as_ref()))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let actual = render(&path);
+        let stderr_path = path.with_extension("stderr");
+        if bless {
+            if actual.is_empty() {
+                let _ = fs::remove_file(&stderr_path);
+            } else {
+                fs::write(&stderr_path, &actual).unwrap();
+            }
+            continue;
+        }
+
+        let expected = fs::read_to_string(&stderr_path).unwrap_or_default();
+        if actual != expected {
+            failures.push(format!(
+                "{}\n--- expected\n{}\n--- actual\n{}",
+                path.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "{} UI test(s) failed, rerun with BLESS=1 to update them\n\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+}
+
+/// The warnings of a program which compiles, or its error
+fn render(path: &Path) -> String {
+    let source_name = path.file_name().unwrap().to_str().unwrap().to_owned();
+    let source_code = fs::read_to_string(path).unwrap();
+    let lints = LintConfig::default();
+
+    let mut output = String::new();
+    match compile_with_warnings(source_name, &source_code, Resolve::new(), &lints) {
+        Ok((_, warnings)) => {
+            for warning in warnings.iter() {
+                render_report(&mut output, warning);
+            }
+        }
+        Err(error) => {
+            for diagnostic in error.diagnostics() {
+                render_report(&mut output, diagnostic);
+            }
+        }
+    }
+    output
+}
+
+fn render_report(output: &mut String, report: &dyn Diagnostic) {
+    GraphicalReportHandler::new_themed(GraphicalTheme::none())
+        .render_report(output, report)
+        .unwrap();
+}
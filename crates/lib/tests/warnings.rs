@@ -0,0 +1,98 @@
+use claw_common::UnwrapPretty;
+use compile_claw::{compile_with_warnings, Lint, LintConfig, LintLevel};
+use miette::{GraphicalReportHandler, GraphicalTheme};
+
+use std::fs;
+
+use wit_parser::Resolve;
+
+#[test]
+fn test_warning_programs() {
+    for f in fs::read_dir("./tests/warning-programs").unwrap() {
+        let f = f.unwrap();
+        let source_name = f.file_name().into_string().unwrap();
+
+        if source_name.ends_with(".warnings.txt") {
+            continue; // skip warning files
+        }
+
+        assert!(source_name.ends_with(".claw"));
+
+        let source_code = fs::read_to_string(f.path()).unwrap();
+
+        let mut warnings_file_path = f.path();
+        warnings_file_path.set_extension("warnings.txt");
+        let warnings_file_contents = fs::read_to_string(warnings_file_path).unwrap();
+
+        let wit = Resolve::new();
+        let lints = lint_config(&source_code);
+
+        let (_, warnings) =
+            compile_with_warnings(source_name.clone(), &source_code, wit, &lints).unwrap_pretty();
+
+        let mut warnings_string = String::new();
+        for warning in warnings.iter() {
+            GraphicalReportHandler::new_themed(GraphicalTheme::none())
+                .render_report(&mut warnings_string, warning)
+                .unwrap();
+        }
+        assert_eq!(
+            warnings_string, warnings_file_contents,
+            "Unexpected warnings for '{}'",
+            source_name
+        );
+    }
+}
+
+#[test]
+fn test_lint_levels() {
+    let source_code = fs::read_to_string("./tests/warning-programs/shift-overflow.claw").unwrap();
+    let warnings = |lints: &LintConfig| {
+        let source_name = "shift-overflow.claw".to_owned();
+        compile_with_warnings(source_name, &source_code, Resolve::new(), lints)
+            .ok()
+            .map(|(_, warnings)| warnings)
+    };
+
+    let mut lints = LintConfig::default();
+    lints.set(Lint::ShiftOverflow, LintLevel::Allow);
+    assert!(warnings(&lints).unwrap().is_empty());
+
+    lints.set(Lint::ShiftOverflow, LintLevel::Deny);
+    assert!(warnings(&lints).is_none());
+}
+
+#[test]
+fn test_strict_lints() {
+    let source_code = fs::read_to_string("./tests/warning-programs/shadowing.claw").unwrap();
+    let compiles = |lints: &LintConfig| {
+        let source_name = "shadowing.claw".to_owned();
+        compile_with_warnings(source_name, &source_code, Resolve::new(), lints).is_ok()
+    };
+
+    // Shadowing is allowed by default but denied like every other lint in strict mode
+    assert!(compiles(&LintConfig::default()));
+    assert!(!compiles(&LintConfig::strict()));
+    for lint in Lint::ALL {
+        assert_eq!(LintConfig::strict().level(*lint), LintLevel::Deny);
+    }
+}
+
+/// Lint levels are set by leading comments like `// warn: shadowing`
+fn lint_config(source_code: &str) -> LintConfig {
+    let mut lints = LintConfig::default();
+    for line in source_code.lines() {
+        let (level, lint) = match line.strip_prefix("// ").and_then(|l| l.split_once(": ")) {
+            Some(setting) => setting,
+            None => break,
+        };
+        let level = match level {
+            "allow" => LintLevel::Allow,
+            "warn" => LintLevel::Warn,
+            "deny" => LintLevel::Deny,
+            _ => panic!("Unknown lint level \"{}\"", level),
+        };
+        lints.set(lint.parse().unwrap(), level);
+    }
+    lints
+}
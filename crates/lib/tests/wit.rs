@@ -0,0 +1,47 @@
+use claw_common::UnwrapPretty;
+use compile_claw::derive_wit;
+
+use std::fs;
+use std::path::Path;
+
+use wit_parser::{Resolve, UnresolvedPackage};
+
+/// The world of the same name in the hand-written test WIT
+fn expected_world(name: &str) -> String {
+    let wit = fs::read_to_string("./tests/programs/wit/claw.wit").unwrap();
+    let start = wit.find(&format!("world {} {{", name)).unwrap();
+    let end = start + wit[start..].find("\n}").unwrap();
+    format!("{}\n}}\n", &wit[start..end])
+}
+
+#[test]
+fn test_derived_wit() {
+    let programs = [
+        ("factorial", "factorial"),
+        ("higher-order", "higher-order"),
+        ("newtypes", "newtypes"),
+        ("quadratic", "quadratic"),
+        ("structs", "structs"),
+        ("unary", "unary"),
+    ];
+    for (file, world) in programs {
+        let path = format!("./tests/programs/{}.claw", file);
+        let input = fs::read_to_string(path).unwrap();
+        let wit = derive_wit(
+            file.to_owned(),
+            &input,
+            Resolve::new(),
+            "claw:derived",
+            world,
+        )
+        .unwrap_pretty();
+
+        let expected = format!("package claw:derived;\n\n{}", expected_world(world));
+        assert_eq!(wit, expected, "Unexpected WIT for '{}'", file);
+
+        // Bindings generators have to be able to read it
+        let path = format!("{}.wit", file);
+        let package = UnresolvedPackage::parse(Path::new(&path), &wit).unwrap();
+        Resolve::new().push(package).unwrap();
+    }
+}
@@ -1,42 +1,68 @@
 use crate::lexer::Token;
 use crate::{
-    expressions::parse_expression, statements::parse_block, types::parse_valtype, ParseInput,
-    ParserError,
+    expressions::parse_expression, statements::parse_body, types::parse_valtype, Feature,
+    ParseInput, ParserError, Sync,
+};
+use ast::{
+    merge, FunctionId, GlobalId, Import, ImportFrom, ImportId, NameId, PlainImport, Span,
+    TypeDefId, TypeId,
 };
-use ast::{FunctionId, GlobalId, Import, ImportFrom, ImportId, NameId, PlainImport, TypeId};
 use claw_ast as ast;
 
-use claw_common::Source;
-
 use crate::names::{parse_ident, parse_interface_name};
 
-pub fn parse_component(src: Source, input: &mut ParseInput) -> Result<ast::Component, ParserError> {
-    let mut component = ast::Component::new(src);
+pub fn parse_component(input: &mut ParseInput) -> Result<ast::Component, ParserError> {
+    let mut component = input.new_component();
 
     while !input.done() {
-        // Check for the export keyword
-        let exported = input.next_if(Token::Export).is_some();
-
-        // Determine the kind of item and parse it
-        match input.peek()?.token {
-            Token::Import => {
-                parse_import(input, &mut component)?;
-            }
-            Token::Let => {
-                parse_global(input, &mut component, exported)?;
-            }
-            Token::Func => {
-                parse_func(input, &mut component, exported)?;
-            }
-            _ => {
-                return Err(input.unexpected_token("Top level item (e.g. import, global, function"))
-            }
-        }
+        let start = input.checkpoint();
+        let result = parse_item(input, &mut component);
+        input.recover(result, start, Sync::Item)?;
     }
 
     Ok(component)
 }
 
+pub(crate) fn parse_item(
+    input: &mut ParseInput,
+    component: &mut ast::Component,
+) -> Result<(), ParserError> {
+    // Check for the export keyword
+    let exported = input.next_if(Token::Export).is_some();
+
+    // Determine the kind of item and parse it
+    match input.peek()?.token {
+        Token::Import => {
+            parse_import(input, component)?;
+        }
+        Token::Let => {
+            parse_global(input, component, exported)?;
+        }
+        Token::Func => {
+            parse_func(input, component, exported, false)?;
+        }
+        Token::Struct => {
+            input.require(Feature::Structs, input.peek()?.span)?;
+            parse_struct(input, component, exported)?;
+        }
+        Token::Enum => {
+            input.require(Feature::Enums, input.peek()?.span)?;
+            parse_enum(input, component, exported)?;
+        }
+        Token::Newtype => {
+            input.require(Feature::Newtypes, input.peek()?.span)?;
+            parse_newtype(input, component, exported)?;
+        }
+        _ => {
+            _ = input.next();
+            return Err(input.unexpected_token(
+                "Top level item (e.g. import, global, function, struct, enum, newtype",
+            ));
+        }
+    }
+    Ok(())
+}
+
 fn parse_import(
     input: &mut ParseInput,
     comp: &mut ast::Component,
@@ -165,26 +191,134 @@ fn parse_global(
     Ok(comp.push_global(global))
 }
 
-fn parse_func(
+pub(crate) fn parse_func(
     input: &mut ParseInput,
     comp: &mut ast::Component,
     exported: bool,
-) -> Result<FunctionId, ParserError> {
-    input.assert_next(Token::Func, "Function signature")?;
+    nested: bool,
+) -> Result<(FunctionId, Span), ParserError> {
+    let start_span = input.assert_next(Token::Func, "Function signature")?;
     let ident = parse_ident(input, comp)?;
     let params = parse_params(input, comp)?;
     let results = parse_results(input, comp)?;
-    let (body, _) = parse_block(input, comp)?;
+    let (body, end_span) = parse_body(input, comp, results.is_some())?;
 
     let function = ast::Function {
         exported,
+        nested,
         ident,
         params,
         results,
         body,
     };
 
-    Ok(comp.push_function(function))
+    let span = merge(&start_span, &end_span);
+    Ok((comp.push_function(function), span))
+}
+
+fn parse_struct(
+    input: &mut ParseInput,
+    comp: &mut ast::Component,
+    exported: bool,
+) -> Result<TypeDefId, ParserError> {
+    input.assert_next(Token::Struct, "Struct definition")?;
+    let ident = parse_ident(input, comp)?;
+    input.assert_next(Token::LBrace, "Struct fields are surrounded by braces")?;
+
+    let mut fields = Vec::new();
+    while input.peek()?.token != Token::RBrace {
+        fields.push(parse_struct_field(input, comp)?);
+
+        if input.next_if(Token::Comma).is_none() {
+            break;
+        }
+    }
+    input.assert_next(Token::RBrace, "Struct fields must be closed by a brace")?;
+
+    let record = ast::RecordTypeDef {
+        exported,
+        ident,
+        fields,
+    };
+
+    Ok(comp.push_type_def(ast::TypeDefinition::Record(record)))
+}
+
+fn parse_struct_field(
+    input: &mut ParseInput,
+    comp: &mut ast::Component,
+) -> Result<ast::RecordField, ParserError> {
+    let ident = parse_ident(input, comp)?;
+    input.assert_next(
+        Token::Colon,
+        "Struct fields must have their types annotated",
+    )?;
+    let type_id = parse_valtype(input, comp)?;
+    let default = match input.next_if(Token::Assign) {
+        Some(_) => Some(parse_expression(input, comp)?),
+        None => None,
+    };
+    Ok(ast::RecordField {
+        ident,
+        type_id,
+        default,
+    })
+}
+
+fn parse_enum(
+    input: &mut ParseInput,
+    comp: &mut ast::Component,
+    exported: bool,
+) -> Result<TypeDefId, ParserError> {
+    input.assert_next(Token::Enum, "Enum definition")?;
+    let ident = parse_ident(input, comp)?;
+    input.assert_next(Token::LBrace, "Enum cases are surrounded by braces")?;
+
+    let mut cases = Vec::new();
+    while input.peek()?.token != Token::RBrace {
+        let ident = parse_ident(input, comp)?;
+        let discriminant = match input.next_if(Token::Assign) {
+            Some(_) => Some(parse_expression(input, comp)?),
+            None => None,
+        };
+        cases.push(ast::EnumCase {
+            ident,
+            discriminant,
+        });
+
+        if input.next_if(Token::Comma).is_none() {
+            break;
+        }
+    }
+    input.assert_next(Token::RBrace, "Enum cases must be closed by a brace")?;
+
+    let enum_def = ast::EnumTypeDef {
+        exported,
+        ident,
+        cases,
+    };
+
+    Ok(comp.push_type_def(ast::TypeDefinition::Enum(enum_def)))
+}
+
+fn parse_newtype(
+    input: &mut ParseInput,
+    comp: &mut ast::Component,
+    exported: bool,
+) -> Result<TypeDefId, ParserError> {
+    input.assert_next(Token::Newtype, "Newtype definition")?;
+    let ident = parse_ident(input, comp)?;
+    input.assert_next(Token::Assign, "Newtypes are given the type they wrap")?;
+    let inner = parse_valtype(input, comp)?;
+    input.assert_next(Token::Semicolon, "Newtype definitions end in a semicolon")?;
+
+    let newtype = ast::NewtypeDef {
+        exported,
+        ident,
+        inner,
+    };
+
+    Ok(comp.push_type_def(ast::TypeDefinition::Newtype(newtype)))
 }
 
 fn parse_params(
@@ -254,7 +388,7 @@ fn parse_fn_type(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::make_input;
+    use crate::{make_input, LangVersion};
     use claw_common::UnwrapPretty;
 
     #[test]
@@ -266,33 +400,270 @@ mod tests {
             counter = counter + 1;
             return counter;
         }";
-        let (src, mut input) = make_input(source);
-        parse_component(src, &mut input).unwrap_pretty();
+        let mut input = make_input(source);
+        parse_component(&mut input).unwrap_pretty();
     }
 
     #[test]
     fn test_empty_function() {
         let source = "func empty() {}";
-        let (src, mut input) = make_input(source);
-        let mut comp = ast::Component::new(src.clone());
-        parse_func(&mut input.clone(), &mut comp, false).unwrap_pretty();
-        parse_component(src, &mut input).unwrap_pretty();
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        parse_func(&mut input.clone(), &mut comp, false, false).unwrap_pretty();
+        parse_component(&mut input).unwrap_pretty();
     }
 
     #[test]
     fn test_basic_function() {
         let source = "func increment() -> u32 { return 0; }";
-        let (src, mut input) = make_input(source);
-        let mut comp = ast::Component::new(src.clone());
-        parse_func(&mut input.clone(), &mut comp, false).unwrap_pretty();
-        parse_component(src, &mut input).unwrap_pretty();
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        parse_func(&mut input.clone(), &mut comp, false, false).unwrap_pretty();
+        parse_component(&mut input).unwrap_pretty();
+    }
+
+    #[test]
+    fn test_function_typed_param() {
+        let source = "func apply(f: func(u32, u32) -> u32, run: func()) -> u32 { return 0; }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let (id, _) = parse_func(&mut input, &mut comp, false, false).unwrap_pretty();
+        assert!(input.done());
+
+        let function = comp.get_function(id);
+        let param_types: Vec<_> = function
+            .params
+            .iter()
+            .map(|(_, type_id)| match comp.get_type(*type_id) {
+                ast::ValType::Func(func_type) => (func_type.params.len(), func_type.results),
+                _ => panic!("Expected a function type"),
+            })
+            .collect();
+        assert_eq!(param_types[0].0, 2);
+        assert!(param_types[0].1.is_some());
+        assert_eq!(param_types[1].0, 0);
+        assert!(param_types[1].1.is_none());
+    }
+
+    #[test]
+    fn test_parse_struct() {
+        let source = "struct point { x: s32 = 0, y: s32, tag: string = \"none\", }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let id = parse_struct(&mut input, &mut comp, false).unwrap_pretty();
+        assert!(input.done());
+
+        let ast::TypeDefinition::Record(record) = comp.get_type_def(id) else {
+            panic!("Expected a struct");
+        };
+        assert_eq!(comp.get_name(record.ident), "point");
+        let fields: Vec<_> = record
+            .fields
+            .iter()
+            .map(|field| (comp.get_name(field.ident), field.default.is_some()))
+            .collect();
+        assert_eq!(fields, [("x", true), ("y", false), ("tag", true)]);
+    }
+
+    #[test]
+    fn test_parse_enum() {
+        let source = "enum status { ok = 200, moved = 300 + 1, gone, }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let id = parse_enum(&mut input, &mut comp, true).unwrap_pretty();
+        assert!(input.done());
+
+        let ast::TypeDefinition::Enum(enum_def) = comp.get_type_def(id) else {
+            panic!("Expected an enum");
+        };
+        assert!(enum_def.exported);
+        assert_eq!(comp.get_name(enum_def.ident), "status");
+        let cases: Vec<_> = enum_def
+            .cases
+            .iter()
+            .map(|case| (comp.get_name(case.ident), case.discriminant.is_some()))
+            .collect();
+        assert_eq!(cases, [("ok", true), ("moved", true), ("gone", false)]);
+    }
+
+    #[test]
+    fn test_parse_newtype() {
+        let source = "newtype meters = f64;";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let id = parse_newtype(&mut input, &mut comp, false).unwrap_pretty();
+        assert!(input.done());
+
+        let ast::TypeDefinition::Newtype(newtype) = comp.get_type_def(id) else {
+            panic!("Expected a newtype");
+        };
+        assert!(!newtype.exported);
+        assert_eq!(comp.get_name(newtype.ident), "meters");
+        assert!(matches!(
+            comp.get_type(newtype.inner),
+            ast::ValType::Primitive(ast::PrimitiveType::F64)
+        ));
     }
 
     #[test]
     fn test_parse_global() {
         let source = "let mut counter: u32 = 0;";
-        let (src, mut input) = make_input(source);
-        let mut comp = ast::Component::new(src);
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
         parse_global(&mut input, &mut comp, false).unwrap_pretty();
     }
+
+    #[test]
+    fn test_append_components() {
+        let mut first =
+            parse_component(&mut make_input("func one() -> u32 { return 1; }")).unwrap_pretty();
+        let second_source = "let limit: u32 = 2;\nfunc add(x: u32) -> u32 { return x + limit; }";
+        let second = parse_component(&mut make_input(second_source)).unwrap_pretty();
+        let (add, _) = second.iter_functions().next().unwrap();
+        let second_add_span = second.name_span(second.get_function(add).ident);
+
+        let remap = first.append(second);
+        let add = first.get_function(remap.function(add));
+        assert_eq!(first.get_name(add.ident), "add");
+        assert_eq!(first.iter_globals().count(), 1);
+
+        // Spans keep their offsets but now point into the appended file
+        let add_span = first.name_span(add.ident);
+        assert_eq!(add_span.start(), second_add_span.start());
+        assert_eq!(add_span.file(), ast::FileId(1));
+
+        let ast::Statement::Return(ret) = first.get_statement(add.body[0]) else {
+            panic!("Expected a return statement");
+        };
+        let ast::Expression::Binary(sum) = first.get_expression(ret.expression.unwrap()) else {
+            panic!("Expected a binary expression");
+        };
+        let ast::Expression::Identifier(limit) = first.get_expression(sum.right) else {
+            panic!("Expected an identifier");
+        };
+        assert_eq!(first.get_name(limit.ident), "limit");
+    }
+
+    #[test]
+    fn test_recovers_from_errors() {
+        let source = "
+        let limit: u32 = ;
+
+        func first() -> u32 {
+            let a: u32 = 1 +;
+            return a;
+        }
+
+        func second() -> u32 {
+            if a { b = ; }
+            let c: u32 = 2
+            return c;
+        }
+
+        ]
+
+        func third() -> u32 { return 3; }";
+        let mut input = make_input(source).with_recovery();
+        let comp = parse_component(&mut input).unwrap_pretty();
+
+        let descriptions: Vec<_> = input
+            .errors()
+            .iter()
+            .map(|error| match error {
+                ParserError::UnexpectedToken { token, .. } => token.as_str(),
+                error => panic!("Expected an unexpected token, got {:?}", error),
+            })
+            .collect();
+        assert_eq!(
+            descriptions,
+            ["Semicolon", "Semicolon", "Semicolon", "Return", "RBracket"]
+        );
+
+        // Everything else is still parsed
+        assert_eq!(comp.iter_globals().count(), 0);
+        let bodies: Vec<_> = comp
+            .iter_functions()
+            .map(|(_, function)| (comp.get_name(function.ident), function.body.len()))
+            .collect();
+        assert_eq!(bodies, [("first", 1), ("second", 2), ("third", 1)]);
+
+        // Without recovery the first error ends the parse
+        assert!(parse_component(&mut make_input(source)).is_err());
+    }
+
+    #[test]
+    fn test_lang_version_gates_features() {
+        let sources = [
+            (Feature::Structs, "struct point { x: u32 }"),
+            (Feature::Enums, "enum level { low, high }"),
+            (Feature::Newtypes, "newtype meters = f64;"),
+            (Feature::FunctionTypes, "func run(f: func()) {}"),
+            (Feature::NestedFunctions, "func outer() { func inner() {} }"),
+            (Feature::ExpressionStatements, "func f() { g(1) + 1; }"),
+            (
+                Feature::IfExpressions,
+                "func f(a: bool) -> u32 { return if a { 1 } else { 2 }; }",
+            ),
+            (
+                Feature::Match,
+                "func f(l: level) -> u32 { return match l { _ => 1 }; }",
+            ),
+            (
+                Feature::Asm,
+                "func f() -> u32 { return asm () -> u32 { \"i32.const 1\" }; }",
+            ),
+            (Feature::Lists, "func f(a: list<u32>) {}"),
+            (Feature::Bytes, "func f(a: bytes) {}"),
+            (Feature::Lists, "func f(a: u32) -> u32 { return a[0]; }"),
+            (Feature::Lists, "func f(a: u32) -> u32 { return a[1..]; }"),
+            (Feature::Options, "func f(a: option<u32>) {}"),
+            (
+                Feature::OverflowOperators,
+                "func f(a: u32) -> u32 { return a +% 1; }",
+            ),
+            (
+                Feature::OverflowOperators,
+                "func f(a: u32) -> u32 { return a -| 1; }",
+            ),
+            (
+                Feature::NotOperators,
+                "func f(a: bool) -> bool { return !a; }",
+            ),
+            (
+                Feature::NotOperators,
+                "func f(a: u32) -> u32 { return ~a; }",
+            ),
+            (Feature::Casts, "func f(a: u32) -> u32 { return a as u32; }"),
+            (
+                Feature::FieldAccess,
+                "func f(a: u32) -> u32 { return a.x; }",
+            ),
+            (
+                Feature::MethodCalls,
+                "func f(a: u32) -> u32 { return a.popcount(); }",
+            ),
+            (Feature::TailExpressions, "func f(a: u32) -> u32 { a }"),
+        ];
+        for (feature, source) in sources {
+            parse_component(&mut make_input(source)).unwrap_pretty();
+
+            let mut input = make_input(source).with_version(LangVersion::V0_2);
+            match parse_component(&mut input) {
+                Err(ParserError::FeatureVersion {
+                    feature: description,
+                    required,
+                    ..
+                }) => {
+                    assert_eq!(description, feature.description());
+                    assert_eq!(required, LangVersion::V0_3);
+                }
+                result => panic!("Expected {:?} to be gated, got {:?}", feature, result),
+            }
+        }
+
+        // Operators from before the gated ones are still accepted
+        let source = "func f(a: s32, b: bool) -> bool { return -a * 2 >> 1 < 3 and b; }";
+        let mut input = make_input(source).with_version(LangVersion::V0_2);
+        parse_component(&mut input).unwrap_pretty();
+    }
 }
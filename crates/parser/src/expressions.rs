@@ -1,11 +1,13 @@
 use crate::lexer::Token;
-use crate::{ParseInput, ParserError};
+use crate::{Feature, ParseInput, ParserError};
 use claw_ast::{
-    self as ast, merge, BinaryExpression, BinaryOp, Call, Component, EnumLiteral, ExpressionId,
-    Identifier, UnaryExpression, UnaryOp,
+    self as ast, merge, AsmInput, BinaryExpression, BinaryOp, Call, Cast, Component, EnumLiteral,
+    ExpressionId, FieldAccess, Identifier, IfExpression, Index, InlineAsm, Match, MatchArm,
+    MethodCall, NameId, Pattern, RecordLiteral, Slice, Span, TypeId, UnaryExpression, UnaryOp,
 };
 
 use crate::names::parse_ident;
+use crate::types::parse_valtype;
 
 pub fn parse_expression(
     input: &mut ParseInput,
@@ -25,6 +27,9 @@ fn pratt_parse(
         Some(op) => {
             let ((), r_bp) = prefix_binding_power(op);
             let start_span = input.next().unwrap().span;
+            if op != UnaryOp::Negate {
+                input.require(Feature::NotOperators, start_span)?;
+            }
             let rhs = pratt_parse(input, comp, r_bp)?;
             let end_span = comp.expression_span(rhs);
             let span = merge(&start_span, &end_span);
@@ -34,6 +39,28 @@ fn pratt_parse(
     };
 
     loop {
+        if input.peekn(0) == Some(&Token::LBracket) {
+            if INDEX_BINDING_POWER < min_bp {
+                break;
+            }
+            lhs = parse_index(lhs, input, comp)?;
+            continue;
+        }
+        if input.peekn(0) == Some(&Token::Dot) {
+            if INDEX_BINDING_POWER < min_bp {
+                break;
+            }
+            lhs = parse_member(lhs, input, comp)?;
+            continue;
+        }
+        if input.peekn(0) == Some(&Token::As) {
+            if CAST_BINDING_POWER < min_bp {
+                break;
+            }
+            lhs = parse_cast(lhs, input, comp)?;
+            continue;
+        }
+
         let bin_op = match peek_bin_op(input) {
             Some(op) => op,
             None => break,
@@ -44,15 +71,18 @@ fn pratt_parse(
             break;
         }
 
-        let _ = input.next(); // Consumes peeked operator
+        let op_span = input.next().unwrap().span; // Consumes peeked operator
+        if is_overflow_op(bin_op) {
+            input.require(Feature::OverflowOperators, op_span)?;
+        }
         let rhs = pratt_parse(input, comp, r_bp)?;
         let bin_expr = BinaryExpression {
             op: bin_op,
             left: lhs,
             right: rhs,
+            parenthesized: false,
         };
-        let span = merge(&comp.expression_span(lhs), &comp.expression_span(rhs));
-        lhs = comp.new_expression(bin_expr.into(), span);
+        lhs = comp.new_binary_expression(bin_expr);
     }
     Ok(lhs)
 }
@@ -62,8 +92,14 @@ fn parse_leaf(input: &mut ParseInput, comp: &mut Component) -> Result<Expression
     let peek1 = input.peekn(1);
     match (peek0, peek1) {
         (Token::LParen, _) => parse_parenthetical(input, comp),
+        (Token::Asm, _) => parse_asm(input, comp),
+        (Token::If, _) => parse_if_expression(input, comp),
+        (Token::Match, _) => parse_match(input, comp),
         (Token::Identifier(_), Some(Token::LParen)) => parse_call(input, comp),
         (Token::Identifier(_), Some(Token::Colon)) => parse_enum(input, comp),
+        (Token::Identifier(_), Some(Token::LBrace)) if is_record_literal(input) => {
+            parse_record(input, comp)
+        }
         (Token::Identifier(_), _) => parse_ident_expr(input, comp),
         _ => parse_literal(input, comp),
     }
@@ -76,6 +112,10 @@ fn parse_parenthetical(
     let _left = input.assert_next(Token::LParen, "Left parenthesis '('")?;
     let inner = parse_expression(input, comp)?;
     let _right = input.assert_next(Token::RParen, "Right parenthesis ')'")?;
+    // Lints tell groupings written out apart from those given by precedence
+    if let ast::Expression::Binary(binary) = comp.get_expression_mut(inner) {
+        binary.parenthesized = true;
+    }
     Ok(inner)
 }
 
@@ -84,11 +124,10 @@ pub fn parse_ident_expr(
     input: &mut ParseInput,
     comp: &mut Component,
 ) -> Result<ExpressionId, ParserError> {
-    match &input.peek()?.token {
-        Token::Identifier(ident) => {
-            let ident = ident.clone();
+    match input.peek()?.token {
+        Token::Identifier(symbol) => {
             let span = input.next().unwrap().span;
-            let ident = comp.new_name(ident, span);
+            let ident = comp.new_name(symbol, span);
             Ok(comp.new_expression(Identifier { ident }.into(), span))
         }
         _ => Err(input.unexpected_token("Parsing identifier expression")),
@@ -101,10 +140,12 @@ fn parse_literal(
 ) -> Result<ExpressionId, ParserError> {
     let next = input.next()?;
     let span = next.span;
-    let literal = match &next.token {
-        Token::StringLiteral(value) => ast::Literal::String(value.to_owned()),
-        Token::IntLiteral(value) => ast::Literal::Integer(*value),
-        Token::FloatLiteral(value) => ast::Literal::Float(*value),
+    let literal = match next.token {
+        Token::StringLiteral(symbol) => {
+            ast::Literal::String(comp.resolve_symbol(symbol).to_owned())
+        }
+        Token::IntLiteral(value) => ast::Literal::Integer(value),
+        Token::FloatLiteral(value) => ast::Literal::Float(value),
         _ => return Err(input.unexpected_token("Parse Literal")),
     };
     Ok(comp.new_expression(literal.into(), span))
@@ -113,6 +154,19 @@ fn parse_literal(
 fn parse_call(input: &mut ParseInput, comp: &mut Component) -> Result<ExpressionId, ParserError> {
     let ident = parse_ident(input, comp)?;
     let start_span = comp.name_span(ident);
+    let (args, end_span) = parse_args(input, comp)?;
+
+    let call = Call { ident, args };
+    let span = merge(&start_span, &end_span);
+
+    Ok(comp.new_expression(call.into(), span))
+}
+
+/// Parse the parenthesized arguments of a call, giving the span of the `)`
+fn parse_args(
+    input: &mut ParseInput,
+    comp: &mut Component,
+) -> Result<(Vec<ExpressionId>, Span), ParserError> {
     input.assert_next(Token::LParen, "Function arguments")?;
 
     let mut args = Vec::new();
@@ -130,11 +184,177 @@ fn parse_call(input: &mut ParseInput, comp: &mut Component) -> Result<Expression
             _ => return Err(input.unexpected_token("Argument list")),
         }
     };
+    Ok((args, end_span))
+}
 
-    let call = Call { ident, args };
+/// Parse a field access or method call following the value it applies to
+///
+/// ```claw
+/// point.x
+/// values.len()
+/// ```
+fn parse_member(
+    inner: ExpressionId,
+    input: &mut ParseInput,
+    comp: &mut Component,
+) -> Result<ExpressionId, ParserError> {
+    input.assert_next(Token::Dot, "Member access '.'")?;
+    let ident = parse_ident(input, comp)?;
+    let start_span = comp.expression_span(inner);
+    if input.peekn(0) != Some(&Token::LParen) {
+        input.require(Feature::FieldAccess, comp.name_span(ident))?;
+        let span = merge(&start_span, &comp.name_span(ident));
+        let access = FieldAccess {
+            record: inner,
+            field: ident,
+        };
+        return Ok(comp.new_expression(access.into(), span));
+    }
+    input.require(Feature::MethodCalls, comp.name_span(ident))?;
+    let (args, end_span) = parse_args(input, comp)?;
+    let call = MethodCall {
+        receiver: inner,
+        ident,
+        args,
+    };
+    Ok(comp.new_expression(call.into(), merge(&start_span, &end_span)))
+}
+
+/// Parse the brackets of an index or slice expression following the indexed list
+fn parse_index(
+    list: ExpressionId,
+    input: &mut ParseInput,
+    comp: &mut Component,
+) -> Result<ExpressionId, ParserError> {
+    let bracket_span = input.assert_next(Token::LBracket, "Left bracket '['")?;
+    input.require(Feature::Lists, bracket_span)?;
+    let start = match input.peek()?.token {
+        Token::Range => None,
+        _ => Some(parse_expression(input, comp)?),
+    };
+    let expression = match (start, input.next_if(Token::Range)) {
+        (Some(index), None) => Index { list, index }.into(),
+        (start, _) => {
+            let end = match input.peek()?.token {
+                Token::RBracket => None,
+                _ => Some(parse_expression(input, comp)?),
+            };
+            Slice { list, start, end }.into()
+        }
+    };
+    let end_span = input.assert_next(Token::RBracket, "Right bracket ']'")?;
+    let span = merge(&comp.expression_span(list), &end_span);
+    Ok(comp.new_expression(expression, span))
+}
+
+/// Parse an if expression, whose branches are each a single expression
+///
+/// ```claw
+/// if a < b { a } else if a == b { 0 } else { b }
+/// ```
+fn parse_if_expression(
+    input: &mut ParseInput,
+    comp: &mut Component,
+) -> Result<ExpressionId, ParserError> {
+    let start_span = input.assert_next(Token::If, "If keyword 'if'")?;
+    input.require(Feature::IfExpressions, start_span)?;
+    let condition = parse_expression(input, comp)?;
+    let (then_value, _) = parse_branch(input, comp)?;
+    input.assert_next(Token::Else, "If expressions need an 'else' branch")?;
+    let (else_value, end_span) = match input.peek()?.token {
+        Token::If => {
+            let else_value = parse_if_expression(input, comp)?;
+            (else_value, comp.expression_span(else_value))
+        }
+        _ => parse_branch(input, comp)?,
+    };
+    let if_expression = IfExpression {
+        condition,
+        then_value,
+        else_value,
+    };
     let span = merge(&start_span, &end_span);
+    Ok(comp.new_expression(if_expression.into(), span))
+}
 
-    Ok(comp.new_expression(call.into(), span))
+/// Parse an expression in braces, giving the span of the `}`
+fn parse_branch(
+    input: &mut ParseInput,
+    comp: &mut Component,
+) -> Result<(ExpressionId, Span), ParserError> {
+    input.assert_next(Token::LBrace, "Left brace '{'")?;
+    let value = parse_expression(input, comp)?;
+    let end_span = input.assert_next(Token::RBrace, "Right brace '}'")?;
+    Ok((value, end_span))
+}
+
+/// Parse a match expression
+///
+/// ```claw
+/// match level { level::low => 1, n if n > 10 => 2, _ => 5 }
+/// ```
+fn parse_match(input: &mut ParseInput, comp: &mut Component) -> Result<ExpressionId, ParserError> {
+    let start_span = input.assert_next(Token::Match, "Match keyword 'match'")?;
+    input.require(Feature::Match, start_span)?;
+    let scrutinee = parse_expression(input, comp)?;
+    input.assert_next(Token::LBrace, "Match arms")?;
+
+    let mut arms = Vec::new();
+    let end_span = loop {
+        if let Some(span) = input.next_if(Token::RBrace) {
+            break span;
+        }
+        let pattern = parse_pattern(input, comp)?;
+        let guard = match input.next_if(Token::If) {
+            Some(_) => Some(parse_expression(input, comp)?),
+            None => None,
+        };
+        input.assert_next(Token::FatArrow, "Patterns are followed by '=>'")?;
+        let value = parse_expression(input, comp)?;
+        arms.push(MatchArm {
+            pattern,
+            guard,
+            value,
+        });
+        if input.next_if(Token::Comma).is_none() {
+            break input.assert_next(Token::RBrace, "Right brace '}'")?;
+        }
+    };
+
+    let match_expression = Match { scrutinee, arms };
+    let span = merge(&start_span, &end_span);
+    Ok(comp.new_expression(match_expression.into(), span))
+}
+
+fn parse_pattern(input: &mut ParseInput, comp: &mut Component) -> Result<Pattern, ParserError> {
+    if let Some(span) = input.next_if(Token::Underscore) {
+        return Ok(Pattern::Wildcard(span));
+    }
+    let enum_name = parse_ident(input, comp)?;
+    if input.next_if(Token::Colon).is_none() {
+        return Ok(Pattern::Binding(enum_name));
+    }
+    input.assert_next(
+        Token::Colon,
+        "Patterns are an enum case like 'name::case', a name or '_'",
+    )?;
+    let case_name = parse_ident(input, comp)?;
+    Ok(Pattern::Case {
+        enum_name,
+        case_name,
+    })
+}
+
+fn parse_cast(
+    inner: ExpressionId,
+    input: &mut ParseInput,
+    comp: &mut Component,
+) -> Result<ExpressionId, ParserError> {
+    let as_span = input.assert_next(Token::As, "Conversions are written 'value as T'")?;
+    input.require(Feature::Casts, as_span)?;
+    let target = parse_valtype(input, comp)?;
+    let span = merge(&comp.expression_span(inner), &comp.type_span(target));
+    Ok(comp.new_expression(Cast { inner, target }.into(), span))
 }
 
 fn parse_enum(input: &mut ParseInput, comp: &mut Component) -> Result<ExpressionId, ParserError> {
@@ -158,21 +378,186 @@ fn parse_enum(input: &mut ParseInput, comp: &mut Component) -> Result<Expression
     Ok(comp.new_expression(enum_lit.into(), span))
 }
 
+/// Whether the braces after the identifier belong to a struct literal rather than a block
+///
+/// A block can't start with `name:` or `..` so the first field decides.
+/// This means that struct literals can't be empty.
+fn is_record_literal(input: &ParseInput) -> bool {
+    match (input.peekn(2), input.peekn(3), input.peekn(4)) {
+        (Some(Token::Identifier(_)), Some(Token::Colon), next) => next != Some(&Token::Colon),
+        (Some(Token::Range), _, _) => true,
+        _ => false,
+    }
+}
+
+/// Parse a struct literal
+///
+/// ```claw
+/// point { x: 1, ..origin }
+/// ```
+fn parse_record(input: &mut ParseInput, comp: &mut Component) -> Result<ExpressionId, ParserError> {
+    let ident = parse_ident(input, comp)?;
+    let start_span = comp.name_span(ident);
+    input.assert_next(Token::LBrace, "Struct fields")?;
+
+    let mut fields = Vec::new();
+    let mut base = None;
+    let end_span = loop {
+        if let Some(span) = input.next_if(Token::RBrace) {
+            break span;
+        }
+
+        if input.next_if(Token::Range).is_some() {
+            base = Some(parse_expression(input, comp)?);
+            break input
+                .assert_next(Token::RBrace, "The base must be the last part of a struct")?;
+        }
+
+        let name = parse_ident(input, comp)?;
+        input.assert_next(Token::Colon, "Field names and values are separated by ':'")?;
+        fields.push((name, parse_expression(input, comp)?));
+
+        let token = input.next()?;
+        match token.token {
+            Token::Comma => continue,
+            Token::RBrace => break token.span,
+            _ => return Err(input.unexpected_token("Struct fields")),
+        }
+    };
+
+    let record = RecordLiteral {
+        ident,
+        fields,
+        base,
+    };
+    let span = merge(&start_span, &end_span);
+
+    Ok(comp.new_expression(record.into(), span))
+}
+
+/// Parse an inline assembly expression
+///
+/// ```claw
+/// asm (a = x, b: u32 = 1) -> u32 clobbers (t: u32) { "local.get $a" "local.get $b" "i32.add" }
+/// ```
+fn parse_asm(input: &mut ParseInput, comp: &mut Component) -> Result<ExpressionId, ParserError> {
+    let start_span = input.assert_next(Token::Asm, "Inline assembly keyword 'asm'")?;
+    input.require(Feature::Asm, start_span)?;
+
+    // Inputs
+    input.assert_next(Token::LParen, "Inline assembly inputs are parenthesized")?;
+    let mut inputs: Vec<AsmInput> = Vec::new();
+    loop {
+        if input.next_if(Token::RParen).is_some() {
+            break;
+        }
+
+        let ident = parse_ident(input, comp)?;
+        let annotation = match input.next_if(Token::Colon) {
+            Some(_) => Some(parse_valtype(input, comp)?),
+            None => None,
+        };
+        input.assert_next(Token::Assign, "Inline assembly inputs are bound with '='")?;
+        let expression = parse_expression(input, comp)?;
+        inputs.push(AsmInput {
+            ident,
+            annotation,
+            expression,
+        });
+
+        let token = input.next()?;
+        match token.token {
+            Token::Comma => continue,
+            Token::RParen => break,
+            _ => return Err(input.unexpected_token("Inline assembly input list")),
+        }
+    }
+
+    // Output
+    input.assert_next(Token::Arrow, "Inline assembly must declare its result type")?;
+    let result = parse_valtype(input, comp)?;
+
+    // Clobbered locals
+    let mut clobbers: Vec<(NameId, TypeId)> = Vec::new();
+    if input.next_if(Token::Clobbers).is_some() {
+        input.assert_next(Token::LParen, "Clobbered locals are parenthesized")?;
+        loop {
+            if input.next_if(Token::RParen).is_some() {
+                break;
+            }
+
+            let name = parse_ident(input, comp)?;
+            input.assert_next(Token::Colon, "Clobbered locals must annotate their type")?;
+            let local_type = parse_valtype(input, comp)?;
+            clobbers.push((name, local_type));
+
+            let token = input.next()?;
+            match token.token {
+                Token::Comma => continue,
+                Token::RParen => break,
+                _ => return Err(input.unexpected_token("Clobbered local list")),
+            }
+        }
+    }
+
+    // Body
+    input.assert_next(Token::LBrace, "Inline assembly body")?;
+    let mut lines = Vec::new();
+    let end_span = loop {
+        let next = input.next()?;
+        match next.token {
+            Token::StringLiteral(line) => lines.push(comp.resolve_symbol(line)),
+            Token::RBrace => break next.span,
+            _ => return Err(input.unexpected_token("Inline assembly body must be string literals")),
+        }
+    };
+
+    let asm = InlineAsm {
+        inputs,
+        result,
+        clobbers,
+        body: lines.join("\n"),
+    };
+    let span = merge(&start_span, &end_span);
+    Ok(comp.new_expression(asm.into(), span))
+}
+
 fn peek_unary_op(input: &mut ParseInput) -> Option<UnaryOp> {
     let next = input.peek().ok()?;
     let op = match &next.token {
         Token::Sub => UnaryOp::Negate,
+        Token::LogicalNot => UnaryOp::LogicalNot,
+        Token::Invert => UnaryOp::Invert,
         _ => return None,
     };
     Some(op)
 }
 
+/// Indexing and member access bind tighter than any prefix or infix operator
+const INDEX_BINDING_POWER: u8 = 210;
+/// Conversions bind tighter than binary operators but looser than negation
+const CAST_BINDING_POWER: u8 = 110;
+
+/// Prefix operators bind tighter than every binary operator,
+/// so `-a * b` is `(-a) * b` and `!a and b` is `(!a) and b`
 fn prefix_binding_power(op: UnaryOp) -> ((), u8) {
     match op {
-        UnaryOp::Negate => ((), 200),
+        UnaryOp::Negate | UnaryOp::LogicalNot | UnaryOp::Invert => ((), 200),
     }
 }
 
+/// Operators which wrap or saturate instead of trapping on overflow
+fn is_overflow_op(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::WrappingAdd
+            | BinaryOp::WrappingSubtract
+            | BinaryOp::WrappingMultiply
+            | BinaryOp::SaturatingAdd
+            | BinaryOp::SaturatingSubtract
+    )
+}
+
 fn peek_bin_op(input: &mut ParseInput) -> Option<BinaryOp> {
     let next = input.peek().ok()?;
     let op = match &next.token {
@@ -204,12 +589,18 @@ fn peek_bin_op(input: &mut ParseInput) -> Option<BinaryOp> {
         Token::Div => BinaryOp::Divide,
         Token::Mod => BinaryOp::Modulo,
 
+        Token::WrappingAdd => BinaryOp::WrappingAdd,
+        Token::WrappingSub => BinaryOp::WrappingSubtract,
+        Token::WrappingMult => BinaryOp::WrappingMultiply,
+        Token::SaturatingAdd => BinaryOp::SaturatingAdd,
+        Token::SaturatingSub => BinaryOp::SaturatingSubtract,
+
         _ => return None,
     };
     Some(op)
 }
 
-fn infix_binding_power(op: BinaryOp) -> (u8, u8) {
+pub(crate) fn infix_binding_power(op: BinaryOp) -> (u8, u8) {
     match op {
         BinaryOp::LogicalOr => (10, 1),
         BinaryOp::LogicalAnd => (20, 21),
@@ -229,9 +620,16 @@ fn infix_binding_power(op: BinaryOp) -> (u8, u8) {
 
         BinaryOp::BitShiftL | BinaryOp::BitShiftR | BinaryOp::ArithShiftR => (80, 81),
 
-        BinaryOp::Add | BinaryOp::Subtract => (90, 91),
+        BinaryOp::Add
+        | BinaryOp::Subtract
+        | BinaryOp::WrappingAdd
+        | BinaryOp::WrappingSubtract
+        | BinaryOp::SaturatingAdd
+        | BinaryOp::SaturatingSubtract => (90, 91),
 
-        BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => (100, 101),
+        BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo | BinaryOp::WrappingMultiply => {
+            (100, 101)
+        }
     }
 }
 
@@ -263,13 +661,16 @@ mod tests {
             ("0x81", 129, make_span(0, 4)),
         ];
         for (source, value, span) in cases {
-            let (src, mut input) = make_input(source);
-            let mut comp = Component::new(src);
+            let mut input = make_input(source);
+            let mut comp = input.new_component();
             let expected_expression = comp.new_expression(Literal::Integer(value).into(), span);
 
-            let found_literal = parse_literal(&mut input.clone(), &mut comp).unwrap();
+            let start = input.checkpoint();
+            let found_literal = parse_literal(&mut input, &mut comp).unwrap();
+            input.restore(start);
             assert!(found_literal.context_eq(&expected_expression, &comp));
-            let found_leaf = parse_leaf(&mut input.clone(), &mut comp).unwrap();
+            let found_leaf = parse_leaf(&mut input, &mut comp).unwrap();
+            input.restore(start);
             assert!(found_leaf.context_eq(&expected_expression, &comp));
             let found_expression = parse_expression(&mut input, &mut comp).unwrap();
             assert!(found_expression.context_eq(&expected_expression, &comp));
@@ -285,14 +686,18 @@ mod tests {
             ("asdf2", make_span(0, 5)),
         ];
         for (source, span) in cases {
-            let (src, mut input) = make_input(source);
-            let mut comp = Component::new(src);
-            let ident = comp.new_name(source.to_owned(), span);
+            let mut input = make_input(source);
+            let mut comp = input.new_component();
+            let symbol = comp.intern(source);
+            let ident = comp.new_name(symbol, span);
             let expected_expression = comp.new_expression(ast::Identifier { ident }.into(), span);
-            let found_ident = parse_ident_expr(&mut input.clone(), &mut comp).unwrap();
+            let start = input.checkpoint();
+            let found_ident = parse_ident_expr(&mut input, &mut comp).unwrap();
+            input.restore(start);
             assert!(found_ident.context_eq(&expected_expression, &comp));
 
-            let found_leaf = parse_leaf(&mut input.clone(), &mut comp).unwrap();
+            let found_leaf = parse_leaf(&mut input, &mut comp).unwrap();
+            input.restore(start);
             assert!(found_leaf.context_eq(&expected_expression, &comp));
 
             let found_expression = parse_expression(&mut input, &mut comp).unwrap();
@@ -310,13 +715,17 @@ mod tests {
             ("(asdf2)", "asdf2", make_span(1, 5)),
         ];
         for (source, ident, span) in cases {
-            let (src, mut input) = make_input(source);
-            let mut comp = Component::new(src);
-            let ident = comp.new_name(ident.to_owned(), span);
+            let mut input = make_input(source);
+            let mut comp = input.new_component();
+            let symbol = comp.intern(ident);
+            let ident = comp.new_name(symbol, span);
             let expected_expression = comp.new_expression(ast::Identifier { ident }.into(), span);
-            let found_expression = parse_parenthetical(&mut input.clone(), &mut comp).unwrap();
+            let start = input.checkpoint();
+            let found_expression = parse_parenthetical(&mut input, &mut comp).unwrap();
+            input.restore(start);
             assert!(found_expression.context_eq(&expected_expression, &comp));
-            let found_expression = parse_leaf(&mut input.clone(), &mut comp).unwrap();
+            let found_expression = parse_leaf(&mut input, &mut comp).unwrap();
+            input.restore(start);
             assert!(found_expression.context_eq(&expected_expression, &comp));
             let found_expression = parse_expression(&mut input, &mut comp).unwrap();
             assert!(found_expression.context_eq(&expected_expression, &comp));
@@ -334,9 +743,10 @@ mod tests {
             let src_span = make_span(0, source.len());
 
             // Construct ast
-            let (src, input) = make_input(source.as_str());
-            let mut comp = Component::new(src);
-            let ident = comp.new_name(ident.to_owned(), ident_span);
+            let input = make_input(source.as_str());
+            let mut comp = input.new_component();
+            let symbol = comp.intern(ident);
+            let ident = comp.new_name(symbol, ident_span);
             let expected_expression = comp.new_expression(
                 ast::Expression::Call(ast::Call {
                     ident,
@@ -373,6 +783,7 @@ mod tests {
                     op: $op,
                     left: lhs,
                     right: rhs,
+                    parenthesized: false,
                 }
                 .into(),
                 span,
@@ -388,8 +799,8 @@ mod tests {
     #[test]
     fn parse_expression_respects_precedence() {
         let source0 = "0 + 1 * 2";
-        let (src0, input0) = make_input(source0);
-        let mut comp0 = Component::new(src0);
+        let input0 = make_input(source0);
+        let mut comp0 = input0.new_component();
         let expected0 = make_ast!(comp0, {
             (0 => 0, 1),
             BinaryOp::Add,
@@ -401,8 +812,8 @@ mod tests {
         });
 
         let source1 = "0 * 1 + 2";
-        let (src1, input1) = make_input(source1);
-        let mut comp1 = Component::new(src1);
+        let input1 = make_input(source1);
+        let mut comp1 = input1.new_component();
         let expected1 = make_ast!(comp1, {
             {
                 (0 => 0, 1),
@@ -421,11 +832,327 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parsing_supports_inline_asm() {
+        let source =
+            r#"asm (a = x, b: u32 = 1) -> u32 clobbers (t: u32) { "local.get $a" "i32.add" }"#;
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        match comp.get_expression(expression) {
+            ast::Expression::Asm(asm) => {
+                assert_eq!(asm.inputs.len(), 2);
+                assert_eq!(comp.get_name(asm.inputs[0].ident), "a");
+                assert_eq!(asm.inputs[0].annotation, None);
+                assert_eq!(comp.get_name(asm.inputs[1].ident), "b");
+                let annotation = asm.inputs[1].annotation.unwrap();
+                assert_eq!(comp.type_span(annotation), make_span(15, 3));
+                assert_eq!(asm.clobbers.len(), 1);
+                assert_eq!(comp.get_name(asm.clobbers[0].0), "t");
+                assert_eq!(asm.body, "local.get $a\ni32.add");
+            }
+            other => panic!("Expected inline asm but found {:?}", other),
+        }
+        assert_eq!(comp.expression_span(expression), make_span(0, source.len()));
+    }
+
+    #[test]
+    fn parsing_supports_overflow_operators() {
+        let source = "a +| b *% c -% d";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        let binary = |expression| match comp.get_expression(expression) {
+            ast::Expression::Binary(binary) => binary.clone(),
+            other => panic!("Expected binary expression but found {:?}", other),
+        };
+        let outer = binary(expression);
+        assert_eq!(outer.op, BinaryOp::WrappingSubtract);
+        let left = binary(outer.left);
+        assert_eq!(left.op, BinaryOp::SaturatingAdd);
+        assert_eq!(binary(left.right).op, BinaryOp::WrappingMultiply);
+    }
+
+    #[test]
+    fn parsing_supports_indexing() {
+        let source = "-values[i + 1][0] * 2";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        let product = match comp.get_expression(expression) {
+            ast::Expression::Binary(binary) => binary.clone(),
+            other => panic!("Expected binary expression but found {:?}", other),
+        };
+        assert_eq!(product.op, BinaryOp::Multiply);
+        let negated = match comp.get_expression(product.left) {
+            ast::Expression::Unary(unary) => unary.inner,
+            other => panic!("Expected unary expression but found {:?}", other),
+        };
+        let outer = match comp.get_expression(negated) {
+            ast::Expression::Index(index) => index.clone(),
+            other => panic!("Expected index expression but found {:?}", other),
+        };
+        assert_eq!(comp.expression_span(negated), make_span(1, 16));
+        match comp.get_expression(outer.list) {
+            ast::Expression::Index(inner) => {
+                assert_eq!(comp.expression_span(inner.index), make_span(8, 5));
+            }
+            other => panic!("Expected index expression but found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parsing_supports_slicing() {
+        let source = "values[1..n + 1][..2][i..]";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        let slice = |expression| match comp.get_expression(expression) {
+            ast::Expression::Slice(slice) => slice.clone(),
+            other => panic!("Expected slice expression but found {:?}", other),
+        };
+        let outer = slice(expression);
+        assert_eq!(comp.expression_span(outer.start.unwrap()), make_span(22, 1));
+        assert!(outer.end.is_none());
+        let middle = slice(outer.list);
+        assert!(middle.start.is_none());
+        assert_eq!(comp.expression_span(middle.end.unwrap()), make_span(19, 1));
+        let inner = slice(middle.list);
+        assert_eq!(comp.expression_span(inner.start.unwrap()), make_span(7, 1));
+        assert_eq!(comp.expression_span(inner.end.unwrap()), make_span(10, 5));
+        assert_eq!(comp.expression_span(expression), make_span(0, source.len()));
+    }
+
+    #[test]
+    fn parsing_supports_casts() {
+        let source = "-code as status + s::a as u32";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        let sum = match comp.get_expression(expression) {
+            ast::Expression::Binary(binary) => binary.clone(),
+            other => panic!("Expected binary expression but found {:?}", other),
+        };
+        assert_eq!(sum.op, BinaryOp::Add);
+        let cast = |expression| match comp.get_expression(expression) {
+            ast::Expression::Cast(cast) => cast.clone(),
+            other => panic!("Expected cast expression but found {:?}", other),
+        };
+        let left = cast(sum.left);
+        assert!(matches!(
+            comp.get_expression(left.inner),
+            ast::Expression::Unary(_)
+        ));
+        assert_eq!(comp.expression_span(sum.left), make_span(0, 15));
+        let right = cast(sum.right);
+        assert!(matches!(
+            comp.get_expression(right.inner),
+            ast::Expression::Enum(_)
+        ));
+        assert_eq!(comp.expression_span(sum.right), make_span(18, 11));
+    }
+
+    #[test]
+    fn parsing_marks_parenthesized_binaries() {
+        let source = "(a & b) == c + d";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        let binary = |expression| match comp.get_expression(expression) {
+            ast::Expression::Binary(binary) => binary.clone(),
+            other => panic!("Expected binary expression but found {:?}", other),
+        };
+        let equals = binary(expression);
+        assert_eq!(equals.op, BinaryOp::Equals);
+        assert!(!equals.parenthesized);
+        assert!(binary(equals.left).parenthesized);
+        assert!(!binary(equals.right).parenthesized);
+    }
+
+    #[test]
+    fn parsing_binds_prefix_operators_tightly() {
+        // (operator at the root, unary operator inside its left operand)
+        let cases = [
+            ("-a * b", BinaryOp::Multiply, UnaryOp::Negate),
+            ("!a and b", BinaryOp::LogicalAnd, UnaryOp::LogicalNot),
+            ("~mask & x", BinaryOp::BitAnd, UnaryOp::Invert),
+            ("-a[0] + 1", BinaryOp::Add, UnaryOp::Negate),
+        ];
+        for (source, bin_op, unary_op) in cases {
+            let mut input = make_input(source);
+            let mut comp = input.new_component();
+            let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+            assert!(input.done());
+
+            let binary = match comp.get_expression(expression) {
+                ast::Expression::Binary(binary) => binary,
+                other => panic!("Expected binary expression but found {:?}", other),
+            };
+            assert_eq!(binary.op, bin_op);
+            match comp.get_expression(binary.left) {
+                ast::Expression::Unary(unary) => assert_eq!(unary.op, unary_op),
+                other => panic!("Expected unary expression but found {:?}", other),
+            }
+        }
+
+        // Parentheses apply the operator to the whole expression
+        let mut input = make_input("!(a and b)");
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        match comp.get_expression(expression) {
+            ast::Expression::Unary(unary) => {
+                assert_eq!(unary.op, UnaryOp::LogicalNot);
+                assert!(matches!(
+                    comp.get_expression(unary.inner),
+                    ast::Expression::Binary(BinaryExpression {
+                        op: BinaryOp::LogicalAnd,
+                        ..
+                    })
+                ));
+            }
+            other => panic!("Expected unary expression but found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parsing_supports_record_literals() {
+        let source = "point { x: a + 1, y: 2, ..origin }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        match comp.get_expression(expression) {
+            ast::Expression::Record(record) => {
+                assert_eq!(comp.get_name(record.ident), "point");
+                assert_eq!(record.fields.len(), 2);
+                assert_eq!(comp.get_name(record.fields[0].0), "x");
+                assert_eq!(comp.get_name(record.fields[1].0), "y");
+                let base = record.base.unwrap();
+                assert_eq!(comp.expression_span(base), make_span(26, 6));
+            }
+            other => panic!("Expected struct literal but found {:?}", other),
+        }
+        assert_eq!(comp.expression_span(expression), make_span(0, source.len()));
+
+        // Braces after a condition are still a block
+        let source = "x { y = 1; }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(matches!(
+            comp.get_expression(expression),
+            ast::Expression::Identifier(_)
+        ));
+        assert_eq!(input.peek().unwrap().token, Token::LBrace);
+    }
+
+    #[test]
+    fn parsing_supports_member_access() {
+        let source = "-s.end.scale(2, k) * 3";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        // Member access binds tighter than the prefix operator
+        let ast::Expression::Binary(binary) = comp.get_expression(expression) else {
+            panic!("Expected binary expression");
+        };
+        let ast::Expression::Unary(unary) = comp.get_expression(binary.left) else {
+            panic!("Expected unary expression");
+        };
+        let call = match comp.get_expression(unary.inner) {
+            ast::Expression::MethodCall(call) => call,
+            other => panic!("Expected method call but found {:?}", other),
+        };
+        assert_eq!(comp.get_name(call.ident), "scale");
+        assert_eq!(call.args.len(), 2);
+        assert_eq!(comp.expression_span(unary.inner), make_span(1, 17));
+
+        let access = match comp.get_expression(call.receiver) {
+            ast::Expression::Field(access) => access,
+            other => panic!("Expected field access but found {:?}", other),
+        };
+        assert_eq!(comp.get_name(access.field), "end");
+        assert_eq!(comp.expression_span(call.receiver), make_span(1, 5));
+        assert!(matches!(
+            comp.get_expression(access.record),
+            ast::Expression::Identifier(_)
+        ));
+    }
+
+    #[test]
+    fn parsing_supports_conditional_expressions() {
+        let source = "if a { 1 } else if b { 2 } else { 3 } + 1";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        // The braces end the if, so it's the left operand
+        let ast::Expression::Binary(binary) = comp.get_expression(expression) else {
+            panic!("Expected binary expression");
+        };
+        let if_expression = match comp.get_expression(binary.left) {
+            ast::Expression::If(if_expression) => if_expression,
+            other => panic!("Expected if expression but found {:?}", other),
+        };
+        assert_eq!(comp.expression_span(binary.left), make_span(0, 37));
+        assert!(matches!(
+            comp.get_expression(if_expression.else_value),
+            ast::Expression::If(_)
+        ));
+
+        let source = "match l { level::low => 1, n if n > 10 => n, _ => x + 2, }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let expression = parse_expression(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        let match_expression = match comp.get_expression(expression) {
+            ast::Expression::Match(match_expression) => match_expression,
+            other => panic!("Expected match expression but found {:?}", other),
+        };
+        let patterns: Vec<_> = match_expression
+            .arms
+            .iter()
+            .map(|arm| match arm.pattern {
+                Pattern::Case {
+                    enum_name,
+                    case_name,
+                } => format!("{}::{}", comp.get_name(enum_name), comp.get_name(case_name)),
+                Pattern::Wildcard(_) => "_".to_owned(),
+                Pattern::Binding(ident) => comp.get_name(ident).to_owned(),
+            })
+            .collect();
+        assert_eq!(patterns, ["level::low", "n", "_"]);
+        let guards: Vec<_> = match_expression
+            .arms
+            .iter()
+            .map(|arm| arm.guard.map(|guard| comp.expression_span(guard)))
+            .collect();
+        assert_eq!(guards, [None, Some(make_span(32, 6)), None]);
+        assert_eq!(comp.expression_span(expression), make_span(0, source.len()));
+    }
+
     #[test]
     fn parse_expression_respects_associativity() {
         let source0 = "0 + 1 + 2";
-        let (src0, input0) = make_input(source0);
-        let mut comp0 = Component::new(src0);
+        let input0 = make_input(source0);
+        let mut comp0 = input0.new_component();
         let expected0 = make_ast!(comp0, {
             { (0 => 0, 1), BinaryOp::Add, (1 => 4, 1) },
             BinaryOp::Add,
@@ -433,8 +1160,8 @@ mod tests {
         });
 
         let source1 = "0 * 1 * 2";
-        let (src1, input1) = make_input(source1);
-        let mut comp1 = Component::new(src1);
+        let input1 = make_input(source1);
+        let mut comp1 = input1.new_component();
         let expected1 = make_ast!(comp1, {
             { (0 => 0, 1), BinaryOp::Multiply, (1 => 4, 1) },
             BinaryOp::Multiply,
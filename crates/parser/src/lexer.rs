@@ -2,15 +2,16 @@
 
 use logos::Logos;
 
-use miette::{Diagnostic, SourceSpan};
+use miette::Diagnostic;
 use thiserror::Error;
 
+use claw_ast::{Interner, Span, Symbol};
 use claw_common::Source;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct TokenData {
     pub token: Token,
-    pub span: SourceSpan,
+    pub span: Span,
 }
 
 #[derive(Error, Debug, Diagnostic)]
@@ -20,30 +21,53 @@ pub struct LexerError {
     #[source_code]
     src: Source,
     #[label("Here")]
-    span: SourceSpan,
+    span: Span,
 }
 
-pub fn tokenize(src: Source, contents: &str) -> Result<Vec<TokenData>, LexerError> {
-    let lexer = Token::lexer(contents);
-
-    lexer
-        .spanned()
-        .map(|(token, span)| match token {
-            Ok(token) => Ok(TokenData {
-                token,
-                span: SourceSpan::from(span),
-            }),
-            Err(_error) => Err(LexerError {
-                src: src.clone(),
-                span: span.into(),
-            }),
-        })
-        .collect()
+/// The tokens of a source file and the identifiers interned while lexing it
+#[derive(Debug, Clone, Default)]
+pub struct Tokens {
+    pub tokens: Vec<TokenData>,
+    pub symbols: Interner,
+}
+
+pub fn tokenize(src: Source, contents: &str) -> Result<Tokens, LexerError> {
+    tokenize_part(src, contents, 0, Interner::default())
+}
+
+/// Tokenize part of a source file which starts `offset` bytes into it,
+/// interning identifiers into `symbols`
+pub fn tokenize_part(
+    src: Source,
+    contents: &str,
+    offset: usize,
+    symbols: Interner,
+) -> Result<Tokens, LexerError> {
+    let mut lexer = Token::lexer_with_extras(contents, symbols);
+
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next() {
+        let range = lexer.span();
+        let span = Span::from(range.start + offset..range.end + offset);
+        match token {
+            Ok(token) => tokens.push(TokenData { token, span }),
+            Err(_error) => return Err(LexerError { src, span }),
+        }
+    }
+
+    Ok(Tokens {
+        tokens,
+        symbols: lexer.extras,
+    })
 }
 
 /// The Token type for the language.
-#[derive(Logos, Debug, PartialEq, Clone)]
+///
+/// Identifiers and string literals are interned as they're lexed,
+/// so tokens don't own any of the source and are cheap to copy.
+#[derive(Logos, Debug, PartialEq, Clone, Copy)]
 #[logos(error = ())]
+#[logos(extras = Interner)]
 #[logos(skip r"[ \t\r\n\f]+")]
 #[logos(skip r"//[^\n]*")]
 #[logos(subpattern word = r"[a-z][a-z0-9]*|[A-Z][A-Z0-9]*")]
@@ -52,7 +76,7 @@ pub enum Token {
     /// Double-quoted string literal
     #[token("\"", parse_string_literal)]
     #[token("r", parse_raw_string_literal)]
-    StringLiteral(String),
+    StringLiteral(Symbol),
 
     /// A Decimal number literal
     #[regex(r"[0-9][_0-9]*", |lex| parse_decint_literal(lex.slice()))]
@@ -64,9 +88,9 @@ pub enum Token {
     #[regex(r"[0-9][_0-9]*\.[0-9][_0-9]*", |lex| parse_decfloat_literal(lex.slice()))]
     FloatLiteral(f64),
 
-    /// An Identifier
-    #[regex(r"(?&id)", |lex| lex.slice().to_string())]
-    Identifier(String),
+    /// An Identifier, interned as it's lexed
+    #[regex(r"(?&id)", |lex| lex.extras.intern(lex.slice()))]
+    Identifier(Symbol),
 
     // Keywords -----------------------------------------
     /// The Export Keyword
@@ -89,6 +113,14 @@ pub enum Token {
     #[token("if")]
     If,
 
+    /// The Else Keyword
+    #[token("else")]
+    Else,
+
+    /// The Match Keyword
+    #[token("match")]
+    Match,
+
     /// The For Keyword
     #[token("for")]
     For,
@@ -113,6 +145,30 @@ pub enum Token {
     #[token("return")]
     Return,
 
+    /// The Struct Keyword
+    #[token("struct")]
+    Struct,
+
+    /// The Enum Keyword
+    #[token("enum")]
+    Enum,
+
+    /// The Newtype Keyword
+    #[token("newtype")]
+    Newtype,
+
+    /// The List Type Keyword
+    #[token("list")]
+    List,
+
+    /// The Bytes Type Keyword, short for `list<u8>`
+    #[token("bytes")]
+    Bytes,
+
+    /// The Option Type Keyword
+    #[token("option")]
+    Option,
+
     /// The Result Type Keyword
     #[token("result")]
     Result,
@@ -189,6 +245,14 @@ pub enum Token {
     #[token("false")]
     False,
 
+    /// The Inline Assembly "asm" Keyword
+    #[token("asm")]
+    Asm,
+
+    /// The Clobbers Keyword
+    #[token("clobbers")]
+    Clobbers,
+
     // Symbols -----------------------------------------
     /// Left Parenthesis Symbol "("
     #[token("(")]
@@ -242,6 +306,14 @@ pub enum Token {
     #[token("->")]
     Arrow,
 
+    /// The Fat Arrow Symbol "=>" between a pattern and its value
+    #[token("=>")]
+    FatArrow,
+
+    /// The Underscore Symbol "_" matching anything
+    #[token("_")]
+    Underscore,
+
     /// Addition Operator "+"
     #[token("+")]
     Add,
@@ -262,8 +334,32 @@ pub enum Token {
     #[token("%")]
     Mod,
 
-    /// Invert Operator "!"
+    /// Wrapping Addition Operator "+%"
+    #[token("+%")]
+    WrappingAdd,
+
+    /// Wrapping Subtraction Operator "-%"
+    #[token("-%")]
+    WrappingSub,
+
+    /// Wrapping Multiplication Operator "*%"
+    #[token("*%")]
+    WrappingMult,
+
+    /// Saturating Addition Operator "+|"
+    #[token("+|")]
+    SaturatingAdd,
+
+    /// Saturating Subtraction Operator "-|"
+    #[token("-|")]
+    SaturatingSub,
+
+    /// Logical Not Operator "!"
     #[token("!")]
+    LogicalNot,
+
+    /// Bitwise Invert Operator "~"
+    #[token("~")]
     Invert,
 
     /// Logical And Operator
@@ -363,12 +459,20 @@ impl std::fmt::Display for Token {
             Token::From => write!(f, "from"),
             Token::Func => write!(f, "func"),
             Token::If => write!(f, "if"),
+            Token::Else => write!(f, "else"),
+            Token::Match => write!(f, "match"),
             Token::For => write!(f, "for"),
             Token::In => write!(f, "in"),
             Token::Loop => write!(f, "loop"),
             Token::Break => write!(f, "break"),
             Token::Continue => write!(f, "continue"),
             Token::Return => write!(f, "return"),
+            Token::Struct => write!(f, "struct"),
+            Token::Enum => write!(f, "enum"),
+            Token::Newtype => write!(f, "newtype"),
+            Token::List => write!(f, "list"),
+            Token::Bytes => write!(f, "bytes"),
+            Token::Option => write!(f, "option"),
             Token::Result => write!(f, "result"),
             Token::String => write!(f, "string"),
             Token::U8 => write!(f, "u8"),
@@ -388,6 +492,8 @@ impl std::fmt::Display for Token {
             Token::Bool => write!(f, "bool"),
             Token::True => write!(f, "true"),
             Token::False => write!(f, "false"),
+            Token::Asm => write!(f, "asm"),
+            Token::Clobbers => write!(f, "clobbers"),
             Token::LParen => write!(f, "("),
             Token::RParen => write!(f, ")"),
             Token::LBrace => write!(f, "{{"),
@@ -401,12 +507,20 @@ impl std::fmt::Display for Token {
             Token::Semicolon => write!(f, ";"),
             Token::Assign => write!(f, "="),
             Token::Arrow => write!(f, "->"),
+            Token::FatArrow => write!(f, "=>"),
+            Token::Underscore => write!(f, "_"),
             Token::Add => write!(f, "+"),
             Token::Sub => write!(f, "-"),
             Token::Mult => write!(f, "*"),
             Token::Div => write!(f, "/"),
             Token::Mod => write!(f, "%"),
-            Token::Invert => write!(f, "!"),
+            Token::WrappingAdd => write!(f, "+%"),
+            Token::WrappingSub => write!(f, "-%"),
+            Token::WrappingMult => write!(f, "*%"),
+            Token::SaturatingAdd => write!(f, "+|"),
+            Token::SaturatingSub => write!(f, "-|"),
+            Token::LogicalNot => write!(f, "!"),
+            Token::Invert => write!(f, "~"),
             Token::LogicalAnd => write!(f, "and"),
             Token::LogicalOr => write!(f, "or"),
             Token::BitOr => write!(f, "|"),
@@ -433,15 +547,24 @@ impl std::fmt::Display for Token {
 }
 
 /// Parses a string according to the JSON string format in ECMA-404.
-fn parse_string_literal(lex: &mut logos::Lexer<'_, Token>) -> Option<String> {
-    let mut c_iter = lex.remainder().chars();
+fn parse_string_literal(lex: &mut logos::Lexer<'_, Token>) -> Option<Symbol> {
+    let remainder = lex.remainder();
+    // Strings without escapes are interned straight from the source
+    if let Some(end) = remainder.find(['"', '\\']) {
+        if remainder[end..].starts_with('"') {
+            lex.bump(end + 1);
+            return Some(lex.extras.intern(&remainder[..end]));
+        }
+    }
+
+    let mut c_iter = remainder.chars();
     let mut buf = String::new();
 
     while let Some(c) = c_iter.next() {
         // End the parse when you encounter another quote
         if c == '"' {
             lex.bump(1);
-            return Some(buf);
+            return Some(lex.extras.intern(&buf));
         }
 
         // If slash, then parse an escaped character
@@ -491,7 +614,7 @@ fn parse_escaped_char(lex: &mut std::str::Chars) -> Option<(char, usize)> {
 }
 
 /// Parses a raw string literal
-fn parse_raw_string_literal(lex: &mut logos::Lexer<'_, Token>) -> Option<String> {
+fn parse_raw_string_literal(lex: &mut logos::Lexer<'_, Token>) -> Option<Symbol> {
     let mut c_iter = lex.remainder().chars();
     let mut buf = String::new();
 
@@ -524,7 +647,7 @@ fn parse_raw_string_literal(lex: &mut logos::Lexer<'_, Token>) -> Option<String>
             hash_count += 1;
 
             if hash_count == starting_hashes {
-                return Some(buf);
+                return Some(lex.extras.intern(&buf));
             }
             continue;
         }
@@ -579,25 +702,26 @@ mod test {
     fn tokenize_func_declaration() {
         let contents = "func test(a: u32) -> u32";
         let src = make_source("test", contents);
-        let ident_test = Token::Identifier("test".to_owned());
-        let ident_a = Token::Identifier("a".to_owned());
+        let mut symbols = Interner::default();
+        let ident_test = Token::Identifier(symbols.intern("test"));
+        let ident_a = Token::Identifier(symbols.intern("a"));
         let output = vec![
-            (Token::Func, SourceSpan::from(0..4)),
-            (ident_test, SourceSpan::from(5..9)),
-            (Token::LParen, SourceSpan::from(9..10)),
-            (ident_a, SourceSpan::from(10..11)),
-            (Token::Colon, SourceSpan::from(11..12)),
-            (Token::U32, SourceSpan::from(13..16)),
-            (Token::RParen, SourceSpan::from(16..17)),
-            (Token::Arrow, SourceSpan::from(18..20)),
-            (Token::U32, SourceSpan::from(21..24)),
+            (Token::Func, Span::from(0..4)),
+            (ident_test, Span::from(5..9)),
+            (Token::LParen, Span::from(9..10)),
+            (ident_a, Span::from(10..11)),
+            (Token::Colon, Span::from(11..12)),
+            (Token::U32, Span::from(13..16)),
+            (Token::RParen, Span::from(16..17)),
+            (Token::Arrow, Span::from(18..20)),
+            (Token::U32, Span::from(21..24)),
         ]
         .into_iter()
         .map(to_token_data)
         .collect::<Vec<TokenData>>();
 
         match tokenize(src, contents) {
-            Ok(tokens) => assert_eq!(output, tokens),
+            Ok(tokens) => assert_eq!(output, tokens.tokens),
             Err(_) => panic!("Should not have failed"),
         }
     }
@@ -606,26 +730,79 @@ mod test {
     fn tokenize_let() {
         let contents = r#"let a = "asdf\"";"#;
         let src = make_source("test", contents);
-        let ident_a = Token::Identifier("a".to_owned());
-        let string_asdf = Token::StringLiteral(String::from(r#"asdf""#));
+        let mut symbols = Interner::default();
+        let ident_a = Token::Identifier(symbols.intern("a"));
+        let string_asdf = Token::StringLiteral(symbols.intern(r#"asdf""#));
         let output = vec![
-            (Token::Let, SourceSpan::from(0..3)),
-            (ident_a, SourceSpan::from(4..5)),
-            (Token::Assign, SourceSpan::from(6..7)),
-            (string_asdf, SourceSpan::from(8..16)),
-            (Token::Semicolon, SourceSpan::from(16..17)),
+            (Token::Let, Span::from(0..3)),
+            (ident_a, Span::from(4..5)),
+            (Token::Assign, Span::from(6..7)),
+            (string_asdf, Span::from(8..16)),
+            (Token::Semicolon, Span::from(16..17)),
         ]
         .into_iter()
         .map(to_token_data)
         .collect::<Vec<TokenData>>();
 
         match tokenize(src, contents) {
-            Ok(tokens) => assert_eq!(output, tokens),
+            Ok(tokens) => assert_eq!(output, tokens.tokens),
             Err(_) => panic!("Should not have failed"),
         }
     }
 
-    fn to_token_data(d: (Token, SourceSpan)) -> TokenData {
+    #[test]
+    fn tokenize_overflow_operators() {
+        let contents = "a +% b -| c*%d";
+        let src = make_source("test", contents);
+        let mut symbols = Interner::default();
+        let mut ident = |name: &str| Token::Identifier(symbols.intern(name));
+        let output = vec![
+            (ident("a"), Span::from(0..1)),
+            (Token::WrappingAdd, Span::from(2..4)),
+            (ident("b"), Span::from(5..6)),
+            (Token::SaturatingSub, Span::from(7..9)),
+            (ident("c"), Span::from(10..11)),
+            (Token::WrappingMult, Span::from(11..13)),
+            (ident("d"), Span::from(13..14)),
+        ]
+        .into_iter()
+        .map(to_token_data)
+        .collect::<Vec<TokenData>>();
+
+        match tokenize(src, contents) {
+            Ok(tokens) => assert_eq!(output, tokens.tokens),
+            Err(_) => panic!("Should not have failed"),
+        }
+    }
+
+    #[test]
+    fn tokenize_strings() {
+        let contents = r#""plain" "esc\taped" "plain""#;
+        let src = make_source("test", contents);
+        let tokens = tokenize(src, contents).unwrap();
+        let strings: Vec<_> = tokens
+            .tokens
+            .iter()
+            .map(|data| match data.token {
+                Token::StringLiteral(symbol) => tokens.symbols.resolve(symbol),
+                _ => panic!("Expected a string literal"),
+            })
+            .collect();
+        assert_eq!(strings, ["plain", "esc\taped", "plain"]);
+        assert_eq!(tokens.tokens[0].token, tokens.tokens[2].token);
+    }
+
+    #[test]
+    fn tokenize_interns_identifiers() {
+        let contents = "a + b * a";
+        let src = make_source("test", contents);
+        let tokens = tokenize(src, contents).unwrap();
+        assert_eq!(tokens.tokens[0].token, tokens.tokens[4].token);
+        assert_ne!(tokens.tokens[0].token, tokens.tokens[2].token);
+        assert_eq!(tokens.symbols.len(), 2);
+    }
+
+    fn to_token_data(d: (Token, Span)) -> TokenData {
         TokenData {
             token: d.0,
             span: d.1,
@@ -6,72 +6,268 @@ mod component;
 mod expressions;
 mod lexer;
 mod names;
+mod printer;
+mod session;
 mod statements;
 mod types;
+mod version;
 
 use std::sync::Arc;
 
 use crate::lexer::{Token, TokenData};
-use ast::{component::Component, Span};
+use ast::{component::Component, Interner, Span, Symbol};
 use claw_ast as ast;
 use claw_common::Source;
 
-use miette::{Diagnostic, NamedSource, SourceSpan};
+use miette::{Diagnostic, NamedSource};
 use thiserror::Error;
 
 use component::parse_component;
 
-pub use lexer::{tokenize, LexerError};
+pub use lexer::{tokenize, tokenize_part, LexerError, Tokens};
+pub use printer::print_component;
+pub use session::Session;
+pub use version::{Feature, LangVersion};
 
-#[derive(Error, Debug, Diagnostic)]
+#[derive(Error, Debug, Diagnostic, Clone)]
 pub enum ParserError {
     #[error("Failed to parse")]
     Base {
         #[source_code]
         src: Source,
         #[label("Unable to parse this code")]
-        span: SourceSpan,
+        span: Span,
     },
     #[error("{description}")]
     UnexpectedToken {
         #[source_code]
         src: Source,
-        #[label("Found {token:?}")]
-        span: SourceSpan,
+        #[label("Found {token}")]
+        span: Span,
         description: String,
-        token: Token,
+        token: String,
     },
     #[error("End of input reached")]
     EndOfInput,
-    #[error("Feature {feature} not supported yet at {token:?}")]
-    NotYetSupported { feature: String, token: Token },
+    #[error("Feature {feature} not supported yet at {token}")]
+    NotYetSupported { feature: String, token: String },
+    #[error("This feature requires lang version {required}")]
+    #[diagnostic(help(
+        "The code is parsed as lang version {declared}, which can be changed \
+        with --lang-version or lang-version in claw.toml"
+    ))]
+    FeatureVersion {
+        #[source_code]
+        src: Source,
+        #[label("{feature}")]
+        span: Span,
+        feature: String,
+        required: LangVersion,
+        declared: LangVersion,
+    },
 }
 
-pub fn parse(src: Source, tokens: Vec<TokenData>) -> Result<Component, ParserError> {
-    let mut input = ParseInput::new(src.clone(), tokens);
-    parse_component(src, &mut input)
+/// Parse the tokens, rejecting syntax newer than `version` and carrying on
+/// past items and statements which fail to parse so that every error in the
+/// file is found, each as a diagnostic of its own.
+///
+/// The component holds everything which did parse, so it is incomplete
+/// whenever there are errors.
+pub fn parse(src: Source, tokens: Tokens, version: LangVersion) -> (Component, Vec<ParserError>) {
+    let mut input = ParseInput::new(src, &tokens)
+        .with_version(version)
+        .with_recovery();
+    let component = match parse_component(&mut input) {
+        Ok(component) => component,
+        Err(error) => {
+            input.errors.push(error);
+            input.new_component()
+        }
+    };
+    (component, input.errors)
 }
 
+/// A cursor over the tokens of a source file.
+///
+/// The tokens are borrowed, so the position in them is just an index
+/// and backtracking is a matter of saving and restoring that index.
 #[derive(Debug, Clone)]
-pub struct ParseInput {
+pub struct ParseInput<'a> {
     src: Source,
-    tokens: Vec<TokenData>,
+    tokens: &'a [TokenData],
+    symbols: &'a Interner,
     index: usize,
+    version: LangVersion,
+    /// Whether failed items and statements are skipped instead of
+    /// ending the parse
+    recover: bool,
+    /// The errors from everything skipped so far
+    errors: Vec<ParserError>,
+}
+
+/// A position in a [ParseInput] which can be returned to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+/// What is being skipped when recovering from an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sync {
+    Item,
+    Statement,
+}
+
+impl Sync {
+    /// Whether the next tokens start a new item or statement
+    fn starts_at(self, input: &ParseInput) -> bool {
+        let Some(token) = input.peekn(0) else {
+            return false;
+        };
+        // `func` followed by a name, not a function type
+        let func = *token == Token::Func && matches!(input.peekn(1), Some(Token::Identifier(_)));
+        match self {
+            Sync::Item => {
+                func || matches!(
+                    token,
+                    Token::Export
+                        | Token::Import
+                        | Token::Let
+                        | Token::Struct
+                        | Token::Enum
+                        | Token::Newtype
+                )
+            }
+            Sync::Statement => func || matches!(token, Token::Let | Token::Return | Token::If),
+        }
+    }
 }
 
-impl ParseInput {
-    pub fn new(src: Source, tokens: Vec<TokenData>) -> Self {
+impl<'a> ParseInput<'a> {
+    pub fn new(src: Source, tokens: &'a Tokens) -> Self {
         ParseInput {
             src,
-            tokens,
+            tokens: &tokens.tokens,
+            symbols: &tokens.symbols,
             index: 0,
+            version: LangVersion::LATEST,
+            recover: false,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Only accept the syntax of the given version of the language
+    pub fn with_version(mut self, version: LangVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Record errors and skip past the code which caused them
+    pub fn with_recovery(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// The errors recorded while recovering
+    pub fn errors(&self) -> &[ParserError] {
+        &self.errors
+    }
+
+    /// Recover from the result of parsing an item or statement which
+    /// started at `start`.
+    ///
+    /// When recovering, an error is recorded and the tokens are skipped
+    /// until the end of what was being parsed: a `;` outside any braces,
+    /// the `}` closing its outermost braces or the start of the next `sync` point.
+    /// Errors are passed on when not recovering or when the input ran out.
+    pub fn recover<T>(
+        &mut self,
+        result: Result<T, ParserError>,
+        start: Checkpoint,
+        sync: Sync,
+    ) -> Result<Option<T>, ParserError> {
+        let error = match result {
+            Ok(value) => return Ok(Some(value)),
+            Err(error) => error,
+        };
+        if !self.recover || matches!(error, ParserError::EndOfInput) {
+            return Err(error);
+        }
+        self.errors.push(error);
+
+        self.restore(start);
+        let mut depth = 0usize;
+        let mut first = true;
+        while let Some(token) = self.peekn(0) {
+            if depth == 0 && !first {
+                if *token == Token::RBrace && sync == Sync::Statement {
+                    // The end of the enclosing block
+                    break;
+                }
+                if sync.starts_at(self) {
+                    break;
+                }
+            }
+            first = false;
+            self.index += 1;
+            match token {
+                Token::Semicolon if depth == 0 => break,
+                Token::LBrace => depth += 1,
+                Token::RBrace if depth <= 1 => break,
+                Token::RBrace => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Fail if the feature, used at `span`, is newer than the version being parsed
+    pub fn require(&self, feature: Feature, span: Span) -> Result<(), ParserError> {
+        if self.version >= feature.since() {
+            return Ok(());
+        }
+        Err(ParserError::FeatureVersion {
+            src: self.src.clone(),
+            span,
+            feature: feature.description().to_owned(),
+            required: feature.since(),
+            declared: self.version,
+        })
+    }
+
+    /// Remember the current position
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.index)
+    }
+
+    /// Go back to a remembered position
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.index = checkpoint.0;
+    }
+
+    /// Create an empty component which shares the identifiers of the input
+    pub fn new_component(&self) -> Component {
+        Component::with_symbols(self.src.clone(), self.symbols.clone())
+    }
+
+    /// Get the string of an identifier token
+    pub fn resolve_symbol(&self, symbol: Symbol) -> &'a str {
+        self.symbols.resolve(symbol)
+    }
+
+    /// Describe a token for an error, spelling out interned strings
+    fn describe(&self, token: &Token) -> String {
+        match token {
+            Token::Identifier(symbol) => format!("Identifier({:?})", self.resolve_symbol(*symbol)),
+            Token::StringLiteral(symbol) => {
+                format!("StringLiteral({:?})", self.resolve_symbol(*symbol))
+            }
+            token => format!("{:?}", token),
         }
     }
 
     pub fn unsupported_error(&self, feature: &str) -> ParserError {
         ParserError::NotYetSupported {
             feature: feature.to_string(),
-            token: self.tokens[self.index].token.clone(),
+            token: self.describe(&self.tokens[self.index].token),
         }
     }
 
@@ -86,7 +282,7 @@ impl ParseInput {
             src: self.src.clone(),
             span: data.span,
             description: description.to_string(),
-            token: data.token.clone(),
+            token: self.describe(&data.token),
         }
     }
 
@@ -102,15 +298,15 @@ impl ParseInput {
         self.index >= self.tokens.len()
     }
 
-    pub fn peek(&self) -> Result<&TokenData, ParserError> {
+    pub fn peek(&self) -> Result<&'a TokenData, ParserError> {
         self.tokens.get(self.index).ok_or(ParserError::EndOfInput)
     }
 
-    pub fn peekn(&self, n: usize) -> Option<&Token> {
+    pub fn peekn(&self, n: usize) -> Option<&'a Token> {
         self.tokens.get(self.index + n).map(|t| &t.token)
     }
 
-    pub fn next(&mut self) -> Result<&TokenData, ParserError> {
+    pub fn next(&mut self) -> Result<&'a TokenData, ParserError> {
         let result = self.tokens.get(self.index);
         self.index += 1;
         result.ok_or(ParserError::EndOfInput)
@@ -135,7 +331,7 @@ impl ParseInput {
         Some(self.next().ok()?.span)
     }
 
-    pub fn slice_next(&mut self, num: usize) -> Result<&[TokenData], ParserError> {
+    pub fn slice_next(&mut self, num: usize) -> Result<&'a [TokenData], ParserError> {
         if self.has(num) {
             let result = &self.tokens[self.index..self.index + num];
             self.index += num;
@@ -146,14 +342,15 @@ impl ParseInput {
     }
 }
 
-pub fn make_input(source: &str) -> (Source, ParseInput) {
+/// Tokenize a test source, leaking the tokens so the input can outlive them
+pub fn make_input(source: &str) -> ParseInput<'static> {
     let src = Arc::new(NamedSource::new("test", source.to_string()));
     let tokens = crate::lexer::tokenize(src.clone(), source).unwrap();
-    (src.clone(), ParseInput::new(src, tokens))
+    ParseInput::new(src, Box::leak(Box::new(tokens)))
 }
 
 pub fn make_span(start: usize, len: usize) -> Span {
-    Span::new(start.into(), len)
+    Span::new(start, len)
 }
 
 #[cfg(test)]
@@ -162,7 +359,7 @@ mod tests {
 
     #[test]
     fn test_peek() {
-        let (_src, mut input) = make_input("export func");
+        let mut input = make_input("export func");
         assert_eq!(input.peek().unwrap().token, Token::Export);
         assert_eq!(input.peek().unwrap().token, Token::Export);
         assert_eq!(input.peek().unwrap().token, Token::Export);
@@ -174,7 +371,7 @@ mod tests {
 
     #[test]
     fn test_peekn() {
-        let (_src, mut input) = make_input("export func () -> {}");
+        let mut input = make_input("export func () -> {}");
         assert_eq!(input.peekn(0).unwrap(), &Token::Export);
         assert_eq!(input.peekn(1).unwrap(), &Token::Func);
         assert_eq!(input.peekn(2).unwrap(), &Token::LParen);
@@ -183,4 +380,15 @@ mod tests {
         assert_eq!(input.peekn(1).unwrap(), &Token::LParen);
         assert_eq!(input.peekn(2).unwrap(), &Token::RParen);
     }
+
+    #[test]
+    fn test_checkpoint() {
+        let mut input = make_input("export func () -> {}");
+        input.next().unwrap();
+        let checkpoint = input.checkpoint();
+        input.slice_next(3).unwrap();
+        assert_eq!(input.peek().unwrap().token, Token::Arrow);
+        input.restore(checkpoint);
+        assert_eq!(input.peek().unwrap().token, Token::Func);
+    }
 }
@@ -4,11 +4,10 @@ use crate::{ParseInput, ParserError};
 
 /// Parse an identifier
 pub fn parse_ident(input: &mut ParseInput, comp: &mut Component) -> Result<NameId, ParserError> {
-    match &input.peek()?.token {
-        Token::Identifier(ident) => {
-            let ident = ident.clone();
+    match input.peek()?.token {
+        Token::Identifier(symbol) => {
             let span = input.next().unwrap().span;
-            Ok(comp.new_name(ident, span))
+            Ok(comp.new_name(symbol, span))
         }
         _ => {
             input.next().unwrap();
@@ -37,8 +36,8 @@ pub fn parse_interface_name(input: &mut ParseInput) -> Result<(PackageName, Stri
 }
 
 fn parse_identifier(input: &mut ParseInput) -> Result<String, ParserError> {
-    match &input.next()?.token {
-        Token::Identifier(ident) => Ok(ident.clone()),
+    match input.next()?.token {
+        Token::Identifier(symbol) => Ok(input.resolve_symbol(symbol).to_owned()),
         _ => Err(input.unexpected_token("Identifier part of interface name")),
     }
 }
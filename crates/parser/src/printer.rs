@@ -0,0 +1,1195 @@
+//! Turns a parsed [Component] back into source code.
+//!
+//! The output is canonical: indentation, spacing and line breaks are
+//! decided by the printer, parentheses are only added where precedence
+//! needs them (or where they were written around a binary expression)
+//! and at most one blank line is kept between items and statements.
+//!
+//! Comments aren't part of the AST, so they're recovered from the gaps
+//! between the tokens of the source. Comments on their own line stay
+//! before the item, statement, field or closing brace that follows them
+//! and comments at the end of a line stay at the end of that line.
+//! Comments inside an expression are moved to the line before its statement.
+
+use crate::ast::{
+    self, BinaryExpression, Component, Expression, ExpressionId, FunctionId, Import, ImportId,
+    NameId, Pattern, PrimitiveType, Statement, StatementId, TypeDefId, TypeDefinition, TypeId,
+    ValType,
+};
+use crate::expressions::infix_binding_power;
+use crate::lexer::{tokenize, Token, TokenData};
+
+const INDENT: &str = "    ";
+
+/// Record literals longer than this, or holding other record literals,
+/// are split over several lines
+const RECORD_WIDTH: usize = 60;
+
+/// Print the component as canonically formatted source code,
+/// keeping the comments of its source.
+pub fn print_component(comp: &Component) -> String {
+    let source = comp.source();
+    let text: &str = source.inner();
+    // The source was parsed into the component, so it can be tokenized
+    let tokens = tokenize(source.clone(), text)
+        .map(|tokens| tokens.tokens)
+        .unwrap_or_default();
+    let comments = find_comments(text, &tokens);
+
+    let mut printer = Printer {
+        comp,
+        text,
+        tokens,
+        comments,
+        next_comment: 0,
+        output: String::new(),
+        depth: 0,
+    };
+    printer.component();
+    printer.output
+}
+
+/// The byte ranges of the `//` comments between the tokens
+fn find_comments(text: &str, tokens: &[TokenData]) -> Vec<(usize, usize)> {
+    let mut gaps = Vec::new();
+    let mut end = 0;
+    for token in tokens {
+        gaps.push(end..token.span.start());
+        end = token.span.end();
+    }
+    gaps.push(end..text.len());
+
+    let mut comments = Vec::new();
+    for gap in gaps {
+        let mut position = gap.start;
+        while let Some(offset) = text[position..gap.end].find("//") {
+            let start = position + offset;
+            let end = text[start..gap.end]
+                .find('\n')
+                .map_or(gap.end, |offset| start + offset);
+            comments.push((start, end));
+            position = end;
+        }
+    }
+    comments
+}
+
+#[derive(Clone, Copy)]
+enum Item {
+    Import(ImportId),
+    TypeDef(TypeDefId),
+    Global(ast::GlobalId),
+    Function(FunctionId),
+}
+
+struct Printer<'a> {
+    comp: &'a Component,
+    text: &'a str,
+    tokens: Vec<TokenData>,
+    comments: Vec<(usize, usize)>,
+    /// The first comment which hasn't been printed yet
+    next_comment: usize,
+    output: String,
+    /// How many levels lines are indented by
+    depth: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn component(&mut self) {
+        let comp = self.comp;
+        let mut items: Vec<(usize, Item)> = Vec::new();
+        for (id, import) in comp.iter_imports() {
+            let name = match import {
+                Import::Plain(import) => Some(import.ident),
+                Import::ImportFrom(import) => import.items.first().map(|(name, _)| *name),
+            };
+            let start = name.map_or(0, |name| self.item_start(name));
+            items.push((start, Item::Import(id)));
+        }
+        for (id, type_def) in comp.iter_type_defs() {
+            items.push((self.item_start(type_def.ident()), Item::TypeDef(id)));
+        }
+        for (id, global) in comp.iter_globals() {
+            items.push((self.item_start(global.ident), Item::Global(id)));
+        }
+        for (id, function) in comp.iter_functions() {
+            if !function.nested {
+                items.push((self.item_start(function.ident), Item::Function(id)));
+            }
+        }
+        items.sort_by_key(|(start, _)| *start);
+
+        for (index, (start, item)) in items.iter().enumerate() {
+            // Items cover every token up to the next one
+            let end = match items.get(index + 1) {
+                Some((next, _)) => self
+                    .tokens
+                    .get(next.saturating_sub(1))
+                    .map_or(0, |token| token.span.end()),
+                None => self.tokens.last().map_or(0, |token| token.span.end()),
+            };
+            let start = self
+                .tokens
+                .get(*start)
+                .map_or(0, |token| token.span.start());
+            self.item(*item, start, end);
+        }
+        self.comments_before(usize::MAX);
+    }
+
+    /// The index of the first token of the item with this name,
+    /// e.g. the `export` of `export func name`
+    fn item_start(&self, name: NameId) -> usize {
+        let mut index = self.token_at(self.comp.name_span(name).start());
+        while index > 0 {
+            let keyword = matches!(
+                self.tokens[index - 1].token,
+                Token::Export
+                    | Token::Import
+                    | Token::LBrace
+                    | Token::Let
+                    | Token::Mut
+                    | Token::Func
+                    | Token::Struct
+                    | Token::Enum
+                    | Token::Newtype
+            );
+            if !keyword {
+                break;
+            }
+            index -= 1;
+        }
+        index
+    }
+
+    fn item(&mut self, item: Item, start: usize, end: usize) {
+        let comp = self.comp;
+        self.comments_before(start);
+        self.blank_line(start);
+        match item {
+            Item::Import(id) => {
+                self.comments_inside(end);
+                let line = self.import(comp.get_import(id));
+                self.line(&line, end);
+            }
+            Item::Global(id) => {
+                let global = comp.get_global(id);
+                self.comments_inside(end);
+                let line = format!(
+                    "{}let {}{}: {} = {};",
+                    export(global.exported),
+                    mutable(global.mutable),
+                    self.name(global.ident),
+                    self.valtype(global.type_id),
+                    self.expression(global.init_value, 0),
+                );
+                self.line(&line, end);
+            }
+            Item::Function(id) => self.function(id, end.saturating_sub(1)),
+            Item::TypeDef(id) => self.type_def(id, end),
+        }
+    }
+
+    fn import(&self, import: &Import) -> String {
+        let alias = |alias: Option<NameId>| match alias {
+            Some(alias) => format!(" as {}", self.name(alias)),
+            None => String::new(),
+        };
+        match import {
+            Import::Plain(import) => {
+                let ast::ExternalType::Function(fn_type) = &import.external_type;
+                let params = self.params(&fn_type.params);
+                format!(
+                    "import {}{}: func({}){};",
+                    self.name(import.ident),
+                    alias(import.alias),
+                    params,
+                    self.results(fn_type.results),
+                )
+            }
+            Import::ImportFrom(import) => {
+                let items: Vec<_> = import
+                    .items
+                    .iter()
+                    .map(|(name, alias_name)| format!("{}{}", self.name(*name), alias(*alias_name)))
+                    .collect();
+                let items = match items.is_empty() {
+                    true => "{}".to_owned(),
+                    false => format!("{{ {} }}", items.join(", ")),
+                };
+                format!(
+                    "import {} from {}:{}/{};",
+                    items, import.package.namespace, import.package.name, import.interface
+                )
+            }
+        }
+    }
+
+    fn type_def(&mut self, id: TypeDefId, end: usize) {
+        let comp = self.comp;
+        let type_def = comp.get_type_def(id);
+        let header = |keyword: &str| {
+            format!(
+                "{}{} {}",
+                export(type_def.exported()),
+                keyword,
+                comp.get_name(type_def.ident())
+            )
+        };
+        let (header, members): (String, Vec<(NameId, String)>) = match type_def {
+            TypeDefinition::Newtype(newtype) => {
+                self.comments_inside(end);
+                let line = format!("{} = {};", header("newtype"), self.valtype(newtype.inner));
+                return self.line(&line, end);
+            }
+            TypeDefinition::Record(record) => {
+                let fields = record
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let default = match field.default {
+                            Some(default) => format!(" = {}", self.expression(default, 1)),
+                            None => String::new(),
+                        };
+                        let line = format!(
+                            "{}: {}{},",
+                            self.name(field.ident),
+                            self.valtype(field.type_id),
+                            default
+                        );
+                        (field.ident, line)
+                    })
+                    .collect();
+                (header("struct"), fields)
+            }
+            TypeDefinition::Enum(enum_def) => {
+                let cases = enum_def
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let discriminant = match case.discriminant {
+                            Some(value) => format!(" = {}", self.expression(value, 1)),
+                            None => String::new(),
+                        };
+                        (
+                            case.ident,
+                            format!("{}{},", self.name(case.ident), discriminant),
+                        )
+                    })
+                    .collect();
+                (header("enum"), cases)
+            }
+        };
+        self.indent_line(&header);
+
+        let open = self.brace_after(comp.name_span(type_def.ident()).end());
+        let close = end.saturating_sub(1);
+        if members.is_empty() && !self.has_comment_before(close) {
+            self.output.push_str(" {}");
+            return self.finish_line(end);
+        }
+        self.output.push_str(" {");
+        self.finish_line(open);
+
+        self.indented(|printer| {
+            for (index, (name, line)) in members.iter().enumerate() {
+                let start = comp.name_span(*name).start();
+                let member_end = match members.get(index + 1) {
+                    Some((next, _)) => printer.end_before(comp.name_span(*next).start()),
+                    None => printer.end_before(close),
+                };
+                printer.comments_before(start);
+                printer.blank_line(start);
+                printer.line(line, member_end);
+            }
+            printer.comments_before(close);
+        });
+        self.indent_line("}");
+        self.finish_line(end);
+    }
+
+    /// Print a function whose body is closed by the `}` at `close`
+    fn function(&mut self, id: FunctionId, close: usize) {
+        let comp = self.comp;
+        let function = comp.get_function(id);
+        let header = format!(
+            "{}func {}({}){}",
+            export(function.exported),
+            self.name(function.ident),
+            self.params(&function.params),
+            self.results(function.results),
+        );
+        self.indent_line(&header);
+        let open = self.brace_after(comp.name_span(function.ident).end());
+        self.block(&function.body, open, close);
+    }
+
+    /// Print ` { statements }` with the braces at `open` and `close`,
+    /// ending the line of the closing brace
+    fn block(&mut self, statements: &[StatementId], open: usize, close: usize) {
+        if statements.is_empty() && !self.has_comment_before(close) {
+            self.output.push_str(" {}");
+            return self.finish_line(close + 1);
+        }
+        self.output.push_str(" {");
+        self.finish_line(open);
+        self.indented(|printer| {
+            for statement in statements {
+                printer.statement(*statement);
+            }
+            printer.comments_before(close);
+        });
+        self.indent_line("}");
+        self.finish_line(close + 1);
+    }
+
+    fn statement(&mut self, id: StatementId) {
+        let comp = self.comp;
+        let span = comp.statement_span(id);
+        self.comments_before(span.start());
+        self.blank_line(span.start());
+
+        let line = match comp.get_statement(id) {
+            Statement::If(if_statement) => {
+                let condition = self.expression(if_statement.condition, self.depth);
+                self.indent_line(&format!("if {}", condition));
+                let condition_end = comp.expression_span(if_statement.condition).end();
+                let open = self.brace_after(condition_end);
+                return self.block(&if_statement.block, open, span.end().saturating_sub(1));
+            }
+            Statement::Func(nested) => {
+                return self.function(nested.function, span.end().saturating_sub(1))
+            }
+            Statement::Let(let_statement) => {
+                let annotation = match let_statement.annotation {
+                    Some(annotation) => format!(": {}", self.valtype(annotation)),
+                    None => String::new(),
+                };
+                format!(
+                    "let {}{}{} = {};",
+                    mutable(let_statement.mutable),
+                    self.name(let_statement.ident),
+                    annotation,
+                    self.expression(let_statement.expression, self.depth),
+                )
+            }
+            Statement::Assign(assign) => format!(
+                "{} = {};",
+                self.name(assign.ident),
+                self.expression(assign.expression, self.depth)
+            ),
+            Statement::Call(call) => {
+                let args: Vec<_> = call
+                    .args
+                    .iter()
+                    .map(|arg| self.expression(*arg, self.depth))
+                    .collect();
+                // Method calls are kept as calls with the receiver first
+                let is_method = comp.name_span(call.ident).start() > span.start();
+                match (is_method, call.args.first()) {
+                    (true, Some(receiver)) => format!(
+                        "{}.{}({});",
+                        self.receiver(*receiver, self.depth),
+                        self.name(call.ident),
+                        args[1..].join(", ")
+                    ),
+                    _ => format!("{}({});", self.name(call.ident), args.join(", ")),
+                }
+            }
+            Statement::Return(return_statement) => match return_statement.expression {
+                // The trailing expression of a body is written without `return`
+                Some(expression) if comp.expression_span(expression) == span => {
+                    self.statement_expression(expression)
+                }
+                Some(expression) => {
+                    format!("return {};", self.expression(expression, self.depth))
+                }
+                None => "return;".to_owned(),
+            },
+            Statement::Expression(statement) => {
+                format!("{};", self.statement_expression(statement.expression))
+            }
+        };
+        self.comments_inside(span.end());
+        self.indent_line(&line);
+        self.finish_line(span.end());
+    }
+
+    /// An expression at the start of a statement, where `if` would start an if statement
+    fn statement_expression(&self, id: ExpressionId) -> String {
+        let expression = self.expression(id, self.depth);
+        match self.comp.get_expression(id) {
+            Expression::If(_) => format!("({})", expression),
+            _ => expression,
+        }
+    }
+
+    /// Print an expression whose lines after the first are indented `indent` times
+    fn expression(&self, id: ExpressionId, indent: usize) -> String {
+        let comp = self.comp;
+        let list = |ids: &[ExpressionId]| {
+            ids.iter()
+                .map(|id| self.expression(*id, indent))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match comp.get_expression(id) {
+            Expression::Identifier(identifier) => self.name(identifier.ident).to_owned(),
+            Expression::Enum(literal) => format!(
+                "{}::{}",
+                self.name(literal.enum_name),
+                self.name(literal.case_name)
+            ),
+            Expression::Literal(literal) => {
+                // Keep the literal as it was written, e.g. in hex or with escapes
+                let written = self.comp.span_text(comp.expression_span(id));
+                if !written.is_empty() {
+                    return written.to_owned();
+                }
+                match literal {
+                    ast::Literal::Integer(value) => value.to_string(),
+                    ast::Literal::Float(value) => format!("{:?}", value),
+                    ast::Literal::String(value) => quote(value),
+                }
+            }
+            Expression::Call(call) => format!("{}({})", self.name(call.ident), list(&call.args)),
+            Expression::MethodCall(call) => format!(
+                "{}.{}({})",
+                self.receiver(call.receiver, indent),
+                self.name(call.ident),
+                list(&call.args)
+            ),
+            Expression::Field(access) => format!(
+                "{}.{}",
+                self.receiver(access.record, indent),
+                self.name(access.field)
+            ),
+            Expression::Index(index) => format!(
+                "{}[{}]",
+                self.receiver(index.list, indent),
+                self.expression(index.index, indent)
+            ),
+            Expression::Slice(slice) => {
+                let bound = |bound: Option<ExpressionId>| match bound {
+                    Some(bound) => self.expression(bound, indent),
+                    None => String::new(),
+                };
+                format!(
+                    "{}[{}..{}]",
+                    self.receiver(slice.list, indent),
+                    bound(slice.start),
+                    bound(slice.end)
+                )
+            }
+            Expression::Cast(cast) => {
+                let inner = self.expression(cast.inner, indent);
+                let inner = match comp.get_expression(cast.inner) {
+                    Expression::Binary(binary) if !binary.parenthesized => format!("({})", inner),
+                    Expression::If(_) | Expression::Match(_) => format!("({})", inner),
+                    _ => inner,
+                };
+                format!("{} as {}", inner, self.valtype(cast.target))
+            }
+            Expression::Unary(unary) => {
+                let inner = self.expression(unary.inner, indent);
+                let inner = match comp.get_expression(unary.inner) {
+                    Expression::Binary(binary) if !binary.parenthesized => format!("({})", inner),
+                    Expression::Cast(_) => format!("({})", inner),
+                    _ => inner,
+                };
+                let op = match unary.op {
+                    ast::UnaryOp::Negate => "-",
+                    ast::UnaryOp::LogicalNot => "!",
+                    ast::UnaryOp::Invert => "~",
+                };
+                format!("{}{}", op, inner)
+            }
+            Expression::Binary(binary) => self.binary(binary, indent),
+            Expression::Record(record) => {
+                let mut fields: Vec<_> = record
+                    .fields
+                    .iter()
+                    .map(|(name, value)| {
+                        format!(
+                            "{}: {}",
+                            self.name(*name),
+                            self.expression(*value, indent + 1)
+                        )
+                    })
+                    .collect();
+                if let Some(base) = record.base {
+                    fields.push(format!("..{}", self.expression(base, indent + 1)));
+                }
+                let name = self.name(record.ident);
+                let single = format!("{} {{ {} }}", name, fields.join(", "));
+                let nested = record
+                    .fields
+                    .iter()
+                    .map(|(_, value)| *value)
+                    .chain(record.base)
+                    .any(|value| matches!(comp.get_expression(value), Expression::Record(_)));
+                if single.len() <= RECORD_WIDTH && !single.contains('\n') && !nested {
+                    return single;
+                }
+                let mut lines = format!("{} {{\n", name);
+                for (index, field) in fields.iter().enumerate() {
+                    // A base can't be followed by a comma
+                    let comma = match record.base.is_some() && index == fields.len() - 1 {
+                        true => "",
+                        false => ",",
+                    };
+                    lines.push_str(&format!("{}{}{}\n", indentation(indent + 1), field, comma));
+                }
+                lines.push_str(&format!("{}}}", indentation(indent)));
+                lines
+            }
+            Expression::Asm(asm) => {
+                let inputs: Vec<_> = asm
+                    .inputs
+                    .iter()
+                    .map(|input| {
+                        let value = self.expression(input.expression, indent);
+                        match input.annotation {
+                            Some(annotation) => format!(
+                                "{}: {} = {}",
+                                self.name(input.ident),
+                                self.valtype(annotation),
+                                value
+                            ),
+                            None => format!("{} = {}", self.name(input.ident), value),
+                        }
+                    })
+                    .collect();
+                let mut text = format!(
+                    "asm ({}) -> {}",
+                    inputs.join(", "),
+                    self.valtype(asm.result)
+                );
+                if !asm.clobbers.is_empty() {
+                    text.push_str(&format!(" clobbers ({})", self.params(&asm.clobbers)));
+                }
+                text.push_str(" {\n");
+                for line in asm.body.split('\n') {
+                    text.push_str(&format!("{}{}\n", indentation(indent + 1), quote(line)));
+                }
+                text.push_str(&format!("{}}}", indentation(indent)));
+                text
+            }
+            Expression::If(if_expression) => {
+                let else_value = match comp.get_expression(if_expression.else_value) {
+                    Expression::If(_) => self.expression(if_expression.else_value, indent),
+                    _ => format!(
+                        "{{ {} }}",
+                        self.expression(if_expression.else_value, indent)
+                    ),
+                };
+                format!(
+                    "if {} {{ {} }} else {}",
+                    self.expression(if_expression.condition, indent),
+                    self.expression(if_expression.then_value, indent),
+                    else_value
+                )
+            }
+            Expression::Match(match_expression) => {
+                let mut text = format!(
+                    "match {} {{\n",
+                    self.expression(match_expression.scrutinee, indent)
+                );
+                for arm in match_expression.arms.iter() {
+                    let pattern = match &arm.pattern {
+                        Pattern::Case {
+                            enum_name,
+                            case_name,
+                        } => format!("{}::{}", self.name(*enum_name), self.name(*case_name)),
+                        Pattern::Wildcard(_) => "_".to_owned(),
+                        Pattern::Binding(ident) => self.name(*ident).to_owned(),
+                    };
+                    let guard = match arm.guard {
+                        Some(guard) => format!(" if {}", self.expression(guard, indent + 1)),
+                        None => String::new(),
+                    };
+                    text.push_str(&format!(
+                        "{}{}{} => {},\n",
+                        indentation(indent + 1),
+                        pattern,
+                        guard,
+                        self.expression(arm.value, indent + 1)
+                    ));
+                }
+                text.push_str(&format!("{}}}", indentation(indent)));
+                text
+            }
+        }
+    }
+
+    /// Print a binary expression, parenthesizing operands which
+    /// would otherwise be parsed as part of a different expression
+    fn binary(&self, binary: &BinaryExpression, indent: usize) -> String {
+        let comp = self.comp;
+        let (l_bp, r_bp) = infix_binding_power(binary.op);
+
+        let left = self.expression(binary.left, indent);
+        let left = match comp.get_expression(binary.left) {
+            // The left operand's operator would take this one's left operand
+            Expression::Binary(left_binary) if !left_binary.parenthesized => {
+                let (_, left_r_bp) = infix_binding_power(left_binary.op);
+                match l_bp >= left_r_bp {
+                    true => format!("({})", left),
+                    false => left,
+                }
+            }
+            Expression::If(_) | Expression::Match(_) => format!("({})", left),
+            _ => left,
+        };
+
+        let right = self.expression(binary.right, indent);
+        let right = match comp.get_expression(binary.right) {
+            // This operator would take the right operand's left operand
+            Expression::Binary(right_binary) if !right_binary.parenthesized => {
+                let (right_l_bp, _) = infix_binding_power(right_binary.op);
+                match right_l_bp < r_bp {
+                    true => format!("({})", right),
+                    false => right,
+                }
+            }
+            _ => right,
+        };
+
+        let text = format!("{} {} {}", left, binary.op.symbol(), right);
+        match binary.parenthesized {
+            true => format!("({})", text),
+            false => text,
+        }
+    }
+
+    /// Print the value that indexing, member access or a method call applies to
+    fn receiver(&self, id: ExpressionId, indent: usize) -> String {
+        let text = self.expression(id, indent);
+        match self.comp.get_expression(id) {
+            Expression::Binary(binary) if binary.parenthesized => text,
+            Expression::Binary(_)
+            | Expression::Unary(_)
+            | Expression::Cast(_)
+            | Expression::If(_)
+            | Expression::Match(_) => format!("({})", text),
+            _ => text,
+        }
+    }
+
+    fn valtype(&self, id: TypeId) -> String {
+        let comp = self.comp;
+        match comp.get_type(id) {
+            ValType::Primitive(primitive) => primitive_name(*primitive).to_owned(),
+            ValType::List(element) => {
+                // `bytes` gives its element type its own span
+                let is_bytes = matches!(
+                    comp.get_type(*element),
+                    ValType::Primitive(PrimitiveType::U8)
+                ) && comp.type_span(*element) == comp.type_span(id)
+                    && !comp.type_span(id).is_empty();
+                match is_bytes {
+                    true => "bytes".to_owned(),
+                    false => format!("list<{}>", self.valtype(*element)),
+                }
+            }
+            ValType::Option(inner) => format!("option<{}>", self.valtype(*inner)),
+            ValType::Result(result) => format!(
+                "result<{}, {}>",
+                self.valtype(result.ok),
+                self.valtype(result.err)
+            ),
+            ValType::Named(name) => self.name(*name).to_owned(),
+            ValType::Func(func_type) => {
+                let params: Vec<_> = func_type
+                    .params
+                    .iter()
+                    .map(|param| self.valtype(*param))
+                    .collect();
+                format!(
+                    "func({}){}",
+                    params.join(", "),
+                    self.results(func_type.results)
+                )
+            }
+        }
+    }
+
+    fn params(&self, params: &[(NameId, TypeId)]) -> String {
+        params
+            .iter()
+            .map(|(name, valtype)| format!("{}: {}", self.name(*name), self.valtype(*valtype)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn results(&self, results: Option<TypeId>) -> String {
+        match results {
+            Some(results) => format!(" -> {}", self.valtype(results)),
+            None => String::new(),
+        }
+    }
+
+    fn name(&self, name: NameId) -> &'a str {
+        self.comp.get_name(name)
+    }
+
+    // Lines and comments
+
+    /// Run `print` with lines indented one more level
+    fn indented(&mut self, print: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        print(self);
+        self.depth -= 1;
+    }
+
+    /// Start a new line at the current depth
+    fn indent_line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.output.push_str(INDENT);
+        }
+        self.output.push_str(text);
+    }
+
+    /// Print a whole line whose source ends at `end`
+    fn line(&mut self, text: &str, end: usize) {
+        self.indent_line(text);
+        self.finish_line(end);
+    }
+
+    /// End the current line, whose source ends at `end`,
+    /// along with the comment which follows it on the same line
+    fn finish_line(&mut self, end: usize) {
+        if let Some(&(start, comment_end)) = self.comments.get(self.next_comment) {
+            if start >= end && !self.text[end..start].contains('\n') {
+                self.output.push(' ');
+                self.output
+                    .push_str(self.text[start..comment_end].trim_end());
+                self.next_comment += 1;
+            }
+        }
+        self.output.push('\n');
+    }
+
+    /// Keep a blank line from the source before what starts at `position`,
+    /// except at the start of the file or a block
+    fn blank_line(&mut self, position: usize) {
+        if self.output.is_empty() || self.output.ends_with("{\n") || self.output.ends_with("\n\n") {
+            return;
+        }
+        let before = &self.text[..position.min(self.text.len())];
+        let content = before.trim_end_matches(|c: char| c.is_ascii_whitespace());
+        if before[content.len()..].matches('\n').count() > 1 {
+            self.output.push('\n');
+        }
+    }
+
+    /// Print the comments which start before `position` on their own lines
+    fn comments_before(&mut self, position: usize) {
+        while let Some(&(start, _)) = self.comments.get(self.next_comment) {
+            if start >= position {
+                break;
+            }
+            self.blank_line(start);
+            self.comment();
+        }
+    }
+
+    /// Print the comments inside what ends at `end` before it, since
+    /// it's printed without the line breaks the comments end
+    fn comments_inside(&mut self, end: usize) {
+        while let Some(&(start, _)) = self.comments.get(self.next_comment) {
+            if start >= end {
+                break;
+            }
+            self.comment();
+        }
+    }
+
+    fn comment(&mut self) {
+        let (start, end) = self.comments[self.next_comment];
+        self.next_comment += 1;
+        let text = self.text[start..end].trim_end();
+        self.indent_line(text);
+        self.output.push('\n');
+    }
+
+    fn has_comment_before(&self, position: usize) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|(start, _)| *start < position)
+    }
+
+    // Tokens
+
+    /// The index of the first token starting at or after `offset`
+    fn token_at(&self, offset: usize) -> usize {
+        self.tokens
+            .partition_point(|token| token.span.start() < offset)
+    }
+
+    fn token_end(&self, index: usize) -> usize {
+        self.tokens[index].span.end()
+    }
+
+    /// The end of the last token starting before `offset`
+    fn end_before(&self, offset: usize) -> usize {
+        match self.token_at(offset) {
+            0 => 0,
+            index => self.token_end(index - 1),
+        }
+    }
+
+    /// The end of the first `{` after `offset`
+    fn brace_after(&self, offset: usize) -> usize {
+        self.tokens[self.token_at(offset)..]
+            .iter()
+            .find(|token| token.token == Token::LBrace)
+            .map_or(offset, |token| token.span.end())
+    }
+}
+
+fn export(exported: bool) -> &'static str {
+    match exported {
+        true => "export ",
+        false => "",
+    }
+}
+
+fn mutable(mutable: bool) -> &'static str {
+    match mutable {
+        true => "mut ",
+        false => "",
+    }
+}
+
+fn indentation(indent: usize) -> String {
+    INDENT.repeat(indent)
+}
+
+fn primitive_name(primitive: PrimitiveType) -> &'static str {
+    match primitive {
+        PrimitiveType::Bool => "bool",
+        PrimitiveType::U8 => "u8",
+        PrimitiveType::S8 => "s8",
+        PrimitiveType::U16 => "u16",
+        PrimitiveType::S16 => "s16",
+        PrimitiveType::U32 => "u32",
+        PrimitiveType::S32 => "s32",
+        PrimitiveType::U64 => "u64",
+        PrimitiveType::S64 => "s64",
+        PrimitiveType::F32 => "f32",
+        PrimitiveType::F64 => "f64",
+        PrimitiveType::String => "string",
+    }
+}
+
+/// Write a string literal which the lexer reads back as `value`
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{
+        BinaryOp, Cast, ContextEq, Global, IdRemap, IgnoreSpans, Literal, Span, UnaryExpression,
+        UnaryOp,
+    };
+    use crate::{parse, tokenize, LangVersion, Tokens};
+    use claw_common::{make_source, Source, UnwrapPretty};
+    use miette::Report;
+
+    use std::fs;
+
+    /// Parse the tokens, failing on the first error
+    fn parse_ok(src: Source, tokens: Tokens) -> Component {
+        let (comp, errors) = parse(src, tokens, LangVersion::LATEST);
+        if let Some(error) = errors.into_iter().next() {
+            panic!("{:?}", Report::new(error));
+        }
+        comp
+    }
+
+    /// The items of the component in the order they're printed,
+    /// nested functions are compared as part of the function they're in
+    fn items(comp: &Component) -> Vec<Item> {
+        let imports = comp.iter_imports().map(|(id, _)| Item::Import(id));
+        let type_defs = comp.iter_type_defs().map(|(id, _)| Item::TypeDef(id));
+        let globals = comp.iter_globals().map(|(id, _)| Item::Global(id));
+        let functions = comp
+            .iter_functions()
+            .filter(|(_, function)| !function.nested)
+            .map(|(id, _)| Item::Function(id));
+        imports
+            .chain(type_defs)
+            .chain(globals)
+            .chain(functions)
+            .collect()
+    }
+
+    fn remap_item(item: Item, remap: &IdRemap) -> Item {
+        match item {
+            Item::Import(id) => Item::Import(remap.import(id)),
+            Item::TypeDef(id) => Item::TypeDef(remap.type_def(id)),
+            Item::Global(id) => Item::Global(remap.global(id)),
+            Item::Function(id) => Item::Function(remap.function(id)),
+        }
+    }
+
+    fn item_eq(left: Item, right: Item, context: &IgnoreSpans) -> bool {
+        match (left, right) {
+            (Item::Import(left), Item::Import(right)) => left.context_eq(&right, context),
+            (Item::TypeDef(left), Item::TypeDef(right)) => left.context_eq(&right, context),
+            (Item::Global(left), Item::Global(right)) => left.context_eq(&right, context),
+            (Item::Function(left), Item::Function(right)) => left.context_eq(&right, context),
+            _ => false,
+        }
+    }
+
+    /// Print the component and check that parsing the output
+    /// gives back the same items, wherever they were written
+    fn assert_round_trips(mut comp: Component, name: &str) {
+        let printed = print_component(&comp);
+        let src = make_source(name, &printed);
+        let tokens = tokenize(src.clone(), &printed).unwrap_pretty();
+        let reparsed = parse_ok(src, tokens);
+
+        let expected = items(&comp);
+        let found = items(&reparsed);
+        assert_eq!(
+            expected.len(),
+            found.len(),
+            "{} printed as\n{}",
+            name,
+            printed
+        );
+
+        // Both have to be in one component to be compared
+        let remap = comp.append(reparsed);
+        let context = IgnoreSpans(&comp);
+        for (left, right) in expected.into_iter().zip(found) {
+            let right = remap_item(right, &remap);
+            assert!(
+                item_eq(left, right, &context),
+                "{} printed as\n{}",
+                name,
+                printed
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trip_programs() {
+        for dir in ["../lib/tests/programs", "../lib/tests/fmt"] {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension() != Some("claw".as_ref()) {
+                    continue;
+                }
+                let name = path.file_name().unwrap().to_string_lossy().to_string();
+                let source_code = fs::read_to_string(&path).unwrap();
+                let src = make_source(&name, &source_code);
+                let tokens = tokenize(src.clone(), &source_code).unwrap_pretty();
+                let comp = parse_ok(src, tokens);
+                assert_round_trips(comp, &name);
+            }
+        }
+    }
+
+    type Build = fn(&mut Component) -> ExpressionId;
+
+    /// Add `let value: u32 = <init_value>;` to the component
+    fn push_global(comp: &mut Component, init_value: ExpressionId) {
+        let symbol = comp.intern("value");
+        let ident = comp.new_name(symbol, Span::default());
+        let type_id = comp.new_type(ValType::Primitive(PrimitiveType::U32), Span::default());
+        comp.push_global(Global {
+            exported: false,
+            mutable: false,
+            ident,
+            type_id,
+            init_value,
+        });
+    }
+
+    /// Print `let value: u32 = <expression>;` for an expression built by `build`
+    fn print_global(build: Build) -> String {
+        let mut comp = Component::new(make_source("test", ""));
+        let init_value = build(&mut comp);
+        push_global(&mut comp, init_value);
+        print_component(&comp)
+    }
+
+    fn literal(comp: &mut Component, value: u64) -> ExpressionId {
+        comp.new_expression(Literal::Integer(value).into(), Span::default())
+    }
+
+    fn binary(
+        comp: &mut Component,
+        op: BinaryOp,
+        left: ExpressionId,
+        right: ExpressionId,
+    ) -> ExpressionId {
+        comp.new_binary_expression(BinaryExpression {
+            op,
+            left,
+            right,
+            parenthesized: false,
+        })
+    }
+
+    fn unary(comp: &mut Component, op: UnaryOp, inner: ExpressionId) -> ExpressionId {
+        comp.new_expression(UnaryExpression { op, inner }.into(), Span::default())
+    }
+
+    const BINARY_OPS: [BinaryOp; 24] = [
+        BinaryOp::Multiply,
+        BinaryOp::Divide,
+        BinaryOp::Modulo,
+        BinaryOp::Add,
+        BinaryOp::Subtract,
+        BinaryOp::WrappingAdd,
+        BinaryOp::WrappingSubtract,
+        BinaryOp::WrappingMultiply,
+        BinaryOp::SaturatingAdd,
+        BinaryOp::SaturatingSubtract,
+        BinaryOp::BitShiftL,
+        BinaryOp::BitShiftR,
+        BinaryOp::ArithShiftR,
+        BinaryOp::LessThan,
+        BinaryOp::LessThanEqual,
+        BinaryOp::GreaterThan,
+        BinaryOp::GreaterThanEqual,
+        BinaryOp::Equals,
+        BinaryOp::NotEquals,
+        BinaryOp::BitOr,
+        BinaryOp::BitXor,
+        BinaryOp::BitAnd,
+        BinaryOp::LogicalOr,
+        BinaryOp::LogicalAnd,
+    ];
+
+    const UNARY_OPS: [UnaryOp; 3] = [UnaryOp::Negate, UnaryOp::LogicalNot, UnaryOp::Invert];
+
+    /// Trees nesting every pair of operators on both sides of each other,
+    /// alone and under each unary operator, so that the printer's
+    /// parentheses are checked against every entry of the precedence table
+    #[test]
+    fn test_round_trip_precedence() {
+        for outer in BINARY_OPS {
+            for inner in BINARY_OPS {
+                let mut comp = Component::new(make_source("generated", ""));
+                let (a, b, c) = (
+                    literal(&mut comp, 1),
+                    literal(&mut comp, 2),
+                    literal(&mut comp, 3),
+                );
+                let left = binary(&mut comp, inner, a, b);
+                let tree = binary(&mut comp, outer, left, c);
+                push_global(&mut comp, tree);
+
+                let (a, b, c) = (
+                    literal(&mut comp, 1),
+                    literal(&mut comp, 2),
+                    literal(&mut comp, 3),
+                );
+                let right = binary(&mut comp, inner, b, c);
+                let tree = binary(&mut comp, outer, a, right);
+                push_global(&mut comp, tree);
+
+                for op in UNARY_OPS {
+                    let (a, b, c) = (
+                        literal(&mut comp, 1),
+                        literal(&mut comp, 2),
+                        literal(&mut comp, 3),
+                    );
+                    let left = binary(&mut comp, inner, a, b);
+                    let left = unary(&mut comp, op, left);
+                    let tree = binary(&mut comp, outer, left, c);
+                    push_global(&mut comp, tree);
+
+                    let (a, b, c) = (
+                        literal(&mut comp, 1),
+                        literal(&mut comp, 2),
+                        literal(&mut comp, 3),
+                    );
+                    let b = unary(&mut comp, op, b);
+                    let right = binary(&mut comp, inner, b, c);
+                    let tree = binary(&mut comp, outer, a, right);
+                    let tree = unary(&mut comp, op, tree);
+                    push_global(&mut comp, tree);
+                }
+
+                let name = format!("{} {}", outer.symbol(), inner.symbol());
+                assert_round_trips(comp, &name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parenthesizes_by_precedence() {
+        let cases: [(Build, &str); 5] = [
+            (
+                |comp| {
+                    let (a, b, c) = (literal(comp, 1), literal(comp, 2), literal(comp, 3));
+                    let sum = binary(comp, BinaryOp::Add, a, b);
+                    binary(comp, BinaryOp::Multiply, sum, c)
+                },
+                "(1 + 2) * 3",
+            ),
+            (
+                |comp| {
+                    let (a, b, c) = (literal(comp, 1), literal(comp, 2), literal(comp, 3));
+                    let product = binary(comp, BinaryOp::Multiply, b, c);
+                    binary(comp, BinaryOp::Add, a, product)
+                },
+                "1 + 2 * 3",
+            ),
+            (
+                |comp| {
+                    let (a, b, c) = (literal(comp, 1), literal(comp, 2), literal(comp, 3));
+                    let difference = binary(comp, BinaryOp::Subtract, a, b);
+                    binary(comp, BinaryOp::Subtract, difference, c)
+                },
+                "1 - 2 - 3",
+            ),
+            (
+                |comp| {
+                    let (a, b, c) = (literal(comp, 1), literal(comp, 2), literal(comp, 3));
+                    let difference = binary(comp, BinaryOp::Subtract, b, c);
+                    binary(comp, BinaryOp::Subtract, a, difference)
+                },
+                "1 - (2 - 3)",
+            ),
+            (
+                |comp| {
+                    let a = literal(comp, 1);
+                    let target =
+                        comp.new_type(ValType::Primitive(PrimitiveType::U32), Span::default());
+                    let inner =
+                        comp.new_expression(Cast { inner: a, target }.into(), Span::default());
+                    let op = UnaryOp::Negate;
+                    comp.new_expression(UnaryExpression { op, inner }.into(), Span::default())
+                },
+                "-(1 as u32)",
+            ),
+        ];
+        for (build, expected) in cases {
+            let expected = format!("let value: u32 = {};\n", expected);
+            assert_eq!(print_global(build), expected);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Parsing a source file as it is edited, for editors and language servers.
+
+use std::ops::Range;
+
+use ast::{Component, Node};
+use claw_ast as ast;
+use claw_common::{make_source, Source};
+
+use crate::component::parse_item;
+use crate::lexer::{tokenize, tokenize_part, LexerError, Tokens};
+use crate::{parse, Checkpoint, LangVersion, ParseInput, ParserError, Sync};
+
+/// A source file which keeps its tokens and syntax tree between edits.
+///
+/// Each top-level item is parsed into a component of its own, so an edit
+/// inside an item only tokenizes and parses that item again and moves the
+/// spans of the items after it. Edits which reach outside a single item,
+/// or change where it ends, parse the whole file again.
+#[derive(Debug)]
+pub struct Session {
+    src: Source,
+    version: LangVersion,
+    tokens: Tokens,
+    items: Vec<Item>,
+}
+
+#[derive(Debug)]
+struct Item {
+    /// The tokens of the item, including any skipped after an error
+    tokens: Range<usize>,
+    /// The item and the functions nested in it
+    component: Component,
+    errors: Vec<ParserError>,
+}
+
+impl Session {
+    /// Tokenize and parse a source file
+    pub fn new(src: Source, version: LangVersion) -> Result<Self, LexerError> {
+        let tokens = tokenize(src.clone(), src.inner())?;
+        let mut session = Session {
+            src,
+            version,
+            tokens,
+            items: Vec::new(),
+        };
+        session.parse_all();
+        Ok(session)
+    }
+
+    /// The source code as of the last edit
+    pub fn source(&self) -> Source {
+        self.src.clone()
+    }
+
+    /// Replace the bytes in `range` with `text` and parse the result.
+    ///
+    /// When the new code can't be tokenized the error is returned and the
+    /// session is left empty until an edit fixes it.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> Result<(), LexerError> {
+        let mut source_code = self.src.inner().clone();
+        source_code.replace_range(range.clone(), text);
+        self.src = make_source(self.src.name(), &source_code);
+        let delta = text.len() as isize - range.len() as isize;
+
+        if let Some(index) = self.item_containing(&range) {
+            if self.reparse_item(index, delta) {
+                return Ok(());
+            }
+        }
+
+        self.tokens = Tokens::default();
+        self.items.clear();
+        self.tokens = tokenize(self.src.clone(), self.src.inner())?;
+        self.parse_all();
+        Ok(())
+    }
+
+    /// The errors found in every item
+    pub fn errors(&self) -> impl Iterator<Item = &ParserError> {
+        self.items.iter().flat_map(|item| &item.errors)
+    }
+
+    /// The component of each top-level item in source order.
+    ///
+    /// Their spans are kept up to date with the edits, but an item which
+    /// is before every edit since it was parsed keeps the source it was
+    /// parsed from, which is the same up to the end of the item.
+    pub fn items(&self) -> impl Iterator<Item = &Component> {
+        self.items.iter().map(|item| &item.component)
+    }
+
+    /// Find the innermost node covering the byte at `offset`
+    /// and the component of the item it is in
+    pub fn node_at(&self, offset: usize) -> Option<(&Component, Node)> {
+        let item = self.items.iter().find(|item| {
+            let bytes = self.bytes(item);
+            bytes.start <= offset && offset < bytes.end
+        })?;
+        let node = item.component.node_at(offset)?;
+        Some((&item.component, node))
+    }
+
+    /// Parse every item into one component, such as for resolving it.
+    ///
+    /// This doesn't tokenize the source again.
+    pub fn component(&self) -> Component {
+        let (component, _) = parse(self.src.clone(), self.tokens.clone(), self.version);
+        component
+    }
+
+    /// The bytes from the start of the item's first token to the end of its last
+    fn bytes(&self, item: &Item) -> Range<usize> {
+        let tokens = &self.tokens.tokens[item.tokens.clone()];
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => first.span.start()..last.span.end(),
+            _ => 0..0,
+        }
+    }
+
+    /// The item an edit of `range` is strictly inside, so that the edit
+    /// can't join it to its neighbours.
+    ///
+    /// Items with errors may not end at a token which stops them
+    /// joining the next item, so they aren't picked.
+    fn item_containing(&self, range: &Range<usize>) -> Option<usize> {
+        self.items.iter().position(|item| {
+            let bytes = self.bytes(item);
+            item.errors.is_empty() && bytes.start < range.start && range.end < bytes.end
+        })
+    }
+
+    /// Tokenize and parse the item at `index` again after an edit inside it
+    /// which changed its length by `delta`.
+    ///
+    /// Returns false when the item's new code doesn't tokenize
+    /// the same way on its own, and so the whole file must be.
+    fn reparse_item(&mut self, index: usize, delta: isize) -> bool {
+        let old = self.items[index].tokens.clone();
+        let bytes = self.bytes(&self.items[index]);
+        let end = bytes.end.saturating_add_signed(delta);
+        let symbols = std::mem::take(&mut self.tokens.symbols);
+        let part = match tokenize_part(
+            self.src.clone(),
+            &self.src.inner()[bytes.start..end],
+            bytes.start,
+            symbols,
+        ) {
+            Ok(part) => part,
+            Err(_) => return false,
+        };
+        // A comment or string left open would run on past the item
+        if part.tokens.last().map(|token| token.span.end()) != Some(end) {
+            return false;
+        }
+
+        let added = part.tokens.len();
+        self.tokens.symbols = part.symbols;
+        self.tokens.tokens.splice(old.clone(), part.tokens);
+        for token in &mut self.tokens.tokens[old.start + added..] {
+            token.span = token.span.shifted(delta);
+        }
+
+        let mut input = ParseInput::new(self.src.clone(), &self.tokens)
+            .with_version(self.version)
+            .with_recovery();
+        input.restore(Checkpoint(old.start));
+        let (component, errors) = parse_one(&mut input);
+        if input.index != old.start + added {
+            // The edit changed where the item ends, which changes the items after it
+            self.parse_all();
+            return true;
+        }
+
+        let moved = added as isize - old.len() as isize;
+        for item in &mut self.items[index + 1..] {
+            let start = item.tokens.start.saturating_add_signed(moved);
+            let end = item.tokens.end.saturating_add_signed(moved);
+            item.tokens = start..end;
+            item.component.shift(self.src.clone(), delta);
+            for error in &mut item.errors {
+                shift_error(error, &self.src, delta);
+            }
+        }
+        self.items[index] = Item {
+            tokens: old.start..old.start + added,
+            component,
+            errors,
+        };
+        true
+    }
+
+    /// Parse every item from the tokens
+    fn parse_all(&mut self) {
+        let mut input = ParseInput::new(self.src.clone(), &self.tokens)
+            .with_version(self.version)
+            .with_recovery();
+        let mut items = Vec::new();
+        while !input.done() {
+            let start = input.index;
+            let (component, errors) = parse_one(&mut input);
+            items.push(Item {
+                tokens: start..input.index,
+                component,
+                errors,
+            });
+        }
+        self.items = items;
+    }
+}
+
+/// Parse the next item into a component of its own,
+/// skipping the rest of it after an error
+fn parse_one(input: &mut ParseInput) -> (Component, Vec<ParserError>) {
+    let mut component = input.new_component();
+    let start = input.checkpoint();
+    let result = parse_item(input, &mut component);
+    if let Err(error) = input.recover(result, start, Sync::Item) {
+        // The input ran out
+        input.errors.push(error);
+        input.index = input.tokens.len();
+    }
+    (component, std::mem::take(&mut input.errors))
+}
+
+/// Move the spans of an error from an item after an edit
+fn shift_error(error: &mut ParserError, new_src: &Source, delta: isize) {
+    match error {
+        ParserError::Base { src, span }
+        | ParserError::UnexpectedToken { src, span, .. }
+        | ParserError::FeatureVersion { src, span, .. } => {
+            *src = new_src.clone();
+            *span = span.shifted(delta);
+        }
+        ParserError::EndOfInput | ParserError::NotYetSupported { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{print_component, tokenize};
+
+    const SOURCE: &str = "\
+export func first(a: u32) -> u32 {
+    return a + 1;
+}
+
+let mut counter: u32 = 0;
+
+func second() -> u32 {
+    return counter * 2;
+}
+";
+
+    fn session(source: &str) -> Session {
+        Session::new(make_source("test", source), LangVersion::LATEST).unwrap()
+    }
+
+    /// The session parses to the same thing as the whole of its source would
+    fn assert_fresh(session: &Session) {
+        let src = session.source();
+        let tokens = tokenize(src.clone(), src.inner()).unwrap();
+        let (fresh, errors) = parse(src.clone(), tokens, LangVersion::LATEST);
+        assert_eq!(
+            print_component(&session.component()),
+            print_component(&fresh)
+        );
+        assert_eq!(session.errors().count(), errors.len());
+    }
+
+    fn node_text(session: &Session, offset: usize) -> &str {
+        let (component, node) = session.node_at(offset).unwrap();
+        component.span_text(component.node_span(node))
+    }
+
+    #[test]
+    fn test_edit_inside_function() {
+        let mut session = session(SOURCE);
+        let one = SOURCE.find("1;").unwrap();
+        assert_eq!(session.item_containing(&(one..one + 1)), Some(0));
+
+        session.edit(one..one + 1, "(1 + 20)").unwrap();
+        assert_fresh(&session);
+        assert_eq!(session.items().count(), 3);
+
+        let source = session.source().inner().clone();
+        let twenty = source.find("20").unwrap();
+        assert_eq!(node_text(&session, twenty), "20");
+        let plus = source.find("1 + 20").unwrap() + 2;
+        assert_eq!(node_text(&session, plus), "1 + 20");
+
+        // The items after the edit have moved
+        let counter = source.find("counter * 2").unwrap();
+        assert_eq!(node_text(&session, counter), "counter");
+        let (component, node) = session.node_at(counter).unwrap();
+        assert_eq!(component.node_span(node).start(), counter);
+    }
+
+    #[test]
+    fn test_edit_between_items() {
+        let mut session = session(SOURCE);
+        let global = SOURCE.find("let mut").unwrap();
+        assert_eq!(session.item_containing(&(global..global)), None);
+
+        session.edit(global..global, "func third() {}\n\n").unwrap();
+        assert_fresh(&session);
+        assert_eq!(session.items().count(), 4);
+    }
+
+    #[test]
+    fn test_edit_changing_item_end() {
+        let mut session = session(SOURCE);
+        let end = SOURCE.find("1;\n}").unwrap() + 2;
+        // Closing the function early leaves its old closing brace on its own
+        session.edit(end..end, " }").unwrap();
+        assert_fresh(&session);
+        assert_eq!(session.errors().count(), 1);
+    }
+
+    #[test]
+    fn test_edit_errors() {
+        let mut session = session(SOURCE);
+        let one = SOURCE.find("1;").unwrap();
+        session.edit(one..one + 1, "").unwrap();
+        assert_fresh(&session);
+        assert_eq!(session.errors().count(), 1);
+
+        // The item with an error is parsed again with the whole file
+        session.edit(one..one, "1").unwrap();
+        assert_fresh(&session);
+        assert_eq!(session.errors().count(), 0);
+
+        // Errors in items after an edit move with them
+        let counter = SOURCE.find("counter *").unwrap();
+        session.edit(counter..counter + 7, "").unwrap();
+        session.edit(one..one + 1, "100").unwrap();
+        assert_fresh(&session);
+        let error = session.errors().next().unwrap();
+        let star = session.source().inner().find("* 2").unwrap();
+        match error {
+            ParserError::UnexpectedToken { span, .. } => assert_eq!(span.start(), star),
+            error => panic!("Expected an unexpected token but found {:?}", error),
+        }
+    }
+
+    #[test]
+    fn test_edit_adding_comment() {
+        let mut session = session(SOURCE);
+        let one = SOURCE.find("1;").unwrap();
+        session.edit(one..one, "// ").unwrap();
+        assert_fresh(&session);
+        assert!(session.errors().count() > 0);
+    }
+}
@@ -1,19 +1,42 @@
 use ast::{Call, Statement};
 
 use crate::ast::{self, merge, Component, Span, StatementId};
+use crate::component::parse_func;
 use crate::lexer::Token;
 use crate::names::parse_ident;
-use crate::{expressions::parse_expression, types::parse_valtype, ParseInput, ParserError};
+use crate::{
+    expressions::parse_expression, types::parse_valtype, Feature, ParseInput, ParserError, Sync,
+};
 
 pub fn parse_block(
     input: &mut ParseInput,
     comp: &mut Component,
+) -> Result<(Vec<StatementId>, Span), ParserError> {
+    parse_statements(input, comp, false)
+}
+
+/// Parse the body of a function, which returns its trailing expression
+/// without a `;` when the function has results
+pub fn parse_body(
+    input: &mut ParseInput,
+    comp: &mut Component,
+    has_results: bool,
+) -> Result<(Vec<StatementId>, Span), ParserError> {
+    parse_statements(input, comp, has_results)
+}
+
+fn parse_statements(
+    input: &mut ParseInput,
+    comp: &mut Component,
+    allow_tail: bool,
 ) -> Result<(Vec<StatementId>, Span), ParserError> {
     let start_span = input.assert_next(Token::LBrace, "Left brace '{'")?;
 
     let mut statements = Vec::new();
     while input.peek()?.token != Token::RBrace {
-        statements.push(parse_statement(input, comp)?);
+        let start = input.checkpoint();
+        let result = parse_statement(input, comp, allow_tail);
+        statements.extend(input.recover(result, start, Sync::Statement)?);
     }
 
     let end_span = input.assert_next(Token::RBrace, "Right brace '}'")?;
@@ -22,9 +45,28 @@ pub fn parse_block(
     Ok((statements, span))
 }
 
-pub fn parse_statement(
+/// Whether the next tokens are a call whose arguments are followed by a `;`
+fn at_call_statement(input: &ParseInput) -> bool {
+    let mut depth = 0;
+    let mut n = 1;
+    loop {
+        match input.peekn(n) {
+            Some(Token::LParen) => depth += 1,
+            Some(Token::RParen) => depth -= 1,
+            Some(_) => {}
+            None => return false,
+        }
+        n += 1;
+        if depth == 0 {
+            return input.peekn(n) == Some(&Token::Semicolon);
+        }
+    }
+}
+
+fn parse_statement(
     input: &mut ParseInput,
     comp: &mut Component,
+    allow_tail: bool,
 ) -> Result<StatementId, ParserError> {
     let peek0 = &input.peek()?.token;
     let peek1 = input.peekn(1);
@@ -32,13 +74,59 @@ pub fn parse_statement(
         (Token::Return, _) => parse_return(input, comp),
         (Token::Let, _) => parse_let(input, comp),
         (Token::If, _) => parse_if(input, comp),
-        (Token::Identifier(_), Some(Token::LParen)) => parse_call(input, comp),
-        (Token::Identifier(_), _) => parse_assign(input, comp),
-        _ => {
-            _ = input.next();
-            Err(input.unexpected_token("Invalid statement start"))
+        (Token::Func, _) => parse_nested_func(input, comp),
+        (Token::Identifier(_), Some(Token::LParen)) if at_call_statement(input) => {
+            parse_call(input, comp)
         }
+        (Token::Identifier(_), Some(Token::Assign)) => parse_assign(input, comp),
+        _ => parse_expression_statement(input, comp, allow_tail),
+    }
+}
+
+/// An expression evaluated for its side effects, or the trailing
+/// expression of a function body which is returned from it
+fn parse_expression_statement(
+    input: &mut ParseInput,
+    comp: &mut Component,
+    allow_tail: bool,
+) -> Result<StatementId, ParserError> {
+    let expression = parse_expression(input, comp)?;
+    let start_span = comp.expression_span(expression);
+    if let Some(end_span) = input.next_if(Token::Semicolon) {
+        let span = merge(&start_span, &end_span);
+        // A method call is a call statement, which may not return anything
+        if let ast::Expression::MethodCall(call) = comp.get_expression(expression) {
+            let call = call.as_call();
+            return Ok(comp.new_statement(ast::Statement::Call(call), span));
+        }
+        let statement = ast::ExpressionStatement { expression };
+        input.require(Feature::ExpressionStatements, span)?;
+        return Ok(comp.new_statement(ast::Statement::Expression(statement), span));
+    }
+    if !allow_tail {
+        input.assert_next(Token::Semicolon, "Semicolon ';'")?;
+    }
+    if input.peek()?.token != Token::RBrace {
+        _ = input.next();
+        return Err(input.unexpected_token(
+            "Only the last expression of a function body can leave out the ';'",
+        ));
     }
+    input.require(Feature::TailExpressions, start_span)?;
+    let statement = ast::Return {
+        expression: Some(expression),
+    };
+    Ok(comp.new_statement(ast::Statement::Return(statement), start_span))
+}
+
+fn parse_nested_func(
+    input: &mut ParseInput,
+    comp: &mut Component,
+) -> Result<StatementId, ParserError> {
+    input.require(Feature::NestedFunctions, input.peek()?.span)?;
+    let (function, span) = parse_func(input, comp, false, true)?;
+    let statement = ast::NestedFunc { function };
+    Ok(comp.new_statement(ast::Statement::Func(statement), span))
 }
 
 fn parse_let(input: &mut ParseInput, comp: &mut Component) -> Result<StatementId, ParserError> {
@@ -142,13 +230,13 @@ mod tests {
     use claw_common::UnwrapPretty;
 
     use super::*;
-    use crate::make_input;
+    use crate::{make_input, make_span};
 
     #[test]
     fn test_parse_block_empty() {
         let source = "{}";
-        let (src, mut input) = make_input(source);
-        let mut comp = Component::new(src);
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
         let _assign_stmt = parse_block(&mut input, &mut comp).unwrap_pretty();
         assert!(input.done());
     }
@@ -156,17 +244,104 @@ mod tests {
     #[test]
     fn test_parse_block() {
         let source = "{a = 0;}";
-        let (src, mut input) = make_input(source);
-        let mut comp = Component::new(src);
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
         let _assign_stmt = parse_block(&mut input, &mut comp).unwrap_pretty();
         assert!(input.done());
     }
 
+    #[test]
+    fn test_parse_nested_func() {
+        let source = "{ let a = 1; func double(x: u32) -> u32 { return x * 2; } }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let (statements, _) = parse_block(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        let function = match comp.get_statement(statements[1]) {
+            Statement::Func(nested) => comp.get_function(nested.function),
+            other => panic!("Expected nested function but found {:?}", other),
+        };
+        assert!(function.nested && !function.exported);
+        assert_eq!(comp.get_name(function.ident), "double");
+        assert_eq!(comp.statement_span(statements[1]), make_span(13, 44));
+    }
+
+    #[test]
+    fn test_parse_tail_expression() {
+        let source = "{ let a = f(1); g(a); a * 2 }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let (statements, _) = parse_body(&mut input, &mut comp, true).unwrap_pretty();
+        assert!(input.done());
+
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(
+            comp.get_statement(statements[1]),
+            Statement::Call(_)
+        ));
+        match comp.get_statement(statements[2]) {
+            Statement::Return(ast::Return {
+                expression: Some(expression),
+            }) => assert_eq!(comp.expression_span(*expression), make_span(22, 5)),
+            other => panic!("Expected return but found {:?}", other),
+        }
+        assert_eq!(comp.statement_span(statements[2]), make_span(22, 5));
+
+        // Calls can be returned too
+        let source = "{ f(g(1), 2) }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let (statements, _) = parse_body(&mut input, &mut comp, true).unwrap_pretty();
+        assert!(matches!(
+            comp.get_statement(statements[0]),
+            Statement::Return(_)
+        ));
+
+        // Only at the end of the body
+        let source = "{ a * 2 let b = 1; }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        assert!(parse_body(&mut input, &mut comp, true).is_err());
+    }
+
+    #[test]
+    fn test_parse_expression_statement() {
+        let source = "{ f(x) + 1; a * b; g(1); }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        let (statements, _) = parse_block(&mut input, &mut comp).unwrap_pretty();
+        assert!(input.done());
+
+        assert_eq!(statements.len(), 3);
+        match comp.get_statement(statements[0]) {
+            Statement::Expression(statement) => {
+                assert_eq!(comp.expression_span(statement.expression), make_span(2, 8))
+            }
+            other => panic!("Expected expression statement but found {:?}", other),
+        }
+        assert_eq!(comp.statement_span(statements[0]), make_span(2, 9));
+        assert!(matches!(
+            comp.get_statement(statements[1]),
+            Statement::Expression(_)
+        ));
+        assert!(matches!(
+            comp.get_statement(statements[2]),
+            Statement::Call(_)
+        ));
+
+        // Outside of a function body every expression needs a `;`
+        let source = "{ a * b }";
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
+        assert!(parse_block(&mut input, &mut comp).is_err());
+    }
+
     #[test]
     fn test_parse_return() {
         let source = "return 0;";
-        let (src, mut input) = make_input(source);
-        let mut comp = Component::new(src);
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
         let _return_stmt = parse_return(&mut input, &mut comp).unwrap_pretty();
         assert!(input.done());
     }
@@ -174,8 +349,8 @@ mod tests {
     #[test]
     fn test_parse_assign() {
         let source = "a = 0;";
-        let (src, mut input) = make_input(source);
-        let mut comp = Component::new(src);
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
         let _assign_stmt = parse_assign(&mut input, &mut comp).unwrap_pretty();
         assert!(input.done());
     }
@@ -183,8 +358,8 @@ mod tests {
     #[test]
     fn test_parse_let() {
         let source = "let start = now();";
-        let (src, mut input) = make_input(source);
-        let mut comp = Component::new(src);
+        let mut input = make_input(source);
+        let mut comp = input.new_component();
         let _let_stmt = parse_let(&mut input, &mut comp).unwrap_pretty();
         assert!(input.done());
     }
@@ -1,12 +1,12 @@
 use crate::lexer::Token;
-use crate::{ParseInput, ParserError};
-use ast::{Component, PrimitiveType, TypeId, ValType};
+use crate::{Feature, ParseInput, ParserError};
+use ast::{merge, Component, PrimitiveType, TypeId, ValType};
 use claw_ast as ast;
 
 pub fn parse_valtype(input: &mut ParseInput, comp: &mut Component) -> Result<TypeId, ParserError> {
     let next = input.next()?;
-    let span = next.span;
-    let valtype = match next.token {
+    let mut span = next.span;
+    let valtype = match &next.token {
         Token::Bool => ValType::Primitive(PrimitiveType::Bool),
         // Unsigned Integers
         Token::U8 => ValType::Primitive(PrimitiveType::U8),
@@ -23,6 +23,51 @@ pub fn parse_valtype(input: &mut ParseInput, comp: &mut Component) -> Result<Typ
         Token::F64 => ValType::Primitive(PrimitiveType::F64),
         // String
         Token::String => ValType::Primitive(PrimitiveType::String),
+        // List
+        Token::List => {
+            input.require(Feature::Lists, span)?;
+            input.assert_next(Token::LT, "List types are written list<T>")?;
+            let element = parse_valtype(input, comp)?;
+            input.assert_next(Token::GT, "Expected '>' to close the list type")?;
+            ValType::List(element)
+        }
+        // Bytes, the same type as list<u8>
+        Token::Bytes => {
+            input.require(Feature::Bytes, span)?;
+            let element = comp.new_type(ValType::Primitive(PrimitiveType::U8), span);
+            ValType::List(element)
+        }
+        // Option
+        Token::Option => {
+            input.require(Feature::Options, span)?;
+            input.assert_next(Token::LT, "Option types are written option<T>")?;
+            let inner = parse_valtype(input, comp)?;
+            input.assert_next(Token::GT, "Expected '>' to close the option type")?;
+            ValType::Option(inner)
+        }
+        // Function
+        Token::Func => {
+            input.require(Feature::FunctionTypes, span)?;
+            input.assert_next(Token::LParen, "Function types are written func(T) -> U")?;
+            let mut params = Vec::new();
+            while input.peek()?.token != Token::RParen {
+                params.push(parse_valtype(input, comp)?);
+                if input.next_if(Token::Comma).is_none() {
+                    break;
+                }
+            }
+            let end_span =
+                input.assert_next(Token::RParen, "Expected ')' to close the parameter types")?;
+            let results = match input.next_if(Token::Arrow) {
+                Some(_) => Some(parse_valtype(input, comp)?),
+                None => None,
+            };
+            let end_span = results.map_or(end_span, |results| comp.type_span(results));
+            span = merge(&span, &end_span);
+            ValType::Func(ast::FuncType { params, results })
+        }
+        // Named types e.g. structs
+        Token::Identifier(symbol) => ValType::Named(comp.new_name(*symbol, span)),
         _ => return Err(input.unexpected_token("Not a legal type")),
     };
     let name_id = comp.new_type(valtype, span);
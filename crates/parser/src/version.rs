@@ -0,0 +1,121 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A version of the language, which decides what syntax is accepted.
+///
+/// Code written for an older version keeps meaning the same thing
+/// when newer versions add syntax, since it can't have used that syntax.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LangVersion {
+    V0_2,
+    V0_3,
+}
+
+impl LangVersion {
+    pub const ALL: &'static [LangVersion] = &[LangVersion::V0_2, LangVersion::V0_3];
+
+    pub const LATEST: LangVersion = LangVersion::V0_3;
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LangVersion::V0_2 => "0.2",
+            LangVersion::V0_3 => "0.3",
+        }
+    }
+}
+
+impl Default for LangVersion {
+    fn default() -> Self {
+        LangVersion::LATEST
+    }
+}
+
+impl fmt::Display for LangVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for LangVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LangVersion::ALL
+            .iter()
+            .copied()
+            .find(|version| version.name() == s)
+            .ok_or_else(|| format!("Unknown lang version \"{}\"", s))
+    }
+}
+
+/// Syntax which isn't available in every version of the language
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    Asm,
+    Structs,
+    NestedFunctions,
+    FunctionTypes,
+    Enums,
+    Newtypes,
+    ExpressionStatements,
+    IfExpressions,
+    Match,
+    Lists,
+    Options,
+    OverflowOperators,
+    NotOperators,
+    Casts,
+    FieldAccess,
+    MethodCalls,
+    TailExpressions,
+    Bytes,
+}
+
+impl Feature {
+    /// The first version of the language with the feature
+    pub fn since(&self) -> LangVersion {
+        match self {
+            Feature::Asm
+            | Feature::Structs
+            | Feature::NestedFunctions
+            | Feature::FunctionTypes
+            | Feature::Enums
+            | Feature::Newtypes
+            | Feature::ExpressionStatements
+            | Feature::IfExpressions
+            | Feature::Match
+            | Feature::Lists
+            | Feature::Options
+            | Feature::OverflowOperators
+            | Feature::NotOperators
+            | Feature::Casts
+            | Feature::FieldAccess
+            | Feature::MethodCalls
+            | Feature::TailExpressions
+            | Feature::Bytes => LangVersion::V0_3,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Feature::Asm => "Inline asm",
+            Feature::Structs => "Struct definition",
+            Feature::NestedFunctions => "Nested function",
+            Feature::FunctionTypes => "Function type",
+            Feature::Enums => "Enum definition",
+            Feature::Newtypes => "Newtype definition",
+            Feature::ExpressionStatements => "Expression statement",
+            Feature::IfExpressions => "If expression",
+            Feature::Match => "Match expression",
+            Feature::Lists => "List",
+            Feature::Options => "Option type",
+            Feature::OverflowOperators => "Wrapping or saturating operator",
+            Feature::NotOperators => "Not operator",
+            Feature::Casts => "Conversion with 'as'",
+            Feature::FieldAccess => "Field access",
+            Feature::MethodCalls => "Method call",
+            Feature::TailExpressions => "Trailing expression without 'return'",
+            Feature::Bytes => "Bytes type",
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use ast::{ExpressionId, NameId, StatementId};
+use claw_ast as ast;
+use std::collections::{HashMap, HashSet};
+
+use crate::builtins::Builtin;
+use crate::function::{LocalId, ParamId};
+use crate::ItemId;
+
+/// Find the index expressions whose bounds check is redundant because the
+/// same index into the same list is always checked before they run.
+///
+/// That is when the index is compared against the list's length, as in
+/// `if i < len(values) { ... }` or `if i >= len(values) { return ...; }`,
+/// or when the same element was already accessed. A check stops applying
+/// once either variable is assigned to.
+pub(crate) fn find_in_bounds(
+    component: &ast::Component,
+    bindings: &HashMap<NameId, ItemId>,
+    body: &[StatementId],
+) -> HashSet<ExpressionId> {
+    let mut checker = BoundsChecker {
+        component,
+        bindings,
+        checked: HashSet::new(),
+        in_bounds: HashSet::new(),
+    };
+    checker.check_block(body);
+    checker.in_bounds
+}
+
+/// A variable that can only be changed by assigning to it
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Variable {
+    Param(ParamId),
+    Local(LocalId),
+}
+
+/// A list and an index which is known to be in bounds of it
+type Check = (Variable, Variable);
+
+struct BoundsChecker<'a> {
+    component: &'a ast::Component,
+    bindings: &'a HashMap<NameId, ItemId>,
+    /// The checks which have always happened before the code being visited
+    checked: HashSet<Check>,
+    in_bounds: HashSet<ExpressionId>,
+}
+
+impl BoundsChecker<'_> {
+    fn check_block(&mut self, block: &[StatementId]) {
+        for statement in block.iter().copied() {
+            match self.component.get_statement(statement) {
+                ast::Statement::Let(let_) => self.check_expression(let_.expression),
+                ast::Statement::Assign(assign) => {
+                    self.check_expression(assign.expression);
+                    if let Some(variable) = self.variable(assign.ident) {
+                        self.checked
+                            .retain(|(list, index)| *list != variable && *index != variable);
+                    }
+                }
+                ast::Statement::Call(call) => {
+                    for arg in call.args.iter() {
+                        self.check_expression(*arg);
+                    }
+                }
+                ast::Statement::If(if_statement) => self.check_if(if_statement),
+                ast::Statement::Return(return_) => {
+                    if let Some(expression) = return_.expression {
+                        self.check_expression(expression);
+                    }
+                }
+                ast::Statement::Expression(statement) => {
+                    self.check_expression(statement.expression)
+                }
+                // Nested functions are checked on their own
+                ast::Statement::Func(_) => {}
+            }
+        }
+    }
+
+    fn check_if(&mut self, if_statement: &ast::If) {
+        self.check_expression(if_statement.condition);
+        let skipped = self.checked.clone();
+
+        self.checked
+            .extend(self.implied_checks(if_statement.condition, true));
+        self.check_block(&if_statement.block);
+
+        let returns = if_statement.block.iter().any(|statement| {
+            matches!(
+                self.component.get_statement(*statement),
+                ast::Statement::Return(_)
+            )
+        });
+        self.checked = if returns {
+            // Only the code for a false condition continues after the block
+            let mut checked = skipped;
+            checked.extend(self.implied_checks(if_statement.condition, false));
+            checked
+        } else {
+            // Either the block ran or it didn't
+            skipped.intersection(&self.checked).copied().collect()
+        };
+    }
+
+    /// Visit the expression's operands in the order they're evaluated
+    fn check_expression(&mut self, expression: ExpressionId) {
+        match self.component.get_expression(expression) {
+            ast::Expression::Identifier(_)
+            | ast::Expression::Enum(_)
+            | ast::Expression::Literal(_) => {}
+            ast::Expression::Call(call) => {
+                for arg in call.args.iter() {
+                    self.check_expression(*arg);
+                }
+            }
+            ast::Expression::MethodCall(call) => {
+                self.check_expression(call.receiver);
+                for arg in call.args.iter() {
+                    self.check_expression(*arg);
+                }
+            }
+            ast::Expression::Field(access) => self.check_expression(access.record),
+            ast::Expression::Unary(unary) => self.check_expression(unary.inner),
+            ast::Expression::Binary(binary) if binary.is_logical() => {
+                self.check_expression(binary.left);
+                // The right operand only runs when the left doesn't decide the result
+                let skipped = self.checked.clone();
+                let runs_when = binary.op == ast::BinaryOp::LogicalAnd;
+                self.checked
+                    .extend(self.implied_checks(binary.left, runs_when));
+                self.check_expression(binary.right);
+                self.checked = skipped;
+            }
+            ast::Expression::Binary(binary) => {
+                self.check_expression(binary.left);
+                self.check_expression(binary.right);
+            }
+            ast::Expression::Index(index) => {
+                self.check_expression(index.list);
+                self.check_expression(index.index);
+                if let Some(check) = self.check(index.list, index.index) {
+                    // After the first access traps, every later one is in bounds
+                    if !self.checked.insert(check) {
+                        self.in_bounds.insert(expression);
+                    }
+                }
+            }
+            ast::Expression::Slice(slice) => {
+                self.check_expression(slice.list);
+                for bound in slice.start.iter().chain(slice.end.iter()) {
+                    self.check_expression(*bound);
+                }
+            }
+            ast::Expression::Cast(cast) => self.check_expression(cast.inner),
+            ast::Expression::Record(record) => {
+                for (_, value) in record.fields.iter() {
+                    self.check_expression(*value);
+                }
+                if let Some(base) = record.base {
+                    self.check_expression(base);
+                }
+            }
+            ast::Expression::Asm(asm) => {
+                for input in asm.inputs.iter() {
+                    self.check_expression(input.expression);
+                }
+            }
+            ast::Expression::If(if_expression) => {
+                self.check_expression(if_expression.condition);
+                // Only one branch runs, knowing how the condition turned out
+                let before = self.checked.clone();
+                for (branch, value) in [
+                    (if_expression.then_value, true),
+                    (if_expression.else_value, false),
+                ] {
+                    self.checked = before.clone();
+                    self.checked
+                        .extend(self.implied_checks(if_expression.condition, value));
+                    self.check_expression(branch);
+                }
+                self.checked = before;
+            }
+            ast::Expression::Match(match_expression) => {
+                self.check_expression(match_expression.scrutinee);
+                let before = self.checked.clone();
+                for arm in match_expression.arms.iter() {
+                    self.checked = before.clone();
+                    if let Some(guard) = arm.guard {
+                        self.check_expression(guard);
+                        self.checked.extend(self.implied_checks(guard, true));
+                    }
+                    self.check_expression(arm.value);
+                }
+                self.checked = before;
+            }
+        }
+    }
+
+    /// The checks made by a condition when it evaluates to `value`
+    fn implied_checks(&self, condition: ExpressionId, value: bool) -> Vec<Check> {
+        use ast::BinaryOp::*;
+        let binary = match self.component.get_expression(condition) {
+            ast::Expression::Binary(binary) => binary,
+            _ => return vec![],
+        };
+        let (index, length) = match (binary.op, value) {
+            (LogicalAnd, true) | (LogicalOr, false) => {
+                let mut checks = self.implied_checks(binary.left, value);
+                checks.extend(self.implied_checks(binary.right, value));
+                return checks;
+            }
+            // `i < len(values)`
+            (LessThan, true) | (GreaterThanEqual, false) => (binary.left, binary.right),
+            // `len(values) > i`
+            (GreaterThan, true) | (LessThanEqual, false) => (binary.right, binary.left),
+            _ => return vec![],
+        };
+        let list = match self.component.get_expression(length) {
+            ast::Expression::Call(call) => match (self.bindings.get(&call.ident), &call.args[..]) {
+                (Some(ItemId::Builtin(Builtin::Len)), [list]) => *list,
+                _ => return vec![],
+            },
+            // `values.len()`
+            ast::Expression::MethodCall(call) => {
+                match (self.bindings.get(&call.ident), &call.args[..]) {
+                    (Some(ItemId::Builtin(Builtin::Len)), []) => call.receiver,
+                    _ => return vec![],
+                }
+            }
+            _ => return vec![],
+        };
+        self.check(list, index).into_iter().collect()
+    }
+
+    fn check(&self, list: ExpressionId, index: ExpressionId) -> Option<Check> {
+        let variable = |expression| match self.component.get_expression(expression) {
+            ast::Expression::Identifier(identifier) => self.variable(identifier.ident),
+            _ => None,
+        };
+        Some((variable(list)?, variable(index)?))
+    }
+
+    /// Globals can be changed by any call, so only params and locals are tracked
+    fn variable(&self, ident: NameId) -> Option<Variable> {
+        match self.bindings.get(&ident)? {
+            ItemId::Param(param) => Some(Variable::Param(*param)),
+            ItemId::Local(local) => Some(Variable::Local(*local)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use claw_ast as ast;
+
+/// Functions known to the compiler which are lowered directly to
+/// instructions instead of being called.
+///
+/// Every builtin is generic over a single primitive operand type `T` and
+/// each of its arguments and its result has a [Shape] built from `T`,
+/// so the type of a builtin call is inferred the same way as a binary operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    Rotl,
+    Rotr,
+    Clz,
+    Ctz,
+    Popcount,
+    Sqrt,
+    Abs,
+    Min,
+    Max,
+    Floor,
+    Ceil,
+    Trunc,
+    Nearest,
+    Copysign,
+    CheckedAdd,
+    CheckedSub,
+    CheckedMul,
+    IsSome,
+    UnwrapOr,
+    Len,
+    Get,
+    /// Builds a string from a format string literal and arguments,
+    /// which are checked against [crate::format] instead of [Shape]s
+    Format,
+    /// Write a string and a newline to stdout, only as a statement
+    Println,
+    /// Write a string and a newline to stderr, only as a statement
+    Eprintln,
+    /// Trap after writing a message and where the panic happened to the
+    /// `panic-handler` import, or to stderr when there isn't one
+    Panic,
+    /// Read a little-endian integer from `bytes` at a byte offset,
+    /// trapping when it doesn't fit
+    Load8,
+    Load16,
+    Load32,
+    Load64,
+    /// Write a little-endian integer into `bytes` at a byte offset,
+    /// trapping when it doesn't fit. The bytes are changed in place,
+    /// so every copy of the list sees the write. Only as a statement.
+    Store8,
+    Store16,
+    Store32,
+    Store64,
+}
+
+/// How a builtin's argument or result type relates to its operand type `T`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /// `T` itself
+    Value,
+    /// `option<T>`
+    Option,
+    /// `list<T>`
+    List,
+    /// A list of any element type, unrelated to `T`
+    AnyList,
+    /// Always `bool`
+    Bool,
+    /// Always `u8`
+    U8,
+    /// Always `u16`
+    U16,
+    /// Always `u32`
+    U32,
+    /// Always `u64`
+    U64,
+    /// Always `bytes`, which is `list<u8>`
+    Bytes,
+    /// Always `string`
+    String,
+}
+
+impl Builtin {
+    pub const ALL: &'static [Builtin] = &[
+        Builtin::Rotl,
+        Builtin::Rotr,
+        Builtin::Clz,
+        Builtin::Ctz,
+        Builtin::Popcount,
+        Builtin::Sqrt,
+        Builtin::Abs,
+        Builtin::Min,
+        Builtin::Max,
+        Builtin::Floor,
+        Builtin::Ceil,
+        Builtin::Trunc,
+        Builtin::Nearest,
+        Builtin::Copysign,
+        Builtin::CheckedAdd,
+        Builtin::CheckedSub,
+        Builtin::CheckedMul,
+        Builtin::IsSome,
+        Builtin::UnwrapOr,
+        Builtin::Len,
+        Builtin::Get,
+        Builtin::Format,
+        Builtin::Println,
+        Builtin::Eprintln,
+        Builtin::Panic,
+        Builtin::Load8,
+        Builtin::Load16,
+        Builtin::Load32,
+        Builtin::Load64,
+        Builtin::Store8,
+        Builtin::Store16,
+        Builtin::Store32,
+        Builtin::Store64,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Rotl => "rotl",
+            Builtin::Rotr => "rotr",
+            Builtin::Clz => "clz",
+            Builtin::Ctz => "ctz",
+            Builtin::Popcount => "popcount",
+            Builtin::Sqrt => "sqrt",
+            Builtin::Abs => "abs",
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Floor => "floor",
+            Builtin::Ceil => "ceil",
+            Builtin::Trunc => "trunc",
+            Builtin::Nearest => "nearest",
+            Builtin::Copysign => "copysign",
+            Builtin::CheckedAdd => "checked-add",
+            Builtin::CheckedSub => "checked-sub",
+            Builtin::CheckedMul => "checked-mul",
+            Builtin::IsSome => "is-some",
+            Builtin::UnwrapOr => "unwrap-or",
+            Builtin::Len => "len",
+            Builtin::Get => "get",
+            Builtin::Format => "format",
+            Builtin::Println => "println",
+            Builtin::Eprintln => "eprintln",
+            Builtin::Panic => "panic",
+            Builtin::Load8 => "load8",
+            Builtin::Load16 => "load16",
+            Builtin::Load32 => "load32",
+            Builtin::Load64 => "load64",
+            Builtin::Store8 => "store8",
+            Builtin::Store16 => "store16",
+            Builtin::Store32 => "store32",
+            Builtin::Store64 => "store64",
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.params().len()
+    }
+
+    pub fn params(&self) -> &'static [Shape] {
+        match self {
+            Builtin::Rotl | Builtin::Rotr => &[Shape::Value, Shape::Value],
+            Builtin::Min | Builtin::Max | Builtin::Copysign => &[Shape::Value, Shape::Value],
+            Builtin::CheckedAdd | Builtin::CheckedSub | Builtin::CheckedMul => {
+                &[Shape::Value, Shape::Value]
+            }
+            Builtin::IsSome => &[Shape::Option],
+            Builtin::UnwrapOr => &[Shape::Option, Shape::Value],
+            Builtin::Len => &[Shape::AnyList],
+            Builtin::Get => &[Shape::List, Shape::U32],
+            // Any number of arguments follow the format string
+            Builtin::Format => &[Shape::String],
+            Builtin::Println | Builtin::Eprintln | Builtin::Panic => &[Shape::String],
+            Builtin::Load8 | Builtin::Load16 | Builtin::Load32 | Builtin::Load64 => {
+                &[Shape::Bytes, Shape::U32]
+            }
+            Builtin::Store8 => &[Shape::Bytes, Shape::U32, Shape::U8],
+            Builtin::Store16 => &[Shape::Bytes, Shape::U32, Shape::U16],
+            Builtin::Store32 => &[Shape::Bytes, Shape::U32, Shape::U32],
+            Builtin::Store64 => &[Shape::Bytes, Shape::U32, Shape::U64],
+            _ => &[Shape::Value],
+        }
+    }
+
+    pub fn result(&self) -> Shape {
+        match self {
+            Builtin::CheckedAdd | Builtin::CheckedSub | Builtin::CheckedMul => Shape::Option,
+            Builtin::Get => Shape::Option,
+            Builtin::IsSome => Shape::Bool,
+            Builtin::Len => Shape::U32,
+            Builtin::Format => Shape::String,
+            Builtin::Load8 => Shape::U8,
+            Builtin::Load16 => Shape::U16,
+            Builtin::Load32 => Shape::U32,
+            Builtin::Load64 => Shape::U64,
+            _ => Shape::Value,
+        }
+    }
+
+    /// Whether calls to the builtin produce a value, the ones
+    /// which don't can only be called as statements
+    pub fn has_result(&self) -> bool {
+        !matches!(
+            self,
+            Builtin::Println
+                | Builtin::Eprintln
+                | Builtin::Panic
+                | Builtin::Store8
+                | Builtin::Store16
+                | Builtin::Store32
+                | Builtin::Store64
+        )
+    }
+
+    /// The number of bytes read or written by a load or store
+    pub fn access_width(&self) -> Option<u32> {
+        match self {
+            Builtin::Load8 | Builtin::Store8 => Some(1),
+            Builtin::Load16 | Builtin::Store16 => Some(2),
+            Builtin::Load32 | Builtin::Store32 => Some(4),
+            Builtin::Load64 | Builtin::Store64 => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Whether the builtin can be applied to values of the given type
+    pub fn accepts(&self, ptype: ast::PrimitiveType) -> bool {
+        use ast::PrimitiveType::*;
+        let is_float = matches!(ptype, F32 | F64);
+        let is_signed = matches!(ptype, S8 | S16 | S32 | S64);
+        let is_int = is_signed || matches!(ptype, U8 | U16 | U32 | U64);
+        match self {
+            Builtin::Rotl | Builtin::Rotr | Builtin::Clz | Builtin::Ctz | Builtin::Popcount => {
+                is_int
+            }
+            Builtin::CheckedAdd | Builtin::CheckedSub | Builtin::CheckedMul => is_int,
+            Builtin::IsSome | Builtin::UnwrapOr | Builtin::Len | Builtin::Get => true,
+            Builtin::Format | Builtin::Println | Builtin::Eprintln | Builtin::Panic => true,
+            Builtin::Load8 | Builtin::Load16 | Builtin::Load32 | Builtin::Load64 => true,
+            Builtin::Store8 | Builtin::Store16 | Builtin::Store32 | Builtin::Store64 => true,
+            Builtin::Abs => is_float || is_signed,
+            Builtin::Min | Builtin::Max => is_float || is_int,
+            Builtin::Sqrt
+            | Builtin::Floor
+            | Builtin::Ceil
+            | Builtin::Trunc
+            | Builtin::Nearest
+            | Builtin::Copysign => is_float,
+        }
+    }
+}
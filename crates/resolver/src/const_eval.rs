@@ -0,0 +1,824 @@
+use ast::{ExpressionId, NameId, PrimitiveType};
+use claw_ast as ast;
+use std::collections::HashMap;
+
+use crate::builtins::Builtin;
+use crate::expression::is_integer;
+use crate::lints::Warnings;
+use crate::types::ResolvedType;
+use crate::{ItemId, ResolverError, ResolverWarning};
+
+/// A value computed at compile time.
+///
+/// Integers are kept within the range of their type,
+/// so an `s8` is stored sign-extended and a `u64` zero-extended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConstValue {
+    Int(i128),
+    Float(f64),
+}
+
+pub(crate) struct ConstEvaluator<'ctx> {
+    component: &'ctx ast::Component,
+    bindings: &'ctx HashMap<NameId, ItemId>,
+    expression_types: &'ctx HashMap<ExpressionId, ResolvedType>,
+}
+
+impl<'ctx> ConstEvaluator<'ctx> {
+    pub(crate) fn new(
+        component: &'ctx ast::Component,
+        bindings: &'ctx HashMap<NameId, ItemId>,
+        expression_types: &'ctx HashMap<ExpressionId, ResolvedType>,
+    ) -> Self {
+        Self {
+            component,
+            bindings,
+            expression_types,
+        }
+    }
+
+    /// Fold every builtin call and operator in the function whose
+    /// operands are all constant, and every `and` and `or` whose
+    /// result is decided by constant operands
+    pub(crate) fn fold(&self) -> HashMap<ExpressionId, ConstValue> {
+        let mut constants = HashMap::new();
+        for expression in self.expression_types.keys() {
+            let foldable = match self.component.get_expression(*expression) {
+                ast::Expression::Call(ast::Call { ident, .. })
+                | ast::Expression::MethodCall(ast::MethodCall { ident, .. }) => {
+                    matches!(self.bindings.get(ident), Some(ItemId::Builtin(_)))
+                }
+                ast::Expression::Binary(_) | ast::Expression::Unary(_) => true,
+                _ => false,
+            };
+            if foldable {
+                if let Some(value) = self.eval(*expression) {
+                    constants.insert(*expression, value);
+                }
+            }
+        }
+        constants
+    }
+
+    /// Reject integer division by a divisor that is always zero, which would always trap
+    pub(crate) fn check_divisors(&self) -> Result<(), ResolverError> {
+        let mut expressions: Vec<_> = self.expression_types.keys().copied().collect();
+        expressions.sort();
+        for expression in expressions {
+            let binary = match self.component.get_expression(expression) {
+                ast::Expression::Binary(binary) => binary,
+                _ => continue,
+            };
+            if !matches!(binary.op, ast::BinaryOp::Divide | ast::BinaryOp::Modulo) {
+                continue;
+            }
+            if self.eval(binary.right) == Some(ConstValue::Int(0)) {
+                return Err(ResolverError::DivideByZero {
+                    src: self.component.source(),
+                    span: self.component.expression_span(binary.right),
+                    op: binary.op,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Warn about shifts by a constant at least as large as the shifted type,
+    /// which wasm would silently mask instead
+    pub(crate) fn check_shifts(&self, warnings: &mut Warnings) -> Result<(), ResolverError> {
+        let mut expressions: Vec<_> = self.expression_types.keys().copied().collect();
+        expressions.sort();
+        for expression in expressions {
+            let binary = match self.component.get_expression(expression) {
+                ast::Expression::Binary(binary) => binary,
+                _ => continue,
+            };
+            if !matches!(
+                binary.op,
+                ast::BinaryOp::BitShiftL | ast::BinaryOp::BitShiftR | ast::BinaryOp::ArithShiftR
+            ) {
+                continue;
+            }
+            let ptype = match self.expression_types[&expression].primitive(self.component) {
+                Some(ptype) if !is_float(ptype) => ptype,
+                _ => continue,
+            };
+            let bits = int_bits(ptype);
+            if let Some(ConstValue::Int(amount)) = self.eval(binary.right) {
+                if amount < 0 || amount >= bits as i128 {
+                    warnings.push(ResolverWarning::ShiftOverflow {
+                        src: self.component.source(),
+                        span: self.component.expression_span(binary.right),
+                        ptype,
+                        amount,
+                        bits,
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Warn about constant arithmetic whose exact result doesn't fit in its type,
+    /// which is wrapped like at runtime unless written with a wrapping operator
+    pub(crate) fn check_overflow(&self, warnings: &mut Warnings) -> Result<(), ResolverError> {
+        let mut expressions: Vec<_> = self.expression_types.keys().copied().collect();
+        expressions.sort();
+        for expression in expressions {
+            let ptype = match self.expression_types[&expression].primitive(self.component) {
+                Some(ptype) if !is_float(ptype) => ptype,
+                _ => continue,
+            };
+            let (exact, wrapped) = match self.component.get_expression(expression) {
+                ast::Expression::Binary(binary) => {
+                    let (left, right) = match (self.eval(binary.left), self.eval(binary.right)) {
+                        (Some(ConstValue::Int(left)), Some(ConstValue::Int(right))) => {
+                            (left, right)
+                        }
+                        _ => continue,
+                    };
+                    match binary.op {
+                        ast::BinaryOp::Add => (left.checked_add(right), left.wrapping_add(right)),
+                        ast::BinaryOp::Subtract => {
+                            (left.checked_sub(right), left.wrapping_sub(right))
+                        }
+                        ast::BinaryOp::Multiply => {
+                            (left.checked_mul(right), left.wrapping_mul(right))
+                        }
+                        _ => continue,
+                    }
+                }
+                ast::Expression::Unary(ast::UnaryExpression {
+                    op: ast::UnaryOp::Negate,
+                    inner,
+                }) => {
+                    // A minus sign on a literal is part of the literal, whose
+                    // range is checked when it is resolved
+                    if signed_literal(self.component, expression).is_some() {
+                        continue;
+                    }
+                    match self.eval(*inner) {
+                        Some(ConstValue::Int(inner)) => (inner.checked_neg(), inner.wrapping_neg()),
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            if exact.is_some_and(|exact| int_fits(exact, ptype)) {
+                continue;
+            }
+            warnings.push(ResolverWarning::ConstantOverflow {
+                src: self.component.source(),
+                span: self.component.expression_span(expression),
+                ptype,
+                value: match exact {
+                    Some(exact) => exact.to_string(),
+                    None => "a value out of range".to_owned(),
+                },
+                wrapped: wrap(wrapped, ptype),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Evaluate the expression if its value can be known at compile time
+    pub(crate) fn eval(&self, expression: ExpressionId) -> Option<ConstValue> {
+        let rtype = self.expression_types.get(&expression)?;
+        let ptype = rtype.primitive(self.component)?;
+        match self.component.get_expression(expression) {
+            ast::Expression::Literal(ast::Literal::Integer(int)) => {
+                if is_float(ptype) {
+                    return None;
+                }
+                Some(ConstValue::Int(wrap(*int as i128, ptype)))
+            }
+            ast::Expression::Literal(ast::Literal::Float(float)) => {
+                Some(ConstValue::Float(round(*float, ptype)))
+            }
+            ast::Expression::Unary(unary) => match (unary.op, self.eval(unary.inner)?) {
+                (ast::UnaryOp::Negate, ConstValue::Int(int)) => {
+                    Some(ConstValue::Int(wrap(-int, ptype)))
+                }
+                (ast::UnaryOp::Negate, ConstValue::Float(float)) => Some(ConstValue::Float(-float)),
+                (ast::UnaryOp::LogicalNot, ConstValue::Int(int)) => {
+                    Some(ConstValue::Int((int == 0) as i128))
+                }
+                (ast::UnaryOp::Invert, ConstValue::Int(int)) => {
+                    Some(ConstValue::Int(wrap(!int, ptype)))
+                }
+                (ast::UnaryOp::LogicalNot | ast::UnaryOp::Invert, ConstValue::Float(_)) => None,
+            },
+            ast::Expression::Call(call) => self.eval_call(call, ptype),
+            // Method calls are the call with the receiver as the first argument
+            ast::Expression::MethodCall(call) => self.eval_call(&call.as_call(), ptype),
+            ast::Expression::Binary(binary) if binary.is_logical() => {
+                // The right operand is never evaluated when the left decides the result,
+                // so it doesn't have to be constant
+                let left = match self.eval(binary.left)? {
+                    ConstValue::Int(left) => left != 0,
+                    ConstValue::Float(_) => return None,
+                };
+                if left == (binary.op == ast::BinaryOp::LogicalOr) {
+                    return Some(ConstValue::Int(left as i128));
+                }
+                self.eval(binary.right)
+            }
+            ast::Expression::Binary(binary) => {
+                let left = self.eval(binary.left)?;
+                let right = self.eval(binary.right)?;
+                eval_binary(binary.op, left, right, ptype)
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluate a call to a builtin whose arguments are all constant
+    fn eval_call(&self, call: &ast::Call, ptype: ast::PrimitiveType) -> Option<ConstValue> {
+        let builtin = match self.bindings.get(&call.ident)? {
+            ItemId::Builtin(builtin) => *builtin,
+            _ => return None,
+        };
+        let args = call
+            .args
+            .iter()
+            .map(|arg| self.eval(*arg))
+            .collect::<Option<Vec<_>>>()?;
+        eval_builtin(builtin, &args, ptype)
+    }
+}
+
+/// Evaluate an integer expression outside of any function, such as the
+/// value of an enum case, which can only use literals and operators.
+///
+/// There's no type to wrap to, so the exact value is given and `None`
+/// when it gets too large or divides by zero.
+pub(crate) fn eval_integer(comp: &ast::Component, expression: ExpressionId) -> Option<i128> {
+    use ast::BinaryOp::*;
+    match comp.get_expression(expression) {
+        ast::Expression::Literal(ast::Literal::Integer(int)) => Some(*int as i128),
+        ast::Expression::Unary(unary) => match unary.op {
+            ast::UnaryOp::Negate => eval_integer(comp, unary.inner)?.checked_neg(),
+            ast::UnaryOp::Invert => Some(!eval_integer(comp, unary.inner)?),
+            ast::UnaryOp::LogicalNot => None,
+        },
+        ast::Expression::Binary(binary) => {
+            let left = eval_integer(comp, binary.left)?;
+            let right = eval_integer(comp, binary.right)?;
+            match binary.op {
+                Add | WrappingAdd | SaturatingAdd => left.checked_add(right),
+                Subtract | WrappingSubtract | SaturatingSubtract => left.checked_sub(right),
+                Multiply | WrappingMultiply => left.checked_mul(right),
+                Divide => left.checked_div(right),
+                Modulo => left.checked_rem(right),
+                BitShiftL if (0..64).contains(&right) => left.checked_mul(1 << right),
+                BitShiftR | ArithShiftR if (0..64).contains(&right) => Some(left >> right),
+                BitOr => Some(left | right),
+                BitXor => Some(left ^ right),
+                BitAnd => Some(left & right),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn eval_builtin(builtin: Builtin, args: &[ConstValue], ptype: PrimitiveType) -> Option<ConstValue> {
+    match (args, builtin) {
+        ([ConstValue::Int(value)], builtin) => {
+            let bits = int_bits(ptype);
+            let unsigned = (*value as u64) & int_mask(ptype);
+            let result = match builtin {
+                Builtin::Clz => (unsigned.leading_zeros() - (64 - bits)) as i128,
+                Builtin::Ctz => unsigned.trailing_zeros().min(bits) as i128,
+                Builtin::Popcount => unsigned.count_ones() as i128,
+                Builtin::Abs => value.abs(),
+                _ => return None,
+            };
+            Some(ConstValue::Int(wrap(result, ptype)))
+        }
+        ([ConstValue::Int(left), ConstValue::Int(right)], builtin) => {
+            let bits = int_bits(ptype);
+            let mask = int_mask(ptype);
+            let unsigned = (*left as u64) & mask;
+            let amount = (*right as u32) % bits;
+            let result = match builtin {
+                Builtin::Rotl => {
+                    let high = unsigned.checked_shl(amount).unwrap_or(0);
+                    let low = unsigned.checked_shr(bits - amount).unwrap_or(0);
+                    ((high | low) & mask) as i128
+                }
+                Builtin::Rotr => {
+                    let low = unsigned.checked_shr(amount).unwrap_or(0);
+                    let high = unsigned.checked_shl(bits - amount).unwrap_or(0);
+                    ((high | low) & mask) as i128
+                }
+                Builtin::Min => *left.min(right),
+                Builtin::Max => *left.max(right),
+                _ => return None,
+            };
+            Some(ConstValue::Int(wrap(result, ptype)))
+        }
+        ([ConstValue::Float(value)], builtin) => {
+            let result = match builtin {
+                Builtin::Sqrt => value.sqrt(),
+                Builtin::Abs => value.abs(),
+                Builtin::Floor => value.floor(),
+                Builtin::Ceil => value.ceil(),
+                Builtin::Trunc => value.trunc(),
+                Builtin::Nearest => value.round_ties_even(),
+                _ => return None,
+            };
+            Some(ConstValue::Float(round(result, ptype)))
+        }
+        ([ConstValue::Float(left), ConstValue::Float(right)], builtin) => {
+            let result = match builtin {
+                Builtin::Min => float_min(*left, *right),
+                Builtin::Max => -float_min(-*left, -*right),
+                Builtin::Copysign => left.copysign(*right),
+                _ => return None,
+            };
+            Some(ConstValue::Float(round(result, ptype)))
+        }
+        _ => None,
+    }
+}
+
+fn eval_binary(
+    op: ast::BinaryOp,
+    left: ConstValue,
+    right: ConstValue,
+    ptype: PrimitiveType,
+) -> Option<ConstValue> {
+    use std::cmp::Ordering::{self, *};
+    let test: fn(Ordering) -> bool = match op {
+        ast::BinaryOp::Equals => |ordering| ordering == Equal,
+        ast::BinaryOp::NotEquals => |ordering| ordering != Equal,
+        ast::BinaryOp::LessThan => |ordering| ordering == Less,
+        ast::BinaryOp::LessThanEqual => |ordering| ordering != Greater,
+        ast::BinaryOp::GreaterThan => |ordering| ordering == Greater,
+        ast::BinaryOp::GreaterThanEqual => |ordering| ordering != Less,
+        _ => return eval_arithmetic(op, left, right, ptype),
+    };
+    // Comparisons give a bool, which is the integer 0 or 1
+    let ordering = match (left, right) {
+        (ConstValue::Int(left), ConstValue::Int(right)) => left.cmp(&right),
+        (ConstValue::Float(left), ConstValue::Float(right)) => left.partial_cmp(&right)?,
+        _ => return None,
+    };
+    Some(ConstValue::Int(test(ordering) as i128))
+}
+
+fn eval_arithmetic(
+    op: ast::BinaryOp,
+    left: ConstValue,
+    right: ConstValue,
+    ptype: PrimitiveType,
+) -> Option<ConstValue> {
+    use ast::BinaryOp::*;
+    let (left, right) = match (left, right) {
+        (ConstValue::Int(left), ConstValue::Int(right)) => (left, right),
+        (ConstValue::Float(left), ConstValue::Float(right)) => {
+            let result = match op {
+                Add => left + right,
+                Subtract => left - right,
+                Multiply => left * right,
+                Divide => left / right,
+                _ => return None,
+            };
+            return Some(ConstValue::Float(round(result, ptype)));
+        }
+        _ => return None,
+    };
+    // Shift amounts are masked to the width of the wasm value holding the type
+    let amount = (right as u32) % int_bits(ptype).max(32);
+    let result = match op {
+        // Plain arithmetic wraps at runtime, like the wrapping operators
+        Add | WrappingAdd => wrap(left + right, ptype),
+        Subtract | WrappingSubtract => wrap(left - right, ptype),
+        Multiply | WrappingMultiply => wrap(left.wrapping_mul(right), ptype),
+        SaturatingAdd => saturate(left + right, ptype),
+        SaturatingSubtract => saturate(left - right, ptype),
+        // Dividing by zero is an error and dividing the minimum
+        // signed value by -1 traps, so neither is folded
+        Divide | Modulo if right == 0 => return None,
+        Divide if int_range(ptype).0 == left && right == -1 && int_bits(ptype) >= 32 => {
+            return None
+        }
+        Divide => wrap(left / right, ptype),
+        Modulo => left % right,
+        BitShiftL => wrap(((left as u64) << amount) as i128, ptype),
+        BitShiftR => wrap((((left as u64) & int_mask(ptype)) >> amount) as i128, ptype),
+        ArithShiftR => wrap(left >> amount, ptype),
+        BitAnd => left & right,
+        BitOr => left | right,
+        BitXor => left ^ right,
+        _ => return None,
+    };
+    Some(ConstValue::Int(result))
+}
+
+/// Matches the semantics of `f32.min` and `f64.min`
+/// which propagate NaN and order -0 before +0
+fn float_min(left: f64, right: f64) -> f64 {
+    if left.is_nan() || right.is_nan() {
+        f64::NAN
+    } else if left == right {
+        if left.is_sign_negative() {
+            left
+        } else {
+            right
+        }
+    } else {
+        left.min(right)
+    }
+}
+
+fn is_float(ptype: PrimitiveType) -> bool {
+    matches!(ptype, PrimitiveType::F32 | PrimitiveType::F64)
+}
+
+fn int_bits(ptype: PrimitiveType) -> u32 {
+    match ptype {
+        PrimitiveType::U8 | PrimitiveType::S8 => 8,
+        PrimitiveType::U16 | PrimitiveType::S16 => 16,
+        PrimitiveType::U32 | PrimitiveType::S32 => 32,
+        _ => 64,
+    }
+}
+
+fn int_mask(ptype: PrimitiveType) -> u64 {
+    u64::MAX >> (64 - int_bits(ptype))
+}
+
+/// Wrap an integer into the range of its type
+fn wrap(value: i128, ptype: PrimitiveType) -> i128 {
+    match ptype {
+        PrimitiveType::U8 => value as u8 as i128,
+        PrimitiveType::S8 => value as i8 as i128,
+        PrimitiveType::U16 => value as u16 as i128,
+        PrimitiveType::S16 => value as i16 as i128,
+        PrimitiveType::U32 => value as u32 as i128,
+        PrimitiveType::S32 => value as i32 as i128,
+        PrimitiveType::U64 => value as u64 as i128,
+        PrimitiveType::S64 => value as i64 as i128,
+        _ => value,
+    }
+}
+
+/// The smallest and largest values of an integer type
+fn int_range(ptype: PrimitiveType) -> (i128, i128) {
+    let bits = int_bits(ptype);
+    match ptype {
+        PrimitiveType::S8 | PrimitiveType::S16 | PrimitiveType::S32 | PrimitiveType::S64 => {
+            (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+        }
+        _ => (0, (1i128 << bits) - 1),
+    }
+}
+
+/// Whether an integer literal, possibly written after a minus sign, is in the range of its type
+pub(crate) fn int_literal_fits(value: u64, negated: bool, ptype: PrimitiveType) -> bool {
+    let value = if negated {
+        -(value as i128)
+    } else {
+        value as i128
+    };
+    int_fits(value, ptype)
+}
+
+/// Whether an integer is in the range of the type
+pub fn int_fits(value: i128, ptype: PrimitiveType) -> bool {
+    let (min, max) = int_range(ptype);
+    min <= value && value <= max
+}
+
+/// A literal, possibly a number with a minus sign in front, which
+/// is what global initializers and struct field defaults are limited to
+pub fn signed_literal(
+    comp: &ast::Component,
+    expression: ExpressionId,
+) -> Option<(&ast::Literal, bool)> {
+    match comp.get_expression(expression) {
+        ast::Expression::Literal(literal) => Some((literal, false)),
+        ast::Expression::Unary(ast::UnaryExpression {
+            op: ast::UnaryOp::Negate,
+            inner,
+        }) => match comp.get_expression(*inner) {
+            ast::Expression::Literal(
+                literal @ (ast::Literal::Integer(_) | ast::Literal::Float(_)),
+            ) => Some((literal, true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The value of a possibly negated literal as the primitive type,
+/// or None if it's a literal of another type or out of range.
+///
+/// A negative integer is given in two's complement within the bits of the type.
+pub fn literal_value(
+    literal: &ast::Literal,
+    negated: bool,
+    ptype: PrimitiveType,
+) -> Option<ast::Literal> {
+    match literal {
+        ast::Literal::Integer(value) => {
+            if !is_integer(ptype) || !int_literal_fits(*value, negated, ptype) {
+                return None;
+            }
+            let value = match negated {
+                true => value.wrapping_neg() & int_mask(ptype),
+                false => *value,
+            };
+            Some(ast::Literal::Integer(value))
+        }
+        ast::Literal::Float(value) if is_float(ptype) => {
+            let value = if negated { -value } else { *value };
+            Some(ast::Literal::Float(value))
+        }
+        ast::Literal::String(_) if ptype == PrimitiveType::String && !negated => {
+            Some(literal.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Clamp an integer into the range of its type
+fn saturate(value: i128, ptype: PrimitiveType) -> i128 {
+    let (min, max) = int_range(ptype);
+    value.clamp(min, max)
+}
+
+/// Round a float to the precision of its type
+fn round(value: f64, ptype: PrimitiveType) -> f64 {
+    match ptype {
+        PrimitiveType::F32 => value as f32 as f64,
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(value: i128) -> ConstValue {
+        ConstValue::Int(value)
+    }
+
+    fn float(value: f64) -> ConstValue {
+        ConstValue::Float(value)
+    }
+
+    #[test]
+    fn checks_int_literal_ranges() {
+        use PrimitiveType::*;
+        assert!(int_literal_fits(255, false, U8));
+        assert!(!int_literal_fits(256, false, U8));
+        assert!(int_literal_fits(0, true, U8));
+        assert!(!int_literal_fits(1, true, U8));
+        assert!(int_literal_fits(128, true, S8));
+        assert!(!int_literal_fits(128, false, S8));
+        assert!(!int_literal_fits(129, true, S8));
+        assert!(int_literal_fits(u64::MAX, false, U64));
+        assert!(int_literal_fits(i64::MIN.unsigned_abs(), true, S64));
+        assert!(!int_literal_fits(i64::MIN.unsigned_abs(), false, S64));
+        assert!(!int_literal_fits(i64::MIN.unsigned_abs() + 1, true, S64));
+    }
+
+    #[test]
+    fn folds_bit_builtins_per_width() {
+        let cases = [
+            (Builtin::Clz, vec![int(1)], PrimitiveType::U8, int(7)),
+            (Builtin::Clz, vec![int(1)], PrimitiveType::U64, int(63)),
+            (Builtin::Ctz, vec![int(0)], PrimitiveType::U16, int(16)),
+            (
+                Builtin::Popcount,
+                vec![int(-1)],
+                PrimitiveType::S32,
+                int(32),
+            ),
+            (
+                Builtin::Rotl,
+                vec![int(0x81), int(1)],
+                PrimitiveType::U8,
+                int(0x03),
+            ),
+            (
+                Builtin::Rotr,
+                vec![int(1), int(65)],
+                PrimitiveType::U64,
+                int(1 << 63),
+            ),
+        ];
+        for (builtin, args, ptype, expected) in cases {
+            assert_eq!(eval_builtin(builtin, &args, ptype), Some(expected));
+        }
+    }
+
+    #[test]
+    fn folds_math_builtins() {
+        let cases = [
+            (Builtin::Abs, vec![int(-128)], PrimitiveType::S8, int(-128)),
+            (
+                Builtin::Min,
+                vec![int(3), int(-7)],
+                PrimitiveType::S32,
+                int(-7),
+            ),
+            (
+                Builtin::Max,
+                vec![int(3), int(7)],
+                PrimitiveType::U32,
+                int(7),
+            ),
+            (
+                Builtin::Sqrt,
+                vec![float(2.25)],
+                PrimitiveType::F64,
+                float(1.5),
+            ),
+            (
+                Builtin::Nearest,
+                vec![float(2.5)],
+                PrimitiveType::F32,
+                float(2.0),
+            ),
+            (
+                Builtin::Copysign,
+                vec![float(1.0), float(-0.0)],
+                PrimitiveType::F64,
+                float(-1.0),
+            ),
+            (
+                Builtin::Max,
+                vec![float(-0.0), float(0.0)],
+                PrimitiveType::F64,
+                float(0.0),
+            ),
+        ];
+        for (builtin, args, ptype, expected) in cases {
+            assert_eq!(eval_builtin(builtin, &args, ptype), Some(expected));
+        }
+        let nan = eval_builtin(
+            Builtin::Min,
+            &[float(f64::NAN), float(1.0)],
+            PrimitiveType::F64,
+        );
+        assert!(matches!(nan, Some(ConstValue::Float(value)) if value.is_nan()));
+    }
+
+    #[test]
+    fn folds_overflow_operators() {
+        use ast::BinaryOp::*;
+        let cases = [
+            (WrappingAdd, 250, 10, PrimitiveType::U8, 4),
+            (WrappingSubtract, 0, 1, PrimitiveType::U32, u32::MAX as i128),
+            (
+                WrappingMultiply,
+                i64::MAX as i128,
+                2,
+                PrimitiveType::S64,
+                -2,
+            ),
+            (SaturatingAdd, 250, 10, PrimitiveType::U8, 255),
+            (SaturatingAdd, 100, 100, PrimitiveType::S8, 127),
+            (SaturatingSubtract, 1, 2, PrimitiveType::U64, 0),
+            (
+                SaturatingSubtract,
+                i32::MIN as i128,
+                1,
+                PrimitiveType::S32,
+                i32::MIN as i128,
+            ),
+        ];
+        for (op, left, right, ptype, expected) in cases {
+            let result = eval_binary(op, int(left), int(right), ptype);
+            assert_eq!(result, Some(int(expected)), "{:?} {} {}", op, left, right);
+        }
+    }
+
+    #[test]
+    fn folds_plain_operators() {
+        use ast::BinaryOp::*;
+        let cases = [
+            (Add, 255, 1, PrimitiveType::U8, Some(0)),
+            (Multiply, 3, 4, PrimitiveType::S32, Some(12)),
+            (
+                Multiply,
+                u64::MAX as i128,
+                2,
+                PrimitiveType::U64,
+                Some(u64::MAX as i128 - 1),
+            ),
+            (Subtract, 0, 1, PrimitiveType::U16, Some(u16::MAX as i128)),
+            (Divide, -7, 2, PrimitiveType::S32, Some(-3)),
+            (Modulo, -7, 2, PrimitiveType::S32, Some(-1)),
+            (Divide, 1, 0, PrimitiveType::U32, None),
+            (Divide, i32::MIN as i128, -1, PrimitiveType::S32, None),
+            (Divide, -128, -1, PrimitiveType::S8, Some(-128)),
+            (BitShiftL, 1, 8, PrimitiveType::U32, Some(256)),
+            (BitShiftL, 1, 8, PrimitiveType::U8, Some(0)),
+            (BitShiftL, 1, 33, PrimitiveType::U32, Some(2)),
+            (BitShiftR, -1, 4, PrimitiveType::S8, Some(0x0F)),
+            (ArithShiftR, -16, 2, PrimitiveType::S8, Some(-4)),
+            (BitAnd, 0xF0, 0x3C, PrimitiveType::U8, Some(0x30)),
+            (BitXor, -1, 1, PrimitiveType::S64, Some(-2)),
+        ];
+        for (op, left, right, ptype, expected) in cases {
+            let result = eval_binary(op, int(left), int(right), ptype);
+            assert_eq!(result, expected.map(int), "{:?} {} {}", op, left, right);
+        }
+
+        assert_eq!(
+            eval_binary(Divide, float(1.0), float(4.0), PrimitiveType::F64),
+            Some(float(0.25))
+        );
+        assert_eq!(
+            eval_binary(Add, float(0.1), float(0.2), PrimitiveType::F32),
+            Some(float((0.1f32 + 0.2f32) as f64))
+        );
+        assert_eq!(
+            eval_binary(Modulo, float(1.0), float(2.0), PrimitiveType::F64),
+            None
+        );
+    }
+
+    #[test]
+    fn folds_comparisons() {
+        use ast::BinaryOp::*;
+        let bool_type = PrimitiveType::Bool;
+        assert_eq!(
+            eval_binary(Equals, int(-1), int(-1), bool_type),
+            Some(int(1))
+        );
+        assert_eq!(
+            eval_binary(LessThan, int(-1), int(0), bool_type),
+            Some(int(1))
+        );
+        assert_eq!(
+            eval_binary(GreaterThanEqual, int(2), int(3), bool_type),
+            Some(int(0))
+        );
+        let nan = float(f64::NAN);
+        assert_eq!(eval_binary(Equals, nan, nan, bool_type), None);
+        assert_eq!(
+            eval_binary(NotEquals, float(0.5), float(1.5), bool_type),
+            Some(int(1))
+        );
+    }
+
+    #[test]
+    fn short_circuits_logical_operators() {
+        use ast::BinaryOp::*;
+        use PrimitiveType::*;
+        let mut comp = ast::Component::new(claw_common::make_source("test", ""));
+        let mut types = HashMap::new();
+        let span = ast::Span::default();
+        let mut typed = |comp: &mut ast::Component, expression: ast::Expression, ptype| {
+            let id = comp.new_expression(expression, span);
+            types.insert(id, ResolvedType::Primitive(ptype));
+            id
+        };
+        let binary = |op, left, right| {
+            ast::Expression::Binary(ast::BinaryExpression {
+                op,
+                left,
+                right,
+                parenthesized: false,
+            })
+        };
+
+        let one = typed(&mut comp, ast::Literal::Integer(1).into(), U32);
+        let two = typed(&mut comp, ast::Literal::Integer(2).into(), U32);
+        let yes = typed(&mut comp, binary(GreaterThan, two, one), Bool);
+        let no = typed(&mut comp, binary(LessThan, two, one), Bool);
+        // A local, whose value is only known at runtime
+        let symbol = comp.intern("unknown");
+        let ident = comp.new_name(symbol, span);
+        let unknown = typed(&mut comp, ast::Identifier { ident }.into(), Bool);
+
+        let cases = [
+            (LogicalAnd, no, unknown, Some(int(0))),
+            (LogicalOr, yes, unknown, Some(int(1))),
+            (LogicalAnd, yes, unknown, None),
+            (LogicalOr, no, unknown, None),
+            (LogicalAnd, yes, no, Some(int(0))),
+            (LogicalOr, no, yes, Some(int(1))),
+            (LogicalAnd, unknown, no, None),
+        ];
+        let cases: Vec<_> = cases
+            .iter()
+            .map(|&(op, left, right, expected)| {
+                let expression = typed(&mut comp, binary(op, left, right), Bool);
+                (expression, expected)
+            })
+            .collect();
+
+        let bindings = HashMap::new();
+        let evaluator = ConstEvaluator::new(&comp, &bindings, &types);
+        for (expression, expected) in cases {
+            assert_eq!(evaluator.eval(expression), expected);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Enums defined in the component, whose cases each have an integer value.
+//!
+//! Values are only used when converting with `as`, an enum value
+//! is the position of its case so that it matches the component model.
+
+use ast::{NameId, TypeDefId};
+use claw_ast as ast;
+
+use std::collections::HashMap;
+
+use crate::const_eval::{eval_integer, int_fits};
+use crate::ResolverError;
+
+/// Check that the cases of each enum have distinct names and values
+pub(crate) fn resolve_enums(comp: &ast::Component) -> Result<(), ResolverError> {
+    for (_, type_def) in comp.iter_type_defs() {
+        if let ast::TypeDefinition::Enum(enum_def) = type_def {
+            check_cases(comp, enum_def)?;
+        }
+    }
+    Ok(())
+}
+
+/// The value of each case of the enum in declaration order
+pub fn discriminants(comp: &ast::Component, enum_id: TypeDefId) -> Vec<i64> {
+    let enum_def = enum_def(comp, enum_id);
+    eval_discriminants(comp, enum_def).expect("Enums are checked by the resolver")
+}
+
+/// The definition of an enum known to be one
+pub fn enum_def(comp: &ast::Component, enum_id: TypeDefId) -> &ast::EnumTypeDef {
+    match comp.get_type_def(enum_id) {
+        ast::TypeDefinition::Enum(enum_def) => enum_def,
+        _ => panic!("Expected an enum"),
+    }
+}
+
+fn check_cases(comp: &ast::Component, enum_def: &ast::EnumTypeDef) -> Result<(), ResolverError> {
+    let mut names: HashMap<&str, NameId> = HashMap::new();
+    for case in enum_def.cases.iter() {
+        let name = comp.get_name(case.ident);
+        if let Some(first) = names.insert(name, case.ident) {
+            return Err(ResolverError::DuplicateCase {
+                src: comp.source(),
+                first_span: comp.name_span(first),
+                span: comp.name_span(case.ident),
+                ident: name.to_owned(),
+            });
+        }
+    }
+
+    let values = eval_discriminants(comp, enum_def)?;
+    let mut seen: HashMap<i64, NameId> = HashMap::new();
+    for (case, value) in enum_def.cases.iter().zip(values) {
+        if let Some(first) = seen.insert(value, case.ident) {
+            return Err(ResolverError::DuplicateDiscriminant {
+                src: comp.source(),
+                first_span: comp.name_span(first),
+                span: comp.name_span(case.ident),
+                value,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Cases without a value are one more than the case before them
+fn eval_discriminants(
+    comp: &ast::Component,
+    enum_def: &ast::EnumTypeDef,
+) -> Result<Vec<i64>, ResolverError> {
+    let mut values = Vec::with_capacity(enum_def.cases.len());
+    let mut next = 0i128;
+    for case in enum_def.cases.iter() {
+        let (value, span) = match case.discriminant {
+            Some(discriminant) => (
+                eval_integer(comp, discriminant),
+                comp.expression_span(discriminant),
+            ),
+            None => (Some(next), comp.name_span(case.ident)),
+        };
+        let value = match value.filter(|value| int_fits(*value, ast::PrimitiveType::S64)) {
+            Some(value) => value,
+            None => {
+                return Err(ResolverError::InvalidDiscriminant {
+                    src: comp.source(),
+                    span,
+                    ident: comp.get_name(case.ident).to_owned(),
+                })
+            }
+        };
+        values.push(value as i64);
+        next = value + 1;
+    }
+    Ok(values)
+}
@@ -1,8 +1,15 @@
-use ast::ExpressionId;
+use ast::{merge, ExpressionId, Span};
 use claw_ast as ast;
 
+use crate::builtins::{Builtin, Shape};
+use crate::const_eval;
+use crate::enums;
+use crate::format::{parse_format, FormatPiece};
+use crate::function::LocalInfo;
+use crate::precedence::check_precedence;
+use crate::records::check_unique;
 use crate::types::{ResolvedType, RESOLVED_BOOL};
-use crate::{FunctionResolver, ItemId, ResolverError};
+use crate::{FunctionResolver, ImportType, ItemId, ResolverError, ResolverWarning};
 
 pub(crate) trait ResolveExpression {
     /// Walk the AST from this node down setting up the resolver.
@@ -87,7 +94,10 @@ macro_rules! gen_resolve_expression {
     }
 }
 
-gen_resolve_expression!([Identifier, Literal, Enum, Call, Unary, Binary]);
+gen_resolve_expression!([
+    Identifier, Literal, Enum, Call, MethodCall, Field, Unary, Binary, Index, Slice, Record, Asm,
+    Cast, If, Match
+]);
 
 impl ResolveExpression for ast::Identifier {
     fn setup_resolve(
@@ -106,6 +116,9 @@ impl ResolveExpression for ast::Identifier {
                 resolver.set_expr_type(expression, ResolvedType::Defined(param_type));
             }
             ItemId::Local(local) => resolver.use_local(local, expression),
+            ItemId::Function(function) => {
+                resolver.set_expr_type(expression, ResolvedType::Function(function));
+            }
             _ => {}
         }
         Ok(())
@@ -143,6 +156,55 @@ impl ResolveExpression for ast::Literal {
         }
         Ok(())
     }
+
+    fn on_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        let value = match self {
+            ast::Literal::Integer(value) => *value,
+            _ => return Ok(()),
+        };
+        let ptype = match rtype.primitive(resolver.component) {
+            Some(ptype) if is_integer(ptype) => ptype,
+            _ => return Ok(()),
+        };
+        // A minus sign in front extends the range, e.g. to allow -128 as an s8
+        let negation = resolver
+            .expr_parent_map
+            .get(&expression)
+            .copied()
+            .filter(|parent| {
+                matches!(
+                    resolver.component.get_expression(*parent),
+                    ast::Expression::Unary(ast::UnaryExpression {
+                        op: ast::UnaryOp::Negate,
+                        ..
+                    })
+                )
+            });
+        if const_eval::int_literal_fits(value, negation.is_some(), ptype) {
+            return Ok(());
+        }
+        let (span, value) = match negation {
+            Some(parent) => (
+                resolver.component.expression_span(parent),
+                format!("-{}", value),
+            ),
+            None => (
+                resolver.component.expression_span(expression),
+                value.to_string(),
+            ),
+        };
+        Err(ResolverError::LiteralOutOfRange {
+            src: resolver.component.source(),
+            span,
+            value,
+            ptype,
+        })
+    }
 }
 
 impl ResolveExpression for ast::EnumLiteral {
@@ -151,8 +213,24 @@ impl ResolveExpression for ast::EnumLiteral {
         expression: ExpressionId,
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
+        let comp = resolver.component;
         let item = resolver.use_name(self.enum_name)?;
         match item {
+            ItemId::Type(ResolvedType::Enum(enum_id)) => {
+                let case_name = comp.get_name(self.case_name);
+                if enums::enum_def(comp, enum_id)
+                    .case(case_name, comp)
+                    .is_none()
+                {
+                    return Err(ResolverError::UnknownCase {
+                        src: comp.source(),
+                        span: comp.name_span(self.case_name),
+                        enum_name: comp.get_name(self.enum_name).to_owned(),
+                        ident: case_name.to_owned(),
+                    });
+                }
+                resolver.set_expr_type(expression, ResolvedType::Enum(enum_id));
+            }
             ItemId::Type(rtype) => {
                 resolver.set_expr_type(expression, rtype);
             }
@@ -162,6 +240,76 @@ impl ResolveExpression for ast::EnumLiteral {
     }
 }
 
+impl ResolveExpression for ast::Cast {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        resolver.setup_child_expression(expression, self.inner)?;
+        let target = ResolvedType::Defined(self.target);
+        resolver.set_expr_type(expression, target);
+        let comp = resolver.component;
+        if let Some(inner) = target.newtype_inner(comp) {
+            // Only the wrapped type can be converted to a newtype
+            resolver.set_expr_type(self.inner, ResolvedType::Defined(inner));
+        } else if target.enum_def(comp).is_some()
+            && const_eval::eval_integer(comp, self.inner).is_some()
+        {
+            // Constants converted to an enum have no other type to go by
+            resolver.set_expr_type(self.inner, ResolvedType::Primitive(ast::PrimitiveType::S64));
+        }
+        Ok(())
+    }
+
+    fn on_child_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        let comp = resolver.component;
+        let target = ResolvedType::Defined(self.target);
+        let unwraps = rtype
+            .newtype_inner(comp)
+            .is_some_and(|inner| target.type_eq(&ResolvedType::Defined(inner), comp));
+        if unwraps || target.newtype(comp).is_some() || rtype.type_eq(&target, comp) {
+            return Ok(());
+        }
+        let int_type = |rtype: ResolvedType| rtype.primitive(comp).filter(|p| is_integer(*p));
+        match (
+            rtype.enum_def(comp),
+            target.enum_def(comp),
+            int_type(target),
+        ) {
+            // Any integer can be converted to an enum, trapping if no case has its value
+            (None, Some(_), _) if int_type(rtype).is_some() => Ok(()),
+            (Some(enum_id), None, Some(ptype)) => {
+                let enum_def = enums::enum_def(comp, enum_id);
+                let values = enums::discriminants(comp, enum_id);
+                for (case, value) in enum_def.cases.iter().zip(values) {
+                    if !const_eval::int_fits(value as i128, ptype) {
+                        return Err(ResolverError::DiscriminantOutOfRange {
+                            src: comp.source(),
+                            span: comp.expression_span(expression),
+                            case: comp.get_name(case.ident).to_owned(),
+                            value,
+                            ptype,
+                        });
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(ResolverError::InvalidCast {
+                src: comp.source(),
+                span: comp.expression_span(expression),
+                from: type_name(rtype, resolver.component),
+                to: type_name(target, resolver.component),
+            }),
+        }
+    }
+}
+
 impl ResolveExpression for ast::Call {
     fn setup_resolve(
         &self,
@@ -185,10 +333,39 @@ impl ResolveExpression for ast::Call {
                 let results = ResolvedType::Defined(*func.results.as_ref().unwrap());
                 (params.collect(), results)
             }
-            _ => panic!("Can only call functions"),
+            // Newtypes are constructed by calling them with the value they wrap
+            ItemId::Type(rtype @ ResolvedType::Newtype(_)) => {
+                let inner = rtype.newtype_inner(resolver.component).unwrap();
+                (vec![ResolvedType::Defined(inner)], rtype)
+            }
+            ItemId::Builtin(Builtin::Format) => {
+                return setup_format_call(self, expression, resolver);
+            }
+            ItemId::Builtin(builtin) if !builtin.has_result() => {
+                return Err(ResolverError::NoResult {
+                    src: resolver.component.source(),
+                    span: resolver.component.expression_span(expression),
+                    ident: builtin.name().to_owned(),
+                });
+            }
+            ItemId::Builtin(builtin) => {
+                return setup_builtin_call(builtin, self, expression, resolver);
+            }
+            _ => {
+                let func_type = resolver.callee_type(self.ident, item)?;
+                let params = func_type.params.into_iter().map(ResolvedType::Defined);
+                let results = ResolvedType::Defined(func_type.results.unwrap());
+                (params.collect(), results)
+            }
         };
-        assert_eq!(params.len(), self.args.len());
-        for (arg, rtype) in self.args.iter().copied().zip(params.into_iter()) {
+        if params.len() != self.args.len() {
+            return Err(ResolverError::CallArgumentsMismatch {
+                src: resolver.component.source(),
+                span: resolver.component.expression_span(expression),
+                ident: resolver.component.get_name(self.ident).to_owned(),
+            });
+        }
+        for (arg, rtype) in self.args.iter().copied().zip(params) {
             resolver.setup_child_expression(expression, arg)?;
             resolver.set_expr_type(arg, rtype);
         }
@@ -197,6 +374,339 @@ impl ResolveExpression for ast::Call {
 
         Ok(())
     }
+
+    fn on_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        match resolver.lookup_name(self.ident)? {
+            ItemId::Builtin(Builtin::Format) => {}
+            ItemId::Builtin(builtin) => {
+                let shape = builtin.result();
+                resolve_builtin_operand(builtin, self, expression, shape, rtype, resolver)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn on_child_resolved(
+        &self,
+        _rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        if let ItemId::Builtin(Builtin::Format) = resolver.lookup_name(self.ident)? {
+            return check_format_args(self, resolver);
+        }
+        if let ItemId::Builtin(builtin) = resolver.lookup_name(self.ident)? {
+            for (arg, shape) in self.args.iter().zip(builtin.params()) {
+                if let Some(rtype) = resolver.expression_types.get(arg).copied() {
+                    resolve_builtin_operand(builtin, self, expression, *shape, rtype, resolver)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Method calls are checked as the call with the receiver as the first argument
+impl ResolveExpression for ast::MethodCall {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        self.as_call().setup_resolve(expression, resolver)
+    }
+
+    fn on_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        self.as_call().on_resolved(rtype, expression, resolver)
+    }
+
+    fn on_child_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        self.as_call()
+            .on_child_resolved(rtype, expression, resolver)
+    }
+}
+
+fn setup_builtin_call(
+    builtin: Builtin,
+    call: &ast::Call,
+    expression: ExpressionId,
+    resolver: &mut FunctionResolver,
+) -> Result<(), ResolverError> {
+    if call.args.len() != builtin.arity() {
+        return Err(ResolverError::CallArgumentsMismatch {
+            src: resolver.component.source(),
+            span: resolver.component.expression_span(expression),
+            ident: builtin.name().to_owned(),
+        });
+    }
+    // Shapes which don't depend on the operand type are known immediately
+    if let Some(rtype) = fixed_type(builtin.result()) {
+        resolver.set_expr_type(expression, rtype);
+    }
+    // Argument and result types are unified once any of them is known
+    for (arg, shape) in call.args.iter().zip(builtin.params()) {
+        resolver.setup_child_expression(expression, *arg)?;
+        if let Some(rtype) = fixed_type(*shape) {
+            resolver.set_expr_type(*arg, rtype);
+        }
+    }
+    Ok(())
+}
+
+/// Check a call to a builtin made as a statement, which has no result.
+///
+/// Only builtins without results have side effects, and their
+/// arguments all have types which don't depend on an operand type.
+pub(crate) fn setup_builtin_statement(
+    builtin: Builtin,
+    call: &ast::Call,
+    resolver: &mut FunctionResolver,
+) -> Result<(), ResolverError> {
+    if call.args.len() != builtin.arity() {
+        return Err(ResolverError::CallArgumentsMismatch {
+            src: resolver.component.source(),
+            span: resolver.component.name_span(call.ident),
+            ident: builtin.name().to_owned(),
+        });
+    }
+    for (arg, shape) in call.args.iter().zip(builtin.params()) {
+        if let Some(rtype) = fixed_type(*shape) {
+            resolver.set_expr_type(*arg, rtype);
+        }
+        // Without a parent expression to check it, it's checked once resolved
+        if *shape == Shape::Bytes {
+            resolver.bytes_args.push((builtin, *arg));
+        }
+    }
+    Ok(())
+}
+
+/// The pieces of the format string literal passed to `format`
+fn format_pieces(
+    call: &ast::Call,
+    expression: ExpressionId,
+    resolver: &FunctionResolver,
+) -> Result<Vec<FormatPiece>, ResolverError> {
+    let comp = resolver.component;
+    let format = match call.args.first() {
+        Some(format) => *format,
+        None => {
+            return Err(ResolverError::CallArgumentsMismatch {
+                src: comp.source(),
+                span: comp.expression_span(expression),
+                ident: Builtin::Format.name().to_owned(),
+            })
+        }
+    };
+    let invalid = |message: String| ResolverError::InvalidFormatString {
+        src: comp.source(),
+        span: comp.expression_span(format),
+        message,
+    };
+    match comp.get_expression(format) {
+        // The arguments are checked at compile time, so the string must be known
+        ast::Expression::Literal(ast::Literal::String(string)) => {
+            parse_format(string).map_err(invalid)
+        }
+        _ => Err(invalid("It must be a string literal".to_owned())),
+    }
+}
+
+fn setup_format_call(
+    call: &ast::Call,
+    expression: ExpressionId,
+    resolver: &mut FunctionResolver,
+) -> Result<(), ResolverError> {
+    let pieces = format_pieces(call, expression, resolver)?;
+    let expected = pieces
+        .iter()
+        .filter(|piece| matches!(piece, FormatPiece::Arg(_)))
+        .count();
+    let found = call.args.len() - 1;
+    if expected != found {
+        return Err(ResolverError::FormatArgumentsMismatch {
+            src: resolver.component.source(),
+            span: resolver.component.expression_span(expression),
+            expected,
+            found,
+        });
+    }
+    let string = ResolvedType::Primitive(ast::PrimitiveType::String);
+    resolver.set_expr_type(expression, string);
+    for arg in call.args.iter() {
+        resolver.setup_child_expression(expression, *arg)?;
+    }
+    resolver.set_expr_type(call.args[0], string);
+    resolver.format_args.extend_from_slice(&call.args[1..]);
+    Ok(())
+}
+
+/// The type of an expression built from number literals when nothing else
+/// decides it, which is `s32` for integers and `f64` if any are floats
+pub(crate) fn default_type(
+    comp: &ast::Component,
+    expression: ExpressionId,
+) -> Option<ResolvedType> {
+    let kind = literal_kind(comp, expression)?;
+    let ptype = match kind {
+        ast::Literal::Float(_) => ast::PrimitiveType::F64,
+        _ => ast::PrimitiveType::S32,
+    };
+    Some(ResolvedType::Primitive(ptype))
+}
+
+/// The most general number literal an expression is built from, if it's only built from them
+fn literal_kind(comp: &ast::Component, expression: ExpressionId) -> Option<&ast::Literal> {
+    match comp.get_expression(expression) {
+        ast::Expression::Literal(literal @ (ast::Literal::Integer(_) | ast::Literal::Float(_))) => {
+            Some(literal)
+        }
+        ast::Expression::Unary(unary) => literal_kind(comp, unary.inner),
+        ast::Expression::Binary(binary) => {
+            let left = literal_kind(comp, binary.left)?;
+            let right = literal_kind(comp, binary.right)?;
+            match left {
+                ast::Literal::Float(_) => Some(left),
+                _ => Some(right),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Check that each argument whose type is known can be written by its placeholder
+fn check_format_args(call: &ast::Call, resolver: &FunctionResolver) -> Result<(), ResolverError> {
+    let comp = resolver.component;
+    let specs = format_pieces(call, call.args[0], resolver)?
+        .into_iter()
+        .filter_map(|piece| match piece {
+            FormatPiece::Arg(spec) => Some(spec),
+            FormatPiece::Text(_) => None,
+        });
+    for (arg, spec) in call.args[1..].iter().zip(specs) {
+        let rtype = match resolver.expression_types.get(arg) {
+            Some(rtype) => *rtype,
+            None => continue,
+        };
+        let accepted = rtype.primitive(comp).filter(|ptype| spec.accepts(*ptype));
+        if accepted.is_none() {
+            return Err(ResolverError::InvalidFormatArgument {
+                src: comp.source(),
+                span: comp.expression_span(*arg),
+                spec,
+                rtype: type_name(rtype, resolver.component),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Recover the operand type of a builtin from the type of its result or one of
+/// its arguments, then use it to set the types of all of the others
+fn resolve_builtin_operand(
+    builtin: Builtin,
+    call: &ast::Call,
+    expression: ExpressionId,
+    shape: Shape,
+    rtype: ResolvedType,
+    resolver: &mut FunctionResolver,
+) -> Result<(), ResolverError> {
+    let comp = resolver.component;
+    let ptype = match shape {
+        Shape::Value => rtype.primitive(comp),
+        Shape::Option => rtype.option_primitive(comp),
+        Shape::List => rtype
+            .list_element(comp)
+            .and_then(|element| ResolvedType::Defined(element).primitive(comp)),
+        Shape::AnyList if rtype.list_element(comp).is_some() => return Ok(()),
+        Shape::AnyList => None,
+        Shape::Bytes => return check_bytes(builtin, expression, rtype, resolver),
+        Shape::Bool | Shape::U8 | Shape::U16 | Shape::U32 | Shape::U64 | Shape::String => {
+            return Ok(())
+        }
+    };
+    let ptype = match ptype.filter(|ptype| builtin.accepts(*ptype)) {
+        Some(ptype) => ptype,
+        None => {
+            return Err(ResolverError::InvalidBuiltinType {
+                src: comp.source(),
+                span: comp.expression_span(expression),
+                name: builtin.name(),
+                rtype: type_name(rtype, resolver.component),
+            })
+        }
+    };
+    if let Some(rtype) = shaped_type(builtin.result(), ptype) {
+        resolver.set_expr_type(expression, rtype);
+    }
+    for (arg, shape) in call.args.iter().zip(builtin.params()) {
+        if let Some(rtype) = shaped_type(*shape, ptype) {
+            resolver.set_expr_type(*arg, rtype);
+        }
+    }
+    Ok(())
+}
+
+/// Check that an argument passed as `bytes` is a `list<u8>`
+pub(crate) fn check_bytes(
+    builtin: Builtin,
+    arg: ExpressionId,
+    rtype: ResolvedType,
+    resolver: &FunctionResolver,
+) -> Result<(), ResolverError> {
+    let comp = resolver.component;
+    let element = rtype
+        .list_element(comp)
+        .and_then(|element| ResolvedType::Defined(element).primitive(comp));
+    if element == Some(ast::PrimitiveType::U8) {
+        return Ok(());
+    }
+    Err(ResolverError::InvalidBuiltinType {
+        src: comp.source(),
+        span: comp.expression_span(arg),
+        name: builtin.name(),
+        rtype: type_name(rtype, resolver.component),
+    })
+}
+
+/// The type of a shape given the operand type, lists can't be built from it
+fn shaped_type(shape: Shape, ptype: ast::PrimitiveType) -> Option<ResolvedType> {
+    match shape {
+        Shape::Value => Some(ResolvedType::Primitive(ptype)),
+        Shape::Option => Some(ResolvedType::Option(ptype)),
+        Shape::List | Shape::AnyList | Shape::Bytes => None,
+        Shape::Bool | Shape::U8 | Shape::U16 | Shape::U32 | Shape::U64 | Shape::String => {
+            fixed_type(shape)
+        }
+    }
+}
+
+fn fixed_type(shape: Shape) -> Option<ResolvedType> {
+    match shape {
+        Shape::Bool => Some(RESOLVED_BOOL),
+        Shape::U8 => Some(ResolvedType::Primitive(ast::PrimitiveType::U8)),
+        Shape::U16 => Some(ResolvedType::Primitive(ast::PrimitiveType::U16)),
+        Shape::U32 => Some(ResolvedType::Primitive(ast::PrimitiveType::U32)),
+        Shape::U64 => Some(ResolvedType::Primitive(ast::PrimitiveType::U64)),
+        Shape::String => Some(ResolvedType::Primitive(ast::PrimitiveType::String)),
+        _ => None,
+    }
 }
 
 impl ResolveExpression for ast::UnaryExpression {
@@ -211,9 +721,10 @@ impl ResolveExpression for ast::UnaryExpression {
     fn on_resolved(
         &self,
         rtype: ResolvedType,
-        _expression: ExpressionId,
+        expression: ExpressionId,
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
+        check_unary_operand(self, rtype, expression, resolver)?;
         resolver.set_expr_type(self.inner, rtype);
         Ok(())
     }
@@ -224,11 +735,495 @@ impl ResolveExpression for ast::UnaryExpression {
         expression: ExpressionId,
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
+        check_unary_operand(self, rtype, expression, resolver)?;
         resolver.set_expr_type(expression, rtype);
         Ok(())
     }
 }
 
+/// Fail if the unary operator can't be applied to values of the type
+fn check_unary_operand(
+    unary: &ast::UnaryExpression,
+    rtype: ResolvedType,
+    expression: ExpressionId,
+    resolver: &FunctionResolver,
+) -> Result<(), ResolverError> {
+    use ast::PrimitiveType::{Bool, F32, F64};
+    let valid = match (unary.op, rtype.primitive(resolver.component)) {
+        (ast::UnaryOp::Negate, Some(ptype)) => is_integer(ptype) || matches!(ptype, F32 | F64),
+        (ast::UnaryOp::LogicalNot, Some(ptype)) => ptype == Bool,
+        (ast::UnaryOp::Invert, Some(ptype)) => is_integer(ptype),
+        (_, None) => false,
+    };
+    if valid {
+        return Ok(());
+    }
+    Err(ResolverError::InvalidUnaryOperatorType {
+        src: resolver.component.source(),
+        span: resolver.component.expression_span(expression),
+        op: unary.op,
+        rtype: type_name(rtype, resolver.component),
+    })
+}
+
+impl ResolveExpression for ast::InlineAsm {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        // Inputs are passed as-is so each keeps whatever type it resolves to
+        for input in self.inputs.iter() {
+            resolver.setup_child_expression(expression, input.expression)?;
+            if let Some(annotation) = input.annotation {
+                resolver.set_expr_type(input.expression, ResolvedType::Defined(annotation));
+            }
+            resolver.asm_inputs.push(input.clone());
+        }
+        resolver.set_expr_type(expression, ResolvedType::Defined(self.result));
+        Ok(())
+    }
+}
+
+impl ResolveExpression for ast::Index {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        resolver.setup_child_expression(expression, self.list)?;
+        resolver.setup_child_expression(expression, self.index)?;
+        resolver.set_expr_type(self.index, ResolvedType::Primitive(ast::PrimitiveType::U32));
+        Ok(())
+    }
+
+    fn on_child_resolved(
+        &self,
+        _rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        // The element type follows from the list, but not the other way around
+        let list_type = match resolver.expression_types.get(&self.list) {
+            Some(list_type) => *list_type,
+            None => return Ok(()),
+        };
+        match list_type.list_element(resolver.component) {
+            Some(element) => resolver.set_expr_type(expression, ResolvedType::Defined(element)),
+            None => {
+                return Err(ResolverError::NotIndexable {
+                    src: resolver.component.source(),
+                    span: resolver.component.expression_span(self.list),
+                    rtype: type_name(list_type, resolver.component),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ResolveExpression for ast::FieldAccess {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        resolver.setup_child_expression(expression, self.record)
+    }
+
+    fn on_child_resolved(
+        &self,
+        rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        // The field type follows from the struct, but not the other way around
+        let comp = resolver.component;
+        let record = match rtype.record(comp).map(|id| comp.get_type_def(id)) {
+            Some(ast::TypeDefinition::Record(record)) => record,
+            _ => {
+                return Err(ResolverError::NoFields {
+                    src: comp.source(),
+                    span: comp.expression_span(self.record),
+                    rtype: type_name(rtype, resolver.component),
+                })
+            }
+        };
+        let name = comp.get_name(self.field);
+        match record.field(name, comp) {
+            Some((_, field)) => {
+                resolver.set_expr_type(expression, ResolvedType::Defined(field.type_id));
+                Ok(())
+            }
+            None => Err(ResolverError::UnknownField {
+                src: comp.source(),
+                span: comp.name_span(self.field),
+                record: comp.get_name(record.ident).to_owned(),
+                ident: name.to_owned(),
+            }),
+        }
+    }
+}
+
+impl ResolveExpression for ast::Slice {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        resolver.setup_child_expression(expression, self.list)?;
+        for bound in self.start.iter().chain(self.end.iter()) {
+            resolver.setup_child_expression(expression, *bound)?;
+            resolver.set_expr_type(*bound, ResolvedType::Primitive(ast::PrimitiveType::U32));
+        }
+        Ok(())
+    }
+
+    fn on_resolved(
+        &self,
+        rtype: ResolvedType,
+        _expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        // A slice has the type of the list it views
+        resolver.set_expr_type(self.list, rtype);
+        Ok(())
+    }
+
+    fn on_child_resolved(
+        &self,
+        _rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        let list_type = match resolver.expression_types.get(&self.list) {
+            Some(list_type) => *list_type,
+            None => return Ok(()),
+        };
+        if list_type.list_element(resolver.component).is_none() {
+            return Err(ResolverError::NotIndexable {
+                src: resolver.component.source(),
+                span: resolver.component.expression_span(self.list),
+                rtype: type_name(list_type, resolver.component),
+            });
+        }
+        resolver.set_expr_type(expression, list_type);
+        Ok(())
+    }
+}
+
+impl ResolveExpression for ast::RecordLiteral {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        let comp = resolver.component;
+        let record_id = match resolver.use_name(self.ident)? {
+            ItemId::Type(ResolvedType::Record(record_id)) => record_id,
+            _ => {
+                return Err(ResolverError::NotAStruct {
+                    src: comp.source(),
+                    span: comp.name_span(self.ident),
+                    ident: comp.get_name(self.ident).to_owned(),
+                })
+            }
+        };
+        let record = match comp.get_type_def(record_id) {
+            ast::TypeDefinition::Record(record) => record,
+            _ => unreachable!("Only structs resolve to records"),
+        };
+        let record_name = comp.get_name(record.ident);
+
+        check_unique(comp, self.fields.iter().map(|(ident, _)| *ident))?;
+        for (ident, value) in self.fields.iter() {
+            let field = match record.field(comp.get_name(*ident), comp) {
+                Some((_, field)) => field,
+                None => {
+                    return Err(ResolverError::UnknownField {
+                        src: comp.source(),
+                        span: comp.name_span(*ident),
+                        record: record_name.to_owned(),
+                        ident: comp.get_name(*ident).to_owned(),
+                    })
+                }
+            };
+            resolver.setup_child_expression(expression, *value)?;
+            resolver.set_expr_type(*value, ResolvedType::Defined(field.type_id));
+        }
+
+        match self.base {
+            Some(base) => {
+                resolver.setup_child_expression(expression, base)?;
+                resolver.set_expr_type(base, ResolvedType::Record(record_id));
+            }
+            None => {
+                // Without a base every field must be given or have a default
+                let missing: Vec<_> = record
+                    .fields
+                    .iter()
+                    .filter(|field| field.default.is_none())
+                    .map(|field| comp.get_name(field.ident))
+                    .filter(|name| !self.fields.iter().any(|(i, _)| comp.get_name(*i) == *name))
+                    .collect();
+                if !missing.is_empty() {
+                    return Err(ResolverError::MissingFields {
+                        src: comp.source(),
+                        span: comp.expression_span(expression),
+                        record: record_name.to_owned(),
+                        fields: missing.join(", "),
+                    });
+                }
+            }
+        }
+
+        resolver.set_expr_type(expression, ResolvedType::Record(record_id));
+        Ok(())
+    }
+}
+
+impl ResolveExpression for ast::IfExpression {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        resolver.setup_child_expression(expression, self.condition)?;
+        resolver.set_expr_type(self.condition, RESOLVED_BOOL);
+        resolver.setup_child_expression(expression, self.then_value)?;
+        resolver.setup_child_expression(expression, self.else_value)?;
+        Ok(())
+    }
+
+    fn on_resolved(
+        &self,
+        rtype: ResolvedType,
+        _expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        resolver.set_expr_type(self.then_value, rtype);
+        resolver.set_expr_type(self.else_value, rtype);
+        Ok(())
+    }
+
+    fn on_child_resolved(
+        &self,
+        _rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        set_from_branches(&[self.then_value, self.else_value], expression, resolver);
+        Ok(())
+    }
+}
+
+impl ResolveExpression for ast::Match {
+    fn setup_resolve(
+        &self,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        resolver.setup_child_expression(expression, self.scrutinee)?;
+        let comp = resolver.component;
+
+        let mut enum_cases = None;
+        // The cases matched so far and the arms which match them
+        let mut covered: Vec<(&str, Span)> = Vec::new();
+        // The first arm which matches anything
+        let mut catch_all = None;
+        // The arm after which every case is matched
+        let mut exhausted = None;
+        for arm in self.arms.iter() {
+            // Guarded arms might not be taken, so they don't cover anything
+            let covering = arm.guard.is_none();
+            let span = pattern_span(&arm.pattern, comp);
+            // Names bound by a pattern are only visible within its arm
+            let checkpoint = resolver.mapping.checkpoint();
+            match arm.pattern {
+                ast::Pattern::Case {
+                    enum_name,
+                    case_name,
+                } => {
+                    let cases = match resolver.use_name(enum_name)? {
+                        ItemId::Type(rtype) => enum_cases_of(rtype, resolver).map(|c| (rtype, c)),
+                        _ => None,
+                    };
+                    let Some((rtype, cases)) = cases else {
+                        return Err(ResolverError::NotAnEnum {
+                            src: comp.source(),
+                            span: comp.name_span(enum_name),
+                            ident: comp.get_name(enum_name).to_owned(),
+                        });
+                    };
+                    let case = comp.get_name(case_name);
+                    if !cases.iter().any(|name| name == case) {
+                        return Err(ResolverError::UnknownCase {
+                            src: comp.source(),
+                            span: comp.name_span(case_name),
+                            enum_name: comp.get_name(enum_name).to_owned(),
+                            ident: case.to_owned(),
+                        });
+                    }
+                    // Patterns of another enum conflict with the scrutinee's type
+                    resolver.set_expr_type(self.scrutinee, rtype);
+                    let earlier = covered.iter().find(|(name, _)| *name == case);
+                    if let Some(cause_span) = catch_all.or(earlier.map(|(_, span)| *span)) {
+                        unreachable_arm(span, cause_span, resolver);
+                    } else if covering {
+                        covered.push((case, span));
+                        if cases
+                            .iter()
+                            .all(|c| covered.iter().any(|(name, _)| name == c))
+                        {
+                            exhausted = Some(span);
+                        }
+                    }
+                    enum_cases = Some(cases);
+                }
+                ast::Pattern::Wildcard(_) => {
+                    if let Some(cause_span) = catch_all.or(exhausted) {
+                        unreachable_arm(span, cause_span, resolver);
+                    } else if covering {
+                        catch_all = Some(span);
+                    }
+                }
+                ast::Pattern::Binding(ident) => {
+                    let info = LocalInfo {
+                        ident,
+                        mutable: false,
+                        annotation: None,
+                    };
+                    let local = resolver.locals.push(info);
+                    resolver.local_spans.insert(local, comp.name_span(ident));
+                    resolver.define_name(ident, ItemId::Local(local))?;
+                    resolver.use_local(local, self.scrutinee);
+                    if let Some(cause_span) = catch_all.or(exhausted) {
+                        unreachable_arm(span, cause_span, resolver);
+                    } else if covering {
+                        catch_all = Some(span);
+                    }
+                }
+            }
+            if let Some(guard) = arm.guard {
+                resolver.setup_child_expression(expression, guard)?;
+                resolver.set_expr_type(guard, RESOLVED_BOOL);
+            }
+            resolver.setup_child_expression(expression, arm.value)?;
+            resolver.mapping.restore(checkpoint);
+        }
+
+        let missing: Vec<_> = match (catch_all.is_some(), enum_cases) {
+            (true, _) => vec![],
+            (false, Some(cases)) => cases
+                .into_iter()
+                .filter(|case| !covered.iter().any(|(name, _)| name == case))
+                .collect(),
+            (false, None) => vec!["any value".to_owned()],
+        };
+        if !missing.is_empty() {
+            return Err(ResolverError::NonExhaustiveMatch {
+                src: comp.source(),
+                span: comp.expression_span(expression),
+                missing: missing.join(", "),
+            });
+        }
+        Ok(())
+    }
+
+    fn on_resolved(
+        &self,
+        rtype: ResolvedType,
+        _expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        for arm in self.arms.iter() {
+            resolver.set_expr_type(arm.value, rtype);
+        }
+        Ok(())
+    }
+
+    fn on_child_resolved(
+        &self,
+        _rtype: ResolvedType,
+        expression: ExpressionId,
+        resolver: &mut FunctionResolver,
+    ) -> Result<(), ResolverError> {
+        let values: Vec<_> = self.arms.iter().map(|arm| arm.value).collect();
+        set_from_branches(&values, expression, resolver);
+
+        // Names bound by patterns have the type of the value matched
+        if let Some(rtype) = resolver.expression_types.get(&self.scrutinee).copied() {
+            for arm in self.arms.iter() {
+                if let ast::Pattern::Binding(ident) = arm.pattern {
+                    if let ItemId::Local(local) = resolver.lookup_name(ident)? {
+                        if !resolver.local_types.contains_key(&local) {
+                            resolver.set_local_type(local, rtype);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The source of a match arm's pattern
+fn pattern_span(pattern: &ast::Pattern, comp: &ast::Component) -> Span {
+    match pattern {
+        ast::Pattern::Case {
+            enum_name,
+            case_name,
+        } => merge(&comp.name_span(*enum_name), &comp.name_span(*case_name)),
+        ast::Pattern::Wildcard(span) => *span,
+        ast::Pattern::Binding(ident) => comp.name_span(*ident),
+    }
+}
+
+/// Warn about a match arm which earlier arms leave nothing for
+fn unreachable_arm(span: Span, cause_span: Span, resolver: &mut FunctionResolver) {
+    resolver
+        .pending_warnings
+        .push(ResolverWarning::UnreachableArm {
+            src: resolver.component.source(),
+            span,
+            cause_span,
+        });
+}
+
+/// Give a conditional expression the type of whichever of its branches
+/// is known, which then passes it on to the others once resolved
+fn set_from_branches(
+    branches: &[ExpressionId],
+    expression: ExpressionId,
+    resolver: &mut FunctionResolver,
+) {
+    let known = branches
+        .iter()
+        .find_map(|branch| resolver.expression_types.get(branch).copied());
+    if let Some(rtype) = known {
+        resolver.set_expr_type(expression, rtype);
+    }
+}
+
+/// The names of the cases of an enum type, or `None` if it isn't one
+fn enum_cases_of(rtype: ResolvedType, resolver: &FunctionResolver) -> Option<Vec<String>> {
+    let comp = resolver.component;
+    match rtype {
+        ResolvedType::Enum(enum_id) => {
+            let cases = enums::enum_def(comp, enum_id).cases.iter();
+            Some(
+                cases
+                    .map(|case| comp.get_name(case.ident).to_owned())
+                    .collect(),
+            )
+        }
+        ResolvedType::Import(import_type) => match &resolver.imports.types[import_type] {
+            ImportType::Enum(enum_type) => Some(enum_type.cases.clone()),
+        },
+        _ => None,
+    }
+}
+
 // Binary Operators
 
 impl ResolveExpression for ast::BinaryExpression {
@@ -240,6 +1235,9 @@ impl ResolveExpression for ast::BinaryExpression {
         if self.is_relation() {
             resolver.set_expr_type(expression, RESOLVED_BOOL);
         }
+        if let Some(warning) = check_precedence(resolver.component, self, expression) {
+            resolver.pending_warnings.push(warning);
+        }
         resolver.setup_child_expression(expression, self.left)?;
         resolver.setup_child_expression(expression, self.right)?;
         Ok(())
@@ -248,9 +1246,23 @@ impl ResolveExpression for ast::BinaryExpression {
     fn on_resolved(
         &self,
         rtype: ResolvedType,
-        _expression: ExpressionId,
+        expression: ExpressionId,
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
+        let valid = match rtype.primitive(resolver.component) {
+            // Strings can also be compared, but that gives a bool
+            Some(ast::PrimitiveType::String) => self.op == ast::BinaryOp::Add,
+            ptype if self.is_integer_only() => ptype.map(is_integer).unwrap_or(false),
+            _ => true,
+        };
+        if !valid {
+            return Err(ResolverError::InvalidOperatorType {
+                src: resolver.component.source(),
+                span: resolver.component.expression_span(expression),
+                op: self.op,
+                rtype: type_name(rtype, resolver.component),
+            });
+        }
         if !self.is_relation() {
             resolver.set_expr_type(self.left, rtype);
             resolver.set_expr_type(self.right, rtype);
@@ -264,6 +1276,19 @@ impl ResolveExpression for ast::BinaryExpression {
         expression: ExpressionId,
         resolver: &mut FunctionResolver,
     ) -> Result<(), ResolverError> {
+        let comp = resolver.component;
+        let opaque = rtype.record(comp).is_some()
+            || rtype.enum_def(comp).is_some()
+            || rtype.newtype(comp).is_some()
+            || rtype.func_type(comp).is_some();
+        if opaque {
+            return Err(ResolverError::InvalidOperatorType {
+                src: resolver.component.source(),
+                span: resolver.component.expression_span(expression),
+                op: self.op,
+                rtype: type_name(rtype, resolver.component),
+            });
+        }
         if !self.is_relation() {
             resolver.set_expr_type(expression, rtype);
         }
@@ -290,3 +1315,32 @@ impl ResolveExpression for ast::BinaryExpression {
         Ok(())
     }
 }
+
+pub(crate) fn is_integer(ptype: ast::PrimitiveType) -> bool {
+    use ast::PrimitiveType::*;
+    matches!(ptype, U8 | S8 | U16 | S16 | U32 | S32 | U64 | S64)
+}
+
+/// Describe a type in a diagnostic, naming primitives directly
+pub(crate) fn type_name(rtype: ResolvedType, comp: &ast::Component) -> String {
+    if let Some(ptype) = rtype.primitive(comp) {
+        format!("{:?}", ptype)
+    } else if let Some(ptype) = rtype.option_primitive(comp) {
+        format!("Option<{:?}>", ptype)
+    } else if let Some(record) = rtype.record(comp) {
+        let ident = comp.get_type_def(record).ident();
+        format!("struct {}", comp.get_name(ident))
+    } else if let Some(enum_id) = rtype.enum_def(comp) {
+        let ident = comp.get_type_def(enum_id).ident();
+        format!("enum {}", comp.get_name(ident))
+    } else if let Some(newtype) = rtype.newtype(comp) {
+        let ident = comp.get_type_def(newtype).ident();
+        format!("newtype {}", comp.get_name(ident))
+    } else if let Some(element) = rtype.list_element(comp) {
+        format!("List<{}>", type_name(ResolvedType::Defined(element), comp))
+    } else if rtype.func_type(comp).is_some() {
+        "function".to_owned()
+    } else {
+        rtype.to_string()
+    }
+}
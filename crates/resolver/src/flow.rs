@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use ast::{merge, ExpressionId, NameId, StatementId};
+use claw_ast as ast;
+
+use crate::builtins::Builtin;
+use crate::const_eval::{ConstEvaluator, ConstValue};
+use crate::lints::Warnings;
+use crate::{ItemId, ResolverError, ResolverWarning};
+
+/// Warn about statements which can never run, `if` conditions which never change
+/// and values which are computed only to be thrown away
+pub(crate) fn check_flow(
+    component: &ast::Component,
+    evaluator: &ConstEvaluator,
+    bindings: &HashMap<NameId, ItemId>,
+    body: &[StatementId],
+    warnings: &mut Warnings,
+) -> Result<(), ResolverError> {
+    FlowChecker {
+        component,
+        evaluator,
+        bindings,
+        warnings,
+    }
+    .check_block(body)?;
+    Ok(())
+}
+
+struct FlowChecker<'a, 'w, 'ctx> {
+    component: &'ctx ast::Component,
+    evaluator: &'a ConstEvaluator<'ctx>,
+    bindings: &'a HashMap<NameId, ItemId>,
+    warnings: &'a mut Warnings<'w>,
+}
+
+impl FlowChecker<'_, '_, '_> {
+    /// Check the block, giving the statement that always returns or panics in it, if any
+    fn check_block(&mut self, block: &[StatementId]) -> Result<Option<StatementId>, ResolverError> {
+        for (index, statement) in block.iter().copied().enumerate() {
+            let diverges = match self.component.get_statement(statement) {
+                ast::Statement::Return(_) => Some(statement),
+                ast::Statement::Call(call) if self.is_panic(call) => Some(statement),
+                ast::Statement::If(if_statement) => self.check_if(if_statement)?,
+                ast::Statement::Expression(statement) => {
+                    self.check_unused(statement.expression)?;
+                    None
+                }
+                _ => None,
+            };
+            if let Some(cause) = diverges {
+                // Everything after the statement that returns is one unreachable region
+                if let Some(last) = block[index + 1..].last() {
+                    let first = block[index + 1];
+                    self.warnings.push(ResolverWarning::UnreachableCode {
+                        src: self.component.source(),
+                        span: merge(
+                            &self.component.statement_span(first),
+                            &self.component.statement_span(*last),
+                        ),
+                        cause_span: self.component.statement_span(cause),
+                        cause: match self.component.get_statement(cause) {
+                            ast::Statement::Return(_) => "returns",
+                            _ => "panics",
+                        },
+                    })?;
+                }
+                return Ok(Some(cause));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Calls to `panic` never come back, unless the name is shadowed
+    fn is_panic(&self, call: &ast::Call) -> bool {
+        matches!(
+            self.bindings.get(&call.ident),
+            Some(ItemId::Builtin(Builtin::Panic))
+        )
+    }
+
+    /// The value of an expression statement is only missed if evaluating it does nothing else
+    fn check_unused(&mut self, expression: ExpressionId) -> Result<(), ResolverError> {
+        if has_side_effects(self.component, expression) {
+            return Ok(());
+        }
+        self.warnings.push(ResolverWarning::UnusedValue {
+            src: self.component.source(),
+            span: self.component.expression_span(expression),
+        })
+    }
+
+    fn check_if(&mut self, if_statement: &ast::If) -> Result<Option<StatementId>, ResolverError> {
+        let condition = match self.evaluator.eval(if_statement.condition) {
+            Some(ConstValue::Int(value)) => Some(value != 0),
+            _ => None,
+        };
+        if let Some(value) = condition {
+            self.warnings.push(ResolverWarning::ConstantCondition {
+                src: self.component.source(),
+                span: self.component.expression_span(if_statement.condition),
+                value,
+            })?;
+        }
+        let diverges = self.check_block(&if_statement.block)?;
+        // Only a block that always runs makes the code after it unreachable
+        Ok(diverges.filter(|_| condition == Some(true)))
+    }
+}
+
+/// Whether evaluating the expression can do anything besides computing its value
+fn has_side_effects(component: &ast::Component, expression: ExpressionId) -> bool {
+    let children = match component.get_expression(expression) {
+        // Calls and inline assembly can do anything
+        ast::Expression::Call(_) | ast::Expression::MethodCall(_) | ast::Expression::Asm(_) => {
+            return true
+        }
+        ast::Expression::Identifier(_) | ast::Expression::Enum(_) | ast::Expression::Literal(_) => {
+            vec![]
+        }
+        ast::Expression::Field(access) => vec![access.record],
+        ast::Expression::If(if_expression) => vec![
+            if_expression.condition,
+            if_expression.then_value,
+            if_expression.else_value,
+        ],
+        ast::Expression::Match(match_expression) => std::iter::once(match_expression.scrutinee)
+            .chain(
+                match_expression
+                    .arms
+                    .iter()
+                    .flat_map(|arm| arm.guard.into_iter().chain([arm.value])),
+            )
+            .collect(),
+        ast::Expression::Unary(unary) => vec![unary.inner],
+        ast::Expression::Binary(binary) => vec![binary.left, binary.right],
+        ast::Expression::Index(index) => vec![index.list, index.index],
+        ast::Expression::Slice(slice) => [Some(slice.list), slice.start, slice.end]
+            .iter()
+            .flatten()
+            .copied()
+            .collect(),
+        ast::Expression::Cast(cast) => vec![cast.inner],
+        ast::Expression::Record(record) => record
+            .fields
+            .iter()
+            .map(|(_, field)| *field)
+            .chain(record.base)
+            .collect(),
+    };
+    children
+        .into_iter()
+        .any(|child| has_side_effects(component, child))
+}
@@ -0,0 +1,148 @@
+//! Format strings for the `format` builtin, e.g. `"{} is {:x} in hex"`.
+//!
+//! Each `{}` placeholder takes the next argument, `{{` and `}}` are
+//! literal braces. A placeholder can have a spec after a colon:
+//! `{:x}` writes an integer in hex and `{:.2}` writes a float with
+//! two decimal places.
+
+use std::fmt;
+
+/// Floats can't be written with more decimal places than this
+pub const MAX_PRECISION: u32 = 17;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FormatPiece {
+    /// Text written as it is
+    Text(String),
+    /// A placeholder for the next argument
+    Arg(FormatSpec),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatSpec {
+    /// `{}`, any number, bool or string
+    Display,
+    /// `{:x}`, an integer in lowercase hexadecimal
+    Hex,
+    /// `{:.N}`, a float with `N` decimal places
+    Precision(u32),
+}
+
+impl FormatSpec {
+    /// Whether arguments of the type can be written with this spec
+    pub fn accepts(&self, ptype: claw_ast::PrimitiveType) -> bool {
+        use claw_ast::PrimitiveType::*;
+        match self {
+            FormatSpec::Display => true,
+            FormatSpec::Hex => matches!(ptype, U8 | S8 | U16 | S16 | U32 | S32 | U64 | S64),
+            FormatSpec::Precision(_) => matches!(ptype, F32 | F64),
+        }
+    }
+}
+
+impl fmt::Display for FormatSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatSpec::Display => write!(f, "{{}}"),
+            FormatSpec::Hex => write!(f, "{{:x}}"),
+            FormatSpec::Precision(precision) => write!(f, "{{:.{}}}", precision),
+        }
+    }
+}
+
+/// Split a format string into its text and placeholders
+pub fn parse_format(format: &str) -> Result<Vec<FormatPiece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err("Unclosed '{', use '{{' for a literal '{'".to_owned()),
+                    }
+                }
+                if !text.is_empty() {
+                    pieces.push(FormatPiece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(FormatPiece::Arg(parse_spec(&placeholder)?));
+            }
+            '}' => return Err("Unmatched '}', use '}}' for a literal '}'".to_owned()),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(FormatPiece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn parse_spec(placeholder: &str) -> Result<FormatSpec, String> {
+    let unknown = || format!("Unknown placeholder \"{{{}}}\"", placeholder);
+    let spec = match placeholder.strip_prefix(':') {
+        Some(spec) => spec,
+        None if placeholder.is_empty() => return Ok(FormatSpec::Display),
+        None => return Err(unknown()),
+    };
+    if spec == "x" {
+        return Ok(FormatSpec::Hex);
+    }
+    let digits = spec.strip_prefix('.').ok_or_else(unknown)?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(unknown());
+    }
+    match digits.parse() {
+        Ok(precision) if precision <= MAX_PRECISION => Ok(FormatSpec::Precision(precision)),
+        _ => Err(format!(
+            "Floats can have at most {} decimal places",
+            MAX_PRECISION
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        let text = |text: &str| FormatPiece::Text(text.to_owned());
+        assert_eq!(
+            parse_format("{} is {:x} {{hex}}").unwrap(),
+            vec![
+                FormatPiece::Arg(FormatSpec::Display),
+                text(" is "),
+                FormatPiece::Arg(FormatSpec::Hex),
+                text(" {hex}"),
+            ]
+        );
+        assert_eq!(
+            parse_format("{:.2}").unwrap(),
+            vec![FormatPiece::Arg(FormatSpec::Precision(2))]
+        );
+        assert_eq!(parse_format("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_format_errors() {
+        for format in ["{", "}", "{0}", "{:y}", "{:.}", "{:.-1}", "{:.18}"] {
+            assert!(
+                parse_format(format).is_err(),
+                "{} should be invalid",
+                format
+            );
+        }
+    }
+}
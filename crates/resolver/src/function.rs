@@ -1,18 +1,23 @@
-use ast::{ExpressionId, NameId, Span, StatementId, TypeId};
+use ast::{ExpressionId, FunctionId, NameId, Span, StatementId, TypeId};
 use claw_ast as ast;
 use claw_common::StackMap;
 
-use cranelift_entity::{entity_impl, EntityList, ListPool, PrimaryMap};
-use std::collections::{HashMap, VecDeque};
+use cranelift_entity::{entity_impl, EntityList, EntityRef, ListPool, PrimaryMap};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[cfg(test)]
 use miette::{miette, LabeledSpan};
 
+use crate::bounds::find_in_bounds;
+use crate::builtins::Builtin;
+use crate::const_eval::{ConstEvaluator, ConstValue};
 use crate::expression::*;
+use crate::flow::check_flow;
 use crate::imports::ImportResolver;
+use crate::lints::Warnings;
 use crate::statement::*;
 use crate::types::ResolvedType;
-use crate::{ItemId, ResolverError};
+use crate::{ItemId, ResolverError, ResolverWarning};
 
 pub(crate) struct FunctionResolver<'ctx> {
     pub(crate) component: &'ctx ast::Component,
@@ -22,6 +27,12 @@ pub(crate) struct FunctionResolver<'ctx> {
     pub(crate) params: PrimaryMap<ParamId, TypeId>,
 
     // Name Resolution
+    /// Names of enclosing function locals which can't be used from this function
+    captures: &'ctx HashSet<String>,
+    /// The functions defined in this one and what they can see
+    nested: Vec<(FunctionId, FunctionScope)>,
+    /// Warnings found while setting up, reported once the function is resolved
+    pub(crate) pending_warnings: Vec<ResolverWarning>,
     /// Entries for each unique local
     pub(crate) locals: PrimaryMap<LocalId, LocalInfo>,
     /// The span for each unique local
@@ -45,6 +56,21 @@ pub(crate) struct FunctionResolver<'ctx> {
 
     // Tye type of each local
     pub local_types: HashMap<LocalId, ResolvedType>,
+    /// Arguments passed as `bytes` to builtins called as statements
+    pub(crate) bytes_args: Vec<(Builtin, ExpressionId)>,
+    /// Inputs of inline asm, which must have a type once resolved
+    pub(crate) asm_inputs: Vec<ast::AsmInput>,
+    /// Arguments written by `format`, which may need a default type
+    pub(crate) format_args: Vec<ExpressionId>,
+    /// The unannotated local each `let` initializer is assigned to, which takes its type
+    pub(crate) initialized_locals: HashMap<ExpressionId, LocalId>,
+}
+
+/// The names visible to a function from outside of it
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FunctionScope {
+    pub(crate) mappings: HashMap<String, ItemId>,
+    pub(crate) captures: HashSet<String>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -74,10 +100,10 @@ impl<'ctx> FunctionResolver<'ctx> {
         component: &'ctx ast::Component,
         imports: &'ctx ImportResolver,
         function: &'ctx ast::Function,
-        mappings: &'ctx HashMap<String, ItemId>,
+        scope: &'ctx FunctionScope,
     ) -> Self {
         let mut params = PrimaryMap::new();
-        let mut mapping: StackMap<String, ItemId> = mappings.clone().into();
+        let mut mapping: StackMap<String, ItemId> = scope.mappings.clone().into();
 
         for (ident, valtype) in function.params.iter() {
             let param = params.push(*valtype);
@@ -90,6 +116,9 @@ impl<'ctx> FunctionResolver<'ctx> {
             imports,
             function,
             params,
+            captures: &scope.captures,
+            nested: Default::default(),
+            pending_warnings: Default::default(),
             mapping,
             locals: Default::default(),
             local_spans: Default::default(),
@@ -100,26 +129,100 @@ impl<'ctx> FunctionResolver<'ctx> {
             local_uses_list_pool: Default::default(),
             local_uses: Default::default(),
             local_types: Default::default(),
+            bytes_args: Default::default(),
+            asm_inputs: Default::default(),
+            format_args: Default::default(),
+            initialized_locals: Default::default(),
         }
     }
 
-    pub(crate) fn resolve(mut self) -> Result<ResolvedFunction, ResolverError> {
+    /// Resolve the function, also giving the scope of each function nested in it
+    pub(crate) fn resolve(
+        mut self,
+        warnings: &mut Warnings,
+    ) -> Result<(ResolvedFunction, Vec<(FunctionId, FunctionScope)>), ResolverError> {
         self.setup_block(&self.function.body)?;
+        for warning in self.pending_warnings.drain(..) {
+            warnings.push(warning)?;
+        }
         self.resolve_types()?;
+        // Nothing else gives literals only written by `format` a type, so they get
+        // the same default as in Rust. One at a time, in case they share a local.
+        for arg in self.format_args.clone() {
+            if self.expression_types.contains_key(&arg) {
+                continue;
+            }
+            let Some(rtype) = default_type(self.component, arg) else {
+                return Err(ResolverError::UnknownFormatArgumentType {
+                    src: self.component.source(),
+                    span: self.component.expression_span(arg),
+                });
+            };
+            self.set_expr_type(arg, rtype);
+            self.resolve_types()?;
+        }
+        for (builtin, arg) in self.bytes_args.iter() {
+            if let Some(rtype) = self.expression_types.get(arg) {
+                check_bytes(*builtin, *arg, *rtype, &self)?;
+            }
+        }
+        for input in self.asm_inputs.iter() {
+            if !self.expression_types.contains_key(&input.expression) {
+                return Err(ResolverError::UnknownAsmInputType {
+                    src: self.component.source(),
+                    span: self.component.expression_span(input.expression),
+                    ident: self.component.get_name(input.ident).to_owned(),
+                });
+            }
+        }
+        // Code can only be generated for locals whose type is known
+        for (local, info) in self.locals.iter() {
+            if !self.local_types.contains_key(&local) {
+                return Err(ResolverError::UnknownLocalType {
+                    src: self.component.source(),
+                    span: self.local_spans[&local],
+                    ident: self.component.get_name(info.ident).to_owned(),
+                });
+            }
+        }
 
-        Ok(ResolvedFunction {
+        let evaluator = ConstEvaluator::new(self.component, &self.bindings, &self.expression_types);
+        evaluator.check_divisors()?;
+        evaluator.check_shifts(warnings)?;
+        evaluator.check_overflow(warnings)?;
+        check_flow(
+            self.component,
+            &evaluator,
+            &self.bindings,
+            &self.function.body,
+            warnings,
+        )?;
+        let constants = evaluator.fold();
+        let in_bounds = find_in_bounds(self.component, &self.bindings, &self.function.body);
+
+        let resolved = ResolvedFunction {
             params: self.params,
             locals: self.locals,
             local_spans: self.local_spans,
             local_types: self.local_types,
             bindings: self.bindings,
             expression_types: self.expression_types,
-        })
+            constants,
+            in_bounds,
+        };
+        Ok((resolved, self.nested))
     }
 
     pub(crate) fn setup_block(&mut self, statements: &[StatementId]) -> Result<(), ResolverError> {
         // Take a checkpoint at the state of the mappings before this block
         let checkpoint = self.mapping.checkpoint();
+        // Nested functions are visible throughout the block they're defined in
+        for statement in statements {
+            if let ast::Statement::Func(nested) = self.component.get_statement(*statement) {
+                let ident = self.component.get_function(nested.function).ident;
+                self.define_name(ident, ItemId::Function(nested.function))?;
+            }
+        }
         // Resolve all of the inner statements
         for statement in statements {
             self.setup_statement(*statement)?;
@@ -152,6 +255,26 @@ impl<'ctx> FunctionResolver<'ctx> {
         Ok(())
     }
 
+    /// Record the names visible to a nested function at the point it's defined
+    pub(crate) fn nest_function(&mut self, function: FunctionId) {
+        let mut scope = FunctionScope {
+            mappings: Default::default(),
+            captures: self.captures.clone(),
+        };
+        for (name, item) in self.mapping.iter() {
+            match item {
+                ItemId::Local(_) | ItemId::Param(_) => {
+                    scope.captures.insert(name.clone());
+                }
+                _ => {
+                    scope.captures.remove(name);
+                    scope.mappings.insert(name.clone(), *item);
+                }
+            }
+        }
+        self.nested.push((function, scope));
+    }
+
     pub(crate) fn define_name(&mut self, ident: NameId, item: ItemId) -> Result<(), ResolverError> {
         self.bindings.insert(ident, item);
         let name = self.component.get_name(ident);
@@ -159,9 +282,26 @@ impl<'ctx> FunctionResolver<'ctx> {
         Ok(())
     }
 
+    /// Where the variable a name currently refers to was defined, if it refers to one
+    pub(crate) fn variable_span(&self, ident: NameId) -> Option<Span> {
+        let name = self.component.get_name(ident);
+        match self.mapping.lookup(name)? {
+            ItemId::Local(local) => self.local_spans.get(local).copied(),
+            ItemId::Param(param) => {
+                let (ident, _) = self.function.params[param.index()];
+                Some(self.component.name_span(ident))
+            }
+            ItemId::Global(global) => {
+                let ident = self.component.get_global(*global).ident;
+                Some(self.component.name_span(ident))
+            }
+            _ => None,
+        }
+    }
+
     pub(crate) fn use_name(&mut self, ident: NameId) -> Result<ItemId, ResolverError> {
         let name = self.component.get_name(ident);
-        let item = match self.mapping.lookup(&name.to_owned()) {
+        let item = match self.mapping.lookup(name) {
             Some(item) => *item,
             None => return self.name_error(ident),
         };
@@ -179,6 +319,13 @@ impl<'ctx> FunctionResolver<'ctx> {
     fn name_error<T>(&self, ident: NameId) -> Result<T, ResolverError> {
         let span = self.component.name_span(ident);
         let ident = self.component.get_name(ident).to_owned();
+        if self.captures.contains(&ident) {
+            return Err(ResolverError::CapturedLocal {
+                src: self.component.source(),
+                span,
+                ident,
+            });
+        }
         Err(ResolverError::NameError {
             src: self.component.source(),
             span,
@@ -186,6 +333,28 @@ impl<'ctx> FunctionResolver<'ctx> {
         })
     }
 
+    /// The signature of a variable being called, which must be written out
+    pub(crate) fn callee_type(
+        &self,
+        ident: NameId,
+        item: ItemId,
+    ) -> Result<ast::FuncType, ResolverError> {
+        let type_id = match item {
+            ItemId::Param(param) => Some(self.params[param]),
+            ItemId::Local(local) => self.locals[local].annotation,
+            ItemId::Global(global) => Some(self.component.get_global(global).type_id),
+            _ => None,
+        };
+        match type_id.map(|type_id| self.component.get_type(type_id)) {
+            Some(ast::ValType::Func(func_type)) => Ok(func_type.clone()),
+            _ => Err(ResolverError::NotCallable {
+                src: self.component.source(),
+                span: self.component.name_span(ident),
+                ident: self.component.get_name(ident).to_owned(),
+            }),
+        }
+    }
+
     pub(crate) fn use_local(&mut self, local: LocalId, expression: ExpressionId) {
         let existing_uses = self.local_uses.get_mut(&local);
         if let Some(uses) = existing_uses {
@@ -218,8 +387,8 @@ impl<'ctx> FunctionResolver<'ctx> {
                             return Err(ResolverError::TypeConflict {
                                 src: self.component.source(),
                                 span,
-                                type_a: *existing_type,
-                                type_b: next_type,
+                                type_a: type_name(*existing_type, self.component),
+                                type_b: type_name(next_type, self.component),
                             });
                         } else {
                             #[cfg(test)]
@@ -236,6 +405,10 @@ impl<'ctx> FunctionResolver<'ctx> {
                     let expression_val = self.component.get_expression(expression);
                     expression_val.on_resolved(next_type, expression, self)?;
 
+                    if let Some(local) = self.initialized_locals.get(&expression).copied() {
+                        self.set_local_type(local, next_type);
+                    }
+
                     if let Some(parent_id) = self.expr_parent_map.get(&expression) {
                         let parent = self.component.get_expression(*parent_id);
                         parent.on_child_resolved(next_type, *parent_id, self)?;
@@ -247,7 +420,12 @@ impl<'ctx> FunctionResolver<'ctx> {
                 ResolverItem::Local(local) => {
                     if let Some(existing_type) = self.local_types.get(&local) {
                         if !next_type.type_eq(existing_type, self.component) {
-                            panic!("Local type error!!!");
+                            return Err(ResolverError::TypeConflict {
+                                src: self.component.source(),
+                                span: self.local_spans[&local],
+                                type_a: type_name(*existing_type, self.component),
+                                type_b: type_name(next_type, self.component),
+                            });
                         } else {
                             #[cfg(test)]
                             self.notify_skipped_local(local);
@@ -345,6 +523,10 @@ pub struct ResolvedFunction {
     pub bindings: HashMap<NameId, ItemId>,
     /// The type of each expression
     pub expression_types: HashMap<ExpressionId, ResolvedType>,
+    /// The value of each expression folded at compile time
+    pub constants: HashMap<ExpressionId, ConstValue>,
+    /// Index expressions known to be in bounds, which skip their bounds check
+    pub in_bounds: HashSet<ExpressionId>,
 }
 
 impl ResolvedFunction {
@@ -3,11 +3,15 @@ use std::collections::HashMap;
 use ast::NameId;
 use claw_ast as ast;
 
+use crate::expression;
 use crate::types::ResolvedType;
 use crate::wit::{self, InterfaceId};
 use crate::ResolverError;
 use cranelift_entity::{entity_impl, PrimaryMap};
 
+/// The name of the import which panics are reported to instead of stderr
+pub const PANIC_HANDLER: &str = "panic-handler";
+
 #[derive(Default)]
 pub struct ImportResolver {
     pub mapping: HashMap<String, ImportItemId>,
@@ -58,7 +62,7 @@ impl ImportResolver {
         for (_, import) in comp.iter_imports() {
             match import {
                 ast::Import::Plain(import) => {
-                    self.resolve_plain_import(import, comp);
+                    self.resolve_plain_import(import, comp)?;
                 }
                 ast::Import::ImportFrom(import) => {
                     self.resolve_import_from(import, comp, wit)?;
@@ -68,12 +72,32 @@ impl ImportResolver {
         Ok(())
     }
 
-    pub fn resolve_plain_import(&mut self, import: &ast::PlainImport, comp: &ast::Component) {
+    pub fn resolve_plain_import(
+        &mut self,
+        import: &ast::PlainImport,
+        comp: &ast::Component,
+    ) -> Result<(), ResolverError> {
         match &import.external_type {
             ast::ExternalType::Function(fn_type) => {
-                self.resolve_plain_import_func(import.ident, import.alias, fn_type, comp);
+                let name = comp.get_name(import.alias.unwrap_or(import.ident));
+                if name == PANIC_HANDLER && !is_panic_handler(fn_type, comp) {
+                    return Err(ResolverError::PanicHandlerSignature {
+                        src: comp.source(),
+                        span: comp.name_span(import.ident),
+                    });
+                }
+                self.resolve_plain_import_func(import.ident, import.alias, fn_type, comp)?;
             }
         };
+        Ok(())
+    }
+
+    /// The imported function which panics are reported to, if there is one
+    pub fn panic_handler(&self) -> Option<ImportFuncId> {
+        self.loose_funcs
+            .iter()
+            .copied()
+            .find(|id| self.funcs[*id].alias == PANIC_HANDLER)
     }
 
     fn resolve_plain_import_func(
@@ -82,7 +106,19 @@ impl ImportResolver {
         alias: Option<NameId>,
         fn_type: &ast::FnType,
         comp: &ast::Component,
-    ) {
+    ) -> Result<(), ResolverError> {
+        let types = fn_type.params.iter().map(|(_, type_id)| *type_id);
+        for type_id in types.chain(fn_type.results) {
+            if !is_importable(comp, type_id, &mut Vec::new()) {
+                return Err(ResolverError::ImportedFunctionType {
+                    src: comp.source(),
+                    span: comp.type_span(type_id),
+                    ident: comp.get_name(name).to_owned(),
+                    rtype: expression::type_name(ResolvedType::Defined(type_id), comp),
+                });
+            }
+        }
+
         let name = comp.get_name(name);
 
         let params = fn_type
@@ -111,6 +147,7 @@ impl ImportResolver {
         let import_item_id = ImportItemId::Func(import_func_id);
         self.mapping.insert(alias.to_owned(), import_item_id);
         self.loose_funcs.push(import_func_id);
+        Ok(())
     }
 
     pub fn resolve_import_from(
@@ -284,3 +321,43 @@ impl<'ctx> InterfaceResolver<'ctx> {
 }
 
 pub struct ResolvedImports {}
+
+/// Only types which lower to primitives can be encoded in a loose import's signature
+fn is_importable(
+    comp: &ast::Component,
+    type_id: ast::TypeId,
+    seen: &mut Vec<ast::TypeDefId>,
+) -> bool {
+    match comp.get_type(type_id) {
+        ast::ValType::Primitive(_) => true,
+        ast::ValType::Named(name) => {
+            let type_def = match comp.find_type_def(comp.get_name(*name)) {
+                Some(type_def) if !seen.contains(&type_def) => type_def,
+                _ => return false,
+            };
+            seen.push(type_def);
+            match comp.get_type_def(type_def) {
+                ast::TypeDefinition::Newtype(newtype) => is_importable(comp, newtype.inner, seen),
+                ast::TypeDefinition::Record(_) | ast::TypeDefinition::Enum(_) => false,
+            }
+        }
+        ast::ValType::Option(_)
+        | ast::ValType::List(_)
+        | ast::ValType::Result(_)
+        | ast::ValType::Func(_) => false,
+    }
+}
+
+/// Panic handlers take the message and return nothing
+fn is_panic_handler(fn_type: &ast::FnType, comp: &ast::Component) -> bool {
+    let is_string = |type_id: ast::TypeId| {
+        matches!(
+            comp.get_type(type_id),
+            ast::ValType::Primitive(ast::PrimitiveType::String)
+        )
+    };
+    match fn_type.params.as_slice() {
+        [(_, param)] => is_string(*param) && fn_type.results.is_none(),
+        _ => false,
+    }
+}
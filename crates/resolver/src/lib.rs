@@ -1,20 +1,31 @@
 #![allow(clippy::single_match)]
 
+mod bounds;
+pub mod builtins;
+pub mod const_eval;
+pub mod enums;
 mod expression;
+mod flow;
+pub mod format;
 mod function;
 mod imports;
+pub mod lints;
+mod precedence;
+pub mod records;
 mod statement;
 pub mod types;
 pub mod wit;
 
-use ast::{FunctionId, GlobalId};
+use ast::{FunctionId, GlobalId, Span};
+use builtins::Builtin;
 use claw_ast as ast;
 use claw_common::Source;
+use lints::{DeniedWarning, Lint, LintConfig, Warnings};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use wit::{ResolvedWit, WitError};
 
-use miette::{Diagnostic, SourceSpan};
+use miette::Diagnostic;
 use thiserror::Error;
 
 pub use function::*;
@@ -26,6 +37,18 @@ pub struct ResolvedComponent {
     pub global_vals: HashMap<GlobalId, ast::Literal>,
     pub imports: ImportResolver,
     pub funcs: HashMap<FunctionId, ResolvedFunction>,
+    pub warnings: Vec<ResolverWarning>,
+}
+
+impl ResolvedComponent {
+    /// Whether any function calls the builtin
+    pub fn uses_builtin(&self, builtin: Builtin) -> bool {
+        self.funcs.values().any(|func| {
+            func.bindings
+                .values()
+                .any(|item| matches!(item, ItemId::Builtin(b) if *b == builtin))
+        })
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -36,6 +59,7 @@ pub enum ItemId {
     Param(ParamId),
     Local(LocalId),
     Function(FunctionId),
+    Builtin(Builtin),
 }
 
 #[derive(Error, Debug, Diagnostic)]
@@ -45,24 +69,32 @@ pub enum ResolverError {
         #[source_code]
         src: Source,
         #[label("This bit")]
-        span: SourceSpan,
+        span: Span,
     },
     #[error("Conflicting types inferred for expression {type_a} != {type_b}")]
     TypeConflict {
         #[source_code]
         src: Source,
         #[label("This bit")]
-        span: SourceSpan,
+        span: Span,
 
-        type_a: ResolvedType,
-        type_b: ResolvedType,
+        type_a: String,
+        type_b: String,
     },
     #[error("Failed to resolve name \"{ident}\"")]
     NameError {
         #[source_code]
         src: Source,
         #[label("Name referenced here")]
-        span: SourceSpan,
+        span: Span,
+        ident: String,
+    },
+    #[error("Nested functions can't use \"{ident}\" from the enclosing function")]
+    CapturedLocal {
+        #[source_code]
+        src: Source,
+        #[label("Used here")]
+        span: Span,
         ident: String,
     },
     #[error("Assigned to immutable variable \"{ident}\"")]
@@ -70,9 +102,9 @@ pub enum ResolverError {
         #[source_code]
         src: Source,
         #[label("Defined here")]
-        defined_span: SourceSpan,
+        defined_span: Span,
         #[label("Assigned here")]
-        assigned_span: SourceSpan,
+        assigned_span: Span,
         ident: String,
     },
     #[error("Function call with wrong number of arguments \"{ident}\"")]
@@ -80,23 +112,449 @@ pub enum ResolverError {
         #[source_code]
         src: Source,
         #[label("Here")]
-        span: SourceSpan,
+        span: Span,
+        ident: String,
+    },
+    #[error("\"{ident}\" is not a function and doesn't have a function type")]
+    NotCallable {
+        #[source_code]
+        src: Source,
+        #[label("Called here")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Exported function \"{ident}\" can't take or return functions")]
+    ExportedFunctionType {
+        #[source_code]
+        src: Source,
+        #[label("This type")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Imported function \"{ident}\" can't take or return {rtype}")]
+    #[diagnostic(help(
+        "Imported functions can only use numbers, bools, strings and newtypes of them"
+    ))]
+    ImportedFunctionType {
+        #[source_code]
+        src: Source,
+        #[label("This type")]
+        span: Span,
+        ident: String,
+        rtype: String,
+    },
+    #[error("Cannot infer the type of asm input \"{ident}\"")]
+    #[diagnostic(help("Give the input a type, e.g. `{ident}: u32 = ...`"))]
+    UnknownAsmInputType {
+        #[source_code]
+        src: Source,
+        #[label("This value")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Cannot infer the type of \"{ident}\"")]
+    #[diagnostic(help("Give it a type, e.g. `let {ident}: u32 = ...`"))]
+    UnknownLocalType {
+        #[source_code]
+        src: Source,
+        #[label("This variable")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Builtin \"{name}\" cannot be applied to {rtype}")]
+    InvalidBuiltinType {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        name: &'static str,
+        rtype: String,
+    },
+    #[error("\"{ident}\" doesn't return a value, so it can only be called as a statement")]
+    NoResult {
+        #[source_code]
+        src: Source,
+        #[label("Used as a value here")]
+        span: Span,
+        ident: String,
+    },
+    #[error("The panic handler must have the type func(message: string)")]
+    PanicHandlerSignature {
+        #[source_code]
+        src: Source,
+        #[label("Imported here")]
+        span: Span,
+    },
+    #[error("Invalid format string: {message}")]
+    InvalidFormatString {
+        #[source_code]
+        src: Source,
+        #[label("This string")]
+        span: Span,
+        message: String,
+    },
+    #[error("Format string has {expected} placeholders but {found} arguments were given")]
+    FormatArgumentsMismatch {
+        #[source_code]
+        src: Source,
+        #[label("Here")]
+        span: Span,
+        expected: usize,
+        found: usize,
+    },
+    #[error("Cannot format {rtype} with \"{spec}\"")]
+    InvalidFormatArgument {
+        #[source_code]
+        src: Source,
+        #[label("This argument")]
+        span: Span,
+        spec: format::FormatSpec,
+        rtype: String,
+    },
+    #[error("Cannot infer the type of this argument")]
+    #[diagnostic(help("Give it a type, e.g. with a variable like `let value: u32 = ...`"))]
+    UnknownFormatArgumentType {
+        #[source_code]
+        src: Source,
+        #[label("This argument")]
+        span: Span,
+    },
+    #[error("Operator {op:?} cannot be applied to {rtype}")]
+    InvalidOperatorType {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        op: ast::BinaryOp,
+        rtype: String,
+    },
+    #[error("Operator {op:?} cannot be applied to {rtype}")]
+    InvalidUnaryOperatorType {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        op: ast::UnaryOp,
+        rtype: String,
+    },
+    #[error("Operator {op:?} always divides by zero")]
+    DivideByZero {
+        #[source_code]
+        src: Source,
+        #[label("This is zero")]
+        span: Span,
+        op: ast::BinaryOp,
+    },
+    #[error("Cannot index into {rtype}")]
+    NotIndexable {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        rtype: String,
+    },
+    #[error("Cannot access fields of {rtype}")]
+    NoFields {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        rtype: String,
+    },
+    #[error("Literal {value} is out of range for {ptype:?}")]
+    LiteralOutOfRange {
+        #[source_code]
+        src: Source,
+        #[label("This literal")]
+        span: Span,
+        value: String,
+        ptype: ast::PrimitiveType,
+    },
+    #[error("Initial value of global \"{ident}\" must be a literal of its type")]
+    InvalidGlobalInit {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Global \"{ident}\" can't have type {rtype}")]
+    #[diagnostic(help("Globals can only hold numbers, bools, enums and newtypes of them"))]
+    InvalidGlobalType {
+        #[source_code]
+        src: Source,
+        #[label("This type")]
+        span: Span,
+        ident: String,
+        rtype: String,
+    },
+    #[error("\"{ident}\" is not a struct")]
+    NotAStruct {
+        #[source_code]
+        src: Source,
+        #[label("Name referenced here")]
+        span: Span,
+        ident: String,
+    },
+    #[error("\"{ident}\" is not an enum")]
+    NotAnEnum {
+        #[source_code]
+        src: Source,
+        #[label("Name referenced here")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Match doesn't cover {missing}")]
+    #[diagnostic(help("Add arms for the missing cases or a '_' arm"))]
+    NonExhaustiveMatch {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        missing: String,
+    },
+    #[error("Struct \"{record}\" has no field \"{ident}\"")]
+    UnknownField {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        record: String,
+        ident: String,
+    },
+    #[error("Field \"{ident}\" is given more than once")]
+    DuplicateField {
+        #[source_code]
+        src: Source,
+        #[label("First here")]
+        first_span: Span,
+        #[label("Again here")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Struct \"{record}\" is missing fields {fields}")]
+    MissingFields {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        record: String,
+        fields: String,
+    },
+    #[error("Default value of field \"{ident}\" must be a literal of its type")]
+    InvalidDefault {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
         ident: String,
     },
+    #[error("{kind} \"{ident}\" contains itself")]
+    RecursiveType {
+        #[source_code]
+        src: Source,
+        #[label("Defined here")]
+        span: Span,
+        kind: &'static str,
+        ident: String,
+    },
+    #[error("Case \"{ident}\" is given more than once")]
+    DuplicateCase {
+        #[source_code]
+        src: Source,
+        #[label("First here")]
+        first_span: Span,
+        #[label("Again here")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Value of case \"{ident}\" must be an integer constant in the range of s64")]
+    InvalidDiscriminant {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        ident: String,
+    },
+    #[error("Cases have the same value {value}")]
+    DuplicateDiscriminant {
+        #[source_code]
+        src: Source,
+        #[label("First here")]
+        first_span: Span,
+        #[label("Again here")]
+        span: Span,
+        value: i64,
+    },
+    #[error("Enum \"{enum_name}\" has no case \"{ident}\"")]
+    UnknownCase {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        enum_name: String,
+        ident: String,
+    },
+    #[error("Can't convert {from} to {to} with `as`")]
+    InvalidCast {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        from: String,
+        to: String,
+    },
+    #[error("Value {value} of case \"{case}\" doesn't fit in {ptype:?}")]
+    DiscriminantOutOfRange {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        case: String,
+        value: i64,
+        ptype: ast::PrimitiveType,
+    },
     #[error("{0} is not yet supported")]
     NotYetSupported(String),
 
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Denied(DeniedWarning),
+
     #[error(transparent)]
     #[diagnostic(transparent)]
     Wit(#[from] WitError),
 }
 
+/// Problems which don't stop the component from being compiled
+#[derive(Error, Debug, Diagnostic, Clone)]
+#[diagnostic(severity(Warning))]
+pub enum ResolverWarning {
+    #[error("Unreachable code")]
+    UnreachableCode {
+        #[source_code]
+        src: Source,
+        #[label("This is never run")]
+        span: Span,
+        #[label("Because this always {cause}")]
+        cause_span: Span,
+        cause: &'static str,
+    },
+    #[error("Unreachable match arm")]
+    UnreachableArm {
+        #[source_code]
+        src: Source,
+        #[label("This arm is never taken")]
+        span: Span,
+        #[label("Because the arms up to here match everything it does")]
+        cause_span: Span,
+    },
+    #[error("Condition is always {value}")]
+    ConstantCondition {
+        #[source_code]
+        src: Source,
+        #[label("This bit")]
+        span: Span,
+        value: bool,
+    },
+    #[error("Shifting {ptype:?} by {amount} is out of range")]
+    ShiftOverflow {
+        #[source_code]
+        src: Source,
+        #[label("Must be less than {bits}")]
+        span: Span,
+        ptype: ast::PrimitiveType,
+        amount: i128,
+        bits: u32,
+    },
+    #[error("Constant arithmetic overflows {ptype:?}")]
+    ConstantOverflow {
+        #[source_code]
+        src: Source,
+        #[label("Evaluates to {value}, which wraps to {wrapped}")]
+        span: Span,
+        ptype: ast::PrimitiveType,
+        value: String,
+        wrapped: i128,
+    },
+    #[error("\"{ident}\" shadows an existing variable")]
+    Shadowing {
+        #[source_code]
+        src: Source,
+        #[label("Defined again here")]
+        span: Span,
+        #[label("Previously defined here")]
+        shadowed_span: Span,
+        ident: String,
+    },
+    #[error("Value of expression is never used")]
+    UnusedValue {
+        #[source_code]
+        src: Source,
+        #[label("This value is discarded")]
+        span: Span,
+    },
+    #[error("`and` is mixed with `or` without parentheses")]
+    #[diagnostic(help("Write `{suggestion}` to make the grouping clear"))]
+    MixedLogicalOps {
+        #[source_code]
+        src: Source,
+        #[label("`and` is grouped first")]
+        span: Span,
+        suggestion: String,
+    },
+    #[error("`{comparison}` is evaluated before `{bitwise}`")]
+    #[diagnostic(help("Write `{suggestion}` to apply `{bitwise}` first"))]
+    BitwiseComparison {
+        #[source_code]
+        src: Source,
+        #[label("`{comparison}` is grouped first")]
+        span: Span,
+        comparison: &'static str,
+        bitwise: &'static str,
+        suggestion: String,
+    },
+}
+
+impl ResolverWarning {
+    /// The lint which controls this warning
+    pub fn lint(&self) -> Lint {
+        match self {
+            ResolverWarning::UnreachableCode { .. } | ResolverWarning::UnreachableArm { .. } => {
+                Lint::UnreachableCode
+            }
+            ResolverWarning::ConstantCondition { .. } => Lint::ConstantCondition,
+            ResolverWarning::ShiftOverflow { .. } => Lint::ShiftOverflow,
+            ResolverWarning::ConstantOverflow { .. } => Lint::ConstantOverflow,
+            ResolverWarning::Shadowing { .. } => Lint::Shadowing,
+            ResolverWarning::UnusedValue { .. } => Lint::UnusedValue,
+            ResolverWarning::MixedLogicalOps { .. } | ResolverWarning::BitwiseComparison { .. } => {
+                Lint::Precedence
+            }
+        }
+    }
+}
+
 pub fn resolve(
     comp: &ast::Component,
     wit: wit::ResolvedWit,
+) -> Result<ResolvedComponent, ResolverError> {
+    resolve_with_lints(comp, wit, &LintConfig::default())
+}
+
+/// Resolve the component, reporting warnings at the given lint levels
+pub fn resolve_with_lints(
+    comp: &ast::Component,
+    wit: wit::ResolvedWit,
+    lints: &LintConfig,
 ) -> Result<ResolvedComponent, ResolverError> {
     let mut mappings: HashMap<String, ItemId> = Default::default();
 
+    // Builtins are added first so that they can be shadowed
+    for builtin in Builtin::ALL.iter() {
+        mappings.insert(builtin.name().to_owned(), ItemId::Builtin(*builtin));
+    }
+
     let mut imports = ImportResolver::default();
     imports.resolve_imports(comp, &wit)?;
     for (name, import) in imports.mapping.iter() {
@@ -110,30 +568,73 @@ pub fn resolve(
         }
     }
 
+    for (id, type_def) in comp.iter_type_defs() {
+        let name = comp.get_name(type_def.ident());
+        let rtype = match type_def {
+            ast::TypeDefinition::Record(_) => ResolvedType::Record(id),
+            ast::TypeDefinition::Enum(_) => ResolvedType::Enum(id),
+            ast::TypeDefinition::Newtype(_) => ResolvedType::Newtype(id),
+        };
+        mappings.insert(name.to_owned(), ItemId::Type(rtype));
+    }
     for (id, global) in comp.iter_globals() {
         let name = comp.get_name(global.ident);
         mappings.insert(name.to_owned(), ItemId::Global(id));
     }
     for (id, function) in comp.iter_functions() {
+        if function.nested {
+            continue;
+        }
         let name = comp.get_name(function.ident);
         mappings.insert(name.to_owned(), ItemId::Function(id));
     }
 
+    records::resolve_records(comp, &mappings)?;
+    enums::resolve_enums(comp)?;
+
+    // Function values only exist inside the component
+    for (_, function) in comp.iter_functions().filter(|(_, f)| f.exported) {
+        let types = function.params.iter().map(|(_, type_id)| *type_id);
+        for type_id in types.chain(function.results) {
+            if contains_func_type(comp, type_id, &mut Vec::new()) {
+                return Err(ResolverError::ExportedFunctionType {
+                    src: comp.source(),
+                    span: comp.type_span(type_id),
+                    ident: comp.get_name(function.ident).to_owned(),
+                });
+            }
+        }
+    }
+
     let mut global_vals: HashMap<GlobalId, ast::Literal> = HashMap::new();
 
     for (id, global) in comp.iter_globals() {
-        let global_val = match comp.get_expression(global.init_value) {
-            ast::Expression::Literal(literal) => literal.clone(),
-            _ => panic!("Only literal expressions allowed in global initializer"),
-        };
-        global_vals.insert(id, global_val);
+        let ptype = global_ptype(comp, global)?;
+        global_vals.insert(id, global_value(comp, global, ptype)?);
     }
 
     let mut funcs: HashMap<FunctionId, ResolvedFunction> = HashMap::new();
+    let mut warnings = Warnings::new(lints);
 
-    for (id, function) in comp.iter_functions() {
-        let resolver = FunctionResolver::new(comp, &imports, function, &mappings);
-        funcs.insert(id, resolver.resolve()?);
+    // Top level functions all see the same scope
+    let top_scope = FunctionScope {
+        mappings,
+        captures: Default::default(),
+    };
+    // Nested functions are resolved after the function they're in
+    let mut pending: VecDeque<(FunctionId, FunctionScope)> = VecDeque::new();
+    for (id, function) in comp.iter_functions().filter(|(_, f)| !f.nested) {
+        let resolver = FunctionResolver::new(comp, &imports, function, &top_scope);
+        let (resolved, nested) = resolver.resolve(&mut warnings)?;
+        funcs.insert(id, resolved);
+        pending.extend(nested);
+    }
+    while let Some((id, scope)) = pending.pop_front() {
+        let function = comp.get_function(id);
+        let resolver = FunctionResolver::new(comp, &imports, function, &scope);
+        let (resolved, nested) = resolver.resolve(&mut warnings)?;
+        funcs.insert(id, resolved);
+        pending.extend(nested);
     }
 
     Ok(ResolvedComponent {
@@ -141,5 +642,117 @@ pub fn resolve(
         global_vals,
         imports,
         funcs,
+        warnings: warnings.into_vec(),
     })
 }
+
+/// Each global is a single core wasm global,
+/// so it can only hold a value which lowers to one number
+fn global_ptype(
+    comp: &ast::Component,
+    global: &ast::Global,
+) -> Result<ast::PrimitiveType, ResolverError> {
+    let stored = stored_type(comp, global.type_id);
+    match stored.primitive(comp) {
+        Some(ptype) if ptype != ast::PrimitiveType::String => Ok(ptype),
+        // Enums are stored as the position of their case
+        _ if stored.enum_def(comp).is_some() => Ok(ast::PrimitiveType::U32),
+        _ => Err(ResolverError::InvalidGlobalType {
+            src: comp.source(),
+            span: comp.type_span(global.type_id),
+            ident: comp.get_name(global.ident).to_owned(),
+            rtype: expression::type_name(ResolvedType::Defined(global.type_id), comp),
+        }),
+    }
+}
+
+/// A newtype is stored as the type it wraps
+fn stored_type(comp: &ast::Component, type_id: ast::TypeId) -> ResolvedType {
+    let mut rtype = ResolvedType::Defined(type_id);
+    while let Some(inner) = rtype.newtype_inner(comp) {
+        rtype = ResolvedType::Defined(inner);
+    }
+    rtype
+}
+
+/// Globals are initialized with a literal, which may be a negative number or an enum case
+fn global_value(
+    comp: &ast::Component,
+    global: &ast::Global,
+    ptype: ast::PrimitiveType,
+) -> Result<ast::Literal, ResolverError> {
+    let span = comp.expression_span(global.init_value);
+    let invalid = || ResolverError::InvalidGlobalInit {
+        src: comp.source(),
+        span,
+        ident: comp.get_name(global.ident).to_owned(),
+    };
+    if let Some(enum_id) = stored_type(comp, global.type_id).enum_def(comp) {
+        return enum_case_value(comp, global.init_value, enum_id).ok_or_else(invalid);
+    }
+    let (literal, negated) =
+        const_eval::signed_literal(comp, global.init_value).ok_or_else(invalid)?;
+    if let ast::Literal::Integer(value) = literal {
+        if expression::is_integer(ptype) && !const_eval::int_literal_fits(*value, negated, ptype) {
+            let sign = if negated { "-" } else { "" };
+            return Err(ResolverError::LiteralOutOfRange {
+                src: comp.source(),
+                span,
+                value: format!("{}{}", sign, value),
+                ptype,
+            });
+        }
+    }
+    const_eval::literal_value(literal, negated, ptype).ok_or_else(invalid)
+}
+
+fn enum_case_value(
+    comp: &ast::Component,
+    expression: ast::ExpressionId,
+    enum_id: ast::TypeDefId,
+) -> Option<ast::Literal> {
+    let literal = match comp.get_expression(expression) {
+        ast::Expression::Enum(literal) => literal,
+        _ => return None,
+    };
+    if comp.find_type_def(comp.get_name(literal.enum_name)) != Some(enum_id) {
+        return None;
+    }
+    let case_name = comp.get_name(literal.case_name);
+    let (index, _) = enums::enum_def(comp, enum_id).case(case_name, comp)?;
+    Some(ast::Literal::Integer(index as u64))
+}
+
+fn contains_func_type(
+    comp: &ast::Component,
+    type_id: ast::TypeId,
+    seen: &mut Vec<ast::TypeDefId>,
+) -> bool {
+    match comp.get_type(type_id) {
+        ast::ValType::Func(_) => true,
+        ast::ValType::Option(inner) | ast::ValType::List(inner) => {
+            contains_func_type(comp, *inner, seen)
+        }
+        ast::ValType::Result(result) => {
+            contains_func_type(comp, result.ok, seen) || contains_func_type(comp, result.err, seen)
+        }
+        ast::ValType::Primitive(_) => false,
+        ast::ValType::Named(name) => {
+            let record = match comp.find_type_def(comp.get_name(*name)) {
+                Some(record) if !seen.contains(&record) => record,
+                _ => return false,
+            };
+            seen.push(record);
+            match comp.get_type_def(record) {
+                ast::TypeDefinition::Record(def) => def
+                    .fields
+                    .iter()
+                    .any(|field| contains_func_type(comp, field.type_id, seen)),
+                ast::TypeDefinition::Enum(_) => false,
+                ast::TypeDefinition::Newtype(newtype) => {
+                    contains_func_type(comp, newtype.inner, seen)
+                }
+            }
+        }
+    }
+}
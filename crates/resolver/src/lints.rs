@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use miette::{Diagnostic, LabeledSpan, Severity, SourceCode};
+
+use crate::{ResolverError, ResolverWarning};
+
+/// A kind of warning whose level can be configured
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnreachableCode,
+    ConstantCondition,
+    ShiftOverflow,
+    ConstantOverflow,
+    Shadowing,
+    UnusedValue,
+    Precedence,
+}
+
+/// What to do when a lint finds something
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintLevel {
+    /// Say nothing
+    Allow,
+    /// Report a warning and keep compiling
+    Warn,
+    /// Report an error and stop compiling
+    Deny,
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[
+        Lint::UnreachableCode,
+        Lint::ConstantCondition,
+        Lint::ShiftOverflow,
+        Lint::ConstantOverflow,
+        Lint::Shadowing,
+        Lint::UnusedValue,
+        Lint::Precedence,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::ConstantCondition => "constant-condition",
+            Lint::ShiftOverflow => "shift-overflow",
+            Lint::ConstantOverflow => "constant-overflow",
+            Lint::Shadowing => "shadowing",
+            Lint::UnusedValue => "unused-value",
+            Lint::Precedence => "precedence",
+        }
+    }
+
+    pub fn default_level(&self) -> LintLevel {
+        match self {
+            Lint::UnreachableCode
+            | Lint::ConstantCondition
+            | Lint::ShiftOverflow
+            | Lint::ConstantOverflow
+            | Lint::UnusedValue
+            | Lint::Precedence => LintLevel::Warn,
+            // Shadowing is idiomatic, but some codebases forbid it
+            Lint::Shadowing => LintLevel::Allow,
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.name() == s)
+            .ok_or_else(|| format!("Unknown lint \"{}\"", s))
+    }
+}
+
+/// The level of each lint, falling back to its default level
+#[derive(Clone, Debug, Default)]
+pub struct LintConfig {
+    levels: HashMap<Lint, LintLevel>,
+}
+
+impl LintConfig {
+    /// Deny every lint, so that any warning stops compilation
+    pub fn strict() -> Self {
+        let levels = Lint::ALL.iter().map(|lint| (*lint, LintLevel::Deny));
+        LintConfig {
+            levels: levels.collect(),
+        }
+    }
+
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+}
+
+/// Collects the warnings of a component, filtered by the lint levels
+pub(crate) struct Warnings<'a> {
+    config: &'a LintConfig,
+    warnings: Vec<ResolverWarning>,
+}
+
+impl<'a> Warnings<'a> {
+    pub fn new(config: &'a LintConfig) -> Self {
+        Warnings {
+            config,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Report the warning, failing if its lint is denied
+    pub fn push(&mut self, warning: ResolverWarning) -> Result<(), ResolverError> {
+        match self.config.level(warning.lint()) {
+            LintLevel::Allow => Ok(()),
+            LintLevel::Warn => {
+                self.warnings.push(warning);
+                Ok(())
+            }
+            LintLevel::Deny => Err(ResolverError::Denied(DeniedWarning(warning))),
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<ResolverWarning> {
+        self.warnings
+    }
+}
+
+/// A warning reported as an error because its lint is denied
+#[derive(Debug)]
+pub struct DeniedWarning(pub ResolverWarning);
+
+impl fmt::Display for DeniedWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for DeniedWarning {}
+
+impl Diagnostic for DeniedWarning {
+    fn severity(&self) -> Option<Severity> {
+        Some(Severity::Error)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = format!("The \"{}\" lint is denied", self.0.lint());
+        Some(Box::new(help))
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.0.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.0.labels()
+    }
+}
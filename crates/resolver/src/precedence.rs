@@ -0,0 +1,61 @@
+use ast::{BinaryOp, ExpressionId};
+use claw_ast as ast;
+
+use crate::ResolverWarning;
+
+/// Warn about operators whose precedence is easily misread when they
+/// are mixed without parentheses, giving the likely intended grouping
+pub(crate) fn check_precedence(
+    comp: &ast::Component,
+    binary: &ast::BinaryExpression,
+    expression: ExpressionId,
+) -> Option<ResolverWarning> {
+    // Groupings written out in parentheses are deliberate
+    let operand = |operand: ExpressionId| match comp.get_expression(operand) {
+        ast::Expression::Binary(inner) if !inner.parenthesized => Some(inner),
+        _ => None,
+    };
+    let span = comp.expression_span(expression);
+
+    // `a or b and c` is grouped as `a or (b and c)`, which is usually what was meant
+    if binary.op == BinaryOp::LogicalOr {
+        for side in [binary.left, binary.right] {
+            if operand(side).is_some_and(|inner| inner.op == BinaryOp::LogicalAnd) {
+                let inner_span = comp.expression_span(side);
+                return Some(ResolverWarning::MixedLogicalOps {
+                    src: comp.source(),
+                    span: inner_span,
+                    suggestion: parenthesize(comp, span, inner_span.start(), inner_span.end()),
+                });
+            }
+        }
+    }
+
+    // `x & 1 == 0` is grouped as `x & (1 == 0)`, where `(x & 1) == 0` was meant
+    if binary.is_bitwise() {
+        let comparison = |side| operand(side).filter(|inner| inner.is_relation());
+        let (inner, start, end) = if let Some(inner) = comparison(binary.right) {
+            (inner, span.start(), comp.expression_span(inner.left).end())
+        } else if let Some(inner) = comparison(binary.left) {
+            (inner, comp.expression_span(inner.right).start(), span.end())
+        } else {
+            return None;
+        };
+        return Some(ResolverWarning::BitwiseComparison {
+            src: comp.source(),
+            span,
+            comparison: inner.op.symbol(),
+            bitwise: binary.op.symbol(),
+            suggestion: parenthesize(comp, span, start, end),
+        });
+    }
+    None
+}
+
+/// The source of the span with parentheses around the part from `start` to `end`
+fn parenthesize(comp: &ast::Component, span: ast::Span, start: usize, end: usize) -> String {
+    let text = comp.span_text(span);
+    let start = start - span.start();
+    let end = end - span.start();
+    format!("{}({}){}", &text[..start], &text[start..end], &text[end..])
+}
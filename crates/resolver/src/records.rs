@@ -0,0 +1,162 @@
+use ast::{NameId, TypeDefId, TypeId};
+use claw_ast as ast;
+
+use std::collections::{HashMap, HashSet};
+
+use crate::const_eval::{literal_value, signed_literal};
+use crate::types::ResolvedType;
+use crate::{ItemId, ResolverError};
+
+/// Check the struct definitions and every use of a named type
+pub(crate) fn resolve_records(
+    comp: &ast::Component,
+    mappings: &HashMap<String, ItemId>,
+) -> Result<(), ResolverError> {
+    for (_, valtype) in comp.iter_types() {
+        if let ast::ValType::Named(name) = valtype {
+            lookup_type_def(comp, mappings, *name)?;
+        }
+    }
+
+    for (id, type_def) in comp.iter_type_defs() {
+        if let ast::TypeDefinition::Record(record) = type_def {
+            check_fields(comp, record)?;
+        }
+        check_recursion(comp, id, &mut Vec::new())?;
+    }
+    Ok(())
+}
+
+/// Find the struct, enum or newtype that a name refers to
+fn lookup_type_def(
+    comp: &ast::Component,
+    mappings: &HashMap<String, ItemId>,
+    ident: NameId,
+) -> Result<TypeDefId, ResolverError> {
+    let name = comp.get_name(ident);
+    match mappings.get(name) {
+        Some(ItemId::Type(
+            ResolvedType::Record(id) | ResolvedType::Enum(id) | ResolvedType::Newtype(id),
+        )) => Ok(*id),
+        Some(_) => Err(ResolverError::NotAStruct {
+            src: comp.source(),
+            span: comp.name_span(ident),
+            ident: name.to_owned(),
+        }),
+        None => Err(ResolverError::NameError {
+            src: comp.source(),
+            span: comp.name_span(ident),
+            ident: name.to_owned(),
+        }),
+    }
+}
+
+/// Check that the same field isn't named twice, used for both definitions and literals
+pub(crate) fn check_unique<'a>(
+    comp: &'a ast::Component,
+    idents: impl Iterator<Item = NameId>,
+) -> Result<(), ResolverError> {
+    let mut seen: HashMap<&'a str, NameId> = HashMap::new();
+    for ident in idents {
+        let name = comp.get_name(ident);
+        if let Some(first) = seen.insert(name, ident) {
+            return Err(ResolverError::DuplicateField {
+                src: comp.source(),
+                first_span: comp.name_span(first),
+                span: comp.name_span(ident),
+                ident: name.to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_fields(comp: &ast::Component, record: &ast::RecordTypeDef) -> Result<(), ResolverError> {
+    check_unique(comp, record.fields.iter().map(|field| field.ident))?;
+
+    // Like global initializers, defaults are limited to literals
+    for field in record.fields.iter() {
+        let default = match field.default {
+            Some(default) => default,
+            None => continue,
+        };
+        if default_value(comp, field).is_none() {
+            return Err(ResolverError::InvalidDefault {
+                src: comp.source(),
+                span: comp.expression_span(default),
+                ident: comp.get_name(field.ident).to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The value of a field when it's left out,
+/// or None if it has no default or the default isn't valid
+pub fn default_value(comp: &ast::Component, field: &ast::RecordField) -> Option<ast::Literal> {
+    let ptype = match comp.get_type(field.type_id) {
+        ast::ValType::Primitive(ptype) => *ptype,
+        _ => return None,
+    };
+    let (literal, negated) = signed_literal(comp, field.default?)?;
+    literal_value(literal, negated, ptype)
+}
+
+/// Structs and newtypes can't contain themselves, even through a
+/// list, as the component model has no recursive types
+fn check_recursion(
+    comp: &ast::Component,
+    type_def: TypeDefId,
+    stack: &mut Vec<TypeDefId>,
+) -> Result<(), ResolverError> {
+    let def = comp.get_type_def(type_def);
+    if stack.contains(&type_def) {
+        let kind = match def {
+            ast::TypeDefinition::Newtype(_) => "Newtype",
+            _ => "Struct",
+        };
+        return Err(ResolverError::RecursiveType {
+            src: comp.source(),
+            span: comp.name_span(def.ident()),
+            kind,
+            ident: comp.get_name(def.ident()).to_owned(),
+        });
+    }
+    stack.push(type_def);
+    let mut contained = HashSet::new();
+    match def {
+        ast::TypeDefinition::Record(record) => {
+            for field in record.fields.iter() {
+                contained_type_defs(comp, field.type_id, &mut contained);
+            }
+        }
+        ast::TypeDefinition::Enum(_) => {}
+        ast::TypeDefinition::Newtype(newtype) => {
+            contained_type_defs(comp, newtype.inner, &mut contained)
+        }
+    }
+    for inner in contained {
+        check_recursion(comp, inner, stack)?;
+    }
+    stack.pop();
+    Ok(())
+}
+
+fn contained_type_defs(comp: &ast::Component, type_id: TypeId, out: &mut HashSet<TypeDefId>) {
+    match comp.get_type(type_id) {
+        ast::ValType::Option(inner) | ast::ValType::List(inner) => {
+            contained_type_defs(comp, *inner, out)
+        }
+        ast::ValType::Result(result) => {
+            contained_type_defs(comp, result.ok, out);
+            contained_type_defs(comp, result.err, out);
+        }
+        // Functions are stored by reference, so their signatures can mention the struct
+        ast::ValType::Primitive(_) | ast::ValType::Func(_) => {}
+        ast::ValType::Named(name) => {
+            if let Some(record) = comp.find_type_def(comp.get_name(*name)) {
+                out.insert(record);
+            }
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use claw_ast as ast;
 
+use crate::expression::setup_builtin_statement;
 use crate::types::{ResolvedType, RESOLVED_BOOL};
-use crate::{FunctionResolver, ItemId, LocalInfo, ResolverError};
+use crate::{FunctionResolver, ItemId, LocalInfo, ResolverError, ResolverWarning};
 
 pub(crate) trait ResolveStatement {
     /// Set up locals
@@ -34,7 +35,7 @@ macro_rules! gen_resolve_statement {
     }
 }
 
-gen_resolve_statement!([Let, Assign, Call, If, Return]);
+gen_resolve_statement!([Let, Assign, Call, If, Return, Func, Expression]);
 
 impl ResolveStatement for ast::Let {
     fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
@@ -45,6 +46,14 @@ impl ResolveStatement for ast::Let {
         };
         let local = resolver.locals.push(info);
         let span = resolver.component.name_span(self.ident);
+        if let Some(shadowed_span) = resolver.variable_span(self.ident) {
+            resolver.pending_warnings.push(ResolverWarning::Shadowing {
+                src: resolver.component.source(),
+                span,
+                shadowed_span,
+                ident: resolver.component.get_name(self.ident).to_owned(),
+            });
+        }
         resolver.local_spans.insert(local, span);
         let item = ItemId::Local(local);
         resolver.define_name(self.ident, item)?;
@@ -52,8 +61,12 @@ impl ResolveStatement for ast::Let {
         resolver.setup_expression(self.expression)?;
         resolver.use_local(local, self.expression);
 
-        if let Some(annotation) = self.annotation {
-            resolver.set_local_type(local, ResolvedType::Defined(annotation))
+        match self.annotation {
+            Some(annotation) => resolver.set_local_type(local, ResolvedType::Defined(annotation)),
+            // Otherwise the local has the type of its initializer
+            None => {
+                resolver.initialized_locals.insert(self.expression, local);
+            }
         }
 
         Ok(())
@@ -105,14 +118,50 @@ impl ResolveStatement for ast::Assign {
 
 impl ResolveStatement for ast::Call {
     fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
-        resolver.use_name(self.ident)?;
+        let item = resolver.use_name(self.ident)?;
         for arg in self.args.iter() {
             resolver.setup_expression(*arg)?;
         }
+        if let ItemId::Builtin(builtin) = item {
+            if !builtin.has_result() {
+                return setup_builtin_statement(builtin, self, resolver);
+            }
+        }
+        if let ItemId::Type(rtype @ ResolvedType::Newtype(_)) = item {
+            let inner = rtype.newtype_inner(resolver.component).unwrap();
+            if self.args.len() != 1 {
+                return Err(ResolverError::CallArgumentsMismatch {
+                    src: resolver.component.source(),
+                    span: resolver.component.name_span(self.ident),
+                    ident: resolver.component.get_name(self.ident).to_owned(),
+                });
+            }
+            resolver.set_expr_type(self.args[0], ResolvedType::Defined(inner));
+        }
+        // Calling a variable goes through its function type
+        if let ItemId::Param(_) | ItemId::Local(_) | ItemId::Global(_) = item {
+            let func_type = resolver.callee_type(self.ident, item)?;
+            if func_type.params.len() != self.args.len() {
+                return Err(ResolverError::CallArgumentsMismatch {
+                    src: resolver.component.source(),
+                    span: resolver.component.name_span(self.ident),
+                    ident: resolver.component.get_name(self.ident).to_owned(),
+                });
+            }
+            for (arg, param) in self.args.iter().zip(func_type.params) {
+                resolver.set_expr_type(*arg, ResolvedType::Defined(param));
+            }
+        }
         Ok(())
     }
 }
 
+impl ResolveStatement for ast::ExpressionStatement {
+    fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
+        resolver.setup_expression(self.expression)
+    }
+}
+
 impl ResolveStatement for ast::If {
     fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
         resolver.set_expr_type(self.condition, RESOLVED_BOOL);
@@ -144,3 +193,11 @@ impl ResolveStatement for ast::Return {
         Ok(())
     }
 }
+
+impl ResolveStatement for ast::NestedFunc {
+    fn setup_resolve(&self, resolver: &mut FunctionResolver) -> Result<(), ResolverError> {
+        // The body is resolved on its own once the enclosing function is done
+        resolver.nest_function(self.function);
+        Ok(())
+    }
+}
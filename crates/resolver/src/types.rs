@@ -1,4 +1,4 @@
-use ast::TypeId;
+use ast::{FunctionId, TypeDefId, TypeId};
 use claw_ast as ast;
 
 use crate::imports::ImportTypeId;
@@ -8,6 +8,16 @@ pub enum ResolvedType {
     Primitive(ast::PrimitiveType),
     Import(ImportTypeId),
     Defined(TypeId),
+    /// An option of a primitive, produced by builtins which can fail
+    Option(ast::PrimitiveType),
+    /// A struct defined in the component
+    Record(TypeDefId),
+    /// An enum defined in the component
+    Enum(TypeDefId),
+    /// A newtype defined in the component
+    Newtype(TypeDefId),
+    /// A function of the component used as a value
+    Function(FunctionId),
 }
 
 impl From<TypeId> for ResolvedType {
@@ -24,11 +34,109 @@ impl std::fmt::Display for ResolvedType {
             ResolvedType::Primitive(p) => (p as &dyn std::fmt::Debug).fmt(f),
             ResolvedType::Import(_) => write!(f, "imported type"),
             ResolvedType::Defined(v) => (v as &dyn std::fmt::Debug).fmt(f),
+            ResolvedType::Option(p) => write!(f, "Option<{:?}>", p),
+            ResolvedType::Record(r) => (r as &dyn std::fmt::Debug).fmt(f),
+            ResolvedType::Enum(e) => (e as &dyn std::fmt::Debug).fmt(f),
+            ResolvedType::Newtype(n) => (n as &dyn std::fmt::Debug).fmt(f),
+            ResolvedType::Function(func) => (func as &dyn std::fmt::Debug).fmt(f),
         }
     }
 }
 
 impl ResolvedType {
+    /// The primitive type this resolves to, if it is one
+    pub fn primitive(&self, comp: &ast::Component) -> Option<ast::PrimitiveType> {
+        match self {
+            ResolvedType::Primitive(ptype) => Some(*ptype),
+            ResolvedType::Import(_)
+            | ResolvedType::Option(_)
+            | ResolvedType::Record(_)
+            | ResolvedType::Enum(_)
+            | ResolvedType::Newtype(_)
+            | ResolvedType::Function(_) => None,
+            ResolvedType::Defined(type_id) => match comp.get_type(*type_id) {
+                ast::ValType::Primitive(ptype) => Some(*ptype),
+                _ => None,
+            },
+        }
+    }
+
+    /// The primitive type wrapped by this option, if it is an option of one
+    pub fn option_primitive(&self, comp: &ast::Component) -> Option<ast::PrimitiveType> {
+        match self {
+            ResolvedType::Option(ptype) => Some(*ptype),
+            ResolvedType::Defined(type_id) => match comp.get_type(*type_id) {
+                ast::ValType::Option(inner) => match comp.get_type(*inner) {
+                    ast::ValType::Primitive(ptype) => Some(*ptype),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The element type of this list, if it is one
+    pub fn list_element(&self, comp: &ast::Component) -> Option<TypeId> {
+        match self {
+            ResolvedType::Defined(type_id) => match comp.get_type(*type_id) {
+                ast::ValType::List(element) => Some(*element),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The struct this resolves to, if it is one
+    pub fn record(&self, comp: &ast::Component) -> Option<TypeDefId> {
+        match self {
+            ResolvedType::Record(type_def) => Some(*type_def),
+            ResolvedType::Defined(type_id) => named_type_def(*type_id, comp)
+                .filter(|id| matches!(comp.get_type_def(*id), ast::TypeDefinition::Record(_))),
+            _ => None,
+        }
+    }
+
+    /// The enum defined in the component this resolves to, if it is one
+    pub fn enum_def(&self, comp: &ast::Component) -> Option<TypeDefId> {
+        match self {
+            ResolvedType::Enum(type_def) => Some(*type_def),
+            ResolvedType::Defined(type_id) => named_type_def(*type_id, comp)
+                .filter(|id| matches!(comp.get_type_def(*id), ast::TypeDefinition::Enum(_))),
+            _ => None,
+        }
+    }
+
+    /// The newtype defined in the component this resolves to, if it is one
+    pub fn newtype(&self, comp: &ast::Component) -> Option<TypeDefId> {
+        match self {
+            ResolvedType::Newtype(type_def) => Some(*type_def),
+            ResolvedType::Defined(type_id) => named_type_def(*type_id, comp)
+                .filter(|id| matches!(comp.get_type_def(*id), ast::TypeDefinition::Newtype(_))),
+            _ => None,
+        }
+    }
+
+    /// The type wrapped by this newtype, if it is one
+    pub fn newtype_inner(&self, comp: &ast::Component) -> Option<TypeId> {
+        match comp.get_type_def(self.newtype(comp)?) {
+            ast::TypeDefinition::Newtype(newtype) => Some(newtype.inner),
+            _ => None,
+        }
+    }
+
+    /// The signature of this function type, if it is one
+    pub fn func_type(&self, comp: &ast::Component) -> Option<ast::FuncType> {
+        match self {
+            ResolvedType::Function(func) => Some(comp.get_function(*func).func_type()),
+            ResolvedType::Defined(type_id) => match comp.get_type(*type_id) {
+                ast::ValType::Func(func_type) => Some(func_type.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub fn type_eq(&self, other: &ResolvedType, comp: &ast::Component) -> bool {
         match (*self, *other) {
             // Both primitive
@@ -48,7 +156,41 @@ impl ResolvedType {
                     _ => false,
                 }
             }
+            // Options of primitives
+            (ResolvedType::Option(left), ResolvedType::Option(right)) => left == right,
+            (ResolvedType::Option(p), defined @ ResolvedType::Defined(_))
+            | (defined @ ResolvedType::Defined(_), ResolvedType::Option(p)) => {
+                defined.option_primitive(comp) == Some(p)
+            }
+            (ResolvedType::Option(_), ResolvedType::Primitive(_))
+            | (ResolvedType::Primitive(_), ResolvedType::Option(_)) => false,
+            // Structs are the same only if they have the same definition
+            (ResolvedType::Record(left), right) | (right, ResolvedType::Record(left)) => {
+                right.record(comp) == Some(left)
+            }
+            // Enums and newtypes too
+            (ResolvedType::Enum(left), right) | (right, ResolvedType::Enum(left)) => {
+                right.enum_def(comp) == Some(left)
+            }
+            (ResolvedType::Newtype(left), right) | (right, ResolvedType::Newtype(left)) => {
+                right.newtype(comp) == Some(left)
+            }
+            // Functions are interchangeable when their signatures match
+            (ResolvedType::Function(_), _) | (_, ResolvedType::Function(_)) => {
+                match (self.func_type(comp), other.func_type(comp)) {
+                    (Some(left), Some(right)) => left.eq(&right, comp),
+                    _ => false,
+                }
+            }
             _ => todo!(),
         }
     }
 }
+
+/// The definition a named type refers to
+fn named_type_def(type_id: TypeId, comp: &ast::Component) -> Option<TypeDefId> {
+    match comp.get_type(type_id) {
+        ast::ValType::Named(name) => comp.find_type_def(comp.get_name(*name)),
+        _ => None,
+    }
+}
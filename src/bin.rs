@@ -1,12 +1,25 @@
-use std::{fs, path::PathBuf, sync::Arc};
+mod manifest;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+};
 
 use clap::Parser;
 
-use claw_codegen::generate;
-use claw_common::OkPretty;
-use claw_parser::{parse, tokenize};
-use claw_resolver::{resolve, wit::ResolvedWit};
-use miette::NamedSource;
+use claw_ast::Component;
+use claw_codegen::{
+    generate, generate_docs, generate_dts, generate_wit, generate_with_objects, link, CoreObject,
+    LinkComponent, Profile,
+};
+use claw_common::{OkPretty, Source};
+use claw_parser::{parse, print_component, tokenize, LangVersion, Tokens};
+use claw_resolver::lints::{Lint, LintConfig, LintLevel};
+use claw_resolver::{resolve_with_lints, wit::ResolvedWit, ResolvedComponent};
+use manifest::Manifest;
+use miette::{NamedSource, Report};
 use wit_parser::Resolve;
 
 #[derive(Parser, Debug)]
@@ -18,6 +31,18 @@ struct Arguments {
 #[derive(Parser, Debug)]
 enum Command {
     Compile(Compile),
+    /// Write the WIT world that a component exports
+    Wit(Wit),
+    /// Compile and link the components of a manifest
+    Build(Build),
+    /// Report the errors and warnings of a source file or manifest without compiling it
+    Check(Check),
+    /// Generate bindings for using a component from another language
+    Bindgen(Bindgen),
+    /// Write markdown documentation for the exports of a component
+    Doc(Doc),
+    /// Format a source file in place
+    Fmt(Fmt),
 }
 
 #[derive(Parser, Debug)]
@@ -26,30 +51,451 @@ struct Compile {
     input: PathBuf,
     #[clap(long)]
     wit: Option<PathBuf>,
+    /// The version of the language to accept, the latest by default
+    #[clap(long, value_name = "VERSION")]
+    lang_version: Option<LangVersion>,
     #[clap(short, long)]
     output: PathBuf,
+    #[clap(flatten)]
+    lints: LintArgs,
+    /// Optimize the output and leave out debug sections
+    #[clap(long)]
+    release: bool,
+    /// Add a section mapping the generated code back to the source
+    #[clap(long)]
+    debug_info: bool,
+}
+
+#[derive(Parser, Debug)]
+struct LintArgs {
+    /// Don't report these lints
+    #[clap(long, value_name = "LINT")]
+    allow: Vec<Lint>,
+    /// Report these lints as warnings
+    #[clap(long, value_name = "LINT")]
+    warn: Vec<Lint>,
+    /// Report these lints as errors
+    #[clap(long, value_name = "LINT")]
+    deny: Vec<Lint>,
+    /// Deny every lint that isn't given a level above
+    #[clap(long)]
+    strict: bool,
+}
+
+impl LintArgs {
+    /// The lint levels, starting from strict ones when the manifest asks for them
+    fn config(&self, strict: bool) -> LintConfig {
+        let mut lints = match self.strict || strict {
+            true => LintConfig::strict(),
+            false => LintConfig::default(),
+        };
+        for lint in self.allow.iter() {
+            lints.set(*lint, LintLevel::Allow);
+        }
+        for lint in self.warn.iter() {
+            lints.set(*lint, LintLevel::Warn);
+        }
+        for lint in self.deny.iter() {
+            lints.set(*lint, LintLevel::Deny);
+        }
+        lints
+    }
 }
 
 impl Compile {
+    fn run(self) -> Option<()> {
+        let lints = self.lints.config(false);
+        let profile = match self.release {
+            true => Profile::Release,
+            false => Profile::Debug,
+        };
+        let version = self.lang_version.unwrap_or_default();
+        let wit = self.wit.as_deref();
+        let wasm = compile_file(
+            &self.input,
+            wit,
+            &lints,
+            &[],
+            profile,
+            self.debug_info,
+            version,
+        )?;
+
+        match fs::write(&self.output, wasm) {
+            Ok(_) => println!("Done"),
+            Err(err) => println!("Error: {:?}", err),
+        }
+
+        Some(())
+    }
+}
+
+/// Compile a source file, printing any warnings or errors
+fn compile_file(
+    input: &Path,
+    wit_path: Option<&Path>,
+    lints: &LintConfig,
+    objects: &[CoreObject],
+    profile: Profile,
+    debug_info: bool,
+    version: LangVersion,
+) -> Option<Vec<u8>> {
+    let (comp, rcomp) = resolve_file(input, wit_path, lints, version)?;
+    generate_with_objects(&comp, &rcomp, objects, profile, debug_info).ok_pretty()
+}
+
+fn read_file(path: &Path) -> Option<Vec<u8>> {
+    fs::read(path)
+        .map_err(|err| println!("Error reading {}: {:?}", path.display(), err))
+        .ok()
+}
+
+/// Parse the tokens of a source file, printing each error found
+fn parse_file(src: Source, tokens: Tokens, version: LangVersion) -> Option<Component> {
+    let (comp, errors) = parse(src, tokens, version);
+    for error in errors.iter().cloned() {
+        println!("{:?}", Report::new(error));
+    }
+    errors.is_empty().then_some(comp)
+}
+
+/// Parse and resolve a source file, printing any warnings or errors
+fn resolve_file(
+    input: &Path,
+    wit_path: Option<&Path>,
+    lints: &LintConfig,
+    version: LangVersion,
+) -> Option<(Component, ResolvedComponent)> {
+    let file_name = input.file_name()?.to_string_lossy().to_string();
+    let file_string = std::fs::read_to_string(input).ok()?;
+    let src = Arc::new(NamedSource::new(file_name, file_string.clone()));
+
+    let tokens = tokenize(src.clone(), &file_string).ok_pretty()?;
+
+    let comp = parse_file(src.clone(), tokens, version)?;
+
+    let mut wit = Resolve::new();
+    if let Some(wit_path) = wit_path {
+        wit.push_path(wit_path).unwrap();
+    }
+    let wit = ResolvedWit::new(wit);
+    let rcomp = resolve_with_lints(&comp, wit, lints).ok_pretty()?;
+    for warning in rcomp.warnings.iter().cloned() {
+        println!("{:?}", Report::new(warning));
+    }
+    Some((comp, rcomp))
+}
+
+#[derive(Parser, Debug)]
+struct Build {
+    #[clap(long, default_value = "claw.toml")]
+    manifest: PathBuf,
+    #[clap(short, long)]
+    output: PathBuf,
+    /// Optimize the output and leave out debug sections,
+    /// regardless of the profile in the manifest
+    #[clap(long)]
+    release: bool,
+    /// Add sections mapping the generated code of each
+    /// Claw source file back to the source
+    #[clap(long)]
+    debug_info: bool,
+}
+
+impl Build {
+    fn run(self) -> Option<()> {
+        let manifest = Manifest::load(&self.manifest).ok_pretty()?;
+        let wit = manifest.package.wit.as_deref();
+        let profile = match self.release {
+            true => Profile::Release,
+            false => manifest.package.profile.unwrap_or_default(),
+        };
+        let version = manifest.package.lang_version.unwrap_or_default();
+        let lints = match manifest.package.strict {
+            true => LintConfig::strict(),
+            false => LintConfig::default(),
+        };
+        let objects = manifest
+            .objects
+            .iter()
+            .map(|(name, object)| {
+                let bytes = read_file(&object.path)?;
+                let name = name.clone();
+                Some(CoreObject { name, bytes })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        // Dependencies are either Claw source files or compiled components
+        let load = |name: String, path: &Path| -> Option<LinkComponent> {
+            let bytes = if path.extension() == Some("claw".as_ref()) {
+                compile_file(
+                    path,
+                    wit,
+                    &lints,
+                    &objects,
+                    profile,
+                    self.debug_info,
+                    version,
+                )?
+            } else {
+                read_file(path)?
+            };
+            Some(LinkComponent { name, bytes })
+        };
+
+        let root = load(manifest.package.name.clone(), &manifest.package.main)?;
+        let dependencies = manifest
+            .dependencies
+            .iter()
+            .map(|(name, dependency)| load(name.clone(), &dependency.path))
+            .collect::<Option<Vec<_>>>()?;
+        let wasm = link(root, dependencies).ok_pretty()?;
+
+        match fs::write(&self.output, wasm) {
+            Ok(_) => println!("Done"),
+            Err(err) => println!("Error: {:?}", err),
+        }
+
+        Some(())
+    }
+}
+
+#[derive(Parser, Debug)]
+struct Check {
+    /// The source file to check, instead of the components of the manifest
+    #[clap(short, long)]
+    input: Option<PathBuf>,
+    #[clap(long)]
+    wit: Option<PathBuf>,
+    /// The version of the language to accept, the latest by default
+    #[clap(long, value_name = "VERSION")]
+    lang_version: Option<LangVersion>,
+    #[clap(long, default_value = "claw.toml")]
+    manifest: PathBuf,
+    #[clap(flatten)]
+    lints: LintArgs,
+}
+
+impl Check {
+    fn run(self) -> Option<()> {
+        match &self.input {
+            Some(input) => {
+                let lints = self.lints.config(false);
+                let version = self.lang_version.unwrap_or_default();
+                resolve_file(input, self.wit.as_deref(), &lints, version)?;
+            }
+            None => {
+                let manifest = Manifest::load(&self.manifest).ok_pretty()?;
+                let wit = manifest.package.wit.as_deref();
+                let lints = self.lints.config(manifest.package.strict);
+                let version = self
+                    .lang_version
+                    .or(manifest.package.lang_version)
+                    .unwrap_or_default();
+                let sources = std::iter::once(&manifest.package.main).chain(
+                    manifest
+                        .dependencies
+                        .values()
+                        .map(|dependency| &dependency.path),
+                );
+                // Every source is checked so that all of the problems are reported at once
+                let mut ok = true;
+                for source in sources.filter(|path| path.extension() == Some("claw".as_ref())) {
+                    ok &= resolve_file(source, wit, &lints, version).is_some();
+                }
+                ok.then_some(())?;
+            }
+        }
+        println!("Done");
+        Some(())
+    }
+}
+
+#[derive(Parser, Debug)]
+struct Wit {
+    #[clap(short, long)]
+    input: PathBuf,
+    #[clap(long)]
+    wit: Option<PathBuf>,
+    /// The version of the language to accept, the latest by default
+    #[clap(long, value_name = "VERSION")]
+    lang_version: Option<LangVersion>,
+    /// Where to write the WIT, instead of printing it
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// The name of the WIT package, `local:<input name>` by default
+    #[clap(long)]
+    package: Option<String>,
+    /// The name of the world, the input name by default
+    #[clap(long)]
+    world: Option<String>,
+}
+
+impl Wit {
     fn run(self) -> Option<()> {
         let file_name = self.input.file_name()?.to_string_lossy().to_string();
+        let file_stem = self.input.file_stem()?.to_string_lossy().to_string();
         let file_string = std::fs::read_to_string(&self.input).ok()?;
         let src = Arc::new(NamedSource::new(file_name, file_string.clone()));
 
         let tokens = tokenize(src.clone(), &file_string).ok_pretty()?;
 
-        let comp = parse(src.clone(), tokens).ok_pretty()?;
+        let version = self.lang_version.unwrap_or_default();
+        let comp = parse_file(src.clone(), tokens, version)?;
 
         let mut wit = Resolve::new();
         if let Some(wit_path) = self.wit {
             wit.push_path(wit_path).unwrap();
         }
         let wit = ResolvedWit::new(wit);
-        let rcomp = resolve(&comp, wit).ok_pretty()?;
+        resolve_with_lints(&comp, wit, &LintConfig::default()).ok_pretty()?;
+
+        let package = self
+            .package
+            .unwrap_or_else(|| format!("local:{}", file_stem));
+        let world = self.world.unwrap_or(file_stem);
+        let output = generate_wit(&comp, &package, &world).ok_pretty()?;
 
+        match self.output {
+            Some(path) => match fs::write(path, output) {
+                Ok(_) => println!("Done"),
+                Err(err) => println!("Error: {:?}", err),
+            },
+            None => print!("{}", output),
+        }
+
+        Some(())
+    }
+}
+
+#[derive(Parser, Debug)]
+struct Bindgen {
+    #[clap(short, long)]
+    input: PathBuf,
+    #[clap(long)]
+    wit: Option<PathBuf>,
+    /// The version of the language to accept, the latest by default
+    #[clap(long, value_name = "VERSION")]
+    lang_version: Option<LangVersion>,
+    /// Generate JavaScript with TypeScript declarations
+    #[clap(long)]
+    js: bool,
+    /// The directory to write the bindings to
+    #[clap(short, long)]
+    out_dir: PathBuf,
+}
+
+impl Bindgen {
+    fn run(self) -> Option<()> {
+        if !self.js {
+            println!("Error: Pick the language to generate bindings for, e.g. --js");
+            return None;
+        }
+        let name = self.input.file_stem()?.to_string_lossy().to_string();
+        let lints = LintConfig::default();
+        let version = self.lang_version.unwrap_or_default();
+        let (comp, rcomp) = resolve_file(&self.input, self.wit.as_deref(), &lints, version)?;
         let wasm = generate(&comp, &rcomp).ok_pretty()?;
+        let dts = generate_dts(&comp).ok_pretty()?;
 
-        match fs::write(&self.output, wasm) {
+        let write = |file: PathBuf, contents: &[u8]| {
+            fs::write(&file, contents)
+                .map_err(|err| println!("Error writing {}: {:?}", file.display(), err))
+                .ok()
+        };
+        fs::create_dir_all(&self.out_dir).ok()?;
+        let wasm_path = self.out_dir.join(format!("{}.wasm", name));
+        write(wasm_path.clone(), &wasm)?;
+        write(self.out_dir.join(format!("{}.d.ts", name)), dts.as_bytes())?;
+
+        // The JavaScript glue which lifts and lowers values is left to jco
+        let status = process::Command::new("jco")
+            .arg("transpile")
+            .arg(&wasm_path)
+            .arg("--out-dir")
+            .arg(&self.out_dir)
+            .args(["--name", &name, "--no-typescript"])
+            .status();
+        match status {
+            Ok(status) if status.success() => println!("Done"),
+            Ok(status) => println!("Error: jco failed with {}", status),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => println!(
+                "Error: Generating JavaScript needs jco, which can be installed with \
+                `npm install -g @bytecodealliance/jco`"
+            ),
+            Err(err) => println!("Error: Failed to run jco: {:?}", err),
+        }
+
+        Some(())
+    }
+}
+
+#[derive(Parser, Debug)]
+struct Doc {
+    #[clap(short, long)]
+    input: PathBuf,
+    #[clap(long)]
+    wit: Option<PathBuf>,
+    /// The version of the language to accept, the latest by default
+    #[clap(long, value_name = "VERSION")]
+    lang_version: Option<LangVersion>,
+    /// Where to write the docs, `<input name>.md` by default
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Doc {
+    fn run(self) -> Option<()> {
+        let title = self.input.file_stem()?.to_string_lossy().to_string();
+        let lints = LintConfig::default();
+        let version = self.lang_version.unwrap_or_default();
+        let (comp, _) = resolve_file(&self.input, self.wit.as_deref(), &lints, version)?;
+        let docs = generate_docs(&comp, &title);
+
+        let input = self.input;
+        let output = self.output.unwrap_or_else(|| input.with_extension("md"));
+        match fs::write(output, docs) {
+            Ok(_) => println!("Done"),
+            Err(err) => println!("Error: {:?}", err),
+        }
+
+        Some(())
+    }
+}
+
+#[derive(Parser, Debug)]
+struct Fmt {
+    #[clap(short, long)]
+    input: PathBuf,
+    /// The version of the language to accept, the latest by default
+    #[clap(long, value_name = "VERSION")]
+    lang_version: Option<LangVersion>,
+    /// Fail if the file isn't formatted instead of formatting it
+    #[clap(long)]
+    check: bool,
+}
+
+impl Fmt {
+    fn run(self) -> Option<()> {
+        let file_name = self.input.file_name()?.to_string_lossy().to_string();
+        let file_string = std::fs::read_to_string(&self.input).ok()?;
+        let src = Arc::new(NamedSource::new(file_name, file_string.clone()));
+
+        let tokens = tokenize(src.clone(), &file_string).ok_pretty()?;
+
+        let version = self.lang_version.unwrap_or_default();
+        let comp = parse_file(src.clone(), tokens, version)?;
+        let formatted = print_component(&comp);
+
+        if self.check {
+            if formatted != file_string {
+                println!("Error: {} is not formatted", self.input.display());
+                return None;
+            }
+            println!("Done");
+            return Some(());
+        }
+        match fs::write(&self.input, formatted) {
             Ok(_) => println!("Done"),
             Err(err) => println!("Error: {:?}", err),
         }
@@ -61,7 +507,17 @@ impl Compile {
 fn main() {
     let args = Arguments::parse();
 
-    match args.command {
+    let result = match args.command {
         Command::Compile(compile) => compile.run(),
+        Command::Wit(wit) => wit.run(),
+        Command::Build(build) => build.run(),
+        Command::Check(check) => check.run(),
+        Command::Bindgen(bindgen) => bindgen.run(),
+        Command::Doc(doc) => doc.run(),
+        Command::Fmt(fmt) => fmt.run(),
     };
+    // Failures have already been reported, but scripts need to see them too
+    if result.is_none() {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,132 @@
+//! The `claw.toml` manifest, which describes how to build a component
+//! out of a root source file and the components it depends on.
+//!
+//! ```toml
+//! [package]
+//! name = "app"
+//! main = "app.claw"
+//! wit = "wit"
+//! profile = "release"
+//! strict = true
+//! lang-version = "0.3"
+//!
+//! [dependencies]
+//! math = { path = "math.claw" }
+//! clock = { path = "clock.wasm" }
+//!
+//! [objects]
+//! fastmath = { path = "fastmath.o.wasm" }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fs, io};
+
+use claw_codegen::Profile;
+use claw_parser::LangVersion;
+use miette::Diagnostic;
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+#[derive(Error, Debug, Diagnostic)]
+pub enum ManifestError {
+    #[error("Failed to read {path}")]
+    Read {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Invalid manifest {path}")]
+    Invalid {
+        path: String,
+        #[source]
+        source: toml::de::Error,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub package: Package,
+    /// The components linked into the package, by name
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+    /// Core wasm modules whose functions the Claw components can import
+    #[serde(default)]
+    pub objects: BTreeMap<String, Object>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Package {
+    pub name: String,
+    /// The source file of the root component
+    pub main: PathBuf,
+    /// The WIT package or directory imports are resolved against
+    pub wit: Option<PathBuf>,
+    /// Either "debug" (the default) or "release"
+    #[serde(default, deserialize_with = "deserialize_parsed")]
+    pub profile: Option<Profile>,
+    /// Deny every lint when building and checking the package
+    #[serde(default)]
+    pub strict: bool,
+    /// The version of the language the sources are written in
+    #[serde(
+        default,
+        rename = "lang-version",
+        deserialize_with = "deserialize_parsed"
+    )]
+    pub lang_version: Option<LangVersion>,
+}
+
+/// Deserialize a string setting with its `FromStr` implementation
+fn deserialize_parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    let name = String::deserialize(deserializer)?;
+    name.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// Either a Claw source file or a compiled component
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Object {
+    pub path: PathBuf,
+}
+
+impl Manifest {
+    /// Read a manifest, making its paths relative to the current directory
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let display = path.display().to_string();
+        let contents = fs::read_to_string(path).map_err(|source| ManifestError::Read {
+            path: display.clone(),
+            source,
+        })?;
+        let mut manifest: Manifest =
+            toml::from_str(&contents).map_err(|source| ManifestError::Invalid {
+                path: display,
+                source,
+            })?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        manifest.package.main = dir.join(&manifest.package.main);
+        manifest.package.wit = manifest.package.wit.map(|wit| dir.join(wit));
+        for dependency in manifest.dependencies.values_mut() {
+            dependency.path = dir.join(&dependency.path);
+        }
+        for object in manifest.objects.values_mut() {
+            object.path = dir.join(&object.path);
+        }
+        Ok(manifest)
+    }
+}